@@ -6,6 +6,7 @@ mod _rel_config {
     {
         pub quickplay_mlvl: u32,
         pub quickplay_mrea: u32,
+        pub run_time_limit: f32,
     }
 }
 pub(crate) use self::_rel_config::RelConfig;
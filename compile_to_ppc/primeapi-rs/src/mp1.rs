@@ -111,6 +111,8 @@ pub enum CStateManager { }
 impl CStateManager
 {
     cpp_field!(player_state: *mut CPlayerState; ptr @ 0x8b8);
+    cpp_field!(escape_timer: f32; val @ 0xf0c);
+    cpp_field!(escape_total_time: f32; val @ 0xf10);
 }
 
 #[repr(C)]
@@ -10,7 +10,7 @@ use primeapi::alignment_utils::Aligned32;
 use primeapi::dol_sdk::dvd::DVDFileInfo;
 use primeapi::mp1::{
     CArchitectureQueue, CGameState, CGuiFrame, CGuiTextSupport, CGuiTextPane, CGuiWidget,
-    CMainFlow, CStringTable, CWorldState,
+    CMainFlow, CStateManager, CStringTable, CWorldState,
 };
 use primeapi::rstl::WString;
 
@@ -20,6 +20,7 @@ include!("../../patches_config.rs");
 static mut REL_CONFIG: RelConfig = RelConfig {
     quickplay_mlvl: 0xFFFFFFFF,
     quickplay_mrea: 0xFFFFFFFF,
+    run_time_limit: 0.0,
 };
 
 #[prolog_fn]
@@ -109,3 +110,26 @@ unsafe extern "C" fn quickplay_hook_advance_game_state(
     }
     CMainFlow::advance_game_state(flow, q)
 }
+
+// Replaces the `blr` of CStateManager::GetEscapeSequenceTimer. The HUD polls it every frame to
+// draw the escape sequence countdown, so the time left in the run is shown there, and once it
+// runs out UpdateEscapeSequenceTimer kills Samus like when an escape sequence fails.
+//
+// ResetEscapeSequenceTimer sets the escape's total time along with the timer, and the run's
+// limit is used as ours. While a real escape sequence (the Frigate, Impact Crater or a custom
+// one) is running its total time differs, so its timer is left alone until it's over.
+#[patch_fn(kind = return,
+           target = "GetEscapeSequenceTimer__13CStateManagerCFv" + 0x4)]
+unsafe extern "C" fn run_time_limit_hook(state_mgr: *mut CStateManager) -> f32
+{
+    let total_time = CStateManager::escape_total_time(state_mgr);
+    let real_escape = total_time != 0.0 && total_time != REL_CONFIG.run_time_limit;
+    if REL_CONFIG.run_time_limit > 0.0 && !real_escape {
+        let play_time = CGameState::play_time(CGameState::global_instance());
+        let remaining = (REL_CONFIG.run_time_limit as f64 - play_time) as f32;
+        // The timer is considered stopped at 0, the smallest positive value kills Samus instead
+        CStateManager::set_escape_timer(state_mgr, remaining.max(f32::EPSILON));
+        CStateManager::set_escape_total_time(state_mgr, REL_CONFIG.run_time_limit);
+    }
+    CStateManager::escape_timer(state_mgr)
+}
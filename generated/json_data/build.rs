@@ -28,4 +28,6 @@ fn main() {
     helper("skippable_cutscenes_competitive.jsonc");
     helper("skippable_cutscenes_pal.jsonc");
     helper("qol.jsonc");
+    helper("game_breaking.jsonc");
+    helper("logic.jsonc");
 }
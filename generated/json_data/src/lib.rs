@@ -2,3 +2,5 @@ pub const SKIPPABLE_CUTSCENES: &'static str = include_str!(concat!(env!("OUT_DIR
 pub const SKIPPABLE_CUTSCENES_PAL: &'static str = include_str!(concat!(env!("OUT_DIR"), "/skippable_cutscenes_pal.jsonc.min.json"));
 pub const SKIPPABLE_CUTSCENES_COMPETITIVE: &'static str = include_str!(concat!(env!("OUT_DIR"), "/skippable_cutscenes_competitive.jsonc.min.json"));
 pub const QOL: &'static str = include_str!(concat!(env!("OUT_DIR"), "/qol.jsonc.min.json"));
+pub const GAME_BREAKING: &'static str = include_str!(concat!(env!("OUT_DIR"), "/game_breaking.jsonc.min.json"));
+pub const LOGIC: &'static str = include_str!(concat!(env!("OUT_DIR"), "/logic.jsonc.min.json"));
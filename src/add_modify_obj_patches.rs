@@ -27,10 +27,17 @@ use crate::{
         PlatformConfig,
         PlatformType,
         BlockConfig,
+        DestructibleBlockConfig,
         HudmemoConfig,
         WaypointConfig,
+        GrapplePointConfig,
+        SpiderTrackConfig,
+        SaveStationConfig,
+        RefillStationConfig,
+        RefillStationKind,
+        SpecialFunctionType,
     },
-    pickup_meta::PickupType,
+    pickup_meta::{PickupModel, PickupType},
     door_meta::DoorType,
 };
 
@@ -669,6 +676,610 @@ pub fn patch_add_waypoint<'r>(
     add_edit_obj_helper!(area, Some(config.id), config.layer, Waypoint, new, update);
 }
 
+pub fn patch_add_grapple_point<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea,
+    config: GrapplePointConfig,
+)
+    -> Result<(), String>
+{
+    macro_rules! new {
+        () => {
+            structs::GrapplePoint {
+                name: b"my grapple point\0".as_cstr(),
+                position: config.position.into(),
+                rotation: config.rotation.unwrap_or([0.0, 0.0, 0.0]).into(),
+                active: config.active.unwrap_or(true) as u8,
+                grapple_params: structs::GrappleParams {
+                    unknown1: 10.0,
+                    unknown2: 10.0,
+                    unknown3: 1.0,
+                    unknown4: 1.0,
+                    unknown5: 1.0,
+                    unknown6: 1.0,
+                    unknown7: 1.0,
+                    unknown8: 45.0,
+                    unknown9: 90.0,
+                    unknown10: 0.0,
+                    unknown11: 0.0,
+
+                    disable_turning: 0,
+                },
+            }
+        };
+    }
+
+    macro_rules! update {
+        ($obj:expr) => {
+            let property_data = $obj.property_data.as_grapple_point_mut().unwrap();
+            property_data.position = config.position.into();
+            if let Some(rotation) = config.rotation {property_data.rotation = rotation.into()}
+            if let Some(active  ) = config.active   {property_data.active   = active as u8   }
+        };
+    }
+
+    add_edit_obj_helper!(area, config.id, config.layer, GrapplePoint, new, update);
+}
+
+const SPIDER_TRACK_PADDING: f32 = 1.0;
+
+pub fn patch_add_spider_track<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea,
+    config: SpiderTrackConfig,
+)
+    -> Result<(), String>
+{
+    let mrea_id = area.mlvl_area.mrea.to_u32().clone();
+    if config.points.len() < 2 {
+        Err(format!(
+            "Spider ball tracks need at least 2 points, got {} in room 0x{:X}",
+            config.points.len(), mrea_id,
+        ))?
+    }
+
+    let layer_id = config.layer.unwrap_or(0);
+    while area.layer_flags.layer_count <= layer_id {
+        area.add_layer(b"New Layer\0".as_cstr());
+    }
+
+    let ids: Vec<u32> = config.points.iter()
+        .map(|_| area.new_object_id_from_layer_id(layer_id as usize))
+        .collect();
+    let surface_id = area.new_object_id_from_layer_id(layer_id as usize);
+
+    // The attraction surface is the volume in which the ball snaps to the track, so it covers
+    // every point with some room to spare for the ball itself
+    let mut min = config.points[0];
+    let mut max = config.points[0];
+    for point in config.points.iter() {
+        for i in 0..3 {
+            min[i] = min[i].min(point[i]);
+            max[i] = max[i].max(point[i]);
+        }
+    }
+    let center: [f32; 3] = [0, 1, 2].map(|i| (min[i] + max[i]) / 2.0);
+    let extent: [f32; 3] = [0, 1, 2].map(|i| max[i] - min[i] + SPIDER_TRACK_PADDING * 2.0);
+
+    let scly = area.mrea().scly_section_mut();
+    let objects = scly.layers.as_mut_vec()[layer_id as usize].objects.as_mut_vec();
+
+    // Each waypoint points to the next one, the game builds the spline from that chain
+    for (i, position) in config.points.iter().enumerate() {
+        let connections = match ids.get(i + 1) {
+            Some(next_id) => vec![
+                structs::Connection {
+                    state: structs::ConnectionState::ARRIVED,
+                    message: structs::ConnectionMsg::NEXT,
+                    target_object_id: *next_id,
+                },
+            ],
+            None => vec![],
+        };
+
+        objects.push(
+            structs::SclyObject {
+                instance_id: ids[i],
+                property_data: structs::SpiderBallWaypoint {
+                    name: b"my spider waypoint\0".as_cstr(),
+                    position: (*position).into(),
+                    rotation: [0.0, 0.0, 0.0].into(),
+                    active: config.active.unwrap_or(true) as u8,
+                    unknown: 0,
+                }.into(),
+                connections: connections.into(),
+            }
+        );
+    }
+
+    objects.push(
+        structs::SclyObject {
+            instance_id: surface_id,
+            property_data: structs::SpiderBallAttractionSurface {
+                name: b"my spider surface\0".as_cstr(),
+                position: center.into(),
+                rotation: [0.0, 0.0, 0.0].into(),
+                scale: extent.into(),
+                active: config.active.unwrap_or(true) as u8,
+            }.into(),
+            connections: vec![].into(),
+        }
+    );
+
+    Ok(())
+}
+
+/// Adds a glowing ring at `position` which triggers a special function of `special_function_type`
+/// when Samus steps into it, for the stations of the game which work that way
+fn add_station<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    position: [f32; 3],
+    special_function_type: u32,
+)
+{
+    let special_function_id = area.new_object_id_from_layer_id(0);
+    add_station_ring(area, game_resources, position, structs::ConnectionMsg::ACTION, special_function_id);
+
+    let layers = area.mrea().scly_section_mut().layers.as_mut_vec();
+    layers[0].objects.as_mut_vec().push(structs::SclyObject {
+        instance_id: special_function_id,
+        property_data: structs::SclyProperty::SpecialFunction(
+            Box::new(structs::SpecialFunction {
+                name: b"my station function\0".as_cstr(),
+                position: position.into(),
+                rotation: [0.0, 0.0, 0.0].into(),
+                type_: special_function_type,
+                unknown0: b"\0".as_cstr(),
+                unknown1: 0.0,
+                unknown2: 0.0,
+                unknown3: 0.0,
+                layer_change_room_id: 0,
+                layer_change_layer_id: 0,
+                item_id: 0,
+                unknown4: 1, // active
+                unknown5: 0.0,
+                unknown6: 0xFFFFFFFF,
+                unknown7: 0xFFFFFFFF,
+                unknown8: 0xFFFFFFFF,
+            })
+        ),
+        connections: vec![].into(),
+    });
+}
+
+/// The game has no station which only refills energy, so this one drops a health refill which
+/// Samus picks up right away. It comes from a pickup generator like enemy drops, which only drops
+/// it when Samus is missing energy.
+fn add_energy_station<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    position: [f32; 3],
+)
+{
+    let model = PickupModel::HealthRefill;
+    let deps_iter = model.dependencies().iter()
+        .map(|&(file_id, fourcc)| structs::Dependency {
+            asset_id: file_id,
+            asset_type: fourcc,
+        });
+    area.add_dependencies(game_resources, 0, deps_iter);
+
+    let generator_id = area.new_object_id_from_layer_id(0);
+    let pickup_id = area.new_object_id_from_layer_id(0);
+    add_station_ring(area, game_resources, position, structs::ConnectionMsg::SET_TO_ZERO, generator_id);
+
+    let mut drop_position = position;
+    drop_position[2] += 1.0;
+
+    let layers = area.mrea().scly_section_mut().layers.as_mut_vec();
+    let objects = layers[0].objects.as_mut_vec();
+
+    objects.push(structs::SclyObject {
+        instance_id: generator_id,
+        property_data: structs::PickupGenerator {
+            name: b"my energy station generator\0".as_cstr(),
+            offset: drop_position.into(),
+            active: 1,
+            frequency: 100.0,
+        }.into(),
+        connections: vec![
+            structs::Connection {
+                state: structs::ConnectionState::ZERO,
+                message: structs::ConnectionMsg::FOLLOW,
+                target_object_id: pickup_id,
+            },
+        ].into(),
+    });
+
+    // The generator spawns copies of this inactive pickup where it is
+    let pickup_data = model.pickup_data();
+    objects.push(structs::SclyObject {
+        instance_id: pickup_id,
+        property_data: structs::Pickup {
+            name: b"my energy station refill\0".as_cstr(),
+            position: drop_position.into(),
+            kind: PickupType::HealthRefill.kind(),
+            max_increase: 0,
+            curr_increase: 999, // capped to Samus' maximum energy
+            drop_rate: 100.0,
+            disappear_timer: 5.0,
+            fade_in_timer: 0.0,
+            active: 0,
+            spawn_delay: 0.0,
+            ..pickup_data
+        }.into(),
+        connections: vec![].into(),
+    });
+}
+
+/// The ring Samus stands in to use a station, sending `message` to `target_id` when she enters it
+fn add_station_ring<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    position: [f32; 3],
+    message: structs::ConnectionMsg,
+    target_id: u32,
+)
+{
+    // The glow ring from the Artifact Temple marks where to stand
+    let deps = [
+        (0x12771AF0, b"CMDL"),
+        (0xA6114429, b"TXTR"),
+    ];
+    let deps_iter = deps.iter()
+        .map(|&(file_id, fourcc)| structs::Dependency {
+            asset_id: file_id,
+            asset_type: FourCC::from_bytes(fourcc),
+        });
+    area.add_dependencies(game_resources, 0, deps_iter);
+
+    let actor_id = area.new_object_id_from_layer_id(0);
+    let trigger_id = area.new_object_id_from_layer_id(0);
+
+    let layers = area.mrea().scly_section_mut().layers.as_mut_vec();
+    let objects = layers[0].objects.as_mut_vec();
+
+    objects.push(structs::SclyObject {
+        instance_id: actor_id,
+        property_data: structs::Actor {
+            name: b"my station\0".as_cstr(),
+            position: position.into(),
+            rotation: [0.0, 0.0, 0.0].into(),
+            scale: [1.0, 1.0, 1.0].into(),
+            hitbox: [0.0, 0.0, 0.0].into(),
+            scan_offset: [0.0, 0.0, 0.0].into(),
+            unknown1: 1.0,
+            unknown2: 0.0,
+            health_info: structs::scly_structs::HealthInfo {
+                health: 5.0,
+                knockback_resistance: 1.0
+            },
+            damage_vulnerability: DoorType::Disabled.vulnerability(),
+            cmdl: ResId::<res_id::CMDL>::new(0x12771AF0),
+            ancs: structs::scly_structs::AncsProp {
+                file_id: ResId::invalid(), // None
+                node_index: 0,
+                default_animation: 0xFFFFFFFF, // -1
+            },
+            actor_params: structs::scly_structs::ActorParameters {
+                light_params: structs::scly_structs::LightParameters {
+                    unknown0: 1,
+                    unknown1: 1.0,
+                    shadow_tessellation: 0,
+                    unknown2: 1.0,
+                    unknown3: 20.0,
+                    color: [1.0, 1.0, 1.0, 1.0].into(),
+                    unknown4: 1,
+                    world_lighting: 1,
+                    light_recalculation: 1,
+                    unknown5: [0.0, 0.0, 0.0].into(),
+                    unknown6: 4,
+                    unknown7: 4,
+                    unknown8: 0,
+                    light_layer_id: 0
+                },
+                scan_params: structs::scly_structs::ScannableParameters {
+                    scan: ResId::invalid(), // None
+                },
+                xray_cmdl: ResId::invalid(), // None
+                xray_cskr: ResId::invalid(), // None
+                thermal_cmdl: ResId::invalid(), // None
+                thermal_cskr: ResId::invalid(), // None
+
+                unknown0: 1,
+                unknown1: 1.0,
+                unknown2: 1.0,
+
+                visor_params: structs::scly_structs::VisorParameters {
+                    unknown0: 0,
+                    target_passthrough: 0,
+                    visor_mask: 15 // Combat|Scan|Thermal|XRay
+                },
+                enable_thermal_heat: 1,
+                unknown3: 0,
+                unknown4: 1,
+                unknown5: 1.0
+            },
+            looping: 1,
+            snow: 1,
+            solid: 0,
+            camera_passthrough: 0,
+            active: 1,
+            unknown8: 0,
+            unknown9: 1.0,
+            unknown10: 0,
+            unknown11: 0,
+            unknown12: 0,
+            unknown13: 0
+        }.into(),
+        connections: vec![].into(),
+    });
+
+    // Stepping into the ring sets off the station, like walking into a vanilla one
+    let mut trigger_position = position;
+    trigger_position[2] += 1.5;
+    objects.push(structs::SclyObject {
+        instance_id: trigger_id,
+        property_data: structs::Trigger {
+            name: b"my station trigger\0".as_cstr(),
+            position: trigger_position.into(),
+            scale: [3.0, 3.0, 3.0].into(),
+            damage_info: structs::scly_structs::DamageInfo {
+                weapon_type: 0,
+                damage: 0.0,
+                radius: 0.0,
+                knockback_power: 0.0
+            },
+            force: [0.0, 0.0, 0.0].into(),
+            flags: 1, // detect player
+            active: 1,
+            deactivate_on_enter: 0,
+            deactivate_on_exit: 0
+        }.into(),
+        connections: vec![
+            structs::Connection {
+                state: structs::ConnectionState::ENTERED,
+                message,
+                target_object_id: target_id,
+            },
+        ].into(),
+    });
+}
+
+pub fn patch_add_save_station<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    config: SaveStationConfig,
+)
+    -> Result<(), String>
+{
+    // Refills Samus and brings up the save dialog
+    add_station(area, game_resources, config.position, SpecialFunctionType::SaveStation as u32);
+
+    let spawn_point_id = area.new_object_id_from_layer_id(0);
+    let layers = area.mrea().scly_section_mut().layers.as_mut_vec();
+
+    // Loading a save puts Samus at the room's default spawn point, so the room needs one
+    let has_default_spawn = layers.iter()
+        .flat_map(|layer| layer.objects.iter())
+        .any(|obj| {
+            obj.property_data.as_spawn_point()
+                .map(|spawn_point| spawn_point.default_spawn != 0)
+                .unwrap_or(false)
+        });
+
+    let objects = layers[0].objects.as_mut_vec();
+
+    if !has_default_spawn {
+        objects.push(structs::SclyObject {
+            instance_id: spawn_point_id,
+            property_data: structs::SpawnPoint {
+                name: b"my save station spawnpoint\0".as_cstr(),
+                position: config.position.into(),
+                rotation: [0.0, 0.0, 0.0].into(),
+                power: 0,
+                ice: 0,
+                wave: 0,
+                plasma: 0,
+                missiles: 0,
+                scan_visor: 0,
+                bombs: 0,
+                power_bombs: 0,
+                flamethrower: 0,
+                thermal_visor: 0,
+                charge: 0,
+                super_missile: 0,
+                grapple: 0,
+                xray: 0,
+                ice_spreader: 0,
+                space_jump: 0,
+                morph_ball: 0,
+                combat_visor: 0,
+                boost_ball: 0,
+                spider_ball: 0,
+                power_suit: 0,
+                gravity_suit: 0,
+                varia_suit: 0,
+                phazon_suit: 0,
+                energy_tanks: 0,
+                unknown0: 0,
+                health_refill: 0,
+                unknown1: 0,
+                wavebuster: 0,
+                default_spawn: 1,
+                active: 1,
+                morphed: 0,
+            }.into(),
+            connections: vec![].into(),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn patch_add_refill_station<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    config: RefillStationConfig,
+)
+    -> Result<(), String>
+{
+    match config.kind {
+        RefillStationKind::Missile => add_station(
+            area, game_resources, config.position, SpecialFunctionType::MissileStation as u32,
+        ),
+        RefillStationKind::PowerBomb => add_station(
+            area, game_resources, config.position, SpecialFunctionType::PowerBombStation as u32,
+        ),
+        RefillStationKind::Energy => add_energy_station(area, game_resources, config.position),
+    }
+
+    Ok(())
+}
+
+pub fn patch_remove_grapple_points<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea,
+    positions: Vec<[f32;3]>,
+)
+    -> Result<(), String>
+{
+    let mrea_id = area.mlvl_area.mrea.to_u32().clone();
+    let scly = area.mrea().scly_section_mut();
+    for position in positions.iter() {
+        let mut found = false;
+        for layer in scly.layers.as_mut_vec().iter_mut() {
+            layer.objects.as_mut_vec().retain(|obj| {
+                let is_match = obj.property_data.as_grapple_point()
+                    .map(|grapple_point| is_near(grapple_point.position.into(), *position))
+                    .unwrap_or(false);
+                found |= is_match;
+                !is_match
+            });
+        }
+
+        if !found {
+            panic!("Failed to find grapple point near {:?} in room 0x{:X}", position, mrea_id);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn patch_remove_spider_tracks<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea,
+    positions: Vec<[f32;3]>,
+)
+    -> Result<(), String>
+{
+    let mrea_id = area.mlvl_area.mrea.to_u32().clone();
+    let scly = area.mrea().scly_section_mut();
+
+    // Map every spider ball waypoint to its neighbors so the whole track can be removed at once
+    let mut neighbors: HashMap<u32, Vec<u32>> = HashMap::new();
+    let mut waypoint_positions: Vec<(u32, [f32;3])> = Vec::new();
+    for layer in scly.layers.iter() {
+        for obj in layer.objects.iter() {
+            let waypoint = match obj.property_data.as_spider_ball_waypoint() {
+                Some(waypoint) => waypoint,
+                None => continue,
+            };
+
+            let id = obj.instance_id & 0x00FFFFFF;
+            waypoint_positions.push((id, waypoint.position.into()));
+            for conn in obj.connections.iter() {
+                let target_id = conn.target_object_id & 0x00FFFFFF;
+                neighbors.entry(id).or_insert_with(Vec::new).push(target_id);
+                neighbors.entry(target_id).or_insert_with(Vec::new).push(id);
+            }
+        }
+    }
+
+    let mut remove_ids = Vec::new();
+    for position in positions.iter() {
+        let start_ids: Vec<u32> = waypoint_positions.iter()
+            .filter(|(_, waypoint_position)| is_near(*waypoint_position, *position))
+            .map(|(id, _)| *id)
+            .collect();
+
+        if start_ids.is_empty() {
+            panic!("Failed to find spider ball track near {:?} in room 0x{:X}", position, mrea_id);
+        }
+
+        let mut stack = start_ids;
+        while let Some(id) = stack.pop() {
+            if remove_ids.contains(&id) {
+                continue;
+            }
+            remove_ids.push(id);
+
+            if let Some(ids) = neighbors.get(&id) {
+                stack.extend(ids.iter().filter(|id| waypoint_positions.iter().any(|(x, _)| x == *id)));
+            }
+        }
+    }
+
+    // The ball would still snap to where the track used to be, so the attraction surfaces around
+    // the removed waypoints go too. A surface shared with a track which stays is left alone.
+    let (removed, kept): (Vec<_>, Vec<_>) = waypoint_positions.iter()
+        .partition(|(id, _)| remove_ids.contains(id));
+    for layer in scly.layers.iter() {
+        for obj in layer.objects.iter() {
+            let surface = match obj.property_data.as_spider_ball_attraction_surface() {
+                Some(surface) => surface,
+                None => continue,
+            };
+            let covers = |(_, position): &&(u32, [f32;3])| is_inside_box(
+                *position,
+                surface.position.into(),
+                surface.rotation.into(),
+                surface.scale.into(),
+            );
+            if removed.iter().any(covers) && !kept.iter().any(covers) {
+                remove_ids.push(obj.instance_id & 0x00FFFFFF);
+            }
+        }
+    }
+
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        layer.objects.as_mut_vec().retain(|obj| !remove_ids.contains(&(obj.instance_id & 0x00FFFFFF)));
+    }
+
+    Ok(())
+}
+
+/// Whether `point` is in the box the game builds from an object's position, rotation (in
+/// degrees, applied X then Y then Z) and scale, give or take `SPIDER_TRACK_PADDING`
+fn is_inside_box(point: [f32;3], center: [f32;3], rotation: [f32;3], scale: [f32;3]) -> bool
+{
+    let mut local = [0, 1, 2].map(|i| point[i] - center[i]);
+
+    // Undo the rotation, in reverse order
+    for i in (0..3).rev() {
+        let angle = -rotation[i].to_radians();
+        let original = local;
+        let x = (i + 1) % 3;
+        let y = (i + 2) % 3;
+        local[x] = original[x] * angle.cos() - original[y] * angle.sin();
+        local[y] = original[x] * angle.sin() + original[y] * angle.cos();
+    }
+
+    (0..3).all(|i| local[i].abs() <= scale[i].abs() / 2.0 + SPIDER_TRACK_PADDING)
+}
+
+fn is_near(a: [f32;3], b: [f32;3]) -> bool
+{
+    const MAX_DISTANCE: f32 = 2.0;
+    let dx = a[0] - b[0];
+    let dy = a[1] - b[1];
+    let dz = a[2] - b[2];
+    dx*dx + dy*dy + dz*dz <= MAX_DISTANCE*MAX_DISTANCE
+}
+
 pub fn patch_add_platform<'r>(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
@@ -864,6 +1475,96 @@ pub fn patch_add_block<'r>(
     Ok(())
 }
 
+pub fn patch_add_destructible_block<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    config: DestructibleBlockConfig,
+    old_scale: bool,
+) -> Result<(), String>
+{
+    let texture = config.texture.unwrap_or(config.material.texture());
+    let vulnerability = match config.vulnerability.as_ref() {
+        Some(vulnerability) => DoorType::from_string(vulnerability.clone())
+            .ok_or(format!("Unknown destructible block vulnerability '{}'", vulnerability))?,
+        None => config.material.vulnerability(),
+    };
+
+    let deps = vec![
+        (texture.cmdl().to_u32(), b"CMDL"),
+        (texture.txtr().to_u32(), b"TXTR"),
+    ];
+    let deps_iter = deps.iter()
+        .map(|&(file_id, fourcc)| structs::Dependency {
+            asset_id: file_id,
+            asset_type: FourCC::from_bytes(fourcc),
+        }
+    );
+    area.add_dependencies(game_resources, 0, deps_iter);
+
+    let layer_id = config.layer.unwrap_or(0);
+    let active = config.active.unwrap_or(true);
+    let scale = config.scale.unwrap_or([1.0, 1.0, 1.0]);
+
+    let actor_id = match config.id {
+        Some(id) => id,
+        None => area.new_object_id_from_layer_id(layer_id as usize),
+    };
+    let damageable_trigger_id = area.new_object_id_from_layer_id(layer_id as usize);
+
+    add_block(
+        area,
+        Some(actor_id),
+        config.position,
+        scale,
+        texture,
+        1,
+        Some(layer_id),
+        active,
+        old_scale,
+    );
+
+    // The block itself can't be damaged, a slightly larger trigger around it takes the hits and
+    // removes the block once destroyed
+    let scly = area.mrea().scly_section_mut();
+    let objects = scly.layers.as_mut_vec()[layer_id as usize].objects.as_mut_vec();
+    objects.push(
+        structs::SclyObject {
+            instance_id: damageable_trigger_id,
+            property_data: structs::DamageableTrigger {
+                name: b"my destructible block\0".as_cstr(),
+                position: config.position.into(),
+                scale: [scale[0] * 1.05, scale[1] * 1.05, scale[2] * 1.05].into(),
+                health_info: structs::scly_structs::HealthInfo {
+                    health: 1.0,
+                    knockback_resistance: 1.0
+                },
+                damage_vulnerability: vulnerability.vulnerability(),
+                unknown0: 0,
+                pattern_txtr0: ResId::invalid(),
+                pattern_txtr1: ResId::invalid(),
+                color_txtr: ResId::invalid(),
+                lock_on: 0,
+                active: active as u8,
+                visor_params: structs::scly_structs::VisorParameters {
+                    unknown0: 0,
+                    target_passthrough: 0,
+                    visor_mask: 15 // Combat|Scan|Thermal|XRay
+                }
+            }.into(),
+            connections: vec![
+                structs::Connection {
+                    state: structs::ConnectionState::DEAD,
+                    message: structs::ConnectionMsg::DEACTIVATE,
+                    target_object_id: actor_id,
+                },
+            ].into(),
+        },
+    );
+
+    Ok(())
+}
+
 pub fn add_block<'r>(
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
     id: Option<u32>,
@@ -0,0 +1,141 @@
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+};
+
+use std::{
+    io::{self, Write},
+    mem,
+};
+
+use crate::{
+    patch_config::PatchConfig,
+    patches::patch_disc,
+};
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+const CHANNEL_CAPACITY: usize = 8;
+
+static ZEROES: [u8; 0x8000] = [0; 0x8000];
+
+/// Forwards everything written to it to an async task in fixed size chunks. The receiving end
+/// can't seek, so skipped regions are sent as zeroes.
+struct ChannelWriter
+{
+    tx: mpsc::Sender<Vec<u8>>,
+    buf: Vec<u8>,
+    bytes_written: u64,
+}
+
+impl ChannelWriter
+{
+    fn new(tx: mpsc::Sender<Vec<u8>>) -> Self
+    {
+        ChannelWriter {
+            tx,
+            buf: Vec::with_capacity(CHUNK_SIZE),
+            bytes_written: 0,
+        }
+    }
+
+    fn send_chunk(&mut self) -> io::Result<()>
+    {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+        self.tx.blocking_send(chunk)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "The output stream was closed"))
+    }
+
+    /// Pads the output to `len` bytes and sends whatever is left in the buffer
+    fn finish(mut self, len: u64) -> io::Result<()>
+    {
+        if self.bytes_written < len {
+            self.skip_bytes(len - self.bytes_written)?;
+        }
+        self.send_chunk()
+    }
+
+    fn skip_bytes(&mut self, mut bytes: u64) -> io::Result<()>
+    {
+        while bytes > 0 {
+            let l = std::cmp::min(bytes, ZEROES.len() as u64);
+            self.write_all(&ZEROES[..l as usize])?;
+            bytes -= l;
+        }
+        Ok(())
+    }
+}
+
+impl Write for ChannelWriter
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+        let l = std::cmp::min(buf.len(), CHUNK_SIZE - self.buf.len());
+        self.buf.extend_from_slice(&buf[..l]);
+        self.bytes_written += l as u64;
+        if self.buf.len() == CHUNK_SIZE {
+            self.send_chunk()?;
+        }
+        Ok(l)
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        self.send_chunk()
+    }
+}
+
+impl structs::WriteExt for ChannelWriter
+{
+    fn skip_bytes(&mut self, bytes: u64) -> io::Result<()>
+    {
+        ChannelWriter::skip_bytes(self, bytes)
+    }
+}
+
+/// Same as `patch_iso`, but streams the patched disc as a plain ISO into `writer` instead of
+/// writing it to `config.output_iso`. Patching still happens synchronously, on tokio's blocking
+/// thread pool, so this can be awaited from a server without tying up one of its worker threads
+/// for the duration of the write.
+///
+/// `PatchConfig` can't be sent between threads, so it's built on the blocking thread by
+/// `make_config` (e.g. `move || PatchConfig::from_json(&json)`). `config.output_iso` and
+/// `config.iso_format` are ignored.
+pub async fn patch_iso_async<F, W, T>(make_config: F, mut pn: T, writer: &mut W)
+    -> Result<(), String>
+    where F: FnOnce() -> Result<PatchConfig, String> + Send + 'static,
+          W: AsyncWrite + Unpin,
+          T: structs::ProgressNotifier + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::channel(CHANNEL_CAPACITY);
+
+    let patcher = tokio::task::spawn_blocking(move || {
+        let config = make_config()?;
+        let result = patch_disc(&config, |gc_disc| {
+            let mut channel_writer = ChannelWriter::new(tx);
+            gc_disc.write(&mut channel_writer, &mut pn)
+                .map_err(|e| format!("Error writing output stream: {}", e))?;
+            channel_writer.finish(structs::GC_DISC_LENGTH as u64)
+                .map_err(|e| format!("Error writing output stream: {}", e))?;
+            pn.notify_flushing_to_disk();
+            Ok(())
+        });
+        // Nothing is written to the output file, so don't leave an empty one behind
+        let _ = config.finish_output(false);
+        result
+    });
+
+    while let Some(chunk) = rx.recv().await {
+        // Returning here drops the receiver, which makes the patching thread bail out with an
+        // error on its next write
+        writer.write_all(&chunk).await
+            .map_err(|e| format!("Error writing output stream: {}", e))?;
+    }
+    writer.flush().await
+        .map_err(|e| format!("Error flushing output stream: {}", e))?;
+
+    patcher.await
+        .map_err(|e| format!("Patching task failed: {}", e))?
+}
@@ -0,0 +1,425 @@
+// Conversions between the game's two streamed audio formats. Rooms stream Nintendo ADPCM DSPs,
+// the front end plays RSFs: headerless CCITT G.721 at 32kHz, the left channel followed by the
+// right one, two samples per byte with the low nibble first.
+//
+// The G.721 coder follows the public domain Sun reference implementation the game's own
+// `g721_decoder` was built from. The encoder has to track the decoder's state exactly, so the
+// C code's `short` truncations are kept.
+
+use std::convert::TryInto;
+
+pub const RSF_SAMPLE_RATE: u32 = 32000;
+
+/// Decodes a mono Nintendo ADPCM DSP file to 16 bit PCM, returning the samples and sample rate
+pub fn decode_dsp(bytes: &[u8]) -> Result<(Vec<i16>, u32), String>
+{
+    if bytes.len() < 0x60 {
+        Err("Not a DSP file, it's too short".to_string())?
+    }
+    let u32_at = |offset: usize| u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let i16_at = |offset: usize| i16::from_be_bytes(bytes[offset..offset + 2].try_into().unwrap());
+
+    let sample_count = u32_at(0x00) as usize;
+    let sample_rate = u32_at(0x08);
+    if i16_at(0x0E) != 0 {
+        Err("Not a DSP file, only ADPCM is supported".to_string())?
+    }
+    let coefs: Vec<i32> = (0..16).map(|i| i16_at(0x1C + i * 2) as i32).collect();
+    let data = &bytes[0x60..];
+    if data.len() < (sample_count + 13) / 14 * 8 {
+        Err(format!("The file is cut short, it should be {} bytes long", 0x60 + (sample_count + 13) / 14 * 8))?
+    }
+
+    let mut samples = Vec::with_capacity(sample_count);
+    let (mut hist1, mut hist2) = (0i32, 0i32);
+    for frame in data.chunks(8) {
+        let predictor = (frame[0] >> 4) as usize & 7;
+        let scale = 1i32 << (frame[0] & 0xF);
+        let (c1, c2) = (coefs[predictor * 2], coefs[predictor * 2 + 1]);
+        for i in 0..14 {
+            if samples.len() == sample_count {
+                return Ok((samples, sample_rate));
+            }
+            let byte = frame[1 + i / 2];
+            let nibble = if i % 2 == 0 { byte >> 4 } else { byte & 0xF };
+            // Sign extend the nibble
+            let nibble = ((nibble as i8) << 4 >> 4) as i32;
+            let sample = (((nibble * scale) << 11) + 1024 + c1 * hist1 + c2 * hist2) >> 11;
+            let sample = sample.clamp(i16::MIN as i32, i16::MAX as i32);
+            samples.push(sample as i16);
+            hist2 = hist1;
+            hist1 = sample;
+        }
+    }
+
+    Ok((samples, sample_rate))
+}
+
+/// Encodes a stereo track as an RSF. The shorter channel is padded with silence.
+pub fn encode_rsf(left: &[i16], right: &[i16]) -> Vec<u8>
+{
+    let len = left.len().max(right.len());
+    let mut rsf = Vec::with_capacity((len + 1) / 2 * 2);
+    for channel in [left, right] {
+        let mut state = G72xState::new();
+        let mut samples = channel.iter().copied()
+            .chain(std::iter::repeat(0))
+            .take((len + 1) / 2 * 2);
+        while let (Some(a), Some(b)) = (samples.next(), samples.next()) {
+            let low = state.encode(a);
+            let high = state.encode(b);
+            rsf.push(low | (high << 4));
+        }
+    }
+    rsf
+}
+
+const POWER2: [i32; 15] = [
+    1, 2, 4, 8, 0x10, 0x20, 0x40, 0x80, 0x100, 0x200, 0x400, 0x800, 0x1000, 0x2000, 0x4000,
+];
+const QTAB_721: [i32; 7] = [-124, 80, 178, 246, 300, 349, 400];
+const DQLNTAB: [i32; 16] = [
+    -2048, 4, 135, 213, 273, 323, 373, 425, 425, 373, 323, 273, 213, 135, 4, -2048,
+];
+const WITAB: [i32; 16] = [
+    -12, 18, 41, 64, 112, 198, 355, 1122, 1122, 355, 198, 112, 64, 41, 18, -12,
+];
+const FITAB: [i32; 16] = [
+    0, 0, 0, 0x200, 0x200, 0x200, 0x600, 0xE00, 0xE00, 0x600, 0x200, 0x200, 0x200, 0, 0, 0,
+];
+
+fn quan(val: i32, table: &[i32]) -> i32
+{
+    table.iter().position(|&t| val < t).unwrap_or(table.len()) as i32
+}
+
+// Multiplies a predictor coefficient with a signal in the coder's 4 bit exponent, 6 bit mantissa
+// floating point format
+fn fmult(an: i32, srn: i32) -> i32
+{
+    let anmag = if an > 0 { an } else { (-an) & 0x1FFF };
+    let anexp = quan(anmag, &POWER2) - 6;
+    let anmant = if anmag == 0 {
+        32
+    } else if anexp >= 0 {
+        anmag >> anexp
+    } else {
+        anmag << -anexp
+    };
+    let wanexp = anexp + ((srn >> 6) & 0xF) - 13;
+    let wanmant = (anmant * (srn & 0o77) + 0x30) >> 4;
+    let retval = if wanexp >= 0 {
+        (wanmant << wanexp) & 0x7FFF
+    } else {
+        wanmant >> -wanexp
+    };
+    if (an ^ srn) < 0 { -retval } else { retval }
+}
+
+// The magnitude as the 4 bit exponent, 6 bit mantissa floating point number, with the sign in
+// the upper bits
+fn to_float(mag: i32, negative: bool) -> i16
+{
+    let exp = quan(mag, &POWER2);
+    let value = (exp << 6) + ((mag << 6) >> exp);
+    (if negative { value - 0x400 } else { value }) as i16
+}
+
+struct G72xState
+{
+    yl: i32,
+    yu: i16,
+    dms: i16,
+    dml: i16,
+    ap: i16,
+    a: [i16; 2],
+    b: [i16; 6],
+    pk: [i16; 2],
+    dq: [i16; 6],
+    sr: [i16; 2],
+    td: bool,
+}
+
+impl G72xState
+{
+    fn new() -> Self
+    {
+        G72xState {
+            yl: 34816,
+            yu: 544,
+            dms: 0,
+            dml: 0,
+            ap: 0,
+            a: [0; 2],
+            b: [0; 6],
+            pk: [0; 2],
+            dq: [32; 6],
+            sr: [32; 2],
+            td: false,
+        }
+    }
+
+    fn predictor_zero(&self) -> i32
+    {
+        (0..6).map(|i| fmult(self.b[i] as i32 >> 2, self.dq[i] as i32)).sum()
+    }
+
+    fn predictor_pole(&self) -> i32
+    {
+        fmult(self.a[1] as i32 >> 2, self.sr[1] as i32) + fmult(self.a[0] as i32 >> 2, self.sr[0] as i32)
+    }
+
+    fn step_size(&self) -> i32
+    {
+        let yu = self.yu as i32;
+        if self.ap >= 256 {
+            return yu;
+        }
+        let mut y = self.yl >> 6;
+        let dif = yu - y;
+        let al = self.ap as i32 >> 2;
+        if dif > 0 {
+            y += (dif * al) >> 6;
+        } else if dif < 0 {
+            y += (dif * al + 0x3F) >> 6;
+        }
+        y
+    }
+
+    /// Encodes a 16 bit sample to a 4 bit code
+    fn encode(&mut self, sample: i16) -> u8
+    {
+        // 14 bit dynamic range
+        let sl = sample as i32 >> 2;
+
+        let sezi = self.predictor_zero() as i16 as i32;
+        let sez = sezi >> 1;
+        let se = ((sezi + self.predictor_pole()) >> 1) as i16 as i32;
+        let d = (sl - se) as i16 as i32;
+
+        let y = self.step_size() as i16 as i32;
+
+        // Quantize the difference
+        let dqm = d.abs();
+        let exp = quan(dqm >> 1, &POWER2);
+        let mant = ((dqm << 7) >> exp) & 0x7F;
+        let dln = (((exp << 7) + mant) - (y >> 2)) as i16 as i32;
+        let i = quan(dln, &QTAB_721);
+        let code = if d < 0 {
+            15 - i
+        } else if i == 0 {
+            15
+        } else {
+            i
+        };
+
+        self.decode_code(code, se, sez, y);
+        code as u8
+    }
+
+    /// Decodes a 4 bit code to a 16 bit sample
+    #[cfg(test)]
+    fn decode(&mut self, code: u8) -> i16
+    {
+        let code = (code & 0xF) as i32;
+        let sezi = self.predictor_zero() as i16 as i32;
+        let sez = sezi >> 1;
+        let se = ((sezi + self.predictor_pole()) >> 1) as i16 as i32;
+        let y = self.step_size() as i16 as i32;
+        (self.decode_code(code, se, sez, y) << 2) as i16
+    }
+
+    // The half of the coder shared by the encoder and decoder: reconstructs the signal from the
+    // code and adapts the predictor. Returns the 14 bit reconstructed sample.
+    fn decode_code(&mut self, code: i32, se: i32, sez: i32, y: i32) -> i32
+    {
+        let dq = reconstruct(code & 8 != 0, DQLNTAB[code as usize], y) as i16 as i32;
+        let sr = if dq < 0 { se - (dq & 0x3FFF) } else { se + dq } as i16 as i32;
+        let dqsez = (sr + sez - se) as i16 as i32;
+        self.update(y, WITAB[code as usize] << 5, FITAB[code as usize], dq, sr, dqsez);
+        sr
+    }
+
+    fn update(&mut self, y: i32, wi: i32, fi: i32, dq: i32, sr: i32, dqsez: i32)
+    {
+        let pk0: i16 = if dqsez < 0 { 1 } else { 0 };
+        let mag = dq & 0x7FFF;
+
+        // Tone and transition detector
+        let ylint = self.yl >> 15;
+        let ylfrac = (self.yl >> 10) & 0x1F;
+        let thr1 = (32 + ylfrac) << ylint;
+        let thr2 = if ylint > 9 { 31 << 10 } else { thr1 } as i16 as i32;
+        let dqthr = (thr2 + (thr2 >> 1)) >> 1;
+        let tr = self.td && mag > dqthr;
+
+        // Quantizer scale factor adaptation
+        let yu = (y + ((wi - y) >> 5)).clamp(544, 5120);
+        self.yu = yu as i16;
+        self.yl += yu + ((-self.yl) >> 6);
+
+        // Adaptive predictor coefficients
+        let mut a2p = 0;
+        if tr {
+            self.a = [0; 2];
+            self.b = [0; 6];
+        } else {
+            let pks1 = pk0 ^ self.pk[0];
+
+            a2p = self.a[1] as i32 - (self.a[1] as i32 >> 7);
+            if dqsez != 0 {
+                let fa1 = if pks1 != 0 { self.a[0] as i32 } else { -(self.a[0] as i32) };
+                if fa1 < -8191 {
+                    a2p -= 0x100;
+                } else if fa1 > 8191 {
+                    a2p += 0xFF;
+                } else {
+                    a2p += fa1 >> 5;
+                }
+
+                if pk0 ^ self.pk[1] != 0 {
+                    if a2p <= -12160 {
+                        a2p = -12288;
+                    } else if a2p >= 12416 {
+                        a2p = 12288;
+                    } else {
+                        a2p -= 0x80;
+                    }
+                } else if a2p <= -12416 {
+                    a2p = -12288;
+                } else if a2p >= 12160 {
+                    a2p = 12288;
+                } else {
+                    a2p += 0x80;
+                }
+            }
+            a2p = a2p as i16 as i32;
+            self.a[1] = a2p as i16;
+
+            let mut a0 = self.a[0] as i32;
+            a0 -= a0 >> 8;
+            if dqsez != 0 {
+                a0 += if pks1 == 0 { 192 } else { -192 };
+            }
+            let a1ul = 15360 - a2p;
+            self.a[0] = a0.clamp(-a1ul, a1ul) as i16;
+
+            for cnt in 0..6 {
+                let mut b = self.b[cnt] as i32;
+                b -= b >> 8;
+                if dq & 0x7FFF != 0 {
+                    b += if (dq ^ self.dq[cnt] as i32) >= 0 { 128 } else { -128 };
+                }
+                self.b[cnt] = b as i16;
+            }
+        }
+
+        self.dq.copy_within(0..5, 1);
+        self.dq[0] = if mag == 0 {
+            if dq >= 0 { 0x20 } else { 0xFC20u16 as i16 }
+        } else {
+            to_float(mag, dq < 0)
+        };
+
+        self.sr[1] = self.sr[0];
+        self.sr[0] = if sr == 0 {
+            0x20
+        } else if sr > 0 {
+            to_float(sr, false)
+        } else if sr > -32768 {
+            to_float(-sr, true)
+        } else {
+            0xFC20u16 as i16
+        };
+
+        self.pk[1] = self.pk[0];
+        self.pk[0] = pk0;
+
+        self.td = !tr && a2p < -11776;
+
+        // Adaptation speed control
+        self.dms = (self.dms as i32 + ((fi - self.dms as i32) >> 5)) as i16;
+        self.dml = (self.dml as i32 + (((fi << 2) - self.dml as i32) >> 7)) as i16;
+
+        let ap = self.ap as i32;
+        self.ap = if tr {
+            256
+        } else if y < 1536 || self.td
+            || (((self.dms as i32) << 2) - self.dml as i32).abs() >= self.dml as i32 >> 3 {
+            ap + ((0x200 - ap) >> 4)
+        } else {
+            ap + ((-ap) >> 4)
+        } as i16;
+    }
+}
+
+fn reconstruct(sign: bool, dqln: i32, y: i32) -> i32
+{
+    let dql = dqln + (y >> 2);
+    if dql < 0 {
+        return if sign { -0x8000 } else { 0 };
+    }
+    let dex = (dql >> 7) & 15;
+    let dqt = 128 + (dql & 127);
+    let dq = (dqt << 7) >> (14 - dex);
+    if sign { dq - 0x8000 } else { dq }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn sine(len: usize, period: f64, amplitude: f64) -> Vec<i16>
+    {
+        (0..len)
+            .map(|i| ((i as f64 * std::f64::consts::TAU / period).sin() * amplitude) as i16)
+            .collect()
+    }
+
+    #[test]
+    fn rsf_decodes_close_to_the_source()
+    {
+        let left = sine(32000, 80.0, 12000.0);
+        let right = sine(31999, 150.0, 6000.0);
+        let rsf = encode_rsf(&left, &right);
+        assert_eq!(rsf.len(), 32000);
+
+        let (left_rsf, right_rsf) = rsf.split_at(rsf.len() / 2);
+        for (source, encoded) in [(&left, left_rsf), (&right, right_rsf)] {
+            let mut state = G72xState::new();
+            let decoded: Vec<i16> = encoded.iter()
+                .flat_map(|byte| [byte & 0xF, byte >> 4])
+                .map(|code| state.decode(code))
+                .collect();
+
+            // Let the predictor settle before comparing
+            let error = source.iter().zip(decoded.iter()).skip(1000)
+                .map(|(&a, &b)| (a as i32 - b as i32).abs())
+                .max()
+                .unwrap();
+            assert!(error < 800, "max error {}", error);
+        }
+    }
+
+    #[test]
+    fn dsp_frames_are_decoded()
+    {
+        let mut dsp = vec![0u8; 0x60];
+        dsp[0x00..0x04].copy_from_slice(&16u32.to_be_bytes());
+        dsp[0x04..0x08].copy_from_slice(&32u32.to_be_bytes());
+        dsp[0x08..0x0C].copy_from_slice(&32000u32.to_be_bytes());
+        // Predictor 1 repeats the previous sample
+        dsp[0x20..0x22].copy_from_slice(&2048i16.to_be_bytes());
+        // A step of 1 << 4, then held; a step of -2 << 0
+        dsp.extend_from_slice(&[0x14, 0x10, 0, 0, 0, 0, 0, 0]);
+        dsp.extend_from_slice(&[0x10, 0xE0, 0, 0, 0, 0, 0, 0]);
+
+        let (samples, sample_rate) = decode_dsp(&dsp).unwrap();
+        assert_eq!(sample_rate, 32000);
+        assert_eq!(samples.len(), 16);
+        assert!(samples[..14].iter().all(|&s| s == 16));
+        assert_eq!(samples[14], 14);
+        assert_eq!(samples[15], 14);
+    }
+}
@@ -2,7 +2,7 @@ use randomprime::{
     patches,
     reader_writer,
     structs,
-    patch_config::PatchConfig,
+    patch_config::{CliCommand, PatchConfig},
 };
 
 use clap::Format;
@@ -101,7 +101,13 @@ fn maybe_pause_at_exit()
 
 fn main_inner() -> Result<(), String>
 {
-    let patch_config = PatchConfig::from_cli_options()?;
+    let patch_config = match PatchConfig::from_cli_options()? {
+        CliCommand::Patch(patch_config) => patch_config,
+        CliCommand::PrintSchema => {
+            println!("{}", PatchConfig::json_schema());
+            return Ok(());
+        },
+    };
     let pn = ProgressNotifier::new(patch_config.quiet);
     patches::patch_iso(patch_config, pn)?;
     println!("Done");
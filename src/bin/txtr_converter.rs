@@ -8,6 +8,8 @@ use structs::{Txtr, TxtrFormat, TxtrPaletteFormat};
 use libsquish_wrapper::{compress_dxt1gcn_block, decompress_dxt1gcn_block};
 use reader_writer::{Readable, Reader, Writable};
 
+use randomprime::txtr_conversions::{box_filter_pixels, encode_rgb5a3};
+
 use std::convert::TryInto;
 use std::collections::HashMap;
 use std::fs::File;
@@ -177,34 +179,6 @@ fn png2txtr(input: &Path, output: &Path, mipmap_count: Option<u8>, mut format: T
     Ok(())
 }
 
-fn box_filter_pixels(pixels: &[u8], w: usize, h: usize, chan_count: usize, discretize_alpha: bool)
-    -> Vec<u8>
-{
-    let mut output = Vec::with_capacity(w * h * chan_count / 4);
-    for iy in 0..h / 2 {
-        for ix in 0..w / 2 {
-            let y = iy * 2;
-            let x = ix * 2;
-            for c in 0..chan_count {
-                output.push(((
-                        (pixels[(y * w + x) * chan_count + c] as u16)
-                        + (pixels[(y * w + x + 1) * chan_count + c] as u16)
-                        + (pixels[((y + 1) * w + x ) * chan_count + c] as u16)
-                        + (pixels[((y + 1) * w + x + 1) * chan_count + c] as u16)
-                    ) / 4) as u8
-                );
-                if discretize_alpha && c == chan_count - 1 {
-                    let last = output.last_mut().unwrap();
-                    if *last > 0 {
-                        *last = 0xff;
-                    }
-                }
-            }
-        }
-    }
-    output
-}
-
 // XXX The following conversion functions are borrowed from URDE https://github.com/AxioDL/urde/blob/master/DataSpec/DNACommon/TXTR.cpp
 fn convert3to8(v: u8) -> u8 {
     (v << 5) | (v << 2)| (v >> 1)
@@ -222,20 +196,6 @@ fn convert6to8(v: u8) -> u8 {
     (v << 2) | (v >> 4)
 }
 
-fn encode_rgb5a3(pixel: [u8; 4]) -> [u8; 2] {
-    let v = if pixel[3] == 0xff {
-        0x8000 | ((pixel[0] as u16 >> 3) << 10)
-            | ((pixel[1] as u16 >> 3) << 5)
-            | (pixel[2] as u16 >> 3)
-    } else {
-        ((pixel[0] as u16 >> 4) << 8)
-            | ((pixel[1] as u16 >> 4) << 4)
-            | (pixel[2] as u16 >> 4)
-            | ((pixel[3] as u16 >> 5) << 12)
-    };
-    v.to_be_bytes()
-}
-
 fn encode_rgb565(pixel: [u8; 3]) -> [u8; 2] {
     let v = ((pixel[0] as u16 >> 3) << 11)
             | ((pixel[1] as u16 >> 2) << 5)
@@ -0,0 +1,172 @@
+use std::{
+    cmp::min,
+    io::{self, Read, Seek, Write},
+};
+
+// Format reference
+// https://github.com/blakesmith/beat/blob/master/doc/bps.txt
+
+const SOURCE_READ: u64 = 0;
+const TARGET_READ: u64 = 1;
+const SOURCE_COPY: u64 = 2;
+const TARGET_COPY: u64 = 3;
+
+pub fn is_bps(patch: &[u8]) -> bool
+{
+    patch.starts_with(b"BPS1")
+}
+
+struct PatchReader<'a>
+{
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PatchReader<'a>
+{
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String>
+    {
+        let bytes = self.pos.checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| "Unexpected end of BPS patch".to_string())?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn number(&mut self) -> Result<u64, String>
+    {
+        let mut n: u64 = 0;
+        let mut shift: u64 = 1;
+        loop {
+            let x = self.take(1)?[0] as u64;
+            n = (x & 0x7F).checked_mul(shift)
+                .and_then(|x| n.checked_add(x))
+                .ok_or_else(|| "Malformed number in BPS patch".to_string())?;
+            if x & 0x80 != 0 {
+                return Ok(n);
+            }
+            shift = shift.checked_shl(7).ok_or_else(|| "Malformed number in BPS patch".to_string())?;
+            n += shift;
+        }
+    }
+
+    /// A relative offset, stored as a magnitude with the sign in the lowest bit
+    fn offset(&mut self) -> Result<i64, String>
+    {
+        let n = self.number()?;
+        let magnitude = (n >> 1) as i64;
+        Ok(if n & 1 != 0 { -magnitude } else { magnitude })
+    }
+}
+
+fn apply_offset(base: u64, offset: i64) -> Result<u64, String>
+{
+    let result = if offset < 0 {
+        base.checked_sub(offset.unsigned_abs())
+    } else {
+        base.checked_add(offset as u64)
+    };
+    result.ok_or_else(|| "BPS patch copies from before the start of the file".to_string())
+}
+
+/// Writes the result of applying the BPS `patch` to `source` to `target`, which has to be
+/// empty. The target is read back for TargetCopy actions, so the whole target never has to be
+/// held in memory.
+pub fn apply_bps<W>(patch: &[u8], source: &[u8], target: &mut W) -> Result<(), String>
+    where W: Read + Write + Seek
+{
+    let io_err = |e: io::Error| format!("Failed to write the patched ISO: {}", e);
+
+    if !is_bps(patch) || patch.len() < 4 + 12 {
+        Err("Not a BPS patch")?
+    }
+    let actions_end = patch.len() - 12;
+    let footer = &patch[actions_end..];
+    let crc = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    let mut patch_crc = crc32fast::Hasher::new();
+    patch_crc.update(&patch[..patch.len() - 4]);
+    if patch_crc.finalize() != crc(&footer[8..]) {
+        Err("The BPS patch is corrupt")?
+    }
+    let mut source_crc = crc32fast::Hasher::new();
+    source_crc.update(source);
+    if source_crc.finalize() != crc(&footer[..4]) {
+        Err("The BPS patch wasn't made for this ISO, please use an unmodified copy of the game")?
+    }
+
+    let mut reader = PatchReader { data: &patch[..actions_end], pos: 4 };
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata_size = reader.number()?;
+    reader.take(metadata_size as usize)?;
+    if source_size != source.len() as u64 {
+        Err("The BPS patch wasn't made for this ISO, please use an unmodified copy of the game")?
+    }
+
+    let mut target_crc = crc32fast::Hasher::new();
+    let mut output_offset: u64 = 0;
+    let mut source_relative_offset: u64 = 0;
+    let mut target_relative_offset: u64 = 0;
+    let mut buf = vec![0u8; 0x10000];
+
+    target.seek(io::SeekFrom::Start(0)).map_err(io_err)?;
+    while reader.pos < actions_end {
+        let data = reader.number()?;
+        let len = (data >> 2) + 1;
+        if len > target_size - output_offset {
+            Err("The BPS patch writes past the end of the patched ISO")?
+        }
+
+        match data & 3 {
+            SOURCE_READ => {
+                let bytes = source.get(output_offset as usize..(output_offset + len) as usize)
+                    .ok_or_else(|| "The BPS patch reads past the end of the ISO".to_string())?;
+                target_crc.update(bytes);
+                target.write_all(bytes).map_err(io_err)?;
+            },
+            TARGET_READ => {
+                let bytes = reader.take(len as usize)?;
+                target_crc.update(bytes);
+                target.write_all(bytes).map_err(io_err)?;
+            },
+            SOURCE_COPY => {
+                source_relative_offset = apply_offset(source_relative_offset, reader.offset()?)?;
+                let bytes = source.get(source_relative_offset as usize..(source_relative_offset + len) as usize)
+                    .ok_or_else(|| "The BPS patch reads past the end of the ISO".to_string())?;
+                target_crc.update(bytes);
+                target.write_all(bytes).map_err(io_err)?;
+                source_relative_offset += len;
+            },
+            TARGET_COPY => {
+                target_relative_offset = apply_offset(target_relative_offset, reader.offset()?)?;
+                if target_relative_offset >= output_offset {
+                    Err("The BPS patch copies from a part of the patched ISO that isn't written yet")?
+                }
+
+                // The copy may overlap the bytes it writes (e.g. to repeat a pattern), so only
+                // read what's already been written at each step
+                let mut remaining = len;
+                while remaining > 0 {
+                    let current_offset = output_offset + (len - remaining);
+                    let l = min(min(remaining, current_offset - target_relative_offset), buf.len() as u64) as usize;
+                    target.seek(io::SeekFrom::Start(target_relative_offset)).map_err(io_err)?;
+                    target.read_exact(&mut buf[..l]).map_err(io_err)?;
+                    target.seek(io::SeekFrom::Start(current_offset)).map_err(io_err)?;
+                    target_crc.update(&buf[..l]);
+                    target.write_all(&buf[..l]).map_err(io_err)?;
+                    target_relative_offset += l as u64;
+                    remaining -= l as u64;
+                }
+            },
+            _ => unreachable!(),
+        }
+        output_offset += len;
+    }
+
+    if output_offset != target_size || target_crc.finalize() != crc(&footer[4..8]) {
+        Err("Applying the BPS patch failed, the patched ISO doesn't match its checksum")?
+    }
+    target.flush().map_err(io_err)?;
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use std::io::{self, Write};
+
+// Format reference
+// https://github.com/blakesmith/beat/blob/master/doc/bps.txt
+
+const SOURCE_READ: u64 = 0;
+const TARGET_READ: u64 = 1;
+
+// Runs of matching bytes shorter than this are cheaper to store as part of the surrounding
+// TargetRead than to split it up
+const MIN_SOURCE_READ: usize = 4;
+
+// Changed data is written out in TargetReads of at most about this size, so a large changed
+// region doesn't have to be held in memory
+const MAX_TARGET_READ: usize = 0x10000;
+
+static ZEROES: [u8; 0x8000] = [0; 0x8000];
+
+/// Writes a BPS patch which turns `source` into the data written to it. Only data at the same
+/// offset in the source is reused, which is enough for an ISO as most files stay where they are.
+pub struct BpsWriter<'s, W: Write>
+{
+    file: W,
+    source: &'s [u8],
+    target_size: u64,
+
+    target_offset: u64,
+    source_read_len: u64,
+    target_read: Vec<u8>,
+
+    target_crc: crc32fast::Hasher,
+    patch_crc: crc32fast::Hasher,
+}
+
+impl<'s, W: Write> BpsWriter<'s, W>
+{
+    pub fn new(file: W, source: &'s [u8], target_size: u64) -> io::Result<Self>
+    {
+        let mut writer = BpsWriter {
+            file,
+            source,
+            target_size,
+
+            target_offset: 0,
+            source_read_len: 0,
+            target_read: Vec::new(),
+
+            target_crc: crc32fast::Hasher::new(),
+            patch_crc: crc32fast::Hasher::new(),
+        };
+        writer.write_patch(b"BPS1")?;
+        writer.write_number(source.len() as u64)?;
+        writer.write_number(target_size)?;
+        writer.write_number(0)?; // no metadata
+        Ok(writer)
+    }
+
+    fn write_patch(&mut self, bytes: &[u8]) -> io::Result<()>
+    {
+        self.patch_crc.update(bytes);
+        self.file.write_all(bytes)
+    }
+
+    fn write_number(&mut self, mut n: u64) -> io::Result<()>
+    {
+        let mut bytes = Vec::with_capacity(10);
+        loop {
+            let x = (n & 0x7F) as u8;
+            n >>= 7;
+            if n == 0 {
+                bytes.push(0x80 | x);
+                break;
+            }
+            bytes.push(x);
+            n -= 1;
+        }
+        self.write_patch(&bytes)
+    }
+
+    fn flush_source_read(&mut self) -> io::Result<()>
+    {
+        if self.source_read_len > 0 {
+            self.write_number(((self.source_read_len - 1) << 2) | SOURCE_READ)?;
+            self.source_read_len = 0;
+        }
+        Ok(())
+    }
+
+    fn flush_target_read(&mut self) -> io::Result<()>
+    {
+        if !self.target_read.is_empty() {
+            let bytes = std::mem::take(&mut self.target_read);
+            self.write_number(((bytes.len() as u64 - 1) << 2) | TARGET_READ)?;
+            self.write_patch(&bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Pads the target to the size given to `new` and writes the checksums
+    pub fn finish(mut self) -> io::Result<W>
+    {
+        while self.target_offset < self.target_size {
+            let l = std::cmp::min(self.target_size - self.target_offset, ZEROES.len() as u64);
+            self.write_all(&ZEROES[..l as usize])?;
+        }
+        self.flush_source_read()?;
+        self.flush_target_read()?;
+
+        let mut source_crc = crc32fast::Hasher::new();
+        source_crc.update(self.source);
+        let source_crc = source_crc.finalize();
+        let target_crc = self.target_crc.clone().finalize();
+        self.write_patch(&source_crc.to_le_bytes())?;
+        self.write_patch(&target_crc.to_le_bytes())?;
+        let patch_crc = self.patch_crc.clone().finalize();
+        self.file.write_all(&patch_crc.to_le_bytes())?;
+        self.file.flush()?;
+        Ok(self.file)
+    }
+}
+
+impl<'s, W: Write> Write for BpsWriter<'s, W>
+{
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize>
+    {
+        if self.target_offset + bytes.len() as u64 > self.target_size {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "More data was written than the target size"))?
+        }
+        self.target_crc.update(bytes);
+
+        let start = self.target_offset as usize;
+        let source = self.source.get(start..).unwrap_or(&[]);
+        let mut i = 0;
+        while i < bytes.len() {
+            // Length of the run of bytes which are (or aren't) the same as in the source
+            let matching = source.get(i) == Some(&bytes[i]);
+            let run = bytes[i..].iter()
+                .enumerate()
+                .take_while(|&(j, b)| (source.get(i + j) == Some(b)) == matching)
+                .count();
+
+            // Short matches in the middle of changed data are kept in the TargetRead, the
+            // other ones become a SourceRead
+            if matching && (run >= MIN_SOURCE_READ || self.target_read.is_empty()) {
+                self.flush_target_read()?;
+                self.source_read_len += run as u64;
+            } else {
+                self.flush_source_read()?;
+                self.target_read.extend_from_slice(&bytes[i..i + run]);
+                if self.target_read.len() >= MAX_TARGET_READ {
+                    self.flush_target_read()?;
+                }
+            }
+            i += run;
+        }
+
+        self.target_offset += bytes.len() as u64;
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        self.file.flush()
+    }
+}
+
+impl<'s, W: Write> structs::WriteExt for BpsWriter<'s, W>
+{
+    fn skip_bytes(&mut self, mut bytes: u64) -> io::Result<()>
+    {
+        // Skipped regions read back as zeroes
+        while bytes > 0 {
+            let l = std::cmp::min(bytes, ZEROES.len() as u64);
+            self.write_all(&ZEROES[..l as usize])?;
+            bytes -= l;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::io::Cursor;
+    use crate::bps_reader::apply_bps;
+
+    fn round_trip(source: &[u8], target: &[u8], target_size: u64) -> Vec<u8>
+    {
+        let mut writer = BpsWriter::new(vec![], source, target_size).unwrap();
+        writer.write_all(target).unwrap();
+        let patch = writer.finish().unwrap();
+
+        let mut patched = Cursor::new(vec![]);
+        apply_bps(&patch, source, &mut patched).unwrap();
+        patched.into_inner()
+    }
+
+    #[test]
+    fn patched_iso_matches_what_was_written()
+    {
+        let source: Vec<u8> = (0..0x30000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let mut target = source.clone();
+        target[10..12].copy_from_slice(b"ab"); // shorter than MIN_SOURCE_READ
+        target[0x100..0x200].iter_mut().for_each(|b| *b = !*b);
+        target[0x8000..0x28000].iter_mut().for_each(|b| *b = b.wrapping_add(1)); // > MAX_TARGET_READ
+        target.extend_from_slice(b"past the end of the source");
+
+        assert_eq!(round_trip(&source, &target, target.len() as u64), target);
+    }
+
+    #[test]
+    fn unwritten_target_is_zero_padded()
+    {
+        let source = vec![1u8; 64];
+        let mut expected = source[..32].to_vec();
+        expected.resize(100, 0);
+
+        assert_eq!(round_trip(&source, &source[..32], 100), expected);
+    }
+
+    #[test]
+    fn writing_past_the_target_size_fails()
+    {
+        let source = vec![1u8; 64];
+        let mut writer = BpsWriter::new(vec![], &source, 16).unwrap();
+        assert!(writer.write_all(&source[..16]).is_ok());
+        assert!(writer.write_all(&[0]).is_err());
+    }
+}
@@ -65,57 +65,6 @@ impl<'a> CbMessage<'a>
 }
 
 
-struct ProgressNotifier
-{
-    total_size: usize,
-    bytes_so_far: usize,
-    cb_data: *const (),
-    cb: extern fn(*const (), *const c_char)
-}
-
-impl ProgressNotifier
-{
-    fn new(cb_data: *const (), cb: extern fn(*const (), *const c_char))
-        -> ProgressNotifier
-    {
-        ProgressNotifier {
-            total_size: 0,
-            bytes_so_far: 0,
-            cb, cb_data
-        }
-    }
-}
-
-impl structs::ProgressNotifier for ProgressNotifier
-{
-    fn notify_total_bytes(&mut self, total_size: usize)
-    {
-        self.total_size = total_size
-    }
-
-    fn notify_writing_file(&mut self, file_name: &reader_writer::CStr, file_bytes: usize)
-    {
-        let percent = self.bytes_so_far as f64 / self.total_size as f64 * 100.;
-        let msg = format!("Writing file {:?}", file_name);
-        (self.cb)(self.cb_data, CbMessage::progress_json(percent, &msg).as_ptr());
-        self.bytes_so_far += file_bytes;
-    }
-
-    fn notify_writing_header(&mut self)
-    {
-        let percent = self.bytes_so_far as f64 / self.total_size as f64 * 100.;
-        (self.cb)(self.cb_data, CbMessage::progress_json(percent, "Writing ISO header").as_ptr());
-    }
-
-    fn notify_flushing_to_disk(&mut self)
-    {
-        (self.cb)(
-            self.cb_data,
-            CbMessage::progress_json(100., "Flushing written data to the disk").as_ptr(),
-        );
-    }
-}
-
 fn inner(config_json: *const c_char, cb_data: *const (), cb: extern fn(*const (), *const c_char))
     -> Result<(), String>
 {
@@ -124,8 +73,9 @@ fn inner(config_json: *const c_char, cb_data: *const (), cb: extern fn(*const ()
 
     let patch_config = PatchConfig::from_json(config_json)?;
 
-    let pn = ProgressNotifier::new(cb_data, cb);
-    patches::patch_iso(patch_config, pn)?;
+    patches::patch_iso_with_progress(patch_config, |update| {
+        (cb)(cb_data, CbMessage::progress_json(update.percent, &update.message()).as_ptr());
+    })?;
     Ok(())
 }
 
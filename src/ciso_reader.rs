@@ -0,0 +1,48 @@
+use reader_writer::byteorder::{ByteOrder, LittleEndian};
+
+use std::cmp::max;
+
+// https://github.com/dolphin-emu/dolphin/blob/8f460a1cda1a4d4208c4da9e01bf775f5f704498/Source/Core/DiscIO/CISOBlob.cpp
+
+const HEADER_SIZE: usize = 0x8000;
+
+pub fn is_ciso(data: &[u8]) -> bool
+{
+    data.len() >= HEADER_SIZE && &data[0..4] == b"CISO"
+}
+
+/// Expands an entire CISO image into an anonymous memory map. Blocks that are absent from the
+/// block map are left zeroed.
+pub fn decompress_ciso(data: &[u8]) -> Result<memmap::Mmap, String>
+{
+    if !is_ciso(data) {
+        Err("Not a CISO file".to_string())?
+    }
+
+    let block_size = LittleEndian::read_u32(&data[4..8]) as usize;
+    if block_size == 0 {
+        Err("CISO header is corrupt".to_string())?
+    }
+    let blocks_map = &data[8..HEADER_SIZE];
+
+    // The block map is padded with zeroes, so the disc ends after the last used block
+    let num_blocks = blocks_map.iter().rposition(|b| *b != 0).map(|i| i + 1).unwrap_or(0);
+    let data_size = max(num_blocks * block_size, structs::GC_DISC_LENGTH);
+
+    let mut output = memmap::MmapMut::map_anon(data_size)
+        .map_err(|e| format!("Failed to allocate memory for CISO decompression: {}", e))?;
+
+    let mut file_offset = HEADER_SIZE;
+    for (i, used) in blocks_map[..num_blocks].iter().enumerate() {
+        if *used == 0 {
+            continue;
+        }
+        let len = std::cmp::min(block_size, data.len().saturating_sub(file_offset));
+        let out_start = i * block_size;
+        output[out_start..out_start + len].copy_from_slice(&data[file_offset..file_offset + len]);
+        file_offset += block_size;
+    }
+
+    output.make_read_only()
+        .map_err(|e| format!("Failed to decompress CISO: {}", e))
+}
@@ -7,21 +7,26 @@ use reader_writer::{
 use structs::{res_id, ResId, Resource, ResourceKind};
 
 use crate::{
-    patch_config::{Version, PatchConfig, GenericTexture},
+    patch_config::{Version, PatchConfig, GenericTexture, LevelConfig, LocalizedText},
     elevators::{World, SpawnRoomData},
     pickup_meta::{self, PickupType, PickupModel},
     door_meta::{DoorType, BlastShieldType},
     ResourceData,
     GcDiscLookupExtensions,
-    extern_assets::ExternPickupModel,
+    extern_assets::{ExternAsset, ExternPickupModel},
     patches::WaterType,
+    text_layout::{FontMetrics, TextBox, HUDMEMO_BOX, SCAN_BOX, SCAN_PAGE_BOX},
+    txtr_conversions::rgba8_to_txtr,
 };
 
 use std::{
     borrow::Cow,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
 };
 
+// Logbook categories: 1 Pirate Data, 2 Chozo Lore, 3 Creatures, 4 Research, 5 Artifacts
+const LOGBOOK_CATEGORY_RESEARCH: u32 = 4;
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct PickupHashKey {
     pub level_id: u32,
@@ -105,6 +110,14 @@ pub mod custom_asset_ids {
         GENERIC_WARP_STRG: STRG,
         WARPING_TO_START_DELAY_STRG: STRG,
         WARPING_TO_OTHER_STRG: STRG,
+        WARP_TO_START_CONFIRM_STRG: STRG,
+
+        // Cutscene skip prompt
+        CUTSCENE_SKIP_PROMPT_STRG: STRG,
+
+        // Objective markers
+        OBJECTIVE_HINT_STRG: STRG,
+        OBJECTIVE_MAP_STRG: STRG,
 
         // Blocks
         BLOCK_COLOR_0: CMDL,
@@ -372,13 +385,8 @@ pub fn build_resource_raw<'r>(file_id: u32, kind: ResourceKind<'r>) -> Resource<
 }
 
 // Assets defined in an external file at RUNTIME
-fn extern_assets_runtime<'r>(extern_assets_dir: Option<String>)
- -> Result<
-     (Vec<Resource<'r>>, HashMap<String, ExternPickupModel>),
-     String>
+fn extern_assets_runtime<'r>(extern_assets: &HashMap<u32, ExternAsset>) -> Vec<Resource<'r>>
 {
-    let (extern_models, extern_assets) = ExternPickupModel::parse(&extern_assets_dir.clone().unwrap())?;
-
     let mut resources = Vec::<Resource<'r>>::new();
     for (id, asset) in extern_assets.iter() {
         let resource = ResourceKind::External(asset.bytes.clone(), asset.fourcc);
@@ -387,7 +395,7 @@ fn extern_assets_runtime<'r>(extern_assets_dir: Option<String>)
         );
     }
 
-    Ok((resources, extern_models))
+    resources
 }
 
 macro_rules! extern_asset {
@@ -512,9 +520,13 @@ pub fn custom_assets<'r>(
     resources: &HashMap<(u32, FourCC),
     structs::Resource<'r>>,
     starting_memo: Option<&str>,
+    level_data: &BTreeMap<String, LevelConfig>,
     pickup_hudmemos: &mut HashMap::<PickupHashKey, ResId<res_id::STRG>>,
     pickup_scans: &mut HashMap<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>,
     extra_scans: &mut HashMap<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>,
+    door_scans: &mut HashMap<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>,
+    extern_assets: &HashMap<u32, ExternAsset>,
+    font: Option<&FontMetrics>,
     config: &PatchConfig,
 )
 ->
@@ -524,7 +536,6 @@ pub fn custom_assets<'r>(
         Vec<ResId<res_id::SCAN>>,
         Vec<Vec<ResId<res_id::SCAN>>>,
         HashMap::<u32, u32>,
-        HashMap<String, ExternPickupModel>,
     ),
     String>
 {
@@ -553,18 +564,15 @@ pub fn custom_assets<'r>(
     */
     let mut string_to_scan_strg: HashMap::<String, (ResId<res_id::SCAN>, ResId<res_id::STRG>)> = HashMap::new();
 
+    /* Same as above, but for blast shield scans which are formatted differently */
+    let mut blast_shield_string_to_scan_strg: HashMap::<String, (ResId<res_id::SCAN>, ResId<res_id::STRG>)> = HashMap::new();
+
     /* Mapping of SCAN id to logbook category for easier SAVW entry creation */
     let mut savw_scan_logbook_category: HashMap::<u32, u32> = HashMap::new();
 
     // External assets
     let mut assets = extern_assets_compile_time();
-    let extern_models = if config.extern_assets_dir.is_some() {
-        let (more_assets, extern_models) = extern_assets_runtime(config.extern_assets_dir.clone())?;
-        assets.extend_from_slice(&more_assets);
-        extern_models // HashMap of extern models available for use
-    } else {
-        HashMap::<String, ExternPickupModel>::new() // empty hashmap (no models available)
-    };
+    assets.extend_from_slice(&extern_assets_runtime(extern_assets));
     // Custom pickup model assets
     assets.extend_from_slice(&create_nothing_icon_cmdl_and_ancs(
         resources,
@@ -651,7 +659,7 @@ pub fn custom_assets<'r>(
     if starting_memo.is_some() {
         assets.push(build_resource(
             custom_asset_ids::STARTING_ITEMS_HUDMEMO_STRG,
-            structs::ResourceKind::Strg(structs::Strg::from_strings(vec![
+            structs::ResourceKind::Strg(strg_from_strings(config.version, vec![
                 format!("&just=center;{}\0", starting_memo.clone().unwrap()),
             ])),
         ));
@@ -672,7 +680,7 @@ pub fn custom_assets<'r>(
 
         assets.push(build_resource(
             pt.hudmemo_strg(),
-            structs::ResourceKind::Strg(structs::Strg::from_strings(vec![
+            structs::ResourceKind::Strg(strg_from_strings(config.version, vec![
                 format!("&just=center;{} acquired!\0", name),
             ])),
         ));
@@ -680,7 +688,7 @@ pub fn custom_assets<'r>(
 
     // Create user-defined hudmemo and scan strings and map to locations //
     let mut custom_asset_offset = 0;
-    for (level_name, level) in config.level_data.iter() {
+    for (level_name, level) in level_data.iter() {
         let world = World::from_json_key(level_name);
         for (room_name, room) in level.rooms.iter() {
             let mut pickup_idx = 0;
@@ -690,8 +698,11 @@ pub fn custom_assets<'r>(
                 for custom_scan in room.extra_scans.as_ref().unwrap().iter() {
                     let contents = &custom_scan.text;
 
+                    // Scans with their own pages or images aren't shared
+                    let shareable = custom_scan.pages.is_none() && custom_scan.images.is_none();
+
                     // Check if this string already has a scan_id //
-                    if string_to_scan_strg.contains_key(contents) {
+                    if shareable && string_to_scan_strg.contains_key(contents) {
                         let (scan_id, strg_id) = string_to_scan_strg.get(contents).unwrap();
 
                         // Add this scan_id as a dep of this world if it wasn't already //
@@ -734,7 +745,15 @@ pub fn custom_assets<'r>(
 
                     let mut category = false;
                     const PAGINATION_SIZE: usize = 123;
-                    while content_len > PAGINATION_SIZE {
+                    if let Some(pages) = custom_scan.pages.as_ref() {
+                        strings.push(contents.clone());
+                        strings.push("\0".to_string()); // logbook category
+                        strings.extend(pages.iter().map(|page| format!("{}\0", page)));
+                        category = true;
+                        content_len = 0;
+                    }
+                    // Without the font's metrics, fall back to splitting pages by length
+                    while font.is_none() && content_len > PAGINATION_SIZE {
                         let mut i = PAGINATION_SIZE - 1;
                         while contents.chars().nth(i).unwrap_or(' ') != ' ' {
                             i -= 1;
@@ -762,32 +781,81 @@ pub fn custom_assets<'r>(
                         strings.push("\0".to_string()); // logbook category
                     }
 
-                    if custom_scan.logbook_title.is_some() || custom_scan.logbook_category.is_some() {
-                        if !custom_scan.logbook_title.is_some() || !custom_scan.logbook_category.is_some() {
-                            panic!("Both logbook title and logbook category are required.");
+                    // Scans which aren't in the logbook can't be read again once the player has
+                    // left the room
+                    let logbook = custom_scan.logbook.unwrap_or(
+                        custom_scan.logbook_title.is_some() || custom_scan.logbook_category.is_some()
+                    );
+                    let mut logbook_category = 0;
+                    if logbook {
+                        logbook_category = custom_scan.logbook_category.unwrap_or(LOGBOOK_CATEGORY_RESEARCH);
+                        if !(1..=5).contains(&logbook_category) {
+                            Err(format!("Invalid logbook category {} for scan in {}, expected 1 to 5", logbook_category, room_name))?
                         }
-                        strings[1] = custom_scan.logbook_title.clone().unwrap() + "\0";
-                        savw_scan_logbook_category.insert(scan_id.to_u32(), custom_scan.logbook_category.clone().unwrap());
+                        let title = custom_scan.logbook_title.clone().unwrap_or_else(|| room_name.to_string());
+                        strings[1] = title + "\0";
+                        savw_scan_logbook_category.insert(scan_id.to_u32(), logbook_category);
                     }
 
-                    assets.extend_from_slice(
-                        &create_item_scan_strg_pair_2(
+                    // Red scans are meant to be read, so credit them like any other logbook entry
+                    if is_red == 1 && custom_scan.logbook.is_none() && logbook_category == 0 {
+                        if let Some(category) = config.red_scans_logbook_category {
+                            strings[1] = format!("{}\0", room_name);
+                            savw_scan_logbook_category.insert(scan_id.to_u32(), category);
+                            logbook_category = category;
+                        }
+                    }
+
+                    let mut scan_strg_pair = create_item_scan_strg_pair_2(
                         scan_id,
                         strg_id,
                         strings,
                         is_red,
-                        *custom_scan.logbook_category.as_ref().unwrap_or(&0),
+                        logbook_category,
                         config.version,
-                        )
                     );
 
+                    let images = custom_scan.images.as_deref().unwrap_or(&[]);
+                    if images.len() > 4 {
+                        Err(format!("Scans can only have 4 images, found {} in {}", images.len(), room_name))?
+                    }
+                    for (i, image_config) in images.iter().enumerate() {
+                        let txtr_id = ResId::<res_id::TXTR>::new(custom_asset_ids::EXTRA_IDS_START.to_u32() + custom_asset_offset);
+                        custom_asset_offset = custom_asset_offset + 1;
+
+                        let png = std::fs::read(&image_config.path)
+                            .map_err(|e| format!("Failed to read scan image '{}': {}", image_config.path, e))?;
+                        let image = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+                            .map_err(|e| format!("Failed to decode scan image '{}': {}", image_config.path, e))?
+                            .into_rgba8();
+                        let (width, height, pixels) = crate::gltf_import::texture_pixels(image);
+                        assets.push(build_resource(
+                            txtr_id,
+                            structs::ResourceKind::External(rgba8_to_txtr(&pixels, width, height), b"TXTR".into()),
+                        ));
+
+                        let scan = scan_strg_pair[0].kind.as_scan_mut().unwrap();
+                        scan.images.as_mut_slice()[i] = structs::ScanImage {
+                            txtr: txtr_id,
+                            appearance_percent: image_config.appear_percent.unwrap_or(0.25 * (i + 1) as f32),
+                            image_position: image_config.pane as u32,
+                            width: 0,
+                            height: 0,
+                            interval: 0.0,
+                            fade_duration: 0.0,
+                        };
+                    }
+                    assets.extend_from_slice(&scan_strg_pair);
+
                     // Map for easy lookup when patching //
                     let key = PickupHashKey::from_location(level_name, room_name, extra_scans_idx);
                     extra_scans.insert(key, (scan_id, strg_id));
                     local_savw_scans_to_add[world as usize].push(scan_id);
 
                     // Cache this scan/strg pair for re-use //
-                    string_to_scan_strg.insert(contents, (scan_id, strg_id));
+                    if shareable {
+                        string_to_scan_strg.insert(contents, (scan_id, strg_id));
+                    }
 
                     extra_scans_idx = extra_scans_idx + 1;
                 }
@@ -841,6 +909,55 @@ pub fn custom_assets<'r>(
                 }
             }
 
+            if room.doors.is_some() {
+                for (dock_num, door) in room.doors.as_ref().unwrap().iter() {
+                    if door.blast_shield_scan_text.is_none() { continue; }
+
+                    let string = format!("{}\0", door.blast_shield_scan_text.as_ref().unwrap());
+                    let key = PickupHashKey::from_location(level_name, room_name, *dock_num);
+
+                    // Check if this string already has a scan_id //
+                    if blast_shield_string_to_scan_strg.contains_key(&string) {
+                        let (scan_id, strg_id) = blast_shield_string_to_scan_strg.get(&string).unwrap();
+
+                        // Add this scan_id as a dep of this world if it wasn't already //
+                        if !local_savw_scans_to_add[world as usize].contains(scan_id) {
+                            local_savw_scans_to_add[world as usize].push(scan_id.clone());
+                        }
+
+                        door_scans.insert(key, (scan_id.clone(), strg_id.clone()));
+                        continue;
+                    }
+
+                    // Get next 2 IDs //
+                    let scan_id = ResId::<res_id::SCAN>::new(custom_asset_ids::EXTRA_IDS_START.to_u32() + custom_asset_offset);
+                    custom_asset_offset = custom_asset_offset + 1;
+                    let strg_id = ResId::<res_id::STRG>::new(custom_asset_ids::EXTRA_IDS_START.to_u32() + custom_asset_offset);
+                    custom_asset_offset = custom_asset_offset + 1;
+
+                    // Formatted the same as the built-in blast shield scans
+                    assets.extend_from_slice(&create_item_scan_strg_pair_2(
+                        scan_id,
+                        strg_id,
+                        vec![
+                            "Analysis complete.\0".to_string(),
+                            "\0".to_string(),
+                            string.clone(),
+                        ],
+                        1,
+                        0,
+                        config.version,
+                    ));
+                    local_savw_scans_to_add[world as usize].push(scan_id);
+
+                    // Map for easy lookup when patching //
+                    door_scans.insert(key, (scan_id, strg_id));
+
+                    // Cache this scan/strg pair for re-use //
+                    blast_shield_string_to_scan_strg.insert(string, (scan_id, strg_id));
+                }
+            }
+
             if room.hudmemos.is_some() {
                 for hudmemo_config in room.hudmemos.as_ref().unwrap().iter() {
                     if hudmemo_config.text.is_none() {
@@ -906,16 +1023,12 @@ pub fn custom_assets<'r>(
                     custom_asset_offset = custom_asset_offset + 1;
 
                     // Build resource //
-                    let strg = structs::ResourceKind::Strg(structs::Strg {
-                        string_tables: vec![
-                            structs::StrgStringTable {
-                                lang: b"ENGL".into(),
-                                strings: vec![format!("&just=center;{}\u{0}",
-                                                        hudmemo_text).into()].into(),
-                            },
-                        ].into(),
-                    });
-                    let resource = build_resource(strg_id, strg);
+                    let mut strg = strg_from_strings(
+                        config.version,
+                        vec![format!("&just=center;{}\u{0}", hudmemo_text.default_text())],
+                    );
+                    localize_strg(&mut strg, hudmemo_text, |text| format!("&just=center;{}\u{0}", text));
+                    let resource = build_resource(strg_id, structs::ResourceKind::Strg(strg));
                     assets.push(resource);
 
                     // Map for easy lookup when patching //
@@ -927,10 +1040,11 @@ pub fn custom_assets<'r>(
                 if pickup.scan_text.is_some()
                 {
                     let scan_text = pickup.scan_text.as_ref().unwrap();
+                    let scan_text_key = localized_text_key(scan_text);
 
                     // Check if this string already has a scan_id //
-                    if string_to_scan_strg.contains_key(scan_text) {
-                        let (scan_id, strg_id) = string_to_scan_strg.get(scan_text).unwrap();
+                    if string_to_scan_strg.contains_key(&scan_text_key) {
+                        let (scan_id, strg_id) = string_to_scan_strg.get(&scan_text_key).unwrap();
 
                         // Add this scan_id as a dep of this world if it wasn't already //
                         if !local_savw_scans_to_add[world as usize].contains(scan_id) {
@@ -950,26 +1064,28 @@ pub fn custom_assets<'r>(
                         custom_asset_offset = custom_asset_offset + 1;
 
                         // Build resource //
-                        if room_name.trim().to_lowercase() == "research core" // make the research core scan red because it goes on the terminal
+                        let mut pair = if room_name.trim().to_lowercase() == "research core" // make the research core scan red because it goes on the terminal
                         {
-                            assets.extend_from_slice(&create_item_scan_strg_pair_2(
+                            create_item_scan_strg_pair_2(
                                 scan_id,
                                 strg_id,
-                                vec![format!("{}\0", scan_text)],
+                                vec![format!("{}\0", scan_text.default_text())],
                                 1,
                                 0,
                                 config.version,
-                            ));
+                            )
                         }
                         else
                         {
-                            assets.extend_from_slice(&create_item_scan_strg_pair(
+                            create_item_scan_strg_pair(
                                 scan_id,
                                 strg_id,
-                                format!("{}\0", scan_text),
+                                format!("{}\0", scan_text.default_text()),
                                 config.version,
-                            ));
-                        }
+                            )
+                        };
+                        localize_strg(pair[1].kind.as_strg_mut().unwrap(), scan_text, |text| format!("{}\0", text));
+                        assets.extend_from_slice(&pair);
 
                         // Map for easy lookup when patching //
                         let key = PickupHashKey::from_location(level_name, room_name, pickup_idx);
@@ -977,7 +1093,7 @@ pub fn custom_assets<'r>(
                         local_savw_scans_to_add[world as usize].push(scan_id);
 
                         // Cache this scan/strg pair for re-use //
-                        string_to_scan_strg.insert(scan_text.to_string(), (scan_id, strg_id));
+                        string_to_scan_strg.insert(scan_text_key, (scan_id, strg_id));
                     }
                 }
 
@@ -989,14 +1105,14 @@ pub fn custom_assets<'r>(
     // Warping to starting area
     assets.push(build_resource(
         custom_asset_ids::WARPING_TO_START_STRG,
-        structs::ResourceKind::Strg(structs::Strg::from_strings(vec![
+        structs::ResourceKind::Strg(strg_from_strings(config.version, vec![
             "&just=center;Returning to starting room...\0".to_string().to_owned(),
         ])),
     ));
 
     assets.push(build_resource(
         custom_asset_ids::GENERIC_WARP_STRG,
-        structs::ResourceKind::Strg(structs::Strg::from_strings(vec![
+        structs::ResourceKind::Strg(strg_from_strings(config.version, vec![
             "&just=center;Warping...\0".to_string().to_owned(),
         ])),
     ));
@@ -1007,16 +1123,40 @@ pub fn custom_assets<'r>(
     }
     assets.push(build_resource(
         custom_asset_ids::WARPING_TO_START_DELAY_STRG,
-        structs::ResourceKind::Strg(structs::Strg::from_strings(vec![
+        structs::ResourceKind::Strg(strg_from_strings(config.version, vec![
             format!("&just=center;Warping in {}s...\0", warp_to_start_delay_s as u32).to_owned(),
         ])),
     ));
     assets.push(build_resource(
         custom_asset_ids::WARPING_TO_OTHER_STRG,
-        structs::ResourceKind::Strg(structs::Strg::from_strings(vec![
+        structs::ResourceKind::Strg(strg_from_strings(config.version, vec![
             format!("&just=center;Warping in 6s...\0").to_owned(),
         ])),
     ));
+    assets.push(build_resource(
+        custom_asset_ids::WARP_TO_START_CONFIRM_STRG,
+        structs::ResourceKind::Strg(strg_from_strings(config.version, vec![
+            "&just=center;Refuse to save again to return to the starting room\0".to_string().to_owned(),
+        ])),
+    ));
+    assets.push(build_resource(
+        custom_asset_ids::CUTSCENE_SKIP_PROMPT_STRG,
+        structs::ResourceKind::Strg(strg_from_strings(config.version, vec![
+            "&just=center;Press START to skip\0".to_string(),
+        ])),
+    ));
+    assets.push(build_resource(
+        custom_asset_ids::OBJECTIVE_HINT_STRG,
+        structs::ResourceKind::Strg(strg_from_strings(config.version, vec![
+            "&just=center;Your objectives have been marked on the map\0".to_string(),
+        ])),
+    ));
+    assets.push(build_resource(
+        custom_asset_ids::OBJECTIVE_MAP_STRG,
+        structs::ResourceKind::Strg(strg_from_strings(config.version, vec![
+            "Objective\0".to_string(),
+        ])),
+    ));
 
     // Custom block asset
     for gt in GenericTexture::iter() {
@@ -1115,7 +1255,7 @@ pub fn custom_assets<'r>(
     }
 
 
-    Ok((assets, global_savw_scans_to_add, local_savw_scans_to_add, savw_scan_logbook_category, extern_models))
+    Ok((assets, global_savw_scans_to_add, local_savw_scans_to_add, savw_scan_logbook_category))
 }
 
 // When modifying resources in an MREA, we need to give the room a copy of the resources/
@@ -1123,6 +1263,7 @@ pub fn custom_assets<'r>(
 pub fn collect_game_resources<'r>(
     gc_disc: &structs::GcDisc<'r>,
     starting_memo: Option<&str>,
+    level_data: &BTreeMap<String, LevelConfig>,
     config: &PatchConfig,
 )
     ->
@@ -1132,6 +1273,7 @@ pub fn collect_game_resources<'r>(
         HashMap<PickupHashKey, ResId<res_id::STRG>>,
         HashMap<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>,
         HashMap<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>,
+        HashMap<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>,
         Vec<ResId<res_id::SCAN>>,
         Vec<Vec<ResId<res_id::SCAN>>>,
         HashMap::<u32, u32>,
@@ -1215,6 +1357,37 @@ pub fn collect_game_resources<'r>(
     ];
     looking_for.extend(custom_scan_point_deps);
 
+    // Models in extern_assets_dir may use textures etc. from the disc. The ones which come from //
+    // extern_assets_dir are added to the output resource pool below                          //
+    let (extern_models, extern_assets) = match config.extern_assets_dir.as_ref() {
+        Some(extern_assets_dir) => ExternPickupModel::parse(extern_assets_dir)?,
+        None => (HashMap::new(), HashMap::new()), // no models available
+    };
+    // They aren't required, a model whose dependencies can't all be found falls back to the
+    // pickup type's own model, see resolve_pickup_model
+    let mut optional = HashSet::<_>::new();
+    for model in extern_models.values() {
+        optional.extend(model.dependencies.iter().cloned());
+    }
+
+    // Audio groups of custom pickup sound effects //
+    for level in level_data.values() {
+        for room in level.rooms.values() {
+            for pickup in room.pickups.iter().flatten() {
+                let audio_group = pickup.sound_effect.as_ref().and_then(|sound_effect| sound_effect.audio_group);
+                if let Some(audio_group) = audio_group {
+                    looking_for.insert((audio_group, FourCC::from_bytes(b"AGSC")));
+                }
+            }
+        }
+    }
+    for sounds in config.pickup_sounds.values() {
+        let audio_group = sounds.sound_effect.as_ref().and_then(|sound_effect| sound_effect.audio_group);
+        if let Some(audio_group) = audio_group {
+            looking_for.insert((audio_group, FourCC::from_bytes(b"AGSC")));
+        }
+    }
+
     if config.enable_ice_traps {
         let player_freeze_deps: Vec<(u32, FourCC)> = vec![
             resource_info!("breakFreezeVisor.PART").into(),
@@ -1250,7 +1423,7 @@ pub fn collect_game_resources<'r>(
         for res in pak.resources.iter() {
             // If this resource is a dependency needed by the patcher, add the resource to the output list //
             let key = (res.file_id, res.fourcc());
-            if looking_for.remove(&key) {
+            if looking_for.remove(&key) || optional.remove(&key) {
                 found.insert(key, res.into_owned());
             }
         }
@@ -1260,11 +1433,22 @@ pub fn collect_game_resources<'r>(
     let mut pickup_hudmemos = HashMap::<PickupHashKey, ResId<res_id::STRG>>::new();
     let mut pickup_scans = HashMap::<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>::new();
     let mut extra_scans = HashMap::<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>::new();
+    let mut door_scans = HashMap::<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>::new();
 
     // Remove extra assets from dependency search since they won't appear     //
     // in any pak. Instead add them to the output resource pool. These assets //
     // are provided as external files checked into the repository.            //
-    let (custom_assets, global_savw_scans_to_add, local_savw_scans_to_add, savw_scan_logbook_category, extern_models) = custom_assets(&found, starting_memo, &mut pickup_hudmemos, &mut pickup_scans, &mut extra_scans, config)?;
+    let font = match FontMetrics::from_disc(gc_disc) {
+        Ok(font) => Some(font),
+        Err(e) => {
+            println!("Warning, custom text won't be wrapped: {}", e);
+            None
+        },
+    };
+    let (mut custom_assets, global_savw_scans_to_add, local_savw_scans_to_add, savw_scan_logbook_category) = custom_assets(&found, starting_memo, level_data, &mut pickup_hudmemos, &mut pickup_scans, &mut extra_scans, &mut door_scans, &extern_assets, font.as_ref(), config)?;
+    if let Some(font) = font.as_ref() {
+        wrap_custom_text(&mut custom_assets, font, &pickup_hudmemos, &pickup_scans, &extra_scans, &door_scans);
+    }
     for res in custom_assets.iter() {
         let key = (res.file_id, res.fourcc());
         looking_for.remove(&key);
@@ -1275,7 +1459,84 @@ pub fn collect_game_resources<'r>(
         panic!("error - still looking for {:?}", looking_for);
     }
 
-    Ok((found, pickup_hudmemos, pickup_scans, extra_scans, global_savw_scans_to_add, local_savw_scans_to_add, savw_scan_logbook_category, extern_models, custom_assets))
+    Ok((found, pickup_hudmemos, pickup_scans, extra_scans, door_scans, global_savw_scans_to_add, local_savw_scans_to_add, savw_scan_logbook_category, extern_models, custom_assets))
+}
+
+/// Breaks the text of custom hudmemos and scans into lines which fit their text box, splitting
+/// scans across as many pages as they need, and warns about any text which is still too long
+fn wrap_custom_text(
+    custom_assets: &mut [structs::Resource],
+    font: &FontMetrics,
+    pickup_hudmemos: &HashMap<PickupHashKey, ResId<res_id::STRG>>,
+    pickup_scans: &HashMap<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>,
+    extra_scans: &HashMap<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>,
+    door_scans: &HashMap<PickupHashKey, (ResId<res_id::SCAN>, ResId<res_id::STRG>)>,
+)
+{
+    let hudmemo_strgs: HashSet<u32> = pickup_hudmemos.values().map(|strg| strg.to_u32()).collect();
+    let scan_strgs: HashSet<u32> = pickup_scans.values()
+        .chain(extra_scans.values())
+        .chain(door_scans.values())
+        .map(|(_, strg)| strg.to_u32())
+        .collect();
+
+    let warn = |text_box: &TextBox, text: &str| {
+        println!("Warning, {} text doesn't fit on screen: '{}'", text_box.name, text.trim_end_matches('\0'));
+    };
+
+    for res in custom_assets.iter_mut() {
+        let is_scan = if hudmemo_strgs.contains(&res.file_id) {
+            false
+        } else if scan_strgs.contains(&res.file_id) {
+            true
+        } else {
+            continue;
+        };
+        let strg = match res.kind.as_strg_mut() {
+            Some(strg) => strg,
+            None => continue,
+        };
+
+        for table in strg.string_tables.as_mut_vec() {
+            let is_english = table.lang == b"ENGL".into();
+
+            if !is_scan {
+                for string in table.strings.as_mut_vec() {
+                    let string = string.as_mut_string();
+                    let (wrapped, fits) = font.layout(string, &HUDMEMO_BOX);
+                    if !fits && is_english {
+                        warn(&HUDMEMO_BOX, string);
+                    }
+                    *string = wrapped;
+                }
+                continue;
+            }
+
+            // A scan's first page is followed by its logbook title, then by the other pages
+            let mut strings: Vec<String> = table.strings.as_mut_vec().iter_mut()
+                .map(|string| string.as_mut_string().clone())
+                .collect();
+            let title = if strings.len() > 1 { strings.remove(1) } else { "\0".to_string() };
+
+            let mut pages = vec![];
+            for string in strings.iter() {
+                let (string_pages, fits) = font.paginate(string, &SCAN_PAGE_BOX);
+                let lines: usize = string_pages.iter().map(|page| page.split('\n').count()).sum();
+                if (!fits || lines > SCAN_BOX.max_lines) && is_english {
+                    warn(&SCAN_BOX, string);
+                }
+                pages.extend(string_pages);
+            }
+
+            if pages.len() > 1 {
+                pages.insert(1, title);
+            }
+            table.strings = pages.into_iter()
+                .map(|page| page.into())
+                .collect::<Vec<_>>()
+                .into();
+        }
+    }
 }
 
 fn create_custom_block_cmdl<'r>(
@@ -1725,6 +1986,31 @@ fn create_ice_trap_icon_ancs<'r>(
     [resources[&resource_info!("new_ice_parasite_bound.CMDL").into()].clone(), new_suit_ancs]
 }
 
+/// Replaces the first string of each of `strg`'s tables with the text for its language, if any
+fn localize_strg<F>(strg: &mut structs::Strg, text: &LocalizedText, format: F)
+    where F: Fn(&str) -> String
+{
+    for table in strg.string_tables.as_mut_vec().iter_mut() {
+        let lang = String::from_utf8_lossy(table.lang.as_bytes()).to_string();
+        if let Some(text) = text.get(&lang) {
+            table.strings.as_mut_vec()[0] = format(text).into();
+        }
+    }
+}
+
+/// Identifies the text of a scan, so the same text can share a SCAN/STRG pair. Per-language text
+/// is keyed by all of its languages, in order.
+fn localized_text_key(text: &LocalizedText) -> String
+{
+    match text {
+        LocalizedText::All(text) => text.clone(),
+        LocalizedText::PerLanguage(texts) => {
+            let texts: BTreeMap<_, _> = texts.iter().collect();
+            format!("{:?}", texts)
+        },
+    }
+}
+
 fn create_item_scan_strg_pair<'r>(
     new_scan: ResId<res_id::SCAN>,
     new_strg: ResId<res_id::STRG>,
@@ -1795,16 +2081,27 @@ fn create_item_scan_strg_pair_2<'r>(
         }),
     );
 
-    let kind = if version == Version::Pal {
-        structs::ResourceKind::Strg(structs::Strg::from_strings_pal(contents))
-    } else {
-        structs::ResourceKind::Strg(structs::Strg::from_strings(contents))
-    };
-
     let strg = build_resource(
         new_strg,
-        kind,
+        structs::ResourceKind::Strg(strg_from_strings(version, contents)),
     );
 
     [scan, strg]
 }
+
+/// A STRG for generated text, with a table for each language the game can be played in, so it
+/// doesn't fall back to another language's table
+fn strg_from_strings<'r>(version: Version, strings: Vec<String>) -> structs::Strg<'r>
+{
+    match version {
+        Version::Pal | Version::PalTrilogy => structs::Strg::from_strings_pal(strings),
+        Version::NtscJ | Version::NtscJTrilogy => {
+            let mut strg = structs::Strg::from_strings(strings);
+            let mut japanese = strg.string_tables.as_mut_vec()[0].clone();
+            japanese.lang = b"JAPN".into();
+            strg.string_tables.as_mut_vec().push(japanese);
+            strg
+        },
+        _ => structs::Strg::from_strings(strings),
+    }
+}
@@ -274,6 +274,21 @@ impl<'a> DolPatcher<'a>
     }
 
 
+    /// The unpatched contents of the DOL at `addr`, if it's in one of the DOL's segments
+    pub fn original_bytes(&self, addr: u32, len: u32) -> Option<&'a [u8]>
+    {
+        self.text_segments.iter().chain(&self.data_segments)
+            .find_map(|seg| match seg {
+                DolSegment::PatchedSegment(seg_addr, patcher)
+                    if addr >= *seg_addr && addr + len <= *seg_addr + patcher.len() as u32 =>
+                {
+                    let start = (addr - seg_addr) as usize;
+                    Some(&patcher.data[start..start + len as usize])
+                },
+                _ => None,
+            })
+    }
+
     pub fn patch(&mut self, start: u32, data: Cow<'a, [u8]>) -> Result<&mut Self, String>
     {
         let mut matching_seg = None;
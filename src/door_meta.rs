@@ -320,6 +320,36 @@ impl DoorType {
         }
     }
 
+    /// What opens the door, for listing it to the player
+    pub fn requirement(&self) -> &'static str {
+        let door = self.to_horizontal();
+
+        match door {
+            DoorType::Blue         => "Any weapon",
+            DoorType::PowerOnly    => "Power Beam",
+            DoorType::Purple       => "Wave Beam",
+            DoorType::White        => "Ice Beam",
+            DoorType::Red          => "Plasma Beam",
+            DoorType::PowerBomb    => "Power Bombs",
+            DoorType::Bomb         => "Morph Ball Bombs",
+            DoorType::Boost        => "Boost Ball",
+            DoorType::Missile      => "Missiles",
+            DoorType::Charge       => "Charge Beam",
+            DoorType::Super        => "Super Missiles",
+            DoorType::Wavebuster   => "Wavebuster",
+            DoorType::Icespreader  => "Ice Spreader",
+            DoorType::Flamethrower => "Flamethrower",
+            DoorType::Ai           => "Help of an enemy",
+            DoorType::Disabled     => "Can't be opened",
+            DoorType::Grapple      => "Grapple Beam",
+            DoorType::Phazon       => "Phazon Beam",
+            DoorType::Thermal      => "Thermal Visor",
+            DoorType::XRay         => "X-Ray Visor",
+            DoorType::Scan         => "Scan Visor",
+            _ => unreachable!(),
+        }
+    }
+
     // The following three are the textures for the damageable trigger
 
     pub fn pattern0_txtr(&self) -> ResId<res_id::TXTR> {
@@ -892,7 +922,7 @@ impl DoorType {
         }
     }
 
-    fn maybe_from_cmdl (cmdl: &u32) -> Option<Self>
+    pub fn maybe_from_cmdl (cmdl: &u32) -> Option<Self>
     {
         match cmdl {
             0x0734977A => Some(DoorType::Blue),
@@ -0,0 +1,417 @@
+use rand::{
+    rngs::StdRng,
+    seq::SliceRandom,
+    Rng,
+    SeedableRng,
+};
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
+
+use crate::{
+    door_meta::DoorType,
+    elevators::World,
+    patch_config::{DoorRandoConfig, LevelConfig},
+    pickup_meta,
+    GcDiscLookupExtensions,
+};
+
+/// Worlds whose doors are part of scripted sequences
+const EXCLUDED_PAKS: &[&str] = &[
+    "Metroid1.pak", // Frigate Orpheon
+    "Metroid7.pak", // Impact Crater
+    "Metroid8.pak", // End Cinema
+];
+
+const DEFAULT_DOOR_TYPES: &[&str] = &["Blue", "Purple", "White", "Red"];
+const DEFAULT_OPEN_TYPES: &[&str] = &["Blue"];
+
+/// Salt for the door rando's RNG, so it doesn't replay the same rolls as the other features
+/// seeded from `seed`
+const SEED_SALT: u64 = u64::from_be_bytes(*b"doorrand");
+
+/// A world and the name of one of its rooms
+type RoomKey = (&'static str, &'static str);
+
+/// A side of a door, i.e. a dock with a door in a room
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct DoorSide
+{
+    world_key: &'static str,
+    room_name: &'static str,
+    dock_num: u32,
+}
+
+impl DoorSide
+{
+    fn room(&self) -> RoomKey
+    {
+        (self.world_key, self.room_name)
+    }
+}
+
+/// A connection between two rooms of a world. Both sides of the dock can have a door, only
+/// one, or neither, in which case it's an open passage.
+#[derive(Debug)]
+struct Dock
+{
+    sides: Vec<DoorSide>,
+    rooms: [RoomKey; 2],
+}
+
+/// The shield picked for a dock, which is the same on both sides so it opens the same way from
+/// either room. Blast shields are per side.
+#[derive(Debug, PartialEq)]
+struct Door
+{
+    dock: usize,
+    shield_type: String,
+    blast_shield_types: Vec<Option<String>>, // one per side
+}
+
+fn is_configured(level_data: &BTreeMap<String, LevelConfig>, side: &DoorSide) -> bool
+{
+    level_data.get(side.world_key)
+        .and_then(|level| level.rooms.get(side.room_name))
+        .and_then(|room| room.doors.as_ref())
+        .and_then(|doors| doors.get(&side.dock_num))
+        .map(|door| door.shield_type.is_some() || door.blast_shield_type.is_some())
+        .unwrap_or(false)
+}
+
+/// Every dock of the worlds which can be randomized, in a fixed order, with the sides of each
+/// dock matched up through the docks of the world's MLVL
+fn docks(gc_disc: &structs::GcDisc) -> Result<Vec<Dock>, String>
+{
+    let mut docks = vec![];
+    for (pak_name, room_infos) in pickup_meta::ROOM_INFO.iter() {
+        if EXCLUDED_PAKS.contains(pak_name) {
+            continue;
+        }
+        let world = World::from_pak(pak_name).unwrap();
+
+        let file_entry = gc_disc.find_file(pak_name)
+            .ok_or_else(|| format!("Could not find {} in the input ISO", pak_name))?;
+        let pak = match *file_entry.file().unwrap() {
+            structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
+            structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
+            _ => panic!(),
+        };
+        let mut mlvl = pak.resources.iter()
+            .find(|res| res.file_id == world.mlvl())
+            .ok_or_else(|| format!("Could not find the MLVL of {}", world.to_str()))?
+            .into_owned();
+        let mlvl = mlvl.kind.as_mlvl_mut().unwrap();
+
+        let room_names: HashMap<u32, &'static str> = room_infos.iter()
+            .map(|room_info| (room_info.room_id.to_u32(), room_info.name().trim()))
+            .collect();
+        let has_door = |mrea_id: u32, dock_num: u32| room_infos.iter()
+            .find(|room_info| room_info.room_id.to_u32() == mrea_id)
+            .map(|room_info| room_info.door_locations.iter()
+                .any(|dl| dl.dock_number == dock_num && dl.door_location.is_some()))
+            .unwrap_or(false);
+
+        let areas: Vec<(u32, Vec<Option<(u32, u32)>>)> = mlvl.areas.iter()
+            .map(|area| {
+                let docks = area.docks.iter()
+                    .map(|dock| dock.connecting_docks.iter()
+                        .next()
+                        .map(|conn| (conn.array_index, conn.dock_index)))
+                    .collect();
+                (area.mrea.to_u32(), docks)
+            })
+            .collect();
+
+        for (mrea_id, area_docks) in areas.iter() {
+            let room_name = match room_names.get(mrea_id) {
+                Some(room_name) => *room_name,
+                None => continue,
+            };
+            for (dock_num, connection) in area_docks.iter().enumerate() {
+                let dock_num = dock_num as u32;
+                let side = DoorSide { world_key: world.to_json_key(), room_name, dock_num };
+
+                let other_side = connection
+                    .and_then(|(area_idx, other_dock_num)| {
+                        let other_mrea_id = areas.get(area_idx as usize)?.0;
+                        let other_side = DoorSide {
+                            world_key: world.to_json_key(),
+                            room_name: room_names.get(&other_mrea_id)?,
+                            dock_num: other_dock_num,
+                        };
+                        Some((other_side, has_door(other_mrea_id, other_dock_num)))
+                    });
+
+                let mut sides = vec![];
+                if has_door(*mrea_id, dock_num) {
+                    sides.push(side);
+                }
+                let rooms = match other_side {
+                    // Each dock is listed from the side which sorts first
+                    Some((other_side, _)) if other_side < side => continue,
+                    Some((other_side, other_has_door)) => {
+                        if other_has_door && other_side != side {
+                            sides.push(other_side);
+                        }
+                        [side.room(), other_side.room()]
+                    },
+                    None => [side.room(), side.room()],
+                };
+                if sides.is_empty() && rooms[0] == rooms[1] {
+                    continue;
+                }
+                docks.push(Dock { sides, rooms });
+            }
+        }
+    }
+
+    Ok(docks)
+}
+
+/// Rooms connected to each other through the docks which can be passed, tracked by merging
+/// groups of rooms
+struct Connectivity
+{
+    groups: BTreeMap<RoomKey, usize>,
+}
+
+impl Connectivity
+{
+    fn new(docks: &[Dock]) -> Self
+    {
+        let groups = docks.iter()
+            .flat_map(|dock| dock.rooms.iter())
+            .enumerate()
+            .map(|(i, room)| (*room, i))
+            .collect();
+        Connectivity { groups }
+    }
+
+    fn is_connected(&self, dock: &Dock) -> bool
+    {
+        self.groups[&dock.rooms[0]] == self.groups[&dock.rooms[1]]
+    }
+
+    fn connect(&mut self, dock: &Dock)
+    {
+        let (from, to) = (self.groups[&dock.rooms[1]], self.groups[&dock.rooms[0]]);
+        for group in self.groups.values_mut() {
+            if *group == from {
+                *group = to;
+            }
+        }
+    }
+}
+
+/// Picks a shield for every dock in `randomized`, then opens up doors until the rooms that
+/// `docks` connects are all reachable from each other through doors of an `open_types` shield
+/// without blast shields. Docks which have doors but aren't randomized are assumed closed.
+fn pick_shields(
+    docks: &[Dock],
+    randomized: &[usize],
+    config: &DoorRandoConfig,
+    rng: &mut StdRng,
+) -> Vec<Door>
+{
+    let door_types: Vec<String> = config.door_types.clone()
+        .unwrap_or_else(|| DEFAULT_DOOR_TYPES.iter().map(|t| t.to_string()).collect());
+    let open_types: Vec<String> = config.open_types.clone()
+        .unwrap_or_else(|| DEFAULT_OPEN_TYPES.iter().map(|t| t.to_string()).collect());
+    let blast_shield_types = config.blast_shield_types.clone().unwrap_or_default();
+    let blast_shield_chance = config.blast_shield_chance.unwrap_or(0.0);
+
+    let mut doors: Vec<Door> = randomized.iter()
+        .map(|dock| {
+            let shield_type = door_types.choose(rng).unwrap().clone();
+            let blast_shield_types = docks[*dock].sides.iter()
+                .map(|_| {
+                    if blast_shield_types.is_empty() || !rng.gen_bool(blast_shield_chance as f64) {
+                        return None;
+                    }
+                    blast_shield_types.choose(rng).cloned()
+                })
+                .collect();
+            Door { dock: *dock, shield_type, blast_shield_types }
+        })
+        .collect();
+
+    // A blast shield keeps the door locked from the other side too, so an open door has none
+    let is_open = |door: &Door| {
+        let shield_type = DoorType::from_string(door.shield_type.clone());
+        open_types.iter().any(|t| DoorType::from_string(t.clone()) == shield_type) &&
+            door.blast_shield_types.iter().all(|t| t.is_none())
+    };
+
+    let mut connectivity = Connectivity::new(docks);
+    for (i, dock) in docks.iter().enumerate() {
+        let open = match doors.iter().find(|door| door.dock == i) {
+            Some(door) => is_open(door),
+            None => dock.sides.is_empty(),
+        };
+        if open {
+            connectivity.connect(dock);
+        }
+    }
+
+    loop {
+        let closed: Vec<usize> = doors.iter()
+            .enumerate()
+            .filter(|(_, door)| !connectivity.is_connected(&docks[door.dock]))
+            .map(|(i, _)| i)
+            .collect();
+        let door = match closed.choose(rng) {
+            Some(i) => &mut doors[*i],
+            None => break,
+        };
+        door.shield_type = open_types.choose(rng).unwrap().clone();
+        door.blast_shield_types.iter_mut().for_each(|t| *t = None);
+        connectivity.connect(&docks[door.dock]);
+    }
+
+    doors
+}
+
+/// Gives every door which `level_data` leaves alone a random shield from `config`,
+/// deterministically from `seed`. Both sides of a door get the same shield, and within each world
+/// every room can be reached from every other through doors of an "open" type, so the shields
+/// never lock off a part of the game.
+pub fn randomize_doors(
+    gc_disc: &structs::GcDisc,
+    level_data: &mut BTreeMap<String, LevelConfig>,
+    config: &DoorRandoConfig,
+    seed: u64,
+)
+    -> Result<(), String>
+{
+    let mut rng = StdRng::seed_from_u64(seed ^ SEED_SALT);
+
+    let docks = docks(gc_disc)?;
+    let randomized: Vec<usize> = docks.iter()
+        .enumerate()
+        .filter(|(_, dock)| !dock.sides.is_empty())
+        .filter(|(_, dock)| !dock.sides.iter().any(|side| is_configured(level_data, side)))
+        .map(|(i, _)| i)
+        .collect();
+
+    for door in pick_shields(&docks, &randomized, config, &mut rng) {
+        let sides = docks[door.dock].sides.iter();
+        for (side, blast_shield_type) in sides.zip(door.blast_shield_types.into_iter()) {
+            let level = level_data.entry(side.world_key.to_string()).or_default();
+            let room = level.rooms.entry(side.room_name.to_string()).or_default();
+            let door_config = room.doors.get_or_insert_with(BTreeMap::new)
+                .entry(side.dock_num)
+                .or_default();
+            door_config.shield_type = Some(door.shield_type.clone());
+            door_config.blast_shield_type = blast_shield_type;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn side(room_name: &'static str, dock_num: u32) -> DoorSide
+    {
+        DoorSide { world_key: "Chozo Ruins", room_name, dock_num }
+    }
+
+    fn door(from: (&'static str, u32), to: (&'static str, u32)) -> Dock
+    {
+        Dock {
+            sides: vec![side(from.0, from.1), side(to.0, to.1)],
+            rooms: [side(from.0, 0).room(), side(to.0, 0).room()],
+        }
+    }
+
+    fn group_count(connectivity: &Connectivity) -> usize
+    {
+        let mut groups: Vec<usize> = connectivity.groups.values().copied().collect();
+        groups.sort();
+        groups.dedup();
+        groups.len()
+    }
+
+    /// A ring of rooms with a shortcut across, and a passage without a door
+    fn test_docks() -> Vec<Dock>
+    {
+        vec![
+            door(("A", 0), ("B", 0)),
+            door(("B", 1), ("C", 0)),
+            door(("C", 1), ("D", 0)),
+            door(("D", 1), ("A", 1)),
+            door(("A", 2), ("C", 2)),
+            Dock { sides: vec![], rooms: [side("D", 0).room(), side("E", 0).room()] },
+            door(("E", 1), ("F", 0)),
+        ]
+    }
+
+    fn test_config() -> DoorRandoConfig
+    {
+        DoorRandoConfig {
+            door_types: Some(vec!["Purple".to_string(), "White".to_string(), "Red".to_string()]),
+            open_types: Some(vec!["Blue".to_string()]),
+            blast_shield_types: Some(vec!["Missile".to_string()]),
+            blast_shield_chance: Some(0.5),
+        }
+    }
+
+    #[test]
+    fn every_room_stays_reachable()
+    {
+        let docks = test_docks();
+        let randomized: Vec<usize> = (0..docks.len()).filter(|i| *i != 5).collect();
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed ^ SEED_SALT);
+            let doors = pick_shields(&docks, &randomized, &test_config(), &mut rng);
+            assert_eq!(doors.len(), randomized.len());
+
+            let mut connectivity = Connectivity::new(&docks);
+            connectivity.connect(&docks[5]);
+            for door in doors.iter() {
+                if door.shield_type == "Blue" && door.blast_shield_types.iter().all(|t| t.is_none()) {
+                    connectivity.connect(&docks[door.dock]);
+                }
+            }
+            assert_eq!(group_count(&connectivity), 1, "seed {} locks off a room", seed);
+        }
+    }
+
+    #[test]
+    fn shields_only_depend_on_the_seed()
+    {
+        let docks = test_docks();
+        let randomized: Vec<usize> = (0..docks.len()).filter(|i| *i != 5).collect();
+        let pick = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed ^ SEED_SALT);
+            pick_shields(&docks, &randomized, &test_config(), &mut rng)
+                .into_iter()
+                .map(|door| (door.shield_type, door.blast_shield_types))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(pick(1234), pick(1234));
+        assert_ne!(pick(1234), pick(4321));
+        assert_eq!(pick(1234), PINNED_1234.iter()
+            .map(|(shield_type, blast_shield_types)| (
+                shield_type.to_string(),
+                blast_shield_types.iter().map(|t| t.map(|t| t.to_string())).collect::<Vec<_>>(),
+            ))
+            .collect::<Vec<_>>());
+    }
+
+    const PINNED_1234: &[(&str, &[Option<&str>])] = &[
+        ("White", &[None, None]),
+        ("Blue", &[None, None]),
+        ("Blue", &[None, None]),
+        ("Blue", &[None, None]),
+        ("White", &[Some("Missile"), None]),
+        ("Blue", &[None, None]),
+    ];
+}
@@ -619,27 +619,35 @@ macro_rules! decl_spawn_rooms {
 impl SpawnRoomData
 {
     pub fn from_str(dest_name: &str) -> Self
+    {
+        match SpawnRoomData::try_from_str(dest_name) {
+            Some(data) => data,
+            None => panic!("Error - Could not find destination '{}'", dest_name),
+        }
+    }
+
+    pub fn try_from_str(dest_name: &str) -> Option<Self>
     {
         let dest_name = dest_name.to_lowercase();
 
         // Handle special destinations //
         if dest_name == "credits" {
-            return *SpawnRoom::EndingCinematic.spawn_room_data();
+            return Some(*SpawnRoom::EndingCinematic.spawn_room_data());
         }
 
         if dest_name == "frigate" || dest_name == "frigate escape cutscene" {
-            return *SpawnRoom::FrigateExteriorDockingHangar.spawn_room_data();
+            return Some(*SpawnRoom::FrigateExteriorDockingHangar.spawn_room_data());
         }
 
         // Handle elevator destinations //
         if let Some(elevator) = Elevator::from_str(&dest_name) {
-            return *elevator.spawn_room_data();
+            return Some(*elevator.spawn_room_data());
         }
 
         // Handle specific room destinations //
         let vec: Vec<&str> = dest_name.split(":").collect();
         if vec.len() != 2 {
-            panic!("Error - Could not find destination '{}'", dest_name);
+            return None;
         }
         let world_name = vec[0].trim();
         let room_name = vec[1].trim();
@@ -654,20 +662,20 @@ impl SpawnRoomData
             let mut idx: u32 = 0;
             for room_info in rooms.iter() { // for each room in the pak
                 if room_info.name().to_lowercase().trim() == room_name { // trim both because "west tower " has an extra space in it
-                    return SpawnRoomData {
+                    return Some(SpawnRoomData {
                         pak_name,
                         mlvl: world.mlvl(),
                         mrea: room_info.room_id.to_u32(),
                         mrea_idx: idx,
                         room_id: 0,
                         name: room_info.name(),
-                    };
+                    });
                 }
                 idx = idx + 1;
             }
         }
 
-        panic!("Error - Could not find room '{}'", dest_name)
+        None
     }
 }
 
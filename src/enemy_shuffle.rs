@@ -0,0 +1,407 @@
+use rand::{
+    rngs::StdRng,
+    seq::SliceRandom,
+    SeedableRng,
+};
+
+use reader_writer::{FourCC, Reader, Writable};
+use structs::{Ancs, Cmdl, Evnt, Scan, SclyPropertyData, scly_structs::TypeVulnerability};
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+use crate::{
+    mlvl_wrapper,
+    patcher::PatcherState,
+    pickup_meta,
+    GcDiscLookupExtensions,
+    ResourceData,
+};
+
+/// Enemies which can trade places with another enemy of the same object type. Bosses and
+/// enemies which only exist as part of a boss fight are deliberately missing.
+const SHUFFLEABLE_ENEMY_TYPES: &[u8] = &[
+    structs::AtomicAlpha::OBJECT_TYPE,
+    structs::AtomicBeta::OBJECT_TYPE,
+    structs::Babygoth::OBJECT_TYPE,
+    structs::Beetle::OBJECT_TYPE,
+    structs::Bloodflower::OBJECT_TYPE,
+    structs::ChozoGhost::OBJECT_TYPE,
+    structs::Drone::OBJECT_TYPE,
+    structs::Eyeball::OBJECT_TYPE,
+    structs::FireFlea::OBJECT_TYPE,
+    structs::FlickerBat::OBJECT_TYPE,
+    structs::FlyingPirate::OBJECT_TYPE,
+    structs::Geemer::OBJECT_TYPE,
+    structs::IceSheegoth::OBJECT_TYPE,
+    structs::JellyZap::OBJECT_TYPE,
+    structs::Magdolite::OBJECT_TYPE,
+    structs::Metaree::OBJECT_TYPE,
+    structs::Metroid::OBJECT_TYPE,
+    structs::Parasite::OBJECT_TYPE,
+    structs::PuddleSpore::OBJECT_TYPE,
+    structs::Puffer::OBJECT_TYPE,
+    structs::Ripper::OBJECT_TYPE,
+    structs::Seedling::OBJECT_TYPE,
+    structs::SpacePirate::OBJECT_TYPE,
+    structs::Tryclops::OBJECT_TYPE,
+    structs::WarWasp::OBJECT_TYPE,
+];
+
+/// Enemies of different types which can stand in for each other when shuffling across types,
+/// because they get around the same way and are about the same size. Types which aren't listed
+/// are only swapped with enemies of the same type.
+const COMPATIBLE_ENEMY_TYPES: &[&[u8]] = &[
+    // Flying
+    &[
+        structs::FireFlea::OBJECT_TYPE,
+        structs::Metroid::OBJECT_TYPE,
+        structs::Puffer::OBJECT_TYPE,
+        structs::WarWasp::OBJECT_TYPE,
+    ],
+    // Small, walking on the floor
+    &[
+        structs::Beetle::OBJECT_TYPE,
+        structs::Parasite::OBJECT_TYPE,
+        structs::Seedling::OBJECT_TYPE,
+    ],
+];
+
+/// Worlds whose enemies are all part of scripted sequences
+const EXCLUDED_PAKS: &[&str] = &[
+    "Metroid1.pak", // Frigate Orpheon
+    "Metroid7.pak", // Impact Crater
+    "Metroid8.pak", // End Cinema
+];
+
+/// Boss rooms and rooms where killing a specific set of enemies is required to progress. Enemies
+/// in these rooms are neither moved nor replaced.
+const EXCLUDED_ROOMS: &[(&str, &str)] = &[
+    ("Metroid2.pak", "Hive Totem"),
+    ("Metroid2.pak", "Ruined Shrine"),
+    ("Metroid2.pak", "Sunchamber"),
+    ("Metroid2.pak", "Burn Dome"),
+    ("Metroid3.pak", "Chapel of the Elders"),
+    ("Metroid3.pak", "Quarantine Cave"),
+    ("Metroid4.pak", "Artifact Temple"),
+    ("metroid5.pak", "Central Dynamo"),
+    ("metroid5.pak", "Elite Quarters"),
+    ("metroid5.pak", "Elite Research"),
+];
+
+/// Resource types which can be pulled into a room alongside a shuffled enemy
+const DEPENDENCY_TYPES: &[&[u8; 4]] = &[
+    b"AFSM", b"ANCS", b"ANIM", b"CINF", b"CMDL", b"CRSC", b"CSKR", b"DPSC", b"ELSC", b"EVNT",
+    b"FRME", b"PART", b"SCAN", b"STRG", b"SWHC", b"TXTR", b"WPSC",
+];
+
+struct EnemyInstance<'r>
+{
+    mrea_id: u32,
+    instance_id: u32,
+    property_data: structs::SclyProperty<'r>,
+}
+
+/// Property data to put in place of an existing enemy, and the resources it needs
+pub struct EnemyReplacement<'r>
+{
+    property_data: structs::SclyProperty<'r>,
+    dependencies: Vec<(u32, FourCC)>,
+    source: (u32, u32), // (MREA id, instance id) of the enemy it's copied from
+}
+
+pub struct EnemyShuffle<'r>
+{
+    /// Keyed by (MREA id, instance id) of the enemy being replaced
+    replacements: HashMap<(u32, u32), EnemyReplacement<'r>>,
+    resources: HashMap<(u32, FourCC), structs::Resource<'r>>,
+}
+
+impl<'r> EnemyShuffle<'r>
+{
+    pub fn rooms(&self) -> HashSet<u32>
+    {
+        self.replacements.keys().map(|(mrea_id, _)| *mrea_id).collect()
+    }
+
+    /// (MREA id, instance id) of every enemy which was replaced, and of the enemy it was
+    /// replaced by, sorted
+    pub fn swaps(&self) -> Vec<((u32, u32), (u32, u32))>
+    {
+        let mut swaps: Vec<_> = self.replacements.iter()
+            .map(|(target, replacement)| (*target, replacement.source))
+            .collect();
+        swaps.sort();
+        swaps
+    }
+}
+
+/// An enemy which can't be hurt by the Power Beam could block progress in rooms that lock until
+/// every enemy is dead, so those stay where they are
+fn is_softlock_prone(property_data: &mut structs::SclyProperty) -> bool
+{
+    if !property_data.supports_vulnerabilities() {
+        return false;
+    }
+
+    property_data.get_vulnerabilities().iter().any(|vulnerability| {
+        vulnerability.power == TypeVulnerability::Immune as u32
+            || vulnerability.power == TypeVulnerability::Reflect as u32
+    })
+}
+
+/// The group of enemies `object_type` can be swapped with, named after its first member
+fn shuffle_group(object_type: u8, across_types: bool) -> u8
+{
+    if !across_types {
+        return object_type;
+    }
+    COMPATIBLE_ENEMY_TYPES.iter()
+        .find(|group| group.contains(&object_type))
+        .map(|group| group[0])
+        .unwrap_or(object_type)
+}
+
+/// Decides which enemies trade places, deterministically from `seed`. Enemies are swapped with
+/// enemies of the same object type, or with `across_types` of any type from the same group of
+/// `COMPATIBLE_ENEMY_TYPES`. The replacement's property data is used whole, except for where the
+/// enemy is placed.
+pub fn plan_enemy_shuffle<'r>(gc_disc: &structs::GcDisc<'r>, seed: u64, across_types: bool)
+    -> Result<EnemyShuffle<'r>, String>
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut resources = HashMap::<u32, structs::Resource<'r>>::new();
+    let mut enemies = HashMap::<u8, Vec<EnemyInstance<'r>>>::new();
+
+    for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+        let file_entry = gc_disc.find_file(pak_name)
+            .ok_or_else(|| format!("Could not find {} in the input ISO", pak_name))?;
+        let pak = match *file_entry.file().unwrap() {
+            structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
+            structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
+            _ => panic!(),
+        };
+
+        for res in pak.resources.iter() {
+            resources.entry(res.file_id).or_insert_with(|| res.into_owned());
+        }
+
+        if EXCLUDED_PAKS.contains(pak_name) {
+            continue;
+        }
+
+        for room_info in rooms.iter() {
+            if EXCLUDED_ROOMS.contains(&(pak_name, room_info.name().trim())) {
+                continue;
+            }
+
+            let mrea_id = room_info.room_id.to_u32();
+            let mut res = pak.resources.iter()
+                .find(|res| res.file_id == mrea_id)
+                .ok_or_else(|| format!("Could not find room 0x{:X} in {}", mrea_id, pak_name))?
+                .into_owned();
+            let mrea = res.kind.as_mrea_mut().unwrap();
+            let scly = mrea.scly_section_mut();
+
+            for scly_layer in scly.layers.as_mut_vec().iter_mut() {
+                for obj in scly_layer.objects.as_mut_vec().iter_mut() {
+                    let object_type = obj.property_data.object_type();
+                    if !SHUFFLEABLE_ENEMY_TYPES.contains(&object_type) {
+                        continue;
+                    }
+
+                    if is_softlock_prone(&mut obj.property_data) {
+                        continue;
+                    }
+
+                    enemies.entry(shuffle_group(object_type, across_types)).or_default().push(EnemyInstance {
+                        mrea_id,
+                        instance_id: obj.instance_id,
+                        property_data: obj.property_data.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    // Iterate in a fixed order so the result only depends on the seed
+    let mut groups: Vec<u8> = enemies.keys().cloned().collect();
+    groups.sort();
+
+    let mut replacements = HashMap::new();
+    let mut dependency_resources = HashMap::new();
+    for group in groups {
+        let instances = &enemies[&group];
+        let mut sources: Vec<usize> = (0..instances.len()).collect();
+        sources.shuffle(&mut rng);
+
+        for (target, source) in instances.iter().zip(sources.into_iter().map(|i| &instances[i])) {
+            if target.mrea_id == source.mrea_id && target.instance_id == source.instance_id {
+                continue;
+            }
+
+            let mut dependencies = vec![];
+            let mut seen = HashSet::new();
+            let mut bytes = vec![];
+            source.property_data.write_to(&mut bytes).unwrap();
+            for id in referenced_ids(&bytes, 1, &resources) {
+                trace_dependencies(&resources, id, &mut dependencies, &mut seen);
+            }
+
+            for (id, fourcc) in dependencies.iter() {
+                dependency_resources.insert((*id, *fourcc), resources[id].clone());
+            }
+
+            replacements.insert((target.mrea_id, target.instance_id), EnemyReplacement {
+                property_data: source.property_data.clone(),
+                dependencies,
+                source: (source.mrea_id, source.instance_id),
+            });
+        }
+    }
+
+    Ok(EnemyShuffle {
+        replacements,
+        resources: dependency_resources,
+    })
+}
+
+/// Every id in `bytes` that refers to a resource which could be an enemy's dependency
+fn referenced_ids(bytes: &[u8], alignment: usize, resources: &HashMap<u32, structs::Resource>)
+    -> Vec<u32>
+{
+    let mut ids = vec![];
+    let mut i = 0;
+    while i + 4 <= bytes.len() {
+        let id = u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        if let Some(res) = resources.get(&id) {
+            if DEPENDENCY_TYPES.iter().any(|fourcc| res.fourcc() == FourCC::from_bytes(fourcc)) {
+                ids.push(id);
+            }
+        }
+        i += alignment;
+    }
+    ids
+}
+
+fn trace_dependencies(
+    resources: &HashMap<u32, structs::Resource>,
+    id: u32,
+    dependencies: &mut Vec<(u32, FourCC)>,
+    seen: &mut HashSet<u32>,
+)
+{
+    if !seen.insert(id) {
+        return;
+    }
+
+    let res = match resources.get(&id) {
+        Some(res) => res,
+        None => return,
+    };
+    let fourcc = res.fourcc();
+    dependencies.push((id, fourcc));
+
+    let buf = ResourceData::new(res).decompress();
+    let mut children = vec![];
+    if fourcc == b"ANCS".into() {
+        let ancs: Ancs = Reader::new(&buf).read(());
+        for char_info in ancs.char_set.char_info.iter() {
+            children.push(char_info.cmdl.to_u32());
+            children.push(char_info.cskr.to_u32());
+            children.push(char_info.cinf.to_u32());
+            children.extend(char_info.particles.part_assets.iter().map(|id| *id));
+            children.extend(char_info.particles.swhc_assets.iter().map(|id| id.to_u32()));
+            if let Some(elsc_assets) = char_info.particles.elsc_assets.as_ref() {
+                children.extend(elsc_assets.iter().map(|id| id.to_u32()));
+            }
+            if let Some(effects) = char_info.effects.as_ref() {
+                for effect in effects.iter() {
+                    children.extend(effect.components.iter().map(|comp| comp.file_id));
+                }
+            }
+            children.extend(char_info.overlay_cmdl.map(|id| id.to_u32()));
+            children.extend(char_info.overlay_cskr.map(|id| id.to_u32()));
+        }
+        if let Some(animation_resources) = ancs.anim_set.animation_resources.as_ref() {
+            for anim_resource in animation_resources.iter() {
+                children.push(anim_resource.anim.to_u32());
+                children.push(anim_resource.evnt.to_u32());
+            }
+        }
+    } else if fourcc == b"CMDL".into() {
+        let cmdl: Cmdl = Reader::new(&buf).read(());
+        for material in cmdl.material_sets.iter() {
+            children.extend(material.texture_ids.iter().map(|id| id.to_u32()));
+        }
+    } else if fourcc == b"EVNT".into() {
+        let evnt: Evnt = Reader::new(&buf).read(());
+        children.extend(evnt.effect_events.iter().map(|effect| effect.effect_file_id));
+    } else if fourcc == b"SCAN".into() {
+        let scan: Scan = Reader::new(&buf).read(());
+        children.push(scan.frme.to_u32());
+        children.push(scan.strg.to_u32());
+    } else if [b"PART", b"ELSC", b"SWHC", b"CRSC", b"DPSC", b"WPSC"].iter().any(|f| fourcc == (*f).into()) {
+        // Particle-like formats are streams of 4 byte tokens, so any aligned word which is a known
+        // id is (almost certainly) a reference
+        children.extend(referenced_ids(&buf, 4, resources));
+    }
+
+    for child in children {
+        trace_dependencies(resources, child, dependencies, seen);
+    }
+}
+
+pub fn patch_shuffle_enemies<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    enemy_shuffle: &EnemyShuffle<'r>,
+)
+    -> Result<(), String>
+{
+    let mrea_id = area.mlvl_area.mrea.to_u32();
+
+    let mut layer_deps = vec![];
+    let scly = area.mrea().scly_section_mut();
+    for (layer, scly_layer) in scly.layers.as_mut_vec().iter_mut().enumerate() {
+        for obj in scly_layer.objects.as_mut_vec().iter_mut() {
+            let replacement = match enemy_shuffle.replacements.get(&(mrea_id, obj.instance_id)) {
+                Some(replacement) => replacement,
+                None => continue,
+            };
+
+            // The replacement takes over this enemy's placement and whether it starts active,
+            // everything else comes from the enemy it was swapped with
+            let position = obj.property_data.get_position();
+            let rotation = obj.property_data.get_rotation();
+            let mut patterned_infos = obj.property_data.get_patterned_infos();
+
+            let mut property_data = replacement.property_data.clone();
+            property_data.set_position(position);
+            property_data.set_rotation(rotation);
+            let mut new_patterned_infos = property_data.get_patterned_infos();
+            for (new, old) in new_patterned_infos.iter_mut().zip(patterned_infos.iter_mut()) {
+                new.active = old.active;
+                new.pathfinding_index = old.pathfinding_index;
+            }
+            property_data.set_patterned_infos(new_patterned_infos);
+
+            obj.property_data = property_data;
+            layer_deps.push((layer, &replacement.dependencies));
+        }
+    }
+
+    // Only load the new assets when the layer the enemy lives on is
+    for (layer, deps) in layer_deps {
+        let deps = deps.iter().map(|(asset_id, asset_type)| structs::Dependency {
+            asset_id: *asset_id,
+            asset_type: *asset_type,
+        });
+        area.add_dependencies(&enemy_shuffle.resources, layer, deps);
+    }
+
+    Ok(())
+}
@@ -1,7 +1,13 @@
 use serde::Deserialize;
 use std::{fs::{self, File}, io::{self, Read}, path::{Path, PathBuf}};
 use std::collections::{HashMap, HashSet};
-use reader_writer::FourCC;
+use reader_writer::{FourCC, Reader, byteorder::{BigEndian, ByteOrder}};
+use structs::{Ancs, Cmdl, Evnt};
+
+use crate::gltf_import::gltf_to_cmdl;
+
+/// Ids given to standalone model files which aren't named after an asset id
+const STANDALONE_MODEL_IDS_START: u32 = 0xDEAE0000;
 
 /* Public Structs */
 #[derive(Debug, Clone)]
@@ -10,6 +16,7 @@ pub struct ExternPickupModel {
     pub cmdl: u32,
     pub scale: f32,
     pub character: u32,
+    pub character_count: u32, // number of characters in the ANCS
     pub dependencies: Vec<(u32, FourCC)>,
 }
 
@@ -64,9 +71,14 @@ impl ExternPickupModel {
             .map_err(|e| format!("Extern Assets dir parse failed: {}", e))?;
     
         // Deserialize JSON
-        let _metadata = fs::read_to_string(Path::new(dir).join("meta.json")).expect(format!("Unable to read extern model metadata from '{}'", dir).as_str());
-        let metadata: MetadataJson = serde_json::from_str(&_metadata)
-            .map_err(|e| format!("Extern Assets metadata.json parse failed: {}", e))?;
+        let metadata_path = Path::new(dir).join("meta.json");
+        let metadata: MetadataJson = if metadata_path.exists() {
+            let _metadata = fs::read_to_string(&metadata_path).expect(format!("Unable to read extern model metadata from '{}'", dir).as_str());
+            serde_json::from_str(&_metadata)
+                .map_err(|e| format!("Extern Assets metadata.json parse failed: {}", e))?
+        } else {
+            MetadataJson::default() // the dir only has standalone models
+        };
 
         // Parse model info
         let mut models: HashMap<String, Self> = HashMap::new();
@@ -107,6 +119,7 @@ impl ExternPickupModel {
                     cmdl: model.cmdl,
                     scale: model.scale,
                     character: model.character,
+                    character_count: 0,
                     dependencies,
                 }
             );
@@ -124,6 +137,7 @@ impl ExternPickupModel {
 
         // Parse asset data
         let mut assets: HashMap<u32, ExternAsset> = HashMap::new();
+        let mut claimed_files: HashSet<PathBuf> = HashSet::new();
         for id in ids_to_find {
             // Find the file which corresponds to this id
             let mut filename = None;
@@ -140,6 +154,7 @@ impl ExternPickupModel {
                 panic!("Failed to find file corresponding to asset id {}", id)
             }
             let filename = filename.unwrap();
+            claimed_files.insert(filename.clone());
             // Derrive FourCC from file extension
             // (I dislike Rust; This is just for parsing 4 letters)
             let fourcc = filename.clone();
@@ -169,6 +184,216 @@ impl ExternPickupModel {
             );
         }
 
+        // CMDL, ANCS and glTF files which meta.json doesn't mention can be used as models directly.
+        // The model is named after the file (e.g. "my_model.CMDL") and anything it references is
+        // either another file in the dir named after its id (e.g. "1A2B3C4D.TXTR") or comes
+        // from the disc.
+        let mut next_id = STANDALONE_MODEL_IDS_START;
+        for file in &files {
+            if claimed_files.contains(file) {
+                continue;
+            }
+
+            // glTF models are converted to a CMDL and its textures
+            let extension = file.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_lowercase());
+            if extension.as_deref() == Some("gltf") || extension.as_deref() == Some("glb") {
+                let name = file.file_name().unwrap().to_string_lossy().to_string();
+                let cmdl_id = next_id;
+                next_id += 1;
+                let (cmdl, textures) = gltf_to_cmdl(file, || {
+                    next_id += 1;
+                    next_id - 1
+                }).map_err(|e| format!("Failed to convert extern model '{}': {}", name, e))?;
+
+                let mut dependencies = vec![(cmdl_id, b"CMDL".into())];
+                for (id, bytes) in textures {
+                    dependencies.push((id, b"TXTR".into()));
+                    assets.insert(id, ExternAsset { fourcc: b"TXTR".into(), bytes });
+                }
+                assets.insert(cmdl_id, ExternAsset { fourcc: b"CMDL".into(), bytes: cmdl });
+
+                models.insert(
+                    name,
+                    ExternPickupModel {
+                        ancs: u32::MAX,
+                        cmdl: cmdl_id,
+                        scale: 1.0,
+                        character: 0,
+                        character_count: 0,
+                        dependencies,
+                    }
+                );
+                continue;
+            }
+
+            let fourcc = match file_fourcc(file) {
+                Some(fourcc) if fourcc == b"CMDL".into() || fourcc == b"ANCS".into() => fourcc,
+                _ => continue,
+            };
+            let name = file.file_name().unwrap().to_string_lossy().to_string();
+            let id = asset_id_from_file_name(file).unwrap_or_else(|| {
+                next_id += 1;
+                next_id - 1
+            });
+
+            let bytes = fs::read(file)
+                .map_err(|e| format!("Failed to read extern model '{}': {}", name, e))?;
+
+            // Pickups without an ANCS just display the CMDL
+            let (ancs, cmdl) = if fourcc == b"ANCS".into() {
+                let ancs: Ancs = Reader::new(&bytes[..]).read(());
+                let cmdl = ancs.char_set.char_info.iter().next()
+                    .map(|char_info| char_info.cmdl.to_u32())
+                    .ok_or_else(|| format!("Extern model '{}' has no characters", name))?;
+                (id, cmdl)
+            } else {
+                (u32::MAX, id)
+            };
+
+            let mut dependencies = vec![];
+            add_standalone_asset(&files, id, fourcc, bytes, &mut assets, &mut dependencies)?;
+
+            models.insert(
+                name,
+                ExternPickupModel {
+                    ancs,
+                    cmdl,
+                    scale: 1.0,
+                    character: 0,
+                    character_count: 0,
+                    dependencies,
+                }
+            );
+        }
+
+        // Referencing a character which isn't in the ANCS crashes the game when the model loads
+        for model in models.values_mut() {
+            model.character_count = assets.get(&model.ancs)
+                .filter(|ancs| ancs.bytes.len() >= 8)
+                .map(|ancs| BigEndian::read_u32(&ancs.bytes[4..8])) // skip ANCS and char set versions
+                .unwrap_or(0);
+        }
+
         Ok((models, assets))
     }
 }
+
+fn file_fourcc(file: &Path) -> Option<FourCC>
+{
+    let extension = file.extension()?.to_str()?.to_uppercase();
+    if extension.len() != 4 {
+        return None;
+    }
+    let bytes = extension.as_bytes();
+    Some(FourCC::from_bytes(&[bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Files named like "1A2B3C4D.TXTR" hold the asset with that id
+fn asset_id_from_file_name(file: &Path) -> Option<u32>
+{
+    let stem = file.file_stem()?.to_str()?;
+    if stem.len() != 8 {
+        return None;
+    }
+    u32::from_str_radix(stem, 16).ok()
+}
+
+/// Every asset id referenced by an extern asset, along with the type it's expected to have
+fn referenced_assets(fourcc: FourCC, bytes: &[u8]) -> Vec<(u32, FourCC)>
+{
+    let mut children = vec![];
+    if fourcc == b"CMDL".into() {
+        let cmdl: Cmdl = Reader::new(bytes).read(());
+        for material in cmdl.material_sets.iter() {
+            children.extend(material.texture_ids.iter().map(|id| (id.to_u32(), b"TXTR".into())));
+        }
+    } else if fourcc == b"ANCS".into() {
+        let ancs: Ancs = Reader::new(bytes).read(());
+        for char_info in ancs.char_set.char_info.iter() {
+            children.push((char_info.cmdl.to_u32(), b"CMDL".into()));
+            children.push((char_info.cskr.to_u32(), b"CSKR".into()));
+            children.push((char_info.cinf.to_u32(), b"CINF".into()));
+            children.extend(char_info.particles.part_assets.iter().map(|id| (*id, b"PART".into())));
+            children.extend(char_info.particles.swhc_assets.iter().map(|id| (id.to_u32(), b"SWHC".into())));
+            if let Some(elsc_assets) = char_info.particles.elsc_assets.as_ref() {
+                children.extend(elsc_assets.iter().map(|id| (id.to_u32(), b"ELSC".into())));
+            }
+            if let Some(effects) = char_info.effects.as_ref() {
+                for effect in effects.iter() {
+                    children.extend(effect.components.iter().map(|comp| (comp.file_id, comp.type_)));
+                }
+            }
+            children.extend(char_info.overlay_cmdl.map(|id| (id.to_u32(), b"CMDL".into())));
+            children.extend(char_info.overlay_cskr.map(|id| (id.to_u32(), b"CSKR".into())));
+        }
+        if let Some(animation_resources) = ancs.anim_set.animation_resources.as_ref() {
+            for anim_resource in animation_resources.iter() {
+                children.push((anim_resource.anim.to_u32(), b"ANIM".into()));
+                children.push((anim_resource.evnt.to_u32(), b"EVNT".into()));
+            }
+        }
+    } else if fourcc == b"EVNT".into() {
+        let evnt: Evnt = Reader::new(bytes).read(());
+        children.extend(evnt.effect_events.iter().map(|effect| (effect.effect_file_id, effect.effect_type)));
+    }
+
+    children.retain(|(id, _)| *id != 0 && *id != u32::MAX);
+    children
+}
+
+/// Reads `file_name` from `dir`, along with every asset it references (recursively) which is
+/// also in the dir. Used to replace vanilla assets, so the asset's own id doesn't matter.
+pub fn read_standalone_asset(dir: &String, file_name: &str)
+    -> Result<(Vec<u8>, HashMap<u32, ExternAsset>), String>
+{
+    let files = parse_dir(dir)
+        .map_err(|e| format!("Failed to read extern assets dir '{}': {}", dir, e))?;
+    let file = files.iter()
+        .find(|file| file.file_name().map(|name| name == file_name).unwrap_or(false))
+        .ok_or_else(|| format!("'{}' isn't in extern assets dir '{}'", file_name, dir))?;
+    let fourcc = file_fourcc(file)
+        .ok_or_else(|| format!("Can't tell the type of extern asset '{}'", file_name))?;
+    let bytes = fs::read(file)
+        .map_err(|e| format!("Failed to read extern asset '{}': {}", file_name, e))?;
+
+    // Id 0 is never referenced, so it can't collide with the asset's dependencies
+    let mut assets = HashMap::new();
+    add_standalone_asset(&files, 0, fourcc, bytes, &mut assets, &mut vec![])?;
+    let bytes = assets.remove(&0).unwrap().bytes;
+    Ok((bytes, assets))
+}
+
+/// Adds a standalone model's asset, and recursively the ones it references, to `assets`.
+/// Referenced assets which aren't in the dir are listed as dependencies to find on the disc.
+fn add_standalone_asset(
+    files: &[PathBuf],
+    id: u32,
+    fourcc: FourCC,
+    bytes: Vec<u8>,
+    assets: &mut HashMap<u32, ExternAsset>,
+    dependencies: &mut Vec<(u32, FourCC)>,
+) -> Result<(), String>
+{
+    dependencies.push((id, fourcc));
+
+    for (child_id, child_fourcc) in referenced_assets(fourcc, &bytes) {
+        if dependencies.iter().any(|(dep, _)| *dep == child_id) {
+            continue;
+        }
+
+        let file = files.iter().find(|file|
+            asset_id_from_file_name(file) == Some(child_id) && file_fourcc(file) == Some(child_fourcc)
+        );
+        match file {
+            Some(file) => {
+                let child_bytes = fs::read(file)
+                    .map_err(|e| format!("Failed to read extern asset '{}': {}", file.display(), e))?;
+                add_standalone_asset(files, child_id, child_fourcc, child_bytes, assets, dependencies)?;
+            },
+            None => dependencies.push((child_id, child_fourcc)),
+        }
+    }
+
+    assets.insert(id, ExternAsset { fourcc, bytes });
+    Ok(())
+}
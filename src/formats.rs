@@ -0,0 +1,99 @@
+//! Stable entry points for reading and writing Metroid Prime's file formats.
+//!
+//! The `structs` and `reader_writer` crates are re-exported from the crate root as a whole, but
+//! they change whenever the patcher needs them to. The items listed here are the part of them
+//! that tools outside of randomprime (level viewers, asset extractors, ...) can rely on: they are
+//! only changed in a breaking way along with a bump of randomprime's minor version.
+//!
+//! Everything borrows from the bytes it was read from, so the disc image has to outlive the
+//! structures read from it. Parsing is lazy, e.g. a `Pak`'s resources are only parsed once
+//! something asks for their contents.
+//!
+//! ```no_run
+//! use randomprime::formats::{self, FourCC, Reader, Strg};
+//!
+//! let iso = std::fs::read("prime.iso").unwrap();
+//! let disc = formats::read_disc(&iso);
+//! let pak = formats::read_pak(&disc, "Metroid2.pak").unwrap();
+//! for res in pak.resources.iter() {
+//!     if res.fourcc() == FourCC::from_bytes(b"STRG") {
+//!         let bytes = formats::resource_bytes(&res);
+//!         let strg: Strg = Reader::new(&bytes[..]).read(());
+//!         println!("{:08X}: {} languages", res.file_id, strg.string_tables.iter().count());
+//!     }
+//! }
+//! ```
+
+pub use reader_writer::{
+    CStr,
+    FourCC,
+    LCow,
+    Readable,
+    Reader,
+    Writable,
+};
+
+pub use structs::{
+    Ancs,
+    Cmdl,
+    Connection,
+    ConnectionMsg,
+    ConnectionState,
+    Evnt,
+    FstEntry,
+    FstEntryFile,
+    GcDisc,
+    Mlvl,
+    Mrea,
+    MreaSection,
+    NamedResource,
+    Pak,
+    Resource,
+    ResourceKind,
+    Scan,
+    Scly,
+    SclyLayer,
+    SclyObject,
+    SclyProperty,
+    Strg,
+    Txtr,
+    TxtrFormat,
+    TxtrPaletteFormat,
+};
+
+use crate::{GcDiscLookupExtensions, ResourceData};
+
+/// Reads the filesystem of a GameCube disc image. Compressed images (GCZ, CISO, RVZ) have to be
+/// decompressed first.
+pub fn read_disc(iso: &[u8]) -> GcDisc<'_>
+{
+    Reader::new(iso).read(())
+}
+
+/// Reads one of the disc's PAKs, e.g. "Metroid2.pak". Returns `None` if there's no such file or
+/// it isn't a PAK.
+pub fn read_pak<'r>(disc: &GcDisc<'r>, name: &str) -> Option<Pak<'r>>
+{
+    match disc.find_file(name)?.file()? {
+        FstEntryFile::Pak(pak) => Some(pak.clone()),
+        FstEntryFile::Unknown(reader) if name.to_lowercase().ends_with(".pak") => {
+            Some(reader.clone().read(()))
+        },
+        _ => None,
+    }
+}
+
+/// The uncompressed contents of a resource, which can be read with the struct for its FourCC
+pub fn resource_bytes(res: &Resource) -> Vec<u8>
+{
+    match &res.kind {
+        ResourceKind::Unknown(_, _) => ResourceData::new(res).decompress().into_owned(),
+        ResourceKind::External(_, _) => ResourceData::new_external(res).decompress().into_owned(),
+        _ => {
+            // Already parsed, write it back out (padded to 32 bytes like in the PAK)
+            let mut bytes = vec![];
+            res.write_to(&mut bytes).unwrap();
+            bytes
+        },
+    }
+}
@@ -0,0 +1,69 @@
+use reader_writer::byteorder::{ByteOrder, LittleEndian};
+
+use flate2::{Decompress, FlushDecompress};
+
+// https://github.com/dolphin-emu/dolphin/blob/8f460a1cda1a4d4208c4da9e01bf775f5f704498/Source/Core/DiscIO/CompressedBlob.cpp
+
+const GCZ_MAGIC: u32 = 0xB10BC001;
+const HEADER_SIZE: usize = 32;
+
+pub fn is_gcz(data: &[u8]) -> bool
+{
+    data.len() >= HEADER_SIZE && LittleEndian::read_u32(&data[0..4]) == GCZ_MAGIC
+}
+
+/// Decompresses an entire GCZ image into an anonymous memory map
+pub fn decompress_gcz(data: &[u8]) -> Result<memmap::Mmap, String>
+{
+    if !is_gcz(data) {
+        Err("Not a GCZ file".to_string())?
+    }
+
+    let compressed_data_size = LittleEndian::read_u64(&data[8..16]);
+    let data_size = LittleEndian::read_u64(&data[16..24]) as usize;
+    let block_size = LittleEndian::read_u32(&data[24..28]) as usize;
+    let num_blocks = LittleEndian::read_u32(&data[28..32]) as usize;
+
+    let data_start = HEADER_SIZE + 12 * num_blocks;
+    if block_size == 0 || data.len() < data_start {
+        Err("GCZ header is corrupt".to_string())?
+    }
+    let block_offsets: Vec<u64> = (0..num_blocks)
+        .map(|i| LittleEndian::read_u64(&data[HEADER_SIZE + i * 8..]))
+        .collect();
+    let compressed = &data[data_start..];
+
+    let mut output = memmap::MmapMut::map_anon(data_size)
+        .map_err(|e| format!("Failed to allocate memory for GCZ decompression: {}", e))?;
+
+    let mut decompressor = Decompress::new(true);
+    for (i, offset) in block_offsets.iter().enumerate() {
+        let is_uncompressed = offset & 0x8000000000000000 != 0;
+        let start = (offset & !0x8000000000000000) as usize;
+        let end = block_offsets.get(i + 1)
+            .map(|o| o & !0x8000000000000000)
+            .unwrap_or(compressed_data_size) as usize;
+
+        let out_start = i * block_size;
+        if out_start >= data_size {
+            break;
+        }
+        let out_end = std::cmp::min(out_start + block_size, data_size);
+
+        let block = compressed.get(start..end)
+            .ok_or_else(|| format!("GCZ block {} is out of bounds", i))?;
+        let out = &mut output[out_start..out_end];
+
+        if is_uncompressed {
+            let len = std::cmp::min(block.len(), out.len());
+            out[..len].copy_from_slice(&block[..len]);
+        } else {
+            decompressor.reset(true);
+            decompressor.decompress(block, out, FlushDecompress::Finish)
+                .map_err(|e| format!("Failed to decompress GCZ block {}: {}", i, e))?;
+        }
+    }
+
+    output.make_read_only()
+        .map_err(|e| format!("Failed to decompress GCZ: {}", e))
+}
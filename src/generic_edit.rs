@@ -4,17 +4,17 @@ use crate::{
     mlvl_wrapper,
 };
 
-use reader_writer::CStrConversionExtension;
+use reader_writer::{CStrConversionExtension, Writable};
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-use crate::patch_config::EditObjConfig;
+use crate::patch_config::{EditObjConfig, EditPropertyConfig, EnemyConfig, PropertyType, PropertyValue};
 
 pub fn patch_edit_objects<'r>
 (
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
-    edit_objs: HashMap<u32, EditObjConfig>,
+    edit_objs: BTreeMap<u32, EditObjConfig>,
 )
 -> Result<(), String>
 {
@@ -179,6 +179,88 @@ pub fn patch_edit_objects<'r>
     Ok(())
 }
 
+pub fn patch_edit_properties<'r>
+(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    edit_properties: Vec<EditPropertyConfig>,
+)
+-> Result<(), String>
+{
+    let mrea_id = area.mlvl_area.mrea.to_u32().clone();
+    let scly = area.mrea().scly_section_mut();
+
+    for config in edit_properties.iter() {
+        let mut found = false;
+        for layer in scly.layers.as_mut_vec().iter_mut() {
+            for obj in layer.objects.as_mut_vec().iter_mut() {
+                if obj.property_data.object_type() != config.object_type {
+                    continue;
+                }
+                if let Some(id) = config.instance_id {
+                    if obj.instance_id & 0x00FFFFFF != id & 0x00FFFFFF {
+                        continue;
+                    }
+                }
+
+                set_raw_property(obj, config)
+                    .map_err(|e| format!("{} in room 0x{:X}", e, mrea_id))?;
+                found = true;
+            }
+        }
+
+        if !found {
+            Err(format!(
+                "Could not find any object of type 0x{:X}{} in room 0x{:X}",
+                config.object_type,
+                config.instance_id.map(|id| format!(" with id 0x{:X}", id)).unwrap_or_default(),
+                mrea_id,
+            ))?
+        }
+    }
+
+    Ok(())
+}
+
+pub fn patch_scale_enemies<'r>
+(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    config: &EnemyConfig,
+)
+-> Result<(), String>
+{
+    let scly = area.mrea().scly_section_mut();
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            if get_patterned_infos(obj).is_empty() {
+                continue; // not an enemy
+            }
+
+            if let Some(value) = config.health {
+                set_health(obj, value, None);
+            }
+
+            if let Some(value) = config.damage {
+                set_damage(obj, value);
+            }
+
+            if let Some(value) = config.speed {
+                set_patterned_speed(obj, value, None);
+            }
+
+            if let Some(value) = config.size {
+                set_patterned_size(obj, value, None);
+                if obj.property_data.supports_scale() {
+                    set_scale(obj, [value, value, value], true);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /* Interface */
 
 pub fn set_position(obj: &mut structs::SclyObject, value: [f32; 3], relative: bool) {
@@ -368,8 +450,71 @@ pub fn set_damage(obj: &mut structs::SclyObject, value: f32) {
     }
 }
 
+/// Overwrites part of an object's property data. Unlike the other setters this works for every
+/// object type, but it's up to the caller to know the layout of the properties. The edited object
+/// is kept as raw bytes and can't be accessed through its typed accessors afterwards, which is why
+/// these edits are registered after every other patch.
+pub fn set_raw_property(obj: &mut structs::SclyObject, config: &EditPropertyConfig) -> Result<(), String> {
+    let value = encode_property_value(config.property_type, &config.value)
+        .ok_or_else(|| format!(
+            "{:?} isn't a valid value for a property of type {:?}",
+            config.value, config.property_type,
+        ))?;
+
+    let mut data = vec![];
+    obj.property_data.write_to(&mut data).unwrap();
+
+    // Skip the property count and the name, whose length differs between objects of the same type
+    let name_end = data.get(4..)
+        .and_then(|name| name.iter().position(|c| *c == 0))
+        .ok_or_else(|| format!("object 0x{:X} has no name", obj.instance_id))?;
+    let start = 4 + name_end + 1 + config.offset as usize;
+    if start + value.len() > data.len() {
+        Err(format!(
+            "offset {} is past the end of object 0x{:X}'s properties ({} bytes)",
+            config.offset, obj.instance_id, data.len() - (4 + name_end + 1),
+        ))?
+    }
+    data[start..start + value.len()].copy_from_slice(&value);
+
+    obj.property_data = structs::SclyProperty::External {
+        object_type: obj.property_data.object_type(),
+        data,
+    };
+
+    Ok(())
+}
+
 /* Helpers */
 
+fn encode_property_value(property_type: PropertyType, value: &PropertyValue) -> Option<Vec<u8>> {
+    let floats = |count: usize| match value {
+        PropertyValue::List(values) if values.len() == count => {
+            Some(values.iter().flat_map(|x| x.to_be_bytes()).collect())
+        },
+        _ => None,
+    };
+
+    match (property_type, value) {
+        (PropertyType::Bool, PropertyValue::Bool(x)) => Some(vec![*x as u8]),
+        (PropertyType::U8, PropertyValue::Number(x)) if *x >= 0.0 && *x <= u8::MAX as f64 => {
+            Some(vec![*x as u8])
+        },
+        (PropertyType::U32, PropertyValue::Number(x)) |
+        (PropertyType::AssetId, PropertyValue::Number(x)) if *x >= 0.0 && *x <= u32::MAX as f64 => {
+            Some((*x as u32).to_be_bytes().to_vec())
+        },
+        (PropertyType::I32, PropertyValue::Number(x)) if *x >= i32::MIN as f64 && *x <= i32::MAX as f64 => {
+            Some((*x as i32).to_be_bytes().to_vec())
+        },
+        (PropertyType::F32, PropertyValue::Number(x)) => Some((*x as f32).to_be_bytes().to_vec()),
+        (PropertyType::Vector3, _) => floats(3),
+        (PropertyType::Color, _) => floats(4),
+        _ => None,
+    }
+}
+
+
 fn should_skip(current: usize, check: Option<usize>) -> bool {
     match check {
         Some(x) => x != current,
@@ -0,0 +1,785 @@
+use image::{imageops::FilterType, ImageFormat};
+use reader_writer::byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
+use serde::Deserialize;
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+};
+
+use crate::txtr_conversions::rgba8_to_txtr;
+
+// Converts glTF 2.0 models (.gltf with external or embedded buffers, or .glb) to a CMDL and the
+// TXTRs it uses. Only what a static prop needs is supported: triangle meshes with positions,
+// normals and one set of UVs, and a base color texture/factor per material. Node transforms are
+// applied, skins, morph targets and animations are ignored.
+//
+// Format documentation
+// https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html
+// https://wiki.axiodl.com/w/CMDL_(Metroid_Prime)
+
+const GLB_MAGIC: u32 = 0x46546C67; // "glTF"
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
+
+const MODE_TRIANGLES: u32 = 4;
+
+const COMPONENT_I8: u32 = 5120;
+const COMPONENT_U8: u32 = 5121;
+const COMPONENT_I16: u32 = 5122;
+const COMPONENT_U16: u32 = 5123;
+const COMPONENT_U32: u32 = 5125;
+const COMPONENT_F32: u32 = 5126;
+
+const MAX_TEXTURE_SIZE: u32 = 1024;
+
+/* Structs for modeling JSON format */
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfJson
+{
+    scene: Option<usize>,
+    scenes: Vec<GltfScene>,
+    nodes: Vec<GltfNode>,
+    meshes: Vec<GltfMesh>,
+    accessors: Vec<GltfAccessor>,
+    buffer_views: Vec<GltfBufferView>,
+    buffers: Vec<GltfBuffer>,
+    materials: Vec<GltfMaterial>,
+    textures: Vec<GltfTexture>,
+    images: Vec<GltfImage>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfScene
+{
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfNode
+{
+    mesh: Option<usize>,
+    children: Vec<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfMesh
+{
+    primitives: Vec<GltfPrimitive>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfPrimitive
+{
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    mode: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfAccessor
+{
+    buffer_view: Option<usize>,
+    byte_offset: usize,
+    component_type: u32,
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    type_: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfBufferView
+{
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfBuffer
+{
+    uri: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfMaterial
+{
+    pbr_metallic_roughness: GltfPbr,
+    alpha_mode: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfPbr
+{
+    base_color_factor: [f32; 4],
+    base_color_texture: Option<GltfTextureInfo>,
+}
+
+impl Default for GltfPbr
+{
+    fn default() -> Self
+    {
+        GltfPbr {
+            base_color_factor: [1.0; 4],
+            base_color_texture: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfTextureInfo
+{
+    index: usize,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfTexture
+{
+    source: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct GltfImage
+{
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+    mime_type: Option<String>,
+}
+
+/* Conversion */
+
+struct Surface
+{
+    material: usize,
+    indices: Vec<u16>,
+}
+
+struct Material
+{
+    texture: usize,
+    transparent: bool,
+}
+
+/// Converts the model at `path`. Returns the CMDL and every TXTR it references, using ids from
+/// `new_id` for the textures.
+pub fn gltf_to_cmdl<F>(path: &Path, mut new_id: F)
+    -> Result<(Vec<u8>, Vec<(u32, Vec<u8>)>), String>
+    where F: FnMut() -> u32
+{
+    let file = fs::read(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let (json, glb_bin) = if file.len() >= 12 && LittleEndian::read_u32(&file[0..4]) == GLB_MAGIC {
+        parse_glb(&file)?
+    } else {
+        (&file[..], None)
+    };
+    let gltf: GltfJson = serde_json::from_slice(json)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+    let buffers = gltf.buffers.iter().enumerate()
+        .map(|(i, buffer)| match (&buffer.uri, glb_bin) {
+            (Some(uri), _) => read_uri(dir, uri),
+            (None, Some(bin)) if i == 0 => Ok(bin.to_vec()),
+            (None, _) => Err(format!("Buffer {} has no data", i)),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut positions: Vec<[f32; 3]> = vec![];
+    let mut normals: Vec<[f32; 3]> = vec![];
+    let mut uvs: Vec<[f32; 2]> = vec![];
+    let mut surfaces: Vec<Surface> = vec![];
+
+    let root_nodes = match gltf.scenes.get(gltf.scene.unwrap_or(0)) {
+        Some(scene) => scene.nodes.clone(),
+        None => (0..gltf.nodes.len()).collect(), // no scenes, treat every node as a root
+    };
+    let mut stack: Vec<(usize, [f32; 16])> = root_nodes.iter().map(|i| (*i, IDENTITY)).collect();
+    while let Some((node_idx, parent_transform)) = stack.pop() {
+        let node = gltf.nodes.get(node_idx)
+            .ok_or_else(|| format!("Node {} doesn't exist", node_idx))?;
+        let transform = mat_mul(&parent_transform, &node_transform(node));
+        stack.extend(node.children.iter().map(|child| (*child, transform)));
+
+        let mesh = match node.mesh {
+            Some(mesh) => gltf.meshes.get(mesh).ok_or_else(|| format!("Mesh {} doesn't exist", mesh))?,
+            None => continue,
+        };
+        for primitive in &mesh.primitives {
+            if primitive.mode.unwrap_or(MODE_TRIANGLES) != MODE_TRIANGLES {
+                println!("Warning, skipping a primitive in {} which isn't made of triangles", path.display());
+                continue;
+            }
+
+            let position_accessor = *primitive.attributes.get("POSITION")
+                .ok_or_else(|| format!("A primitive in {} has no positions", path.display()))?;
+            let prim_positions = read_accessor(&gltf, &buffers, position_accessor, 3)?;
+            let vertex_count = prim_positions.len();
+
+            let prim_indices: Vec<usize> = match primitive.indices {
+                Some(accessor) => read_indices(&gltf, &buffers, accessor)?,
+                None => (0..vertex_count).collect(),
+            };
+            if prim_indices.iter().any(|i| *i >= vertex_count) {
+                Err(format!("A primitive in {} has out of bounds indices", path.display()))?
+            }
+
+            let prim_normals = match primitive.attributes.get("NORMAL") {
+                Some(accessor) => read_accessor(&gltf, &buffers, *accessor, 3)?,
+                None => smooth_normals(&prim_positions, &prim_indices),
+            };
+            let prim_uvs = match primitive.attributes.get("TEXCOORD_0") {
+                Some(accessor) => read_accessor(&gltf, &buffers, *accessor, 2)?,
+                None => vec![vec![0.0, 0.0]; vertex_count],
+            };
+            if prim_normals.len() != vertex_count || prim_uvs.len() != vertex_count {
+                Err(format!(
+                    "A primitive in {} has {} positions but {} normals and {} texture coordinates",
+                    path.display(), vertex_count, prim_normals.len(), prim_uvs.len(),
+                ))?
+            }
+
+            let material = match primitive.material {
+                Some(material) if material >= gltf.materials.len() => {
+                    Err(format!("Material {} doesn't exist in {}", material, path.display()))?
+                },
+                Some(material) => material,
+                None => gltf.materials.len(), // the default material
+            };
+
+            let base = positions.len();
+            if base + vertex_count > u16::MAX as usize {
+                Err(format!("{} has too many vertices (the limit is {})", path.display(), u16::MAX))?
+            }
+            for i in 0..vertex_count {
+                // glTF is Y up, the game is Z up
+                let p = transform_point(&transform, &prim_positions[i]);
+                let n = transform_direction(&transform, &prim_normals[i]);
+                positions.push([p[0], -p[2], p[1]]);
+                normals.push([n[0], -n[2], n[1]]);
+                // Textures are stored bottom row first
+                uvs.push([prim_uvs[i][0], 1.0 - prim_uvs[i][1]]);
+            }
+
+            surfaces.push(Surface {
+                material,
+                indices: prim_indices.iter().map(|i| (base + i) as u16).collect(),
+            });
+        }
+    }
+
+    if surfaces.is_empty() {
+        Err(format!("{} doesn't contain any meshes", path.display()))?
+    }
+
+    // Every material needs a texture for the TEV setup below, so untextured materials get a
+    // small one filled with their base color. The last material is the default material.
+    let mut textures: Vec<(u32, Vec<u8>)> = vec![];
+    let mut materials: Vec<Material> = vec![];
+    let default_material = GltfMaterial::default();
+    for material in gltf.materials.iter().chain(std::iter::once(&default_material)) {
+        let pbr = &material.pbr_metallic_roughness;
+        let image = match &pbr.base_color_texture {
+            Some(texture_info) => {
+                let image_idx = gltf.textures.get(texture_info.index)
+                    .and_then(|texture| texture.source)
+                    .ok_or_else(|| format!("Texture {} has no image", texture_info.index))?;
+                read_image(&gltf, &buffers, dir, image_idx)?
+            },
+            None => image::RgbaImage::from_pixel(4, 4, image::Rgba([255; 4])),
+        };
+        let (width, height, mut pixels) = texture_pixels(image);
+        for pixel in pixels.chunks_mut(4) {
+            for (c, factor) in pixel.iter_mut().zip(pbr.base_color_factor.iter()) {
+                *c = (*c as f32 * factor.max(0.0).min(1.0)).round() as u8;
+            }
+        }
+
+        textures.push((new_id(), rgba8_to_txtr(&pixels, width, height)));
+        materials.push(Material {
+            texture: textures.len() - 1,
+            transparent: material.alpha_mode.as_deref() == Some("BLEND"),
+        });
+    }
+
+    let texture_ids: Vec<u32> = textures.iter().map(|(id, _)| *id).collect();
+    let cmdl = write_cmdl(&positions, &normals, &uvs, &texture_ids, &materials, &surfaces);
+    Ok((cmdl, textures))
+}
+
+fn parse_glb(file: &[u8]) -> Result<(&[u8], Option<&[u8]>), String>
+{
+    let mut json = None;
+    let mut bin = None;
+    let mut pos = 12;
+    while pos + 8 <= file.len() {
+        let len = LittleEndian::read_u32(&file[pos..pos + 4]) as usize;
+        let kind = LittleEndian::read_u32(&file[pos + 4..pos + 8]);
+        let data = file.get(pos + 8..pos + 8 + len)
+            .ok_or_else(|| "GLB chunk is out of bounds".to_string())?;
+        match kind {
+            GLB_CHUNK_JSON => json = Some(data),
+            GLB_CHUNK_BIN => bin = Some(data),
+            _ => (),
+        }
+        pos += 8 + len;
+    }
+    Ok((json.ok_or_else(|| "GLB file has no JSON chunk".to_string())?, bin))
+}
+
+fn read_uri(dir: &Path, uri: &str) -> Result<Vec<u8>, String>
+{
+    if uri.starts_with("data:") {
+        let data = uri.splitn(2, ";base64,").nth(1)
+            .ok_or_else(|| "Only base64 data URIs are supported".to_string())?;
+        return base64_decode(data);
+    }
+    let path = dir.join(uri.replace("%20", " "));
+    fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn base64_decode(data: &str) -> Result<Vec<u8>, String>
+{
+    let mut result = Vec::with_capacity(data.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in data.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b'\r' | b'\n' | b' ' => continue,
+            _ => Err(format!("Invalid character '{}' in base64 data", c as char))?,
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((acc >> bits) as u8);
+        }
+    }
+    Ok(result)
+}
+
+fn buffer_view_data<'a>(gltf: &GltfJson, buffers: &'a [Vec<u8>], view_idx: usize)
+    -> Result<(&'a [u8], Option<usize>), String>
+{
+    let view = gltf.buffer_views.get(view_idx)
+        .ok_or_else(|| format!("Buffer view {} doesn't exist", view_idx))?;
+    let data = buffers.get(view.buffer)
+        .and_then(|buffer| buffer.get(view.byte_offset..view.byte_offset + view.byte_length))
+        .ok_or_else(|| format!("Buffer view {} is out of bounds", view_idx))?;
+    Ok((data, view.byte_stride))
+}
+
+/// Reads an accessor as floats, normalizing integer components if needed
+fn read_accessor(gltf: &GltfJson, buffers: &[Vec<u8>], accessor_idx: usize, components: usize)
+    -> Result<Vec<Vec<f32>>, String>
+{
+    let accessor = gltf.accessors.get(accessor_idx)
+        .ok_or_else(|| format!("Accessor {} doesn't exist", accessor_idx))?;
+    let expected_type = match components {
+        2 => "VEC2",
+        3 => "VEC3",
+        _ => "VEC4",
+    };
+    if accessor.type_ != expected_type {
+        Err(format!("Accessor {} is a {} instead of a {}", accessor_idx, accessor.type_, expected_type))?
+    }
+
+    let view_idx = match accessor.buffer_view {
+        Some(view_idx) => view_idx,
+        None => return Ok(vec![vec![0.0; components]; accessor.count]),
+    };
+    let (data, stride) = buffer_view_data(gltf, buffers, view_idx)?;
+
+    let component_size = component_size(accessor.component_type)?;
+    let stride = stride.unwrap_or(component_size * components);
+    let mut result = Vec::with_capacity(accessor.count);
+    for i in 0..accessor.count {
+        let start = accessor.byte_offset + i * stride;
+        let element = data.get(start..start + component_size * components)
+            .ok_or_else(|| format!("Accessor {} is out of bounds", accessor_idx))?;
+        let values = element.chunks_exact(component_size)
+            .map(|c| match (accessor.component_type, accessor.normalized) {
+                (COMPONENT_F32, _) => LittleEndian::read_f32(c),
+                (COMPONENT_U8, true) => c[0] as f32 / 255.0,
+                (COMPONENT_U16, true) => LittleEndian::read_u16(c) as f32 / 65535.0,
+                (COMPONENT_I8, true) => (c[0] as i8 as f32 / 127.0).max(-1.0),
+                (COMPONENT_I16, true) => (LittleEndian::read_i16(c) as f32 / 32767.0).max(-1.0),
+                (COMPONENT_U8, false) => c[0] as f32,
+                (COMPONENT_U16, false) => LittleEndian::read_u16(c) as f32,
+                (COMPONENT_I8, false) => c[0] as i8 as f32,
+                (COMPONENT_I16, false) => LittleEndian::read_i16(c) as f32,
+                _ => LittleEndian::read_u32(c) as f32,
+            })
+            .collect();
+        result.push(values);
+    }
+    Ok(result)
+}
+
+fn read_indices(gltf: &GltfJson, buffers: &[Vec<u8>], accessor_idx: usize) -> Result<Vec<usize>, String>
+{
+    let accessor = gltf.accessors.get(accessor_idx)
+        .ok_or_else(|| format!("Accessor {} doesn't exist", accessor_idx))?;
+    let view_idx = accessor.buffer_view
+        .ok_or_else(|| format!("Index accessor {} has no data", accessor_idx))?;
+    let (data, stride) = buffer_view_data(gltf, buffers, view_idx)?;
+
+    let component_size = component_size(accessor.component_type)?;
+    let stride = stride.unwrap_or(component_size);
+    (0..accessor.count)
+        .map(|i| {
+            let start = accessor.byte_offset + i * stride;
+            let c = data.get(start..start + component_size)
+                .ok_or_else(|| format!("Accessor {} is out of bounds", accessor_idx))?;
+            Ok(match accessor.component_type {
+                COMPONENT_U8 => c[0] as usize,
+                COMPONENT_U16 => LittleEndian::read_u16(c) as usize,
+                COMPONENT_U32 => LittleEndian::read_u32(c) as usize,
+                _ => Err(format!("Accessor {} can't be used for indices", accessor_idx))?,
+            })
+        })
+        .collect()
+}
+
+fn component_size(component_type: u32) -> Result<usize, String>
+{
+    match component_type {
+        COMPONENT_I8 | COMPONENT_U8 => Ok(1),
+        COMPONENT_I16 | COMPONENT_U16 => Ok(2),
+        COMPONENT_U32 | COMPONENT_F32 => Ok(4),
+        _ => Err(format!("Unknown accessor component type {}", component_type)),
+    }
+}
+
+fn read_image(gltf: &GltfJson, buffers: &[Vec<u8>], dir: &Path, image_idx: usize)
+    -> Result<image::RgbaImage, String>
+{
+    let image = gltf.images.get(image_idx)
+        .ok_or_else(|| format!("Image {} doesn't exist", image_idx))?;
+    let bytes = match (&image.uri, image.buffer_view) {
+        (Some(uri), _) => read_uri(dir, uri)?,
+        (None, Some(view_idx)) => buffer_view_data(gltf, buffers, view_idx)?.0.to_vec(),
+        (None, None) => Err(format!("Image {} has no data", image_idx))?,
+    };
+    if image.mime_type.as_deref().map(|mime_type| mime_type != "image/png").unwrap_or(false)
+        || !bytes.starts_with(b"\x89PNG")
+    {
+        Err(format!("Image {} isn't a PNG, which is the only supported image format", image_idx))?
+    }
+    let image = image::load_from_memory_with_format(&bytes, ImageFormat::Png)
+        .map_err(|e| format!("Failed to decode image {}: {}", image_idx, e))?;
+    Ok(image.into_rgba8())
+}
+
+/// Resizes the image to power of two dimensions the GameCube can use
+pub(crate) fn texture_pixels(image: image::RgbaImage) -> (usize, usize, Vec<u8>)
+{
+    let fit = |x: u32| x.next_power_of_two().max(4).min(MAX_TEXTURE_SIZE);
+    let (width, height) = (fit(image.width()), fit(image.height()));
+    let image = if (width, height) != image.dimensions() {
+        image::imageops::resize(&image, width, height, FilterType::Triangle)
+    } else {
+        image
+    };
+    (width as usize, height as usize, image.into_raw())
+}
+
+fn smooth_normals(positions: &[Vec<f32>], indices: &[usize]) -> Vec<Vec<f32>>
+{
+    let mut normals = vec![[0.0f32; 3]; positions.len()];
+    for tri in indices.chunks_exact(3) {
+        let (a, b, c) = (&positions[tri[0]], &positions[tri[1]], &positions[tri[2]]);
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+        for i in tri {
+            for k in 0..3 {
+                normals[*i][k] += n[k];
+            }
+        }
+    }
+    normals.iter().map(|n| normalize(n).to_vec()).collect()
+}
+
+/* Matrix math, column major like glTF */
+
+const IDENTITY: [f32; 16] = [
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 1.0, 0.0,
+    0.0, 0.0, 0.0, 1.0,
+];
+
+fn node_transform(node: &GltfNode) -> [f32; 16]
+{
+    if let Some(matrix) = node.matrix {
+        return matrix;
+    }
+
+    let [tx, ty, tz] = node.translation.unwrap_or([0.0; 3]);
+    let [x, y, z, w] = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+    let [sx, sy, sz] = node.scale.unwrap_or([1.0; 3]);
+    [
+        (1.0 - 2.0 * (y * y + z * z)) * sx, (2.0 * (x * y + z * w)) * sx, (2.0 * (x * z - y * w)) * sx, 0.0,
+        (2.0 * (x * y - z * w)) * sy, (1.0 - 2.0 * (x * x + z * z)) * sy, (2.0 * (y * z + x * w)) * sy, 0.0,
+        (2.0 * (x * z + y * w)) * sz, (2.0 * (y * z - x * w)) * sz, (1.0 - 2.0 * (x * x + y * y)) * sz, 0.0,
+        tx, ty, tz, 1.0,
+    ]
+}
+
+fn mat_mul(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16]
+{
+    let mut result = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            result[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    result
+}
+
+fn transform_point(m: &[f32; 16], p: &[f32]) -> [f32; 3]
+{
+    [
+        m[0] * p[0] + m[4] * p[1] + m[8] * p[2] + m[12],
+        m[1] * p[0] + m[5] * p[1] + m[9] * p[2] + m[13],
+        m[2] * p[0] + m[6] * p[1] + m[10] * p[2] + m[14],
+    ]
+}
+
+fn transform_direction(m: &[f32; 16], d: &[f32]) -> [f32; 3]
+{
+    normalize(&[
+        m[0] * d[0] + m[4] * d[1] + m[8] * d[2],
+        m[1] * d[0] + m[5] * d[1] + m[9] * d[2],
+        m[2] * d[0] + m[6] * d[1] + m[10] * d[2],
+    ])
+}
+
+fn normalize(v: &[f32; 3]) -> [f32; 3]
+{
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len == 0.0 {
+        [0.0, 0.0, 1.0]
+    } else {
+        [v[0] / len, v[1] / len, v[2] / len]
+    }
+}
+
+/* CMDL writing */
+
+fn pad_to_32(bytes: &mut Vec<u8>)
+{
+    bytes.resize((bytes.len() + 31) & !31, 0);
+}
+
+fn write_material(out: &mut Vec<u8>, material: &Material, group: u32)
+{
+    // Depth write, texture slot 0 used, and depth sorting for blended materials
+    let flags = 0x80 | (1 << 16) | if material.transparent { 0x10 } else { 0 };
+    out.write_u32::<BigEndian>(flags).unwrap();
+    out.write_u32::<BigEndian>(1).unwrap(); // texture count
+    out.write_u32::<BigEndian>(material.texture as u32).unwrap();
+    out.write_u32::<BigEndian>(0x30F).unwrap(); // indexed positions, normals and UVs
+    out.write_u32::<BigEndian>(group).unwrap();
+
+    // Blend factors (destination first)
+    if material.transparent {
+        out.write_u16::<BigEndian>(5).unwrap(); // GX_BL_INVSRCALPHA
+        out.write_u16::<BigEndian>(4).unwrap(); // GX_BL_SRCALPHA
+    } else {
+        out.write_u16::<BigEndian>(0).unwrap(); // GX_BL_ZERO
+        out.write_u16::<BigEndian>(1).unwrap(); // GX_BL_ONE
+    }
+
+    // One lit color channel
+    out.write_u32::<BigEndian>(1).unwrap();
+    out.write_u32::<BigEndian>(1).unwrap();
+
+    // A single TEV stage, color = texture * lighting and alpha = texture
+    out.write_u32::<BigEndian>(1).unwrap();
+    out.write_u32::<BigEndian>(0xF | (0x8 << 5) | (0xA << 10) | (0xF << 15)).unwrap(); // 0, TEXC, RASC, 0
+    out.write_u32::<BigEndian>(0x7 | (0x7 << 5) | (0x7 << 10) | (0x4 << 15)).unwrap(); // 0, 0, 0, TEXA
+    out.write_u32::<BigEndian>(0x100).unwrap(); // add, clamp, to TEVPREV
+    out.write_u32::<BigEndian>(0x100).unwrap();
+    out.write_u8(0).unwrap();
+    out.write_u8(0).unwrap(); // constant alpha 1
+    out.write_u8(0).unwrap(); // constant color 1
+    out.write_u8(4).unwrap(); // GX_COLOR0A0
+    // TEV stage texture input
+    out.write_u16::<BigEndian>(0).unwrap();
+    out.write_u8(0).unwrap(); // tex coord
+    out.write_u8(0).unwrap(); // texture
+
+    // One texgen, 2x4 identity matrix from UV set 0
+    out.write_u32::<BigEndian>(1).unwrap();
+    out.write_u32::<BigEndian>(1 | (4 << 4) | (10 << 9) | (20 << 15)).unwrap();
+
+    // No UV animations
+    out.write_u32::<BigEndian>(4).unwrap();
+    out.write_u32::<BigEndian>(0).unwrap();
+}
+
+fn write_cmdl(
+    positions: &[[f32; 3]],
+    normals: &[[f32; 3]],
+    uvs: &[[f32; 2]],
+    texture_ids: &[u32],
+    materials: &[Material],
+    surfaces: &[Surface],
+) -> Vec<u8>
+{
+    let mut sections: Vec<Vec<u8>> = vec![];
+
+    // Material set
+    let mut material_set = vec![];
+    material_set.write_u32::<BigEndian>(texture_ids.len() as u32).unwrap();
+    for id in texture_ids {
+        material_set.write_u32::<BigEndian>(*id).unwrap();
+    }
+    let mut material_data = vec![];
+    let mut material_ends = vec![];
+    for (i, material) in materials.iter().enumerate() {
+        write_material(&mut material_data, material, i as u32);
+        material_ends.push(material_data.len() as u32);
+    }
+    material_set.write_u32::<BigEndian>(materials.len() as u32).unwrap();
+    for end in material_ends {
+        material_set.write_u32::<BigEndian>(end).unwrap();
+    }
+    material_set.extend_from_slice(&material_data);
+    sections.push(material_set);
+
+    // Vertex data: positions, normals, colors (unused), UVs
+    let mut section = vec![];
+    for p in positions {
+        p.iter().for_each(|x| section.write_f32::<BigEndian>(*x).unwrap());
+    }
+    sections.push(section);
+    let mut section = vec![];
+    for n in normals {
+        n.iter().for_each(|x| section.write_f32::<BigEndian>(*x).unwrap());
+    }
+    sections.push(section);
+    sections.push(vec![]);
+    let mut section = vec![];
+    for uv in uvs {
+        uv.iter().for_each(|x| section.write_f32::<BigEndian>(*x).unwrap());
+    }
+    sections.push(section);
+
+    // Surfaces, split so no display list draws more than a u16's worth of vertices
+    let mut surface_sections = vec![];
+    for surface in surfaces {
+        let max_vertices = (u16::MAX as usize / 3) * 3;
+        for chunk in surface.indices.chunks(max_vertices) {
+            surface_sections.push(write_surface(positions, normals, surface.material, chunk));
+        }
+    }
+
+    let mut surface_offsets = vec![];
+    surface_offsets.write_u32::<BigEndian>(surface_sections.len() as u32).unwrap();
+    let mut end = 0;
+    for surface in &mut surface_sections {
+        pad_to_32(surface);
+        end += surface.len() as u32;
+        surface_offsets.write_u32::<BigEndian>(end).unwrap();
+    }
+    sections.push(surface_offsets);
+    sections.extend(surface_sections);
+
+    for section in &mut sections {
+        pad_to_32(section);
+    }
+
+    let mut aabb = [f32::MAX, f32::MAX, f32::MAX, f32::MIN, f32::MIN, f32::MIN];
+    for p in positions {
+        for k in 0..3 {
+            aabb[k] = aabb[k].min(p[k]);
+            aabb[k + 3] = aabb[k + 3].max(p[k]);
+        }
+    }
+
+    let mut cmdl = vec![];
+    cmdl.write_u32::<BigEndian>(0xDEADBABE).unwrap();
+    cmdl.write_u32::<BigEndian>(2).unwrap();
+    cmdl.write_u32::<BigEndian>(0).unwrap(); // float normals, no short UVs
+    aabb.iter().for_each(|x| cmdl.write_f32::<BigEndian>(*x).unwrap());
+    cmdl.write_u32::<BigEndian>(sections.len() as u32).unwrap();
+    cmdl.write_u32::<BigEndian>(1).unwrap(); // material set count
+    for section in &sections {
+        cmdl.write_u32::<BigEndian>(section.len() as u32).unwrap();
+    }
+    pad_to_32(&mut cmdl);
+    for section in &sections {
+        cmdl.extend_from_slice(section);
+    }
+    cmdl
+}
+
+fn write_surface(positions: &[[f32; 3]], normals: &[[f32; 3]], material: usize, indices: &[u16])
+    -> Vec<u8>
+{
+    let mut centroid = [0.0f32; 3];
+    let mut normal = [0.0f32; 3];
+    for i in indices {
+        for k in 0..3 {
+            centroid[k] += positions[*i as usize][k] / indices.len() as f32;
+            normal[k] += normals[*i as usize][k];
+        }
+    }
+    let normal = normalize(&normal);
+
+    let mut display_list = vec![];
+    display_list.write_u8(0x90).unwrap(); // GX_TRIANGLES, vertex format 0
+    display_list.write_u16::<BigEndian>(indices.len() as u16).unwrap();
+    for i in indices {
+        // Same index for position, normal and UV
+        for _ in 0..3 {
+            display_list.write_u16::<BigEndian>(*i).unwrap();
+        }
+    }
+    pad_to_32(&mut display_list);
+
+    let mut surface = vec![];
+    centroid.iter().for_each(|x| surface.write_f32::<BigEndian>(*x).unwrap());
+    surface.write_u32::<BigEndian>(material as u32).unwrap();
+    surface.write_u16::<BigEndian>(0x8000).unwrap(); // mantissa
+    surface.write_u16::<BigEndian>(display_list.len() as u16).unwrap();
+    surface.write_u32::<BigEndian>(0).unwrap(); // parent model pointer, set at runtime
+    surface.write_u32::<BigEndian>(0).unwrap(); // next surface pointer, set at runtime
+    surface.write_u32::<BigEndian>(0).unwrap(); // extra data size
+    normal.iter().for_each(|x| surface.write_f32::<BigEndian>(*x).unwrap());
+    pad_to_32(&mut surface);
+    surface.extend_from_slice(&display_list);
+    surface
+}
@@ -0,0 +1,287 @@
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{ToPrimitive, Zero};
+
+use std::collections::BTreeMap;
+
+use crate::{
+    elevators::{Elevator, World},
+    patch_config::{LevelConfig, PickupConfig},
+    pickup_meta::{self, PickupType},
+};
+
+/// The elevator section of a layout string which leaves every elevator vanilla, used when the
+/// string has none
+const VANILLA_ELEVATOR_LAYOUT: &str = "qzoCAr2fwehJmRjM";
+
+/// Note the swapped "WV" and "wv", the original front ends encode layouts with this table
+const LAYOUT_CHAR_TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUWVXYZabcdefghijklmnopqrstuwvxyz0123456789-_";
+
+/// Pickups in the order layout strings number them, with the amount each one gives. Missile
+/// expansions and the launcher share a number.
+const LAYOUT_PICKUPS: [(PickupType, i32); 37] = [
+    (PickupType::Missile, 5),
+    (PickupType::EnergyTank, 1),
+    (PickupType::ThermalVisor, 1),
+    (PickupType::XRayVisor, 1),
+    (PickupType::VariaSuit, 1),
+    (PickupType::GravitySuit, 1),
+    (PickupType::PhazonSuit, 1),
+    (PickupType::MorphBall, 1),
+    (PickupType::BoostBall, 1),
+    (PickupType::SpiderBall, 1),
+    (PickupType::MorphBallBomb, 1),
+    (PickupType::PowerBomb, 1), // expansion
+    (PickupType::PowerBomb, 4),
+    (PickupType::ChargeBeam, 1),
+    (PickupType::SpaceJumpBoots, 1),
+    (PickupType::GrappleBeam, 1),
+    (PickupType::SuperMissile, 1),
+    (PickupType::Wavebuster, 1),
+    (PickupType::IceSpreader, 1),
+    (PickupType::Flamethrower, 1),
+    (PickupType::WaveBeam, 1),
+    (PickupType::IceBeam, 1),
+    (PickupType::PlasmaBeam, 1),
+    (PickupType::ArtifactOfLifegiver, 1),
+    (PickupType::ArtifactOfWild, 1),
+    (PickupType::ArtifactOfWorld, 1),
+    (PickupType::ArtifactOfSun, 1),
+    (PickupType::ArtifactOfElder, 1),
+    (PickupType::ArtifactOfSpirit, 1),
+    (PickupType::ArtifactOfTruth, 1),
+    (PickupType::ArtifactOfChozo, 1),
+    (PickupType::ArtifactOfWarrior, 1),
+    (PickupType::ArtifactOfNewborn, 1),
+    (PickupType::ArtifactOfNature, 1),
+    (PickupType::ArtifactOfStrength, 1),
+    (PickupType::Nothing, 0),
+    (PickupType::ScanVisor, 1),
+];
+
+/// Index of `PickupType::PowerBomb`'s expansion in `LAYOUT_PICKUPS`, which keeps its own model
+const POWER_BOMB_EXPANSION: u8 = 11;
+
+/// Number of pickup locations a layout string places items in
+const LAYOUT_PICKUP_COUNT: usize = 100;
+
+/// Elevators, then the starting point, which can also be the Landing Site
+const LAYOUT_ELEVATOR_COUNT: usize = 20;
+
+/// A layout string decoded, as passed with `--layout` by the front ends which predate profiles
+#[derive(Debug, PartialEq)]
+pub struct LegacyLayout
+{
+    /// The index in `LAYOUT_PICKUPS` of each pickup location's item, in `ROOM_INFO` order
+    pub pickups: Vec<u8>,
+    /// The index of each elevator's destination in `Elevator::iter()` order, followed by the
+    /// starting point's
+    pub elevators: Vec<u8>,
+}
+
+fn checksum(mut num: BigUint, checksum_size: usize) -> u32
+{
+    if checksum_size == 0 {
+        return 0;
+    }
+    let modulus = BigUint::from(1u32 << checksum_size);
+    let mut sum = 0;
+    while !num.is_zero() {
+        let (quotient, remainder) = num.div_rem(&modulus);
+        sum = (sum + remainder.to_u32().unwrap()) % (1 << checksum_size);
+        num = quotient;
+    }
+    sum
+}
+
+/// Decodes one section of a layout string into a number below each of `sizes`
+fn decode_section(text: &str, sizes: &[u32], layout_data_size: usize, checksum_size: usize)
+    -> Result<Vec<u8>, String>
+{
+    let mut num = BigUint::zero();
+    for c in text.bytes().rev() {
+        let idx = LAYOUT_CHAR_TABLE.iter().position(|i| *i == c)
+            .ok_or_else(|| format!("invalid character '{}'", c as char))?;
+        num = (num << 6) + BigUint::from(idx);
+    }
+
+    // The bits at odd positions are stored in reverse
+    let bits = num.to_str_radix(2).into_bytes();
+    let even = bits.iter().step_by(2);
+    let mut odd = bits.iter().skip(1).step_by(2).rev();
+    let mut unshuffled = vec![];
+    for bit in even {
+        unshuffled.push(*bit);
+        unshuffled.extend(odd.next());
+    }
+    let mut num = BigUint::parse_bytes(&unshuffled, 2).unwrap();
+
+    let expected_checksum = &num >> layout_data_size;
+    num -= &expected_checksum << layout_data_size;
+    if expected_checksum.to_u32() != Some(checksum(num.clone(), checksum_size)) {
+        Err("checksum failed")?
+    }
+
+    let mut values = vec![];
+    for size in sizes.iter().rev() {
+        let (quotient, remainder) = num.div_rem(&BigUint::from(*size));
+        values.push(remainder.to_u8().unwrap());
+        num = quotient;
+    }
+    values.reverse();
+    Ok(values)
+}
+
+/// Decodes a layout string: an optional 16 character elevator section and a '.', then 87
+/// characters of pickups, with a leading '!' when the Scan Visor is one of them
+pub fn parse_layout(text: &str) -> Result<LegacyLayout, String>
+{
+    let (elevator_text, pickup_text) = match text.split_once('.') {
+        Some((elevator_text, pickup_text)) => (elevator_text, pickup_text),
+        None => (VANILLA_ELEVATOR_LAYOUT, text),
+    };
+    if elevator_text.len() != 16 {
+        Err("Invalid layout: the section before the '.' should be 16 characters")?
+    }
+
+    let (pickup_text, has_scan_visor) = match pickup_text.strip_prefix('!') {
+        Some(pickup_text) => (pickup_text, true),
+        None => (pickup_text, false),
+    };
+    if pickup_text.len() != 87 {
+        Err("Invalid layout: the pickup section should be 87 characters")?
+    }
+
+    let pickups = if has_scan_visor {
+        decode_section(pickup_text, &[37; LAYOUT_PICKUP_COUNT], 521, 1)
+    } else {
+        decode_section(pickup_text, &[36; LAYOUT_PICKUP_COUNT], 517, 5)
+    }.map_err(|e| format!("Invalid layout: pickup {}", e))?;
+
+    let mut elevator_sizes = vec![20; LAYOUT_ELEVATOR_COUNT];
+    elevator_sizes.push(21);
+    let elevators = decode_section(elevator_text, &elevator_sizes, 91, 5)
+        .map_err(|e| format!("Invalid layout: elevator {}", e))?;
+
+    Ok(LegacyLayout { pickups, elevators })
+}
+
+impl LegacyLayout
+{
+    /// Adds the layout's pickups and elevators to `level_data`, and returns the starting room if
+    /// it isn't the Landing Site. Rooms which already have pickups and elevators which already
+    /// have a destination are left as they are.
+    pub fn apply(&self, level_data: &mut BTreeMap<String, LevelConfig>) -> Option<String>
+    {
+        let mut pickups = self.pickups.iter();
+        for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+            let world = World::from_pak(pak_name).unwrap();
+            for room_info in rooms.iter().filter(|room_info| !room_info.pickup_locations.is_empty()) {
+                let room_pickups = room_info.pickup_locations.iter()
+                    .map(|_| {
+                        let idx = *pickups.next().unwrap();
+                        let (pickup_type, amount) = LAYOUT_PICKUPS[idx as usize];
+                        PickupConfig {
+                            pickup_type: pickup_type.name().to_string(),
+                            curr_increase: Some(amount),
+                            max_increase: Some(amount),
+                            model: if idx == POWER_BOMB_EXPANSION {
+                                Some("Power Bomb Expansion".to_string())
+                            } else {
+                                None
+                            },
+                            ..Default::default()
+                        }
+                    })
+                    .collect();
+
+                level_data.entry(world.to_json_key().to_string())
+                    .or_default()
+                    .rooms.entry(room_info.name().trim().to_string())
+                    .or_default()
+                    .pickups.get_or_insert(room_pickups);
+            }
+        }
+
+        let elevators: Vec<Elevator> = Elevator::iter().collect();
+        for (elevator, dest) in elevators.iter().zip(&self.elevators) {
+            let dest = elevators[*dest as usize];
+            if dest == elevator.elevator_data().default_dest {
+                continue;
+            }
+            let world = World::from_pak(elevator.elevator_data().pak_name).unwrap();
+            level_data.entry(world.to_json_key().to_string())
+                .or_default()
+                .transports.entry(elevator.elevator_data().name.replace('\0', " "))
+                .or_insert_with(|| dest.elevator_data().name.replace('\0', " "));
+        }
+
+        elevators.get(self.elevators[LAYOUT_ELEVATOR_COUNT] as usize)
+            .map(|start| start.elevator_data().name.replace('\0', " "))
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    /// Every item where it is in the vanilla game, as the original front ends encode it
+    const VANILLA_LAYOUT: &str = "NCiq7nTAtTnqPcap9VMQk_o8Qj6ZjbPiOdYDB5tgtwL_f01-UpYklNGnL-gTu5IeVW3IoUiflH5LqNXB3wVEER4";
+
+    #[test]
+    fn vanilla_layout_decodes_to_vanilla_items()
+    {
+        let layout = parse_layout(VANILLA_LAYOUT).unwrap();
+        let mut level_data = BTreeMap::new();
+        assert_eq!(layout.apply(&mut level_data), None);
+
+        let pickups = &level_data["Chozo Ruins"].rooms["Main Plaza"].pickups.as_ref().unwrap();
+        let types: Vec<&str> = pickups.iter().map(|pickup| pickup.pickup_type.as_str()).collect();
+        assert_eq!(types, ["Missile", "Missile", "Missile", "Energy Tank"]);
+
+        let pickup = &level_data["Magmoor Caverns"].rooms["Fiery Shores"].pickups.as_ref().unwrap()[1];
+        assert_eq!(pickup.pickup_type, "Power Bomb");
+        assert_eq!(pickup.curr_increase, Some(1));
+        assert_eq!(pickup.model.as_deref(), Some("Power Bomb Expansion"));
+
+        let pickup = &level_data["Phazon Mines"].rooms["Central Dynamo"].pickups.as_ref().unwrap()[0];
+        assert_eq!(pickup.pickup_type, "Power Bomb");
+        assert_eq!(pickup.curr_increase, Some(4));
+
+        assert!(level_data.values().all(|level| level.transports.is_empty()));
+    }
+
+    #[test]
+    fn scan_visor_and_elevators_are_decoded()
+    {
+        // Vanilla except for a Super Missile in the Main Plaza's half-pipe, the Scan Visor at the
+        // Landing Site and nothing in the Magmoor Workstation
+        let layout = parse_layout(
+            "!XLTTsXF4zK5XF8GW6QjVTZgcs9DKlR9F5rqKfRGQywsZjE0R27cnvl_Sp2kKgIR6CXJSiVwfmfVBAx22sBHN4UN"
+        ).unwrap();
+        assert_eq!(LAYOUT_PICKUPS[layout.pickups[0] as usize].0, PickupType::SuperMissile);
+        assert_eq!(LAYOUT_PICKUPS[layout.pickups[58] as usize].0, PickupType::ScanVisor);
+        assert_eq!(LAYOUT_PICKUPS[layout.pickups[99] as usize].0, PickupType::Nothing);
+
+        // The first two elevators swapped, starting at Phendrana Drifts North
+        let layout = parse_layout(
+            "Jle0ognhT5ZN6Mwe.NCiq7nTAtTnqPcap9VMQk_o8Qj6ZjbPiOdYDB5tgtwL_f01-UpYklNGnL-gTu5IeVW3IoUiflH5LqNXB3wVEER4"
+        ).unwrap();
+        let mut level_data = BTreeMap::new();
+        let starting_room = layout.apply(&mut level_data);
+        assert_eq!(starting_room.as_deref(), Some("Phendrana Drifts North (Phendrana Shorelines)"));
+        let transports = &level_data["Chozo Ruins"].transports;
+        assert_eq!(transports["Chozo Ruins West (Main Plaza)"], "Chozo Ruins North (Sun Tower)");
+        assert_eq!(transports["Chozo Ruins North (Sun Tower)"], "Chozo Ruins West (Main Plaza)");
+    }
+
+    #[test]
+    fn corrupt_layout_is_rejected()
+    {
+        let mut corrupt = VANILLA_LAYOUT.to_string();
+        corrupt.replace_range(..1, "M");
+        assert!(parse_layout(&corrupt).is_err());
+        assert!(parse_layout(&VANILLA_LAYOUT[1..]).is_err());
+    }
+}
@@ -18,21 +18,53 @@ use std::{
 
 pub mod generic_edit;
 pub mod add_modify_obj_patches;
+#[cfg(feature = "async")]
+pub mod async_writer;
+pub mod audio_conversions;
+#[cfg(feature = "bps")]
+pub mod bps_reader;
+#[cfg(feature = "bps")]
+pub mod bps_writer;
 pub mod c_interface;
 pub mod custom_assets;
 pub mod extern_assets;
+#[cfg(feature = "ciso")]
+pub mod ciso_reader;
+#[cfg(feature = "ciso")]
 pub mod ciso_writer;
 pub mod dol_patcher;
 pub mod elevators;
+pub mod enemy_shuffle;
+pub mod formats;
+#[cfg(feature = "gcz")]
+pub mod gcz_reader;
+#[cfg(feature = "gcz")]
 pub mod gcz_writer;
+pub mod gltf_import;
+pub mod legacy_layout;
+pub mod logic;
+pub mod memory_budget;
 pub mod mlvl_wrapper;
+pub mod music_shuffle;
 pub mod patch_config;
 pub mod patcher;
 pub mod patches;
+#[cfg(feature = "rvz")]
+pub mod rvz_reader;
 pub mod pickup_meta;
+pub mod pickup_pool;
+pub mod plugin;
+pub mod save_budget;
 pub mod door_meta;
+pub mod door_rando;
+#[cfg(feature = "smoke-test")]
+pub mod smoke_test;
+pub mod spoiler;
 pub mod starting_items;
+pub mod starting_room;
+pub mod text_layout;
 pub mod txtr_conversions;
+pub mod verify;
 
 pub trait GcDiscLookupExtensions<'a>
 {
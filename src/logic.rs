@@ -0,0 +1,295 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use json_strip::strip_jsonc_comments;
+use serde::Deserialize;
+
+use crate::{
+    elevators::World,
+    patch_config::LevelConfig,
+    pickup_meta::{self, PickupType},
+    starting_items::StartingItems,
+};
+
+/// What the player needs to do something, e.g. go through a door or get to an item
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub enum Requirement
+{
+    Nothing,              // "nothing"
+    Item(String),         // {"item": "Morph Ball"}
+    Count(String, u32),   // {"count": ["Missile", 10]}, by capacity rather than pickups
+    Trick(String),        // {"trick": "Scan Dash"}, only if the profile enables it
+    All(Vec<Requirement>),
+    Any(Vec<Requirement>),
+}
+
+impl Default for Requirement
+{
+    fn default() -> Self
+    {
+        Requirement::Nothing
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LogicLocation
+{
+    pub room: String, // "<world>:<room>", like startingRoom
+    #[serde(default)]
+    pub index: usize, // which of the room's pickups
+    #[serde(default)]
+    pub requirement: Requirement,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LogicRegion
+{
+    #[serde(default)]
+    pub connections: BTreeMap<String, Requirement>, // region name: what it takes to get there
+    #[serde(default)]
+    pub locations: Vec<LogicLocation>,
+}
+
+/// How the game's regions connect and which items they need. A seed is beatable if `goal` can
+/// be reached from `start` by collecting every item that can be reached along the way.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LogicGraph
+{
+    pub start: String,
+    pub goal: String,
+    #[serde(default)]
+    pub tricks: Vec<String>,
+    pub regions: BTreeMap<String, LogicRegion>,
+}
+
+fn parse_item(name: &str) -> Result<PickupType, String>
+{
+    PickupType::iter()
+        .find(|pt| pt.name().eq_ignore_ascii_case(name.trim()))
+        .ok_or_else(|| format!("Unknown item '{}' in the logic graph", name))
+}
+
+/// The world key and room name of a "<world>:<room>" string
+fn parse_room(room: &str) -> Result<(&'static str, &'static str), String>
+{
+    let (world_name, room_name) = room.split_once(':')
+        .ok_or_else(|| format!("Expected '<world>:<room>' in the logic graph, got '{}'", room))?;
+    let world = World::iter()
+        .find(|world| world.to_json_key().eq_ignore_ascii_case(world_name.trim()))
+        .ok_or_else(|| format!("Unknown world '{}' in the logic graph", world_name))?;
+
+    pickup_meta::ROOM_INFO.iter()
+        .filter(|(pak_name, _)| World::from_pak(pak_name) == Some(world))
+        .flat_map(|(_, rooms)| rooms.iter())
+        .find(|room_info| room_info.name().trim().eq_ignore_ascii_case(room_name.trim()))
+        .map(|room_info| (world.to_json_key(), room_info.name().trim()))
+        .ok_or_else(|| format!("Unknown room '{}' in the logic graph", room))
+}
+
+/// Capacity the player starts with, in the same units as `Requirement::Count`
+fn starting_inventory(starting_items: &StartingItems) -> HashMap<PickupType, u32>
+{
+    let items = [
+        (PickupType::CombatVisor, starting_items.combat_visor as u32),
+        (PickupType::PowerBeam, starting_items.power_beam as u32),
+        (PickupType::ScanVisor, starting_items.scan_visor as u32),
+        (PickupType::Missile, starting_items.missiles.max(0) as u32),
+        (PickupType::EnergyTank, starting_items.energy_tanks.max(0) as u32),
+        (PickupType::PowerBomb, starting_items.power_bombs.max(0) as u32),
+        (PickupType::WaveBeam, starting_items.wave as u32),
+        (PickupType::IceBeam, starting_items.ice as u32),
+        (PickupType::PlasmaBeam, starting_items.plasma as u32),
+        (PickupType::ChargeBeam, starting_items.charge as u32),
+        (PickupType::MorphBall, starting_items.morph_ball as u32),
+        (PickupType::MorphBallBomb, starting_items.bombs as u32),
+        (PickupType::SpiderBall, starting_items.spider_ball as u32),
+        (PickupType::BoostBall, starting_items.boost_ball as u32),
+        (PickupType::VariaSuit, starting_items.varia_suit as u32),
+        (PickupType::GravitySuit, starting_items.gravity_suit as u32),
+        (PickupType::PhazonSuit, starting_items.phazon_suit as u32),
+        (PickupType::ThermalVisor, starting_items.thermal_visor as u32),
+        (PickupType::XRayVisor, starting_items.xray as u32),
+        (PickupType::SpaceJumpBoots, starting_items.space_jump as u32),
+        (PickupType::GrappleBeam, starting_items.grapple as u32),
+        (PickupType::SuperMissile, starting_items.super_missile as u32),
+        (PickupType::Wavebuster, starting_items.wavebuster as u32),
+        (PickupType::IceSpreader, starting_items.ice_spreader as u32),
+        (PickupType::Flamethrower, starting_items.flamethrower as u32),
+    ];
+    items.iter().filter(|(_, amount)| *amount > 0).copied().collect()
+}
+
+impl Requirement
+{
+    fn validate(&self, tricks: &[String]) -> Result<(), String>
+    {
+        match self {
+            Requirement::Nothing => (),
+            Requirement::Item(name) | Requirement::Count(name, _) => {
+                parse_item(name)?;
+            },
+            Requirement::Trick(name) => {
+                if !tricks.contains(name) {
+                    Err(format!("Trick '{}' isn't in the logic graph's list of tricks", name))?
+                }
+            },
+            Requirement::All(requirements) | Requirement::Any(requirements) => {
+                for requirement in requirements.iter() {
+                    requirement.validate(tricks)?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn is_met(&self, inventory: &HashMap<PickupType, u32>, tricks: &BTreeSet<String>) -> bool
+    {
+        let amount = |name: &str| parse_item(name).ok()
+            .and_then(|pt| inventory.get(&pt).copied())
+            .unwrap_or(0);
+        match self {
+            Requirement::Nothing => true,
+            Requirement::Item(name) => amount(name) > 0,
+            Requirement::Count(name, count) => amount(name) >= *count,
+            Requirement::Trick(name) => tricks.contains(name),
+            Requirement::All(requirements) => requirements.iter().all(|r| r.is_met(inventory, tricks)),
+            Requirement::Any(requirements) => requirements.iter().any(|r| r.is_met(inventory, tricks)),
+        }
+    }
+}
+
+impl LogicGraph
+{
+    pub fn from_json(json: &str) -> Result<Self, String>
+    {
+        let json = strip_jsonc_comments(json, true);
+        let graph: LogicGraph = serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse the logic graph: {}", e))?;
+        graph.validate()?;
+        Ok(graph)
+    }
+
+    fn validate(&self) -> Result<(), String>
+    {
+        for region in [&self.start, &self.goal].iter() {
+            if !self.regions.contains_key(*region) {
+                Err(format!("Unknown region '{}' in the logic graph", region))?
+            }
+        }
+        for (name, region) in self.regions.iter() {
+            for (target, requirement) in region.connections.iter() {
+                if !self.regions.contains_key(target) {
+                    Err(format!("Region '{}' connects to unknown region '{}'", name, target))?
+                }
+                requirement.validate(&self.tricks)?;
+            }
+            for location in region.locations.iter() {
+                parse_room(&location.room)?;
+                location.requirement.validate(&self.tricks)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `goal` can be reached with the items placed by `level_data`. Pickups which
+    /// `level_data` doesn't place count as `Nothing`, like when patching.
+    pub fn is_beatable(
+        &self,
+        level_data: &BTreeMap<String, LevelConfig>,
+        starting_items: &StartingItems,
+        tricks: &BTreeSet<String>,
+    )
+        -> bool
+    {
+        let mut inventory = starting_inventory(starting_items);
+        let mut collected = BTreeSet::new();
+        loop {
+            let mut reachable = BTreeSet::new();
+            let mut to_visit = vec![&self.start];
+            while let Some(name) = to_visit.pop() {
+                if !reachable.insert(name) {
+                    continue;
+                }
+                for (target, requirement) in self.regions[name].connections.iter() {
+                    if !reachable.contains(target) && requirement.is_met(&inventory, tricks) {
+                        to_visit.push(target);
+                    }
+                }
+            }
+            if reachable.contains(&self.goal) {
+                return true;
+            }
+
+            let mut progress = false;
+            for name in reachable.iter() {
+                for location in self.regions[*name].locations.iter() {
+                    if !location.requirement.is_met(&inventory, tricks) {
+                        continue;
+                    }
+                    let (world_key, room_name) = parse_room(&location.room).unwrap();
+                    if !collected.insert((world_key, room_name, location.index)) {
+                        continue;
+                    }
+                    progress = true;
+
+                    let pickup = level_data.get(world_key)
+                        .and_then(|level| level.rooms.get(room_name))
+                        .and_then(|room| room.pickups.as_ref())
+                        .and_then(|pickups| pickups.get(location.index));
+                    if let Some(pickup) = pickup {
+                        let pickup_type = PickupType::from_str(&pickup.pickup_type);
+                        let amount = match pickup_type {
+                            PickupType::Nothing | PickupType::HealthRefill => 0,
+                            PickupType::Missile => pickup.max_increase.or(pickup.curr_increase).unwrap_or(5),
+                            _ => pickup.max_increase.or(pickup.curr_increase).unwrap_or(1),
+                        };
+                        *inventory.entry(pickup_type).or_insert(0) += amount.max(0) as u32;
+                    }
+                }
+            }
+            if !progress {
+                return false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn builtin_logic_loads()
+    {
+        let logic = LogicGraph::from_json(json_data::LOGIC).unwrap();
+        assert!(!logic.is_beatable(&BTreeMap::new(), &StartingItems::default(), &BTreeSet::new()));
+    }
+
+    #[test]
+    fn builtin_logic_covers_every_pickup()
+    {
+        let logic = LogicGraph::from_json(json_data::LOGIC).unwrap();
+        let mut locations = BTreeSet::new();
+        for region in logic.regions.values() {
+            for location in region.locations.iter() {
+                let (world_key, room_name) = parse_room(&location.room).unwrap();
+                assert!(locations.insert((world_key, room_name, location.index)), "{} is listed twice", location.room);
+            }
+        }
+
+        for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+            let world_key = World::from_pak(pak_name).unwrap().to_json_key();
+            for room_info in rooms.iter() {
+                for idx in 0..room_info.pickup_locations.len() {
+                    let key = (world_key, room_info.name().trim(), idx);
+                    assert!(locations.remove(&key), "{:?} isn't in the built-in logic", key);
+                }
+            }
+        }
+        assert!(locations.is_empty(), "{:?} aren't pickup locations", locations);
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+use reader_writer::{FourCC, Readable, Reader};
+
+use crate::{
+    elevators::World,
+    pickup_meta,
+    GcDiscLookupExtensions,
+};
+
+/// Roughly what's left of the GameCube's 24MiB of main RAM for an area's resources once the
+/// engine, the player and the world's shared resources are loaded
+const AREA_MAIN_RAM_BUDGET: usize = 8 * 1024 * 1024;
+
+/// Sound effect groups are uploaded to ARAM, which is mostly taken by the game's own streaming
+/// buffers and the sounds every area uses
+const AREA_ARAM_BUDGET: usize = 3 * 1024 * 1024;
+
+/// Estimated memory taken by the resources an area depends on. It's an upper bound: every
+/// layer's dependencies are counted, even if the layer starts out inactive.
+#[derive(Clone, Copy)]
+pub struct AreaMemory
+{
+    pub main_ram: usize,
+    pub aram: usize,
+}
+
+/// Size of a resource once it's loaded, i.e. decompressed
+fn resource_memory_size(res: &structs::Resource) -> usize
+{
+    let bytes = match &res.kind {
+        structs::ResourceKind::Unknown(reader, _) => &reader[..],
+        structs::ResourceKind::External(bytes, _) => &bytes[..],
+        _ => return res.size(),
+    };
+    if res.compressed && bytes.len() >= 4 {
+        // Compressed resources start with their decompressed size
+        Reader::new(bytes).read::<u32>(()) as usize
+    } else {
+        bytes.len()
+    }
+}
+
+fn is_aram_resource(fourcc: FourCC) -> bool
+{
+    fourcc == b"AGSC".into() || fourcc == b"CSNG".into()
+}
+
+/// Estimates the memory used by every area of every world, keyed by MREA id. Worlds whose PAK no
+/// longer has its MLVL, like the Frigate's once `empty_frigate_pak` is done with it, are skipped.
+pub fn estimate_area_memory(gc_disc: &structs::GcDisc) -> HashMap<u32, AreaMemory>
+{
+    let mut estimates = HashMap::new();
+    for world in World::iter() {
+        let file_entry = match gc_disc.find_file(world.to_pak_str()) {
+            Some(file_entry) => file_entry,
+            None => continue,
+        };
+        let pak = match *file_entry.file().unwrap() {
+            structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
+            structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
+            _ => panic!(),
+        };
+        estimate_world_memory(&pak, world, &mut estimates);
+    }
+
+    estimates
+}
+
+fn estimate_world_memory(pak: &structs::Pak, world: World, estimates: &mut HashMap<u32, AreaMemory>)
+{
+    let mut mlvl = match pak.resources.iter().find(|res| res.file_id == world.mlvl()) {
+        Some(res) => res.into_owned(),
+        None => return,
+    };
+    let mlvl = mlvl.kind.as_mlvl_mut().unwrap();
+
+    let sizes: HashMap<(u32, FourCC), usize> = pak.resources.iter()
+        .map(|res| ((res.file_id, res.fourcc()), resource_memory_size(&res)))
+        .collect();
+
+    for area in mlvl.areas.iter() {
+        let mrea_id = area.mrea.to_u32();
+        let mut memory = AreaMemory {
+            main_ram: sizes.get(&(mrea_id, b"MREA".into())).copied().unwrap_or(0),
+            aram: 0,
+        };
+
+        let mut seen = HashSet::new();
+        for layer in area.dependencies.deps.iter() {
+            for dep in layer.iter() {
+                if !seen.insert((dep.asset_id, dep.asset_type)) {
+                    continue;
+                }
+                let size = sizes.get(&(dep.asset_id, dep.asset_type)).copied().unwrap_or(0);
+                if is_aram_resource(dep.asset_type) {
+                    memory.aram += size;
+                } else {
+                    memory.main_ram += size;
+                }
+            }
+        }
+
+        estimates.insert(mrea_id, memory);
+    }
+}
+
+fn mib(bytes: usize) -> f32
+{
+    bytes as f32 / (1024.0 * 1024.0)
+}
+
+/// Warns about areas whose injected assets grew them past what fits in memory on console, which
+/// otherwise shows up as a crash when entering the room. Areas which were already that large in
+/// the vanilla game are left alone, the estimate is only a rough upper bound.
+pub fn check_memory_budget(vanilla: &HashMap<u32, AreaMemory>, patched: &HashMap<u32, AreaMemory>)
+{
+    for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+        for room_info in rooms.iter() {
+            let mrea_id = room_info.room_id.to_u32();
+            let (before, after) = match (vanilla.get(&mrea_id), patched.get(&mrea_id)) {
+                (Some(before), Some(after)) => (*before, *after),
+                _ => continue,
+            };
+
+            let budgets = [
+                ("main RAM", before.main_ram, after.main_ram, AREA_MAIN_RAM_BUDGET),
+                ("ARAM", before.aram, after.aram, AREA_ARAM_BUDGET),
+            ];
+            for (memory, before, after, budget) in budgets.iter() {
+                if after > budget && after > before {
+                    println!(
+                        "Warning, {} ({}) needs about {:.1}MiB of {} (up from {:.1}MiB), it may crash on console",
+                        room_info.name().trim(), pak_name, mib(*after), memory, mib(*before),
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test
+{
+    use super::*;
+
+    use std::iter;
+
+    /// A PAK with a single empty resource, as reading one without any resources isn't supported
+    static TINY_PAK: [u8; 64] = [
+        0, 3, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        0, 0, 0, 0, b'X', b'X', b'X', b'X', 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 64, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    /// A PAK emptied the way `empty_frigate_pak` does it, down to a single dummy resource
+    pub(crate) fn emptied_pak() -> structs::Pak<'static>
+    {
+        let mut pak: structs::Pak = Reader::new(&TINY_PAK).read(());
+        let res = crate::custom_assets::build_resource_raw(
+            0,
+            structs::ResourceKind::External(vec![0; 64], b"XXXX".into())
+        );
+        pak.resources = iter::once(res).collect();
+        pak
+    }
+
+    #[test]
+    fn emptied_pak_is_skipped()
+    {
+        let mut estimates = HashMap::new();
+        estimate_world_memory(&emptied_pak(), World::FrigateOrpheon, &mut estimates);
+        assert!(estimates.is_empty());
+    }
+}
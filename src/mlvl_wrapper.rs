@@ -208,6 +208,12 @@ impl<'r, 'mlvl, 'cursor, 'list> MlvlArea<'r, 'mlvl, 'cursor, 'list>
         self.mrea().scly_section_mut().layers.as_mut_vec().push(SclyLayer::new());
     }
 
+    /// Whether one of the area's layers already depends on `dep`, so it doesn't need to be added
+    pub fn has_dependency(&self, dep: &Dependency) -> bool
+    {
+        self.mlvl_area.dependencies.deps.iter().any(|layer| layer.iter().any(|i| *i == *dep))
+    }
+
     pub fn add_dependencies<I>(&mut self, pickup_resources: &HashMap<(u32, FourCC), Resource<'r>>,
                                layer_num: usize, deps: I)
         where I: Iterator<Item=Dependency>,
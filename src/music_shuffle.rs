@@ -0,0 +1,137 @@
+use rand::{
+    rngs::StdRng,
+    seq::SliceRandom,
+    SeedableRng,
+};
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+};
+
+use crate::{
+    mlvl_wrapper,
+    patcher::PatcherState,
+    patches::string_to_cstr,
+    pickup_meta,
+    GcDiscLookupExtensions,
+};
+
+/// Which background music track replaces which. Tracks are swapped as a whole, so rooms which
+/// shared a track (and play it seamlessly from one room to the next) still share one afterwards.
+pub struct MusicShuffle
+{
+    /// Keyed by MREA id, indexes into `mappings`
+    rooms: HashMap<u32, usize>,
+    mappings: Vec<HashMap<String, String>>,
+}
+
+impl MusicShuffle
+{
+    pub fn contains_room(&self, mrea_id: u32) -> bool
+    {
+        self.rooms.contains_key(&mrea_id)
+    }
+}
+
+/// Streamed audio which plays as the room's background music, i.e. not a jingle
+fn is_background_music(streamed_audio: &structs::StreamedAudio) -> bool
+{
+    streamed_audio.is_music != 0 && streamed_audio.oneshot == 0
+}
+
+/// Shuffles the background music of every room, deterministically from `seed`. With `per_world`
+/// tracks only move around within the world they're from.
+pub fn plan_music_shuffle(gc_disc: &structs::GcDisc, seed: u64, per_world: bool)
+    -> Result<MusicShuffle, String>
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut rooms = HashMap::new();
+    let mut groups: Vec<BTreeSet<String>> = vec![];
+    for (pak_name, room_infos) in pickup_meta::ROOM_INFO.iter() {
+        let file_entry = gc_disc.find_file(pak_name)
+            .ok_or_else(|| format!("Could not find {} in the input ISO", pak_name))?;
+        let pak = match *file_entry.file().unwrap() {
+            structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
+            structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
+            _ => panic!(),
+        };
+
+        if per_world || groups.is_empty() {
+            groups.push(BTreeSet::new());
+        }
+        let group = groups.len() - 1;
+
+        for room_info in room_infos.iter() {
+            let mrea_id = room_info.room_id.to_u32();
+            let mut res = pak.resources.iter()
+                .find(|res| res.file_id == mrea_id)
+                .ok_or_else(|| format!("Could not find room 0x{:X} in {}", mrea_id, pak_name))?
+                .into_owned();
+            let mrea = res.kind.as_mrea_mut().unwrap();
+            let scly = mrea.scly_section_mut();
+
+            for scly_layer in scly.layers.as_mut_vec().iter_mut() {
+                for obj in scly_layer.objects.as_mut_vec().iter_mut() {
+                    let streamed_audio = match obj.property_data.as_streamed_audio() {
+                        Some(streamed_audio) => streamed_audio,
+                        None => continue,
+                    };
+                    if !is_background_music(&streamed_audio) {
+                        continue;
+                    }
+
+                    groups[group].insert(streamed_audio.audio_file_name.to_string_lossy().into_owned());
+                    rooms.insert(mrea_id, group);
+                }
+            }
+        }
+    }
+
+    // Sets are sorted, so the result only depends on the seed
+    let mappings = groups.into_iter()
+        .map(|tracks| {
+            let tracks: Vec<String> = tracks.into_iter().collect();
+            let mut shuffled = tracks.clone();
+            shuffled.shuffle(&mut rng);
+            tracks.into_iter().zip(shuffled.into_iter()).collect()
+        })
+        .collect();
+
+    Ok(MusicShuffle { rooms, mappings })
+}
+
+pub fn patch_shuffle_music<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    music_shuffle: &MusicShuffle,
+)
+    -> Result<(), String>
+{
+    let mrea_id = area.mlvl_area.mrea.to_u32();
+    let mapping = match music_shuffle.rooms.get(&mrea_id) {
+        Some(group) => &music_shuffle.mappings[*group],
+        None => return Ok(()),
+    };
+
+    let scly = area.mrea().scly_section_mut();
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            let streamed_audio = match obj.property_data.as_streamed_audio_mut() {
+                Some(streamed_audio) => streamed_audio,
+                None => continue,
+            };
+            if !is_background_music(streamed_audio) {
+                continue;
+            }
+
+            let file_name = streamed_audio.audio_file_name.to_string_lossy().into_owned();
+            if let Some(new_file_name) = mapping.get(&file_name) {
+                streamed_audio.audio_file_name = string_to_cstr(new_file_name.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,55 +1,73 @@
 use std::{
-    ffi::CStr,
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     fs::{File, OpenOptions},
     fs,
     fmt,
+    path::Path,
 };
 
+#[cfg(feature = "cli")]
 use clap::{
     Arg,
     App,
     crate_version,
 };
 
+use schemars::JsonSchema;
 use serde::{Serialize, Deserialize};
 
+#[cfg(feature = "ciso")]
+use crate::ciso_reader;
+#[cfg(feature = "gcz")]
+use crate::gcz_reader;
+#[cfg(feature = "rvz")]
+use crate::rvz_reader;
+
 use crate::{
     starting_items::StartingItems,
+    logic::LogicGraph,
+    plugin::PatchPlugins,
+    starting_room::{parse_starting_room, pick_random_starting_room, StartingSpawn},
     pickup_meta::PickupType,
-    custom_assets::custom_asset_ids, door_meta::DoorType,
+    custom_assets::custom_asset_ids, door_meta::{BlastShieldType, DoorType},
+    GcDiscLookupExtensions,
 };
 
-use reader_writer::{FourCC, Reader};
+use reader_writer::{FourCC, Reader, WithRead};
 
 use structs::{res_id, ResId};
 
 use json_data::*;
 use json_strip::strip_jsonc_comments;
 
-use crate::elevators::World;
+use crate::elevators::{Elevator, SpawnRoomData, World};
 
 /*** Parsed Config (fn patch_iso) ***/
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub enum RunMode
 {
     CreateIso,
     ExportLogbook,
     ExportAssets,
+    ExtractAssets,
+    ExportRoomMap,
+    ApplyPatch,
+    ValidateProfile,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(deny_unknown_fields)]
 pub enum IsoFormat
 {
     Iso,
     Gcz,
     Ciso,
+    Bps,
 }
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Copy, Clone)]
 #[serde(deny_unknown_fields)]
 pub enum ArtifactHintBehavior
 {
@@ -58,7 +76,7 @@ pub enum ArtifactHintBehavior
     All,
 }
 
-#[derive(PartialEq, Debug, Serialize, Deserialize, Copy, Clone)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, JsonSchema, Copy, Clone)]
 #[serde(deny_unknown_fields)]
 pub enum CutsceneMode
 {
@@ -70,7 +88,7 @@ pub enum CutsceneMode
     Major,
 }
 
-#[derive(PartialEq, Debug, Serialize, Deserialize, Copy, Clone)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, JsonSchema, Copy, Clone)]
 #[serde(deny_unknown_fields)]
 pub enum Visor
 {
@@ -80,7 +98,7 @@ pub enum Visor
     Thermal,
 }
 
-#[derive(PartialEq, Debug, Serialize, Deserialize, Copy, Clone)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, JsonSchema, Copy, Clone)]
 #[serde(deny_unknown_fields)]
 pub enum Beam
 {
@@ -90,7 +108,35 @@ pub enum Beam
     Plasma,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, JsonSchema, Copy, Clone)]
+#[serde(deny_unknown_fields)]
+pub enum Language
+{
+    English,
+    French,
+    German,
+    Spanish,
+    Italian,
+    Japanese,
+}
+
+impl Language
+{
+    /// The STRG string table holding this language
+    pub fn strg_lang(&self) -> FourCC
+    {
+        match self {
+            Language::English  => b"ENGL".into(),
+            Language::French   => b"FREN".into(),
+            Language::German   => b"GERM".into(),
+            Language::Spanish  => b"SPAN".into(),
+            Language::Italian  => b"ITAL".into(),
+            Language::Japanese => b"JAPN".into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct GameBanner
 {
@@ -101,7 +147,7 @@ pub struct GameBanner
     pub description: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct PickupConfig
 {
@@ -111,8 +157,8 @@ pub struct PickupConfig
     pub curr_increase: Option<i32>,
     pub max_increase: Option<i32>,
     pub model: Option<String>,
-    pub scan_text: Option<String>,
-    pub hudmemo_text: Option<String>,
+    pub scan_text: Option<LocalizedText>,
+    pub hudmemo_text: Option<LocalizedText>,
     pub respawn: Option<bool>,
     pub position: Option<[f32;3]>,
     pub modal_hudmemo: Option<bool>,
@@ -122,9 +168,49 @@ pub struct PickupConfig
     pub invisible_and_silent: Option<bool>,
     pub thermal_only: Option<bool>,
     pub scale: Option<[f32; 3]>,
+    pub remote_connections: Option<Vec<PickupRemoteConnection>>,
+    pub aura: Option<PickupAuraConfig>, // overrides gameConfig.pickupAura
+    pub scan_to_reveal: Option<bool>, // the pickup stays invisible until its location is scanned
+    pub jingle: Option<String>, // "major", "minor", "silent" or the path of a DSP on the disc
+    pub sound_effect: Option<PickupSoundEffectConfig>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PickupSoundEffectConfig
+{
+    pub id: u32, // sound id played when the pickup is collected
+    pub audio_group: Option<u32>, // AGSC containing the sound, if the room doesn't already load it
+}
+
+// Used by every pickup of a type which doesn't set its own jingle or sound effect
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PickupSoundsConfig
+{
+    pub jingle: Option<String>,
+    pub sound_effect: Option<PickupSoundEffectConfig>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PickupAuraConfig
+{
+    pub particle: Option<bool>, // false removes the glowing particle effect around the model
+    pub light_color: Option<[f32;4]>, // RGBA color of the light the model is lit with
+}
+
+// Sent when the pickup is collected. Because the message is delivered through a memory relay, the
+// target may be in any room of the same world (the area is encoded in the instance id)
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PickupRemoteConnection
+{
+    pub target_id: u32,
+    pub message: ConnectionMsg,
 }
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ScanConfig
 {
@@ -132,12 +218,60 @@ pub struct ScanConfig
     pub combat_visible: Option<bool>,
     pub rotation: Option<f32>,
     pub is_red: Option<bool>,
-    pub logbook_category: Option<u32>,
-    pub logbook_title: Option<String>,
+    pub logbook: Option<bool>, // implied by logbook_category/logbook_title
+    pub logbook_category: Option<u32>, // defaults to Research
+    pub logbook_title: Option<String>, // defaults to the room's name
     pub text: String,
+    pub pages: Option<Vec<String>>, // shown after `text`, which is then kept on one page instead of being split by length
+    pub images: Option<Vec<ScanImageConfig>>, // at most 4
+}
+
+/// A scan in front of where Samus spawns with the seed, its hash and the settings it was
+/// patched with
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SeedInfoScanConfig
+{
+    pub permalink: Option<String>, // made by the frontend, shown as is on the last page
+}
+
+/// Which of the scan screen's image panes an image covers. The top row is made of panes 0 to 3
+/// and the bottom row of panes 4 to 7, an image can span neighbouring panes of the same row.
+#[derive(Deserialize, JsonSchema, Debug, Copy, Clone)]
+pub enum ScanImagePane
+{
+    #[serde(rename = "0")] Pane0,
+    #[serde(rename = "1")] Pane1,
+    #[serde(rename = "2")] Pane2,
+    #[serde(rename = "3")] Pane3,
+    #[serde(rename = "01")] Pane01,
+    #[serde(rename = "12")] Pane12,
+    #[serde(rename = "23")] Pane23,
+    #[serde(rename = "012")] Pane012,
+    #[serde(rename = "123")] Pane123,
+    #[serde(rename = "0123")] Pane0123,
+    #[serde(rename = "4")] Pane4,
+    #[serde(rename = "5")] Pane5,
+    #[serde(rename = "6")] Pane6,
+    #[serde(rename = "7")] Pane7,
+    #[serde(rename = "45")] Pane45,
+    #[serde(rename = "56")] Pane56,
+    #[serde(rename = "67")] Pane67,
+    #[serde(rename = "456")] Pane456,
+    #[serde(rename = "567")] Pane567,
+    #[serde(rename = "4567")] Pane4567,
 }
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ScanImageConfig
+{
+    pub path: String, // PNG image
+    pub pane: ScanImagePane,
+    pub appear_percent: Option<f32>, // how far along the scan has to be for the image to show up, 0.0 to 1.0
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DoorDestination
 {
@@ -145,17 +279,19 @@ pub struct DoorDestination
     pub dock_num: u32,
 }
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DoorConfig
 {
     #[serde(alias  = "type")]
     pub shield_type: Option<String>,
     pub blast_shield_type: Option<String>,
+    pub blast_shield_model: Option<String>, // name of a model in extern_assets_dir
+    pub blast_shield_scan_text: Option<String>,
     pub destination: Option<DoorDestination>, // Must be in same area. Ex: "destination":"Main Plaza"
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct SuitColors
 {
@@ -163,9 +299,84 @@ pub struct SuitColors
     pub varia_deg: Option<i16>,
     pub gravity_deg: Option<i16>,
     pub phazon_deg: Option<i16>,
+
+    // Applied on top of the rotation above
+    pub power: Option<ColorAdjustment>,
+    pub varia: Option<ColorAdjustment>,
+    pub gravity: Option<ColorAdjustment>,
+    pub phazon: Option<ColorAdjustment>,
+
+    // Glowing parts of the suits' helmets, applied after the suit's own adjustment
+    pub visor: Option<ColorAdjustment>,
+
+    // First-person arm cannon of every suit, applied after the suit's own adjustment
+    pub beam: Option<ColorAdjustment>,
+}
+
+/// Colors of the morph ball's effects, the same for every suit. Set alongside `SuitColors`, these
+/// win over the suits' rotation.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BallColors
+{
+    pub glow: Option<[u8; 3]>,
+    pub boost: Option<[u8; 3]>,
+    pub boost_charged: Option<[u8; 3]>,
+    pub spider: Option<ColorAdjustment>,
+}
+
+/// Look of a world's rooms, made with a camera filter over the whole screen
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Copy, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum ColorGradingPreset
+{
+    Warm,
+    Cool,
+    Noir, // also darkens the room's distance fog
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+/// Colors of each beam's particle effects
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BeamColors
+{
+    pub power: Option<BeamColorConfig>,
+    pub wave: Option<BeamColorConfig>,
+    pub ice: Option<BeamColorConfig>,
+    pub plasma: Option<BeamColorConfig>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BeamColorConfig
+{
+    pub shot: Option<ColorAdjustment>, // projectiles, charged shots and muzzle flashes
+    pub charge: Option<ColorAdjustment>, // charging effect at the tip of the arm cannon
+}
+
+/// HSV and tint adjustment of a set of textures. Unset fields leave that part of the color alone.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ColorAdjustment
+{
+    pub hue_deg: Option<i16>,
+    pub saturation: Option<f32>, // multiplier, 0.0 is grayscale
+    pub value: Option<f32>, // multiplier
+    pub tint: Option<[u8; 3]>, // RGB multiplier, [255, 255, 255] is no tint
+}
+
+/// Names of CMDL files in `extern_assets_dir` which replace each suit's model
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SuitModels
+{
+    pub power: Option<String>,
+    pub varia: Option<String>,
+    pub gravity: Option<String>,
+    pub phazon: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DefaultGameOptions
 {
@@ -184,7 +395,7 @@ pub struct DefaultGameOptions
     pub swap_beam_controls: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct WaterConfig
 {
@@ -196,7 +407,7 @@ pub struct WaterConfig
     pub scale: [f32;3],
 }
 
-#[derive(PartialEq, Debug, Serialize, Deserialize, Copy, Clone)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, JsonSchema, Copy, Clone)]
 #[serde(deny_unknown_fields)]
 pub enum PlatformType
 {
@@ -204,7 +415,7 @@ pub enum PlatformType
     Snow,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct PlatformConfig
 {
@@ -220,7 +431,7 @@ pub struct PlatformConfig
     // pub scale: [f32;3],
 }
 
-#[derive(PartialEq, Debug, Serialize, Deserialize, Copy, Clone)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, JsonSchema, Copy, Clone)]
 #[serde(deny_unknown_fields)]
 pub enum GenericTexture
 {
@@ -281,7 +492,7 @@ impl GenericTexture
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct BlockConfig
 {
@@ -293,7 +504,120 @@ pub struct BlockConfig
     pub texture: Option<GenericTexture>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, JsonSchema, Copy, Clone)]
+#[serde(deny_unknown_fields)]
+pub enum BlockMaterial
+{
+    #[serde(alias="bendezium", alias="BENDEZIUM")]
+    Bendezium, // Power Bombs
+    #[serde(alias="cordite", alias="CORDITE")]
+    Cordite,   // Super Missiles
+    #[serde(alias="sandstone", alias="SANDSTONE")]
+    Sandstone, // Bombs
+}
+
+impl BlockMaterial
+{
+    pub fn vulnerability(self) -> DoorType
+    {
+        match self {
+            BlockMaterial::Bendezium => DoorType::PowerBomb,
+            BlockMaterial::Cordite   => DoorType::Super,
+            BlockMaterial::Sandstone => DoorType::Bomb,
+        }
+    }
+
+    pub fn texture(self) -> GenericTexture
+    {
+        match self {
+            BlockMaterial::Bendezium => GenericTexture::Mine,
+            BlockMaterial::Cordite   => GenericTexture::Crater,
+            BlockMaterial::Sandstone => GenericTexture::Sandstone,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DestructibleBlockConfig
+{
+    pub id: Option<u32>,
+    pub active: Option<bool>,
+    pub layer: Option<u32>,
+    pub position: [f32;3],
+    pub scale: Option<[f32;3]>,
+    pub material: BlockMaterial,
+    pub texture: Option<GenericTexture>, // defaults to the material's look
+    pub vulnerability: Option<String>, // maps to DoorType, defaults to the material's weakness
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SmokeTestConfig
+{
+    pub dolphin_path: String, // Dolphin's executable, ideally dolphin-emu-nogui
+    pub seconds: Option<u32>, // how long the game has to run without crashing, 30 by default
+    pub visit_rooms: Option<Vec<String>>, // also boot a copy of the seed starting in each of these rooms
+    pub report_path: Option<String>, // where to write the pass/fail result of each room as JSON
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CrateLootConfig
+{
+    pub drop_chance: Option<f32>, // 0-100, chance that anything drops at all
+    pub weights: Option<BTreeMap<String, f32>>, // refill type -> relative weight, unlisted types are left vanilla
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BeamCombosConfig
+{
+    // false makes collecting the combo do nothing
+    pub super_missile: Option<bool>,
+    pub wavebuster: Option<bool>,
+    pub ice_spreader: Option<bool>,
+    pub flamethrower: Option<bool>,
+
+    // missiles used when firing, vanilla is 5 for Super Missile and 10 for the others
+    pub super_missile_cost: Option<u32>,
+    pub ice_spreader_cost: Option<u32>,
+    pub wavebuster_cost: Option<u32>,
+    pub flamethrower_cost: Option<u32>,
+
+    // seconds per missile while holding the fire button, vanilla is 0.2
+    pub wavebuster_drain_period: Option<f32>,
+    pub flamethrower_drain_period: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EnemyConfig
+{
+    // Multipliers applied to every enemy (and boss)
+    pub health: Option<f32>,
+    pub damage: Option<f32>,
+    pub speed: Option<f32>,
+    pub size: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BoundingBoxConfig
+{
+    pub min: [f32;3],
+    pub max: [f32;3],
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PickupShuffleZonesConfig
+{
+    pub bounding_boxes: Option<Vec<BoundingBoxConfig>>, // replaces the room's own bounds
+    pub positions: Option<Vec<[f32;3]>>, // exact candidate positions, takes priority over boundingBoxes
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct EscapeSequenceConfig
 {
@@ -304,7 +628,7 @@ pub struct EscapeSequenceConfig
     pub stop_trigger_scale: [f32;3],
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct CameraHintConfig
 {
@@ -329,7 +653,7 @@ pub struct CameraHintConfig
     pub behavior: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct LockOnPoint
 {
@@ -342,7 +666,7 @@ pub struct LockOnPoint
     pub no_lock: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Copy, Clone, Eq, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub enum DamageType {
     #[serde(alias="power", alias="POWER")]
@@ -373,7 +697,7 @@ pub enum DamageType {
     Hot,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct TriggerConfig
 {
@@ -390,7 +714,7 @@ pub struct TriggerConfig
     pub deactivate_on_exit: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Copy, Clone, Eq, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub enum SpecialFunctionType {
     What = 0,
@@ -460,7 +784,7 @@ pub enum SpecialFunctionType {
     WeaponSwitch,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct SpecialFunctionConfig
 {
@@ -492,7 +816,7 @@ pub struct SpecialFunctionConfig
 }
 
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ActorRotateConfig
 {
@@ -505,7 +829,7 @@ pub struct ActorRotateConfig
     pub update_active: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct StreamedAudioConfig
 {
@@ -521,7 +845,7 @@ pub struct StreamedAudioConfig
     pub is_music: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct EditObjConfig
 {
@@ -540,6 +864,86 @@ pub struct EditObjConfig
     pub healths: Option<HashMap<u32,f32>>,
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PropertyType
+{
+    Bool,
+    U8,
+    U32,
+    I32,
+    F32,
+    Vector3,
+    Color,
+    AssetId,
+}
+
+/// Text shown in every language, or only in some of them, keyed by STRG language ("ENGL", "JAPN",
+/// ...). Languages which aren't listed keep their original text.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum LocalizedText
+{
+    All(String),
+    PerLanguage(HashMap<String, String>),
+}
+
+impl LocalizedText
+{
+    pub fn get(&self, lang: &str) -> Option<&str>
+    {
+        match self {
+            LocalizedText::All(text) => Some(text),
+            LocalizedText::PerLanguage(texts) => texts.iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(lang))
+                .map(|(_, text)| text.as_str()),
+        }
+    }
+
+    /// Text for languages which aren't listed when there's no original text to keep, preferring
+    /// English
+    pub fn default_text(&self) -> &str
+    {
+        match self {
+            LocalizedText::All(text) => text,
+            LocalizedText::PerLanguage(texts) => self.get("ENGL")
+                .or_else(|| texts.iter().min_by_key(|(key, _)| key.as_str()).map(|(_, text)| text.as_str()))
+                .unwrap_or(""),
+        }
+    }
+
+    /// Every text given, in no particular order
+    pub fn texts(&self) -> Vec<&str>
+    {
+        match self {
+            LocalizedText::All(text) => vec![text],
+            LocalizedText::PerLanguage(texts) => texts.values().map(|text| text.as_str()).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum PropertyValue
+{
+    Bool(bool),
+    Number(f64),
+    List(Vec<f32>),
+}
+
+/// Overwrites raw property data, for edits there isn't a dedicated option for
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EditPropertyConfig
+{
+    pub object_type: u8,
+    pub instance_id: Option<u32>, // every object of the type in the room if unset
+    pub offset: u32, // in bytes, from the end of the object's name
+    #[serde(rename = "type")]
+    pub property_type: PropertyType,
+    pub value: PropertyValue,
+}
+
 // None = 0,
 // PerspLin = 2,
 // PerspExp = 4,
@@ -552,7 +956,7 @@ pub struct EditObjConfig
 // OrthoRevExp = 14,
 // OrthoRevExp2 = 15,
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct FogConfig
 {
@@ -564,7 +968,7 @@ pub struct FogConfig
     pub range_delta: Option<[f32;2]>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct RepositionConfig
 {
@@ -574,7 +978,7 @@ pub struct RepositionConfig
     pub destination_rotation: f32,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct HudmemoConfig
 {
@@ -586,7 +990,7 @@ pub struct HudmemoConfig
     pub modal: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct WaypointConfig
 {
@@ -606,7 +1010,53 @@ pub struct WaypointConfig
     pub animation: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GrapplePointConfig
+{
+    pub id: Option<u32>,
+    pub layer: Option<u32>,
+    pub position: [f32;3],
+    pub rotation: Option<[f32;3]>,
+    pub active: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SpiderTrackConfig
+{
+    pub layer: Option<u32>,
+    pub active: Option<bool>,
+    pub points: Vec<[f32;3]>, // spline the ball follows, in order
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SaveStationConfig
+{
+    pub position: [f32;3], // on the floor, where Samus stands to save
+    pub map_icon: Option<bool>, // defaults to true
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RefillStationKind
+{
+    Missile,
+    PowerBomb,
+    Energy,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RefillStationConfig
+{
+    pub kind: RefillStationKind,
+    pub position: [f32;3], // on the floor, where Samus stands to refill
+    pub map_icon: Option<bool>, // defaults to true, only missile stations have one
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Copy, Clone, Eq, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub enum EnviornmentalEffect {
     None,
@@ -615,7 +1065,7 @@ pub enum EnviornmentalEffect {
     Bubbles,
 }
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Copy, Clone, Eq, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum ConnectionState {
     ANY = 0xFFFFFFFF,
@@ -653,7 +1103,7 @@ pub enum ConnectionState {
     INHERIT_BOUNDS = 0x20,
 }
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Copy, Clone, Eq, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum ConnectionMsg {
     NONE = 0xFFFFFFFF,
@@ -708,7 +1158,7 @@ pub enum ConnectionMsg {
     SUSPENDED_MOVE = 0x30,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ConnectionConfig
 {
@@ -718,7 +1168,7 @@ pub struct ConnectionConfig
     pub message: ConnectionMsg,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct RelayConfig
 {
@@ -727,7 +1177,7 @@ pub struct RelayConfig
     pub active: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct TimerConfig
 {
@@ -740,7 +1190,7 @@ pub struct TimerConfig
     pub start_immediately: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ActorKeyFrameConfig
 {
@@ -754,7 +1204,7 @@ pub struct ActorKeyFrameConfig
     pub total_playback: f32,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct SpawnPointConfig
 {
@@ -768,24 +1218,40 @@ pub struct SpawnPointConfig
     pub items: Option<StartingItems>,
 }
 
-#[derive(Deserialize, Debug, Default, Clone)]
+// Lets the schema describe the structs crate's enum without it depending on schemars
+#[derive(JsonSchema)]
+#[schemars(remote = "structs::MapaObjectVisibilityMode")]
+#[allow(dead_code)]
+enum MapaObjectVisibilityModeDef
+{
+    Always,
+    MapStationOrVisit,
+    Visit,
+    Never,
+    MapStationOrVisit2,
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct RoomConfig
 {
     pub superheated: Option<bool>,
     pub remove_water: Option<bool>,
     pub submerge: Option<bool>,
+    #[schemars(with = "Option<MapaObjectVisibilityModeDef>")]
 	pub map_default_state: Option<structs::MapaObjectVisibilityMode>,
     pub liquids: Option<Vec<WaterConfig>>,
     pub pickups: Option<Vec<PickupConfig>>,
     pub extra_scans: Option<Vec<ScanConfig>>,
-    pub doors: Option<HashMap<u32, DoorConfig>>,
+    pub doors: Option<BTreeMap<u32, DoorConfig>>,
     pub spawn_position_override: Option<[f32;3]>,
     pub bounding_box_offset: Option<[f32;3]>,
     pub bounding_box_scale: Option<[f32;3]>,
     pub platforms: Option<Vec<PlatformConfig>>,
     pub camera_hints: Option<Vec<CameraHintConfig>>,
     pub blocks: Option<Vec<BlockConfig>>,
+    pub destructible_blocks: Option<Vec<DestructibleBlockConfig>>,
+    pub crate_loot: Option<CrateLootConfig>, // overrides gameConfig.crateLoot
     pub lock_on_points: Option<Vec<LockOnPoint>>,
     pub fog: Option<FogConfig>,
     pub ambient_lighting_scale: Option<f32>, // 1.0 is default lighting
@@ -796,10 +1262,10 @@ pub struct RoomConfig
     pub escape_sequences: Option<Vec<EscapeSequenceConfig>>,
     pub repositions: Option<Vec<RepositionConfig>>,
     pub hudmemos: Option<Vec<HudmemoConfig>>,
-    pub layers: Option<HashMap<u32, bool>>,
-    pub layer_objs: Option<HashMap<u32, u32>>,
+    pub layers: Option<BTreeMap<u32, bool>>,
+    pub layer_objs: Option<BTreeMap<u32, u32>>,
     pub delete_ids: Option<Vec<u32>>,
-    pub audio_override: Option<HashMap<String, String>>, // key=instance_id, value=/audio/min_phazonL.dsp|/audio/min_phazonR.dsp
+    pub audio_override: Option<BTreeMap<String, String>>, // key=instance_id, value=/audio/min_phazonL.dsp|/audio/min_phazonR.dsp
     pub add_connections: Option<Vec<ConnectionConfig>>,
     pub remove_connections: Option<Vec<ConnectionConfig>>,
     pub relays: Option<Vec<RelayConfig>>,
@@ -811,23 +1277,40 @@ pub struct RoomConfig
     pub special_functions: Option<Vec<SpecialFunctionConfig>>,
     pub actor_rotates: Option<Vec<ActorRotateConfig>>,
     pub streamed_audios: Option<Vec<StreamedAudioConfig>>,
-    pub edit_objs: Option<HashMap<u32, EditObjConfig>>,
+    pub edit_objs: Option<BTreeMap<u32, EditObjConfig>>,
+    pub edit_properties: Option<Vec<EditPropertyConfig>>,
     pub waypoints: Option<Vec<WaypointConfig>>,
+    pub grapple_points: Option<Vec<GrapplePointConfig>>,
+    pub spider_tracks: Option<Vec<SpiderTrackConfig>>,
+    pub save_stations: Option<Vec<SaveStationConfig>>,
+    pub refill_stations: Option<Vec<RefillStationConfig>>,
+    pub remove_grapple_points: Option<Vec<[f32;3]>>, // removes grapple points near these positions
+    pub remove_spider_tracks: Option<Vec<[f32;3]>>, // removes the whole track passing near these positions
+    pub pickup_shuffle_zones: Option<PickupShuffleZonesConfig>, // where shufflePickupPosition may place pickups in this room
+    pub enemy_config: Option<EnemyConfig>, // overrides gameConfig.enemyConfig
+    pub music_override: Option<String>, // /audio/rui_flaaghraL.dsp|/audio/rui_flaaghraR.dsp or file(s) in extern_assets_dir
+    pub disable_occlusion: Option<bool>, // removes the room's visibility tree so added objects aren't culled
+    pub map_name: Option<String>, // shown on the map and when entering the room
     // Don't forget to update merge_json when adding here
 }
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct LevelConfig
 {
     #[serde(default)]
-    pub transports: HashMap<String, String>,
+    pub transports: BTreeMap<String, String>,
+
+    // Where the player appears in the destination room, keyed by elevator name. Mostly useful
+    // when a transport leads to a room which isn't an elevator
+    #[serde(default)]
+    pub transport_spawn_positions: BTreeMap<String, [f32;3]>,
 
     #[serde(default)]
-    pub rooms: HashMap<String, RoomConfig>,
+    pub rooms: BTreeMap<String, RoomConfig>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct CtwkConfig
 {
@@ -898,9 +1381,23 @@ pub struct CtwkConfig
 
     // GuiColors.CTWK
     pub hud_color: Option<[f32;3]>, // RGB, 0 - 1.0
+    pub hud_colors: Option<HudColors>, // applied on top of hud_color
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HudColors
+{
+    // RGB, 0 - 1.0. The brightness of each original color is kept.
+    pub energy_bar: Option<[f32;3]>,
+    pub radar: Option<[f32;3]>,
+    pub missile_icon: Option<[f32;3]>,
+    pub threat_meter: Option<[f32;3]>,
+    pub visor_frames: Option<[f32;3]>,
+    pub text: Option<[f32;3]>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct IncineratorDroneConfig {
     pub contraption_start_delay_minimum_time: Option<f32>,
@@ -915,7 +1412,7 @@ pub struct IncineratorDroneConfig {
     pub reset_contraption_random_time: Option<f32>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Copy, Clone, Eq, PartialEq)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct HallOfTheEldersBombSlotCoversConfig {
     pub wave: Option<BombSlotCover>,
@@ -923,7 +1420,7 @@ pub struct HallOfTheEldersBombSlotCoversConfig {
     pub plasma: Option<BombSlotCover>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Copy, Clone, Eq, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub enum BombSlotCover {
     #[serde(alias="wave", alias="WAVE")]
@@ -934,7 +1431,7 @@ pub enum BombSlotCover {
     Plasma,
 }
 
-#[derive(PartialEq, Debug, Serialize, Deserialize, Copy, Clone)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, JsonSchema, Copy, Clone)]
 #[serde(deny_unknown_fields)]
 pub enum PhazonDamageModifier
 {
@@ -957,7 +1454,7 @@ pub enum Version
     PalTrilogy,
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Copy, Clone)]
+#[derive(Deserialize, JsonSchema, Serialize, Debug, PartialEq, Copy, Clone)]
 pub enum DoorOpenMode
 {
     Original, // Doors always stay the same color after opening
@@ -967,7 +1464,33 @@ pub enum DoorOpenMode
     // BlueAll, // All Doors downgrade to Blue after opening
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Copy, Clone)]
+#[derive(Deserialize, JsonSchema, Serialize, Debug, PartialEq, Copy, Clone)]
+pub enum FrigateEscapeFailure
+{
+    Reset, // Samus dies when the countdown runs out and the game reloads the last save
+    Continue, // Escape sequence timers count up instead, so they never run out
+}
+
+#[derive(Deserialize, JsonSchema, Serialize, Debug, PartialEq, Copy, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum FillMode
+{
+    Random, // Any item can go in any location
+    MajorMinor, // Upgrades go in the vanilla upgrade locations, expansions in the vanilla expansion locations
+}
+
+// Door types are named like for shieldType and blastShieldType
+#[derive(Deserialize, JsonSchema, Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DoorRandoConfig
+{
+    pub door_types: Option<Vec<String>>, // picked from evenly, defaults to the four beam colors
+    pub open_types: Option<Vec<String>>, // every room can be reached through these, defaults to blue
+    pub blast_shield_types: Option<Vec<String>>,
+    pub blast_shield_chance: Option<f32>, // of each side of a door getting a blast shield, defaults to 0
+}
+
+#[derive(Deserialize, JsonSchema, Serialize, Debug, PartialEq, Copy, Clone)]
 pub enum DifficultyBehavior
 {
     Either,
@@ -997,8 +1520,17 @@ impl fmt::Display for Version
 pub struct PatchConfig
 {
     pub run_mode: RunMode,
+    #[serde(skip_serializing)]
     pub logbook_filename: Option<String>,
+    #[serde(skip_serializing)]
     pub export_asset_dir: Option<String>,
+    #[serde(skip_serializing)]
+    pub room_map_filename: Option<String>,
+    #[serde(skip_serializing)]
+    pub spoiler_filename: Option<String>,
+    #[serde(skip_serializing)]
+    pub patch_file: Option<String>,
+    #[serde(skip_serializing)]
     pub extern_assets_dir: Option<String>,
     pub seed: u64,
     pub uuid: Option<[u8;16]>,
@@ -1012,12 +1544,27 @@ pub struct PatchConfig
     pub iso_format: IsoFormat,
     #[serde(skip_serializing)]
     pub output_iso: File,
+    #[serde(skip_serializing)]
+    pub output_iso_path: String,
+    #[serde(skip_serializing)]
+    pub atomic_output: bool,
+    #[serde(skip_serializing)]
+    pub print_output_hash: bool,
+    #[serde(skip_serializing)]
+    pub verify: bool,
+    #[serde(skip_serializing)]
+    pub fill_junk: bool,
+    #[serde(skip_serializing)]
+    pub smoke_test: Option<SmokeTestConfig>,
 
     pub qol_cutscenes: CutsceneMode,
+    pub cutscene_skip_prompt: bool,
+    pub room_name_popups: bool,
     pub qol_game_breaking: bool,
     pub qol_cosmetic: bool,
     pub qol_pickup_scans: bool,
     pub qol_general: bool,
+    pub cosmetic_door_colors: bool,
 
     pub phazon_elite_without_dynamo: bool,
     pub main_plaza_door: bool,
@@ -1026,6 +1573,8 @@ pub struct PatchConfig
     pub backwards_upper_mines: bool,
     pub backwards_lower_mines: bool,
     pub patch_power_conduits: bool,
+    pub charge_beam_optional: bool,
+    pub scan_visor_optional: bool,
     pub remove_mine_security_station_locks: bool,
     pub remove_hive_mecha: bool,
     pub power_bomb_arboretum_sandstone: bool,
@@ -1036,20 +1585,42 @@ pub struct PatchConfig
     pub maze_seeds: Option<Vec<u32>>,
 
     #[serde(skip_serializing)] // stop racers from peeking at locations
-    pub level_data: HashMap<String, LevelConfig>,
+    pub level_data: BTreeMap<String, LevelConfig>,
 
-    pub strg: HashMap<String, Vec<String>>, // "<decimal asset ID>": <non-null terminated table of strings>
+    pub pickup_pool: BTreeMap<PickupType, u32>,
+    pub fill_mode: FillMode,
+    pub door_rando: Option<DoorRandoConfig>,
+
+    #[serde(skip_serializing)]
+    pub logic: Option<LogicGraph>,
+    pub tricks: BTreeSet<String>,
+    pub require_beatable: bool,
+
+    pub strg: BTreeMap<String, Vec<String>>, // "<decimal asset ID>": <non-null terminated table of strings>
 
     pub starting_room: String,
+    pub starting_spawn: Option<StartingSpawn>, // "@<spawn>" at the end of startingRoom
     pub starting_memo: Option<String>,
     pub spring_ball: bool,
     pub warp_to_start: bool,
     pub warp_to_start_delay_s: f32,
+    pub warp_to_start_fade: bool,
+    pub warp_to_start_sound: Option<PickupSoundEffectConfig>,
+    pub warp_to_start_confirm: bool,
 
     pub automatic_crash_screen: bool,
     pub etank_capacity: u32,
+    pub combo_ammo_costs: Option<[u32; 5]>, // indexed by beam, each beam's combo
+    pub combo_ammo_drain_periods: Option<[f32; 5]>, // indexed by beam, each beam's combo
+    pub red_scans_logbook_category: Option<u32>, // custom red scans without their own category are logged here
+    pub logbook_excluded_scans: Vec<u32>, // SCAN asset IDs which don't count towards completion
     pub shuffle_pickup_position: bool,
     pub shuffle_pickup_pos_all_rooms: bool,
+    pub shuffle_enemies: bool,
+    pub shuffle_enemies_across_types: bool,
+    pub shuffle_music: bool,
+    pub shuffle_music_per_world: bool,
+    pub shuffle_room_names: bool,
     pub remove_vanilla_blast_shields: bool,
     pub nonvaria_heat_damage: bool,
     pub heat_damage_per_sec: f32,
@@ -1057,13 +1628,15 @@ pub struct PatchConfig
     pub phazon_damage_per_sec: f32,
     pub phazon_damage_modifier: PhazonDamageModifier,
     pub staggered_suit_damage: bool,
-    pub item_max_capacity: HashMap<PickupType, u32>,
+    pub suit_damage_reduction: Option<[f32;4]>, // percent, indexed by the number of suits collected
+    pub item_max_capacity: BTreeMap<PickupType, u32>,
     // Use RoomConfig::map_default_state instead of global map_default_state
     pub map_default_state: structs::MapState,
     pub auto_enabled_elevators: bool,
     pub skip_ridley: bool,
     pub multiworld_dol_patches: bool,
     pub update_hint_state_replacement: Option<Vec<u8>>,
+    #[serde(skip_serializing)]
     pub quiet: bool,
 
     pub starting_items: StartingItems,
@@ -1072,6 +1645,8 @@ pub struct PatchConfig
     pub starting_visor: Visor,
     pub starting_beam: Beam,
     pub escape_sequence_counts_up: bool,
+    pub frigate_escape_time: Option<f32>,
+    pub run_time_limit: Option<f32>,
     pub enable_ice_traps: bool,
     pub missile_station_pb_refill: bool,
     pub door_open_mode: DoorOpenMode,
@@ -1079,13 +1654,25 @@ pub struct PatchConfig
     pub artifact_hint_behavior: ArtifactHintBehavior,
 
     #[serde(skip_serializing)]
-    pub flaahgra_music_files: Option<[nod_wrapper::FileWrapper; 2]>,
+    pub flaahgra_music_files: Option<[Box<dyn WithRead>; 2]>, // right, left
+    pub music_gain: f32,
+    pub sfx_gain: f32,
+    pub music_track_gain: BTreeMap<String, f32>, // by part of the track's file name, on top of music_gain
 
     pub skip_splash_screens: bool,
     pub default_game_options: Option<DefaultGameOptions>,
     pub suit_colors: Option<SuitColors>,
+    pub ball_colors: Option<BallColors>,
+    pub beam_colors: Option<BeamColors>,
+    pub color_grading: BTreeMap<String, ColorGradingPreset>, // world -> preset
+    pub main_menu_music: Option<String>, // An RSF, or "left.dsp|right.dsp" converted to RSF
+    pub file_select_music: Option<String>,
+    pub language: Option<Language>, // generated text only replaces strings of this language
+    pub suit_models: Option<SuitModels>,
     pub force_fusion: bool,
+    #[serde(skip_serializing)]
     pub cache_dir: String,
+    pub texture_overrides: BTreeMap<String, String>, // asset id or name -> PNG path
 
     pub quickplay: bool,
     pub quickpatch: bool,
@@ -1093,37 +1680,62 @@ pub struct PatchConfig
     pub game_banner: GameBanner,
     pub comment: String,
     pub main_menu_message: String,
+    pub main_menu_seed_hash: bool,
+    pub seed_info_scan: Option<SeedInfoScanConfig>,
 
     pub credits_string: Option<String>,
     pub results_string: Option<String>,
-    pub artifact_hints: Option<HashMap<String,String>>, // e.g. "Strength":"This item can be found in Ruined Fountain"
+    pub inventory_text: BTreeMap<String, LocalizedText>, // item name -> pause menu description
+    pub artifact_hints: Option<BTreeMap<String,String>>, // e.g. "Strength":"This item can be found in Ruined Fountain"
+    pub objective_rooms: Vec<String>,
+    pub artifact_objectives: bool,
+    pub pause_screen_objectives: bool,
+    pub door_legend: bool,
     pub required_artifact_count: Option<u32>,
     pub artifact_temple_layer_overrides: Option<HashMap<String,bool>>,
     pub no_doors: bool,
-    pub boss_sizes: HashMap<String,f32>,
+    pub boss_sizes: BTreeMap<String,f32>,
     pub shoot_in_grapple: bool,
     pub difficulty_behavior: DifficultyBehavior,
     pub legacy_block_size: bool,
+    pub crate_loot: Option<CrateLootConfig>,
+    pub pickup_aura: Option<PickupAuraConfig>,
+    pub pickup_sounds: BTreeMap<PickupType, PickupSoundsConfig>,
+    pub enemy_config: Option<EnemyConfig>,
     pub ctwk_config: CtwkConfig,
+
+    #[serde(skip_serializing)]
+    pub plugins: PatchPlugins, // only settable by programs using randomprime as a library
 }
 
 /*** Un-Parsed Config (doubles as JSON input specification) ***/
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct Preferences
 {
     skip_splash_screens: Option<bool>,
     default_game_options: Option<DefaultGameOptions>,
     suit_colors: Option<SuitColors>,
+    ball_colors: Option<BallColors>,
+    beam_colors: Option<BeamColors>,
+    color_grading: Option<BTreeMap<String, ColorGradingPreset>>,
+    main_menu_music: Option<String>,
+    file_select_music: Option<String>,
+    language: Option<Language>,
+    suit_models: Option<SuitModels>,
     force_fusion: Option<bool>,
     cache_dir: Option<String>,
+    texture_overrides: Option<BTreeMap<String, String>>,
 
     qol_game_breaking: Option<bool>,
     qol_cosmetic: Option<bool>,
     qol_cutscenes: Option<String>,
+    cutscene_skip_prompt: Option<bool>,
+    room_name_popups: Option<bool>,
     qol_pickup_scans: Option<bool>,
     qol_general: Option<bool>,
+    cosmetic_door_colors: Option<bool>,
 
     map_default_state: Option<String>,
     artifact_hint_behavior: Option<String>,
@@ -1131,26 +1743,42 @@ struct Preferences
     visible_bounding_box: Option<bool>,
 
     trilogy_disc_path: Option<String>,
+    flaahgra_music_dir: Option<String>, // holds rui_flaaghraR.dsp and rui_flaaghraL.dsp
+    flaahgra_music_files: Option<[String; 2]>, // right, left
+    music_gain: Option<f32>, // scales the volume of every room's music, 1.0 leaves it as is
+    sfx_gain: Option<f32>, // scales the volume of every sound effect, in the AGSCs
+    music_track_gain: Option<BTreeMap<String, f32>>,
     quickplay: Option<bool>,
     quickpatch: Option<bool>,
     quiet: Option<bool>,
 }
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct GameConfig
 {
     starting_room: Option<String>,
+    starting_room_pool: Option<Vec<String>>, // what "random" picks from, instead of the safe rooms
+    starting_room_blocklist: Option<Vec<String>>, // what "random" never picks
     starting_memo: Option<String>,
     spring_ball: Option<bool>,
     warp_to_start: Option<bool>,
     warp_to_start_delay_s: Option<f32>,
+    warp_to_start_fade: Option<bool>, // the screen fades to black while waiting to warp
+    warp_to_start_sound: Option<PickupSoundEffectConfig>, // played when the warp is triggered
+    warp_to_start_confirm: Option<bool>, // refusing to save has to be done twice to warp
 
     shuffle_pickup_position: Option<bool>,
     shuffle_pickup_pos_all_rooms: Option<bool>,
+    shuffle_enemies: Option<bool>,
+    shuffle_enemies_across_types: Option<bool>, // within the groups of enemy_shuffle::COMPATIBLE_ENEMY_TYPES
+    shuffle_music: Option<bool>,
+    shuffle_music_per_world: Option<bool>,
+    shuffle_room_names: Option<bool>,
     remove_vanilla_blast_shields: Option<bool>,
     nonvaria_heat_damage: Option<bool>,
     staggered_suit_damage: Option<bool>,
+    suit_damage_reduction: Option<[f32;4]>,
     heat_damage_per_sec: Option<f32>,
     poison_damage_per_sec: Option<f32>,
     phazon_damage_per_sec: Option<f32>,
@@ -1166,12 +1794,18 @@ struct GameConfig
     starting_visor: Option<String>,
     starting_beam: Option<String>,
     escape_sequence_counts_up: Option<bool>,
+    frigate_escape_time: Option<f32>, // seconds
+    frigate_escape_failure: Option<FrigateEscapeFailure>,
+    run_time_limit: Option<f32>, // seconds of in-game time
     enable_ice_traps: Option<bool>,
     missile_station_pb_refill: Option<bool>,
     door_open_mode: Option<DoorOpenMode>,
 
     etank_capacity: Option<u32>,
+    beam_combos: Option<BeamCombosConfig>,
     item_max_capacity: Option<HashMap<String,u32>>,
+    red_scans_logbook_category: Option<u32>,
+    logbook_excluded_scans: Option<Vec<u32>>,
 
     phazon_elite_without_dynamo: Option<bool>,
     main_plaza_door: Option<bool>,
@@ -1180,6 +1814,8 @@ struct GameConfig
     backwards_upper_mines: Option<bool>,
     backwards_lower_mines: Option<bool>,
     patch_power_conduits: Option<bool>,
+    charge_beam_optional: Option<bool>,
+    scan_visor_optional: Option<bool>,
     remove_mine_security_station_locks: Option<bool>,
     remove_hive_mecha: Option<bool>,
     power_bomb_arboretum_sandstone: Option<bool>,
@@ -1191,31 +1827,52 @@ struct GameConfig
     game_banner: Option<GameBanner>,
     comment: Option<String>,
     main_menu_message: Option<String>,
+    main_menu_seed_hash: Option<bool>, // words identifying the seed under mainMenuMessage
+    seed_info_scan: Option<SeedInfoScanConfig>,
 
     credits_string: Option<String>,
     results_string: Option<String>,
-    artifact_hints: Option<HashMap<String,String>>, // e.g. "Strength":"This item can be found in Ruined Fountain"
+    inventory_text: Option<BTreeMap<String, LocalizedText>>,
+    artifact_hints: Option<BTreeMap<String,String>>, // e.g. "Strength":"This item can be found in Ruined Fountain"
+    objective_rooms: Option<Vec<String>>, // e.g. "Phendrana Drifts:Quarantine Cave", flashed on the map
+    artifact_objectives: Option<bool>, // Also flash the rooms the artifacts are in
+    pause_screen_objectives: Option<bool>, // List the objectives in the Power Suit's inventory description
+    door_legend: Option<bool>,
     artifact_temple_layer_overrides: Option<HashMap<String,bool>>,
     required_artifact_count: Option<u32>,
     no_doors: Option<bool>, // Remove every door from the game
-    boss_sizes: Option<HashMap<String,f32>>,
+    boss_sizes: Option<BTreeMap<String,f32>>,
     shoot_in_grapple: Option<bool>,
     difficulty_behavior: Option<DifficultyBehavior>,
     legacy_block_size: Option<bool>,
+    crate_loot: Option<CrateLootConfig>,
+    pickup_aura: Option<PickupAuraConfig>,
+    pickup_sounds: Option<BTreeMap<String, PickupSoundsConfig>>, // by pickup type
+    enemy_config: Option<EnemyConfig>,
 }
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct PatchConfigPrivate
 {
     #[serde(alias = "$schema")]
+    #[schemars(rename = "$schema")]
     _schema: Option<String>,
 
     run_mode: Option<String>,
     logbook_filename: Option<String>,
     export_asset_dir: Option<String>,
+    room_map_filename: Option<String>,
+    spoiler_filename: Option<String>,
     input_iso: Option<String>,
     output_iso: Option<String>,
+    output_format: Option<String>,
+    patch_file: Option<String>,
+    atomic_output: Option<bool>,
+    print_output_hash: Option<bool>,
+    verify: Option<bool>,
+    fill_junk: Option<bool>,
+    smoke_test: Option<SmokeTestConfig>,
     force_vanilla_layout: Option<bool>,
     extern_assets_dir: Option<String>,
     seed: Option<u64>,
@@ -1231,14 +1888,33 @@ struct PatchConfigPrivate
     tweaks: CtwkConfig,
 
     #[serde(default)]
-    level_data: HashMap<String, LevelConfig>,
+    level_data: BTreeMap<String, LevelConfig>,
+
+    pickup_pool: Option<HashMap<String, u32>>,
+    fill_mode: Option<FillMode>,
+    door_rando: Option<DoorRandoConfig>,
+    logic_file: Option<String>,
+    tricks: Option<Vec<String>>,
+    require_beatable: Option<bool>,
     
     #[serde(default)]
-    strg: HashMap<String, Vec<String>>, // "<decimal asset ID>": <non-null terminated table of strings>
+    strg: BTreeMap<String, Vec<String>>, // "<decimal asset ID>": <non-null terminated table of strings>
 }
 
 /*** Parse Patcher Input ***/
 
+fn for_each_pickup<F>(level_data: &mut BTreeMap<String, LevelConfig>, mut f: F)
+    where F: FnMut(&mut PickupConfig)
+{
+    for level in level_data.values_mut() {
+        for room in level.rooms.values_mut() {
+            for pickup in room.pickups.iter_mut().flatten() {
+                f(pickup);
+            }
+        }
+    }
+}
+
 fn extend_option_vec<T>(dest: &mut Option<Vec<T>>, src: Option<Vec<T>>) {
     if let Some(src_vec) = src {
         if dest.is_none() {
@@ -1275,6 +1951,13 @@ macro_rules! merge_optional {
     };
 }
 
+/// What the command line asked the patcher to do
+pub enum CliCommand
+{
+    Patch(PatchConfig),
+    PrintSchema, // --generate-schema
+}
+
 impl PatchConfig
 {
     pub fn from_json(json: &str) -> Result<Self, String>
@@ -1285,7 +1968,33 @@ impl PatchConfig
         result.parse()
     }
 
-    pub fn from_cli_options() -> Result<Self, String>
+    /// JSON Schema of the profile format accepted by `from_json`, generated from the config structs
+    pub fn json_schema() -> String
+    {
+        let schema = schemars::schema_for!(PatchConfigPrivate);
+        serde_json::to_string_pretty(&schema).unwrap()
+    }
+
+    /// Moves the temporary output file over the real output if `written`, or deletes it
+    /// otherwise. Does nothing when `atomic_output` is disabled.
+    pub fn finish_output(&self, written: bool) -> Result<(), String>
+    {
+        if !self.atomic_output {
+            return Ok(());
+        }
+
+        let temp_path = temp_output_path(&self.output_iso_path, true);
+        if written {
+            fs::rename(&temp_path, &self.output_iso_path)
+                .map_err(|e| format!("Failed to move {} to {}: {}", temp_path, self.output_iso_path, e))
+        } else {
+            fs::remove_file(&temp_path)
+                .map_err(|e| format!("Failed to remove {}: {}", temp_path, e))
+        }
+    }
+
+    #[cfg(feature = "cli")]
+    pub fn from_cli_options() -> Result<CliCommand, String>
     {
         let matches = App::new("randomprime ISO patcher")
             .version(crate_version!())
@@ -1295,6 +2004,17 @@ impl PatchConfig
             .arg(Arg::with_name("output iso path")
                 .long("output-iso")
                 .takes_value(true))
+            .arg(Arg::with_name("output format")
+                .long("output-format")
+                .help("iso, gcz, ciso or bps (a patch against the input ISO). Defaults to the output ISO's extension")
+                .takes_value(true))
+            .arg(Arg::with_name("apply patch")
+                .long("apply-patch")
+                .help("Apply the specified BPS patch (see --output-format) to the input ISO and write the result to the output ISO, instead of patching from a profile")
+                .takes_value(true))
+            .arg(Arg::with_name("no atomic output")
+                .long("no-atomic-output")
+                .help("Write directly to the output ISO instead of renaming a temporary file once patching succeeds"))
             .arg(Arg::with_name("extern assets dir")
                 .long("extern-assets-dir")
                 .takes_value(true))
@@ -1317,7 +2037,7 @@ impl PatchConfig
                 .takes_value(true))
             .arg(Arg::with_name("starting room")
                 .long("starting-room")
-                .help("Room which the player starts their adventure from. Format - <world>:<room name>, where <world> is [Frigate|Tallon|Chozo|Magmoor|Phendrana|Mines|Crater]")
+                .help("Room which the player starts their adventure from. Format - <world>:<room name>, where <world> is [Frigate|Tallon|Chozo|Magmoor|Phendrana|Mines|Crater], or \"random\" to pick a save station or elevator from the seed. Add @<index> to pick one of the room's spawn points, or @<x>,<y>,<z> to spawn at a position")
                 .takes_value(true))
             .arg(Arg::with_name("starting memo")
                 .long("starting-memo")
@@ -1329,6 +2049,9 @@ impl PatchConfig
             .arg(Arg::with_name("warp to start")
                 .long("warp-to-start")
                 .help("Allows player to warp to start from any save station"))
+            .arg(Arg::with_name("warp to start confirm")
+                .long("warp-to-start-confirm")
+                .help("Warping to start has to be confirmed by refusing to save a second time"))
             .arg(Arg::with_name("automatic crash screen")
                 .long("automatic-crash-screen")
                 .help("Makes the crash screen appear without any button combination required"))
@@ -1378,6 +2101,19 @@ impl PatchConfig
                 .help(concat!("Location of a ISO of Metroid Prime Trilogy. If provided the ",
                                 "Flaahgra fight music will be used to replace the original"))
                 .takes_value(true))
+            .arg(Arg::with_name("flaahgra music dir")
+                .long("flaahgra-music-dir")
+                .help(concat!("Folder with rui_flaaghraR.dsp and rui_flaaghraL.dsp from Metroid Prime Trilogy, ",
+                                "used like --flaahgra-music-disc-path without needing the whole disc"))
+                .takes_value(true))
+            .arg(Arg::with_name("music gain")
+                .long("music-gain")
+                .help("Scales the volume of the music in every room, 1.0 leaves it as is")
+                .takes_value(true))
+            .arg(Arg::with_name("sfx gain")
+                .long("sfx-gain")
+                .help("Scales the volume of the sound effects in every room, 1.0 leaves it as is")
+                .takes_value(true))
             .arg(Arg::with_name("quiet")
                 .long("quiet")
                 .help("Don't print the progress messages"))
@@ -1408,12 +2144,89 @@ impl PatchConfig
                 .long("text-file-comment")
                 .hidden(true)
                 .takes_value(true))
+            // Flags of the original randomprime CLI, so older front-ends keep working
+            .arg(Arg::with_name("legacy layout")
+                .long("layout")
+                .hidden(true)
+                .takes_value(true))
+            .arg(Arg::with_name("legacy skip frigate")
+                .long("skip-frigate")
+                .hidden(true))
+            .arg(Arg::with_name("legacy skip hudmenus")
+                .long("skip-hudmenus")
+                .hidden(true))
+            .arg(Arg::with_name("legacy obfuscate items")
+                .long("obfuscate-items")
+                .hidden(true))
+            .arg(Arg::with_name("legacy keep fmvs")
+                .long("keep-fmvs")
+                .hidden(true))
+            .arg(Arg::with_name("legacy trilogy disc path")
+                .long("trilogy-disc-path")
+                .hidden(true)
+                .takes_value(true))
             .arg(Arg::with_name("run mode")
                 .long("run-mode")
                 .hidden(false)
                 .takes_value(true))
+            .arg(Arg::with_name("dump room map")
+                .long("dump-room-map")
+                .help("Write a map of every world's rooms (MREA id and dock count) read from the input ISO to the specified .json or .csv file and exit")
+                .takes_value(true))
+            .arg(Arg::with_name("extract assets")
+                .long("extract-assets")
+                .help("Write every asset in the input ISO's PAKs to the specified directory, one subdirectory per PAK with files named like extern assets, and exit")
+                .takes_value(true))
+            .arg(Arg::with_name("validate profile")
+                .long("validate-profile")
+                .help("Check that the profile's custom text fits on screen when read with the input ISO's fonts, then exit without patching"))
+            .arg(Arg::with_name("print output hash")
+                .long("print-output-hash")
+                .help("Print the SHA-256 of the output once it's written. The same profile and input ISO always give the same output."))
+            .arg(Arg::with_name("spoiler")
+                .long("spoiler")
+                .help("Write where every pickup, elevator and door ended up once the seed's shuffles are done to the specified .json file")
+                .takes_value(true))
+            .arg(Arg::with_name("fill junk")
+                .long("fill-junk")
+                .help("Fill unused disc space with the junk found on retail discs instead of zeroes"))
+            .arg(Arg::with_name("logic file")
+                .long("logic-file")
+                .help("Logic graph (.json) describing how rooms connect and what their items require")
+                .takes_value(true))
+            .arg(Arg::with_name("require beatable")
+                .long("require-beatable")
+                .help("Check the seed can be beaten using --logic-file (or the built-in logic), reshuffling pickupPool until it can"))
+            .arg(Arg::with_name("verify")
+                .long("verify")
+                .help("Once the ISO is written, read it back and check the FST, DOL and PAKs aren't corrupt"))
+            .arg(Arg::with_name("smoke test")
+                .long("smoke-test")
+                .help("Once the ISO is written, boot it in Dolphin (the specified executable) and check the game doesn't crash")
+                .takes_value(true))
+            .arg(Arg::with_name("smoke test seconds")
+                .long("smoke-test-seconds")
+                .help("How long the game has to run during the smoke test, 30 by default")
+                .takes_value(true)
+                .validator(|s| s.parse::<u32>().map(|_| ())
+                                            .map_err(|_| "Expected an integer".to_string())))
+            .arg(Arg::with_name("visit rooms")
+                .long("visit-rooms")
+                .help("With --smoke-test, also check that each of these comma separated rooms (e.g. 'Chozo:Main Plaza') loads without crashing")
+                .takes_value(true))
+            .arg(Arg::with_name("smoke test report")
+                .long("smoke-test-report")
+                .help("Write the result of each room visited by --visit-rooms to the specified JSON file")
+                .takes_value(true))
+            .arg(Arg::with_name("generate schema")
+                .long("generate-schema")
+                .help("Print the JSON Schema of the profile format and exit"))
             .get_matches();
 
+        if matches.is_present("generate schema") {
+            return Ok(CliCommand::PrintSchema);
+        }
+
         let mut patch_config = if matches.is_present("profile json path") {
             let json_path = matches.value_of("profile json path").unwrap();
             let cli_json_config_raw: &str = &fs::read_to_string(json_path)
@@ -1450,8 +2263,41 @@ impl PatchConfig
             "auto enabled elevators" => patch_config.game_config.auto_enabled_elevators,
             "spring ball" => patch_config.game_config.spring_ball,
             "warp to start" => patch_config.game_config.warp_to_start,
+            "warp to start confirm" => patch_config.game_config.warp_to_start_confirm,
         );
 
+        // legacy, the flags they map onto take priority
+        if let Some(layout) = matches.value_of("legacy layout") {
+            println!("Warning, --layout is deprecated, list the pickups in levelData.<world>.rooms.<room>.pickups and the elevators in levelData.<world>.transports instead");
+            let starting_room = crate::legacy_layout::parse_layout(layout)?.apply(&mut patch_config.level_data);
+            if let Some(starting_room) = starting_room {
+                patch_config.game_config.starting_room.get_or_insert(starting_room);
+            }
+        }
+        if matches.is_present("legacy obfuscate items") {
+            println!("Warning, --obfuscate-items is deprecated, set \"model\": \"Nothing\" on the pickups in levelData instead");
+            for_each_pickup(&mut patch_config.level_data, |pickup| {
+                pickup.model.get_or_insert_with(|| "Nothing".to_string());
+            });
+        }
+        if matches.is_present("legacy skip frigate") {
+            println!("Warning, --skip-frigate is deprecated, use --starting-room \"Tallon:Landing Site\" instead");
+            patch_config.game_config.starting_room = Some("Tallon:Landing Site".to_string());
+        }
+        if matches.is_present("legacy skip hudmenus") {
+            println!("Warning, --skip-hudmenus is deprecated, set \"modalHudmemo\": false on the pickups in levelData instead");
+            for_each_pickup(&mut patch_config.level_data, |pickup| {
+                pickup.modal_hudmemo.get_or_insert(false);
+            });
+        }
+        if matches.is_present("legacy keep fmvs") {
+            println!("Warning, --keep-fmvs is deprecated and does nothing, FMVs are only removed by --quickpatch");
+        }
+        if let Some(trilogy_disc_path) = matches.value_of("legacy trilogy disc path") {
+            println!("Warning, --trilogy-disc-path is deprecated, use --flaahgra-music-disc-path instead");
+            patch_config.preferences.trilogy_disc_path = Some(trilogy_disc_path.to_string());
+        }
+
         // string
         if let Some(input_iso_path) = matches.value_of("input iso path") {
             patch_config.input_iso  = Some(input_iso_path.to_string());
@@ -1459,6 +2305,43 @@ impl PatchConfig
         if let Some(output_iso_path) = matches.value_of("output iso path") {
             patch_config.output_iso = Some(output_iso_path.to_string());
         }
+        if let Some(output_format) = matches.value_of("output format") {
+            patch_config.output_format = Some(output_format.to_string());
+        }
+        if let Some(patch_file) = matches.value_of("apply patch") {
+            patch_config.run_mode = Some("apply_patch".to_string());
+            patch_config.patch_file = Some(patch_file.to_string());
+        }
+        if let Some(dolphin_path) = matches.value_of("smoke test") {
+            patch_config.smoke_test = Some(SmokeTestConfig {
+                dolphin_path: dolphin_path.to_string(),
+                seconds: matches.value_of("smoke test seconds").map(|s| s.parse().unwrap()),
+                visit_rooms: matches.value_of("visit rooms")
+                    .map(|rooms| rooms.split(',').map(|room| room.trim().to_string()).collect()),
+                report_path: matches.value_of("smoke test report").map(|s| s.to_string()),
+            });
+        }
+        if matches.is_present("print output hash") {
+            patch_config.print_output_hash = Some(true);
+        }
+        if matches.is_present("verify") {
+            patch_config.verify = Some(true);
+        }
+        if let Some(logic_file) = matches.value_of("logic file") {
+            patch_config.logic_file = Some(logic_file.to_string());
+        }
+        if matches.is_present("require beatable") {
+            patch_config.require_beatable = Some(true);
+        }
+        if let Some(spoiler_filename) = matches.value_of("spoiler") {
+            patch_config.spoiler_filename = Some(spoiler_filename.to_string());
+        }
+        if matches.is_present("fill junk") {
+            patch_config.fill_junk = Some(true);
+        }
+        if matches.is_present("no atomic output") {
+            patch_config.atomic_output = Some(false);
+        }
         if let Some(extern_assets_dir) = matches.value_of("extern assets dir") {
             patch_config.extern_assets_dir = Some(extern_assets_dir.to_string());
         }
@@ -1471,6 +2354,15 @@ impl PatchConfig
         if let Some(trilogy_disc_path) = matches.value_of("trilogy disc path") {
             patch_config.preferences.trilogy_disc_path = Some(trilogy_disc_path.to_string());
         }
+        if let Some(flaahgra_music_dir) = matches.value_of("flaahgra music dir") {
+            patch_config.preferences.flaahgra_music_dir = Some(flaahgra_music_dir.to_string());
+        }
+        if let Some(music_gain) = matches.value_of("music gain") {
+            patch_config.preferences.music_gain = Some(music_gain.parse::<f32>().unwrap());
+        }
+        if let Some(sfx_gain) = matches.value_of("sfx gain") {
+            patch_config.preferences.sfx_gain = Some(sfx_gain.parse::<f32>().unwrap());
+        }
         if let Some(starting_room) = matches.value_of("starting room") {
             patch_config.game_config.starting_room = Some(starting_room.to_string());
         }
@@ -1483,6 +2375,17 @@ impl PatchConfig
         if let Some(run_mode) = matches.value_of("run mode") {
             patch_config.run_mode = Some(run_mode.to_string());
         }
+        if matches.is_present("validate profile") {
+            patch_config.run_mode = Some("validate_profile".to_string());
+        }
+        if let Some(room_map_filename) = matches.value_of("dump room map") {
+            patch_config.run_mode = Some("export_room_map".to_string());
+            patch_config.room_map_filename = Some(room_map_filename.to_string());
+        }
+        if let Some(extract_dir) = matches.value_of("extract assets") {
+            patch_config.run_mode = Some("extract_assets".to_string());
+            patch_config.export_asset_dir = Some(extract_dir.to_string());
+        }
 
         // integer/float
         if let Some(s) = matches.value_of("seed") {
@@ -1516,7 +2419,7 @@ impl PatchConfig
             );
         }
 
-        patch_config.parse()
+        Ok(CliCommand::Patch(patch_config.parse()?))
     }
 }
 
@@ -1561,6 +2464,7 @@ impl PatchConfigPrivate
                 extend_option_vec!(platforms         , self_room_config, other_room_config);
                 extend_option_vec!(camera_hints      , self_room_config, other_room_config);
                 extend_option_vec!(blocks            , self_room_config, other_room_config);
+                extend_option_vec!(destructible_blocks, self_room_config, other_room_config);
                 extend_option_vec!(lock_on_points    , self_room_config, other_room_config);
                 extend_option_vec!(escape_sequences  , self_room_config, other_room_config);
                 extend_option_vec!(repositions       , self_room_config, other_room_config);
@@ -1578,10 +2482,45 @@ impl PatchConfigPrivate
                 extend_option_vec!(actor_rotates     , self_room_config, other_room_config);
                 extend_option_vec!(streamed_audios   , self_room_config, other_room_config);
                 extend_option_vec!(waypoints         , self_room_config, other_room_config);
+                extend_option_vec!(grapple_points    , self_room_config, other_room_config);
+                extend_option_vec!(spider_tracks     , self_room_config, other_room_config);
+                extend_option_vec!(save_stations     , self_room_config, other_room_config);
+                extend_option_vec!(refill_stations   , self_room_config, other_room_config);
+                extend_option_vec!(remove_grapple_points, self_room_config, other_room_config);
+                extend_option_vec!(remove_spider_tracks , self_room_config, other_room_config);
+                extend_option_vec!(edit_properties   , self_room_config, other_room_config);
+
+                if self_room_config.spawn_position_override.is_none() {
+                    self_room_config.spawn_position_override = other_room_config.spawn_position_override;
+                }
+
+                if self_room_config.crate_loot.is_none() {
+                    self_room_config.crate_loot = other_room_config.crate_loot.clone();
+                }
+
+                if self_room_config.pickup_shuffle_zones.is_none() {
+                    self_room_config.pickup_shuffle_zones = other_room_config.pickup_shuffle_zones.clone();
+                }
+
+                if self_room_config.enemy_config.is_none() {
+                    self_room_config.enemy_config = other_room_config.enemy_config.clone();
+                }
+
+                if self_room_config.music_override.is_none() {
+                    self_room_config.music_override = other_room_config.music_override.clone();
+                }
+
+                if self_room_config.disable_occlusion.is_none() {
+                    self_room_config.disable_occlusion = other_room_config.disable_occlusion;
+                }
+
+                if self_room_config.map_name.is_none() {
+                    self_room_config.map_name = other_room_config.map_name.clone();
+                }
 
                 if let Some(other_layers) = &other_room_config.layers {
                     if self_room_config.layers.is_none() {
-                        self_room_config.layers = Some(HashMap::new());
+                        self_room_config.layers = Some(BTreeMap::new());
                     }
 
                     let self_layers = self_room_config.layers.as_mut().unwrap();
@@ -1601,7 +2540,7 @@ impl PatchConfigPrivate
 
                 if let Some(other_edit_objs) = &other_room_config.edit_objs {
                     if self_room_config.edit_objs.is_none() {
-                        self_room_config.edit_objs = Some(HashMap::new());
+                        self_room_config.edit_objs = Some(BTreeMap::new());
                     }
 
                     let self_edit_objs = self_room_config.edit_objs.as_mut().unwrap();
@@ -1692,17 +2631,33 @@ impl PatchConfigPrivate
     // parse and then handle configuration macros (e.g. a bool loading in several pages of JSON changes)
     fn parse(&self) -> Result<PatchConfig, String>
     {
+        let input_iso_path = self.input_iso.as_deref().unwrap_or("prime.iso").trim();
+        let output_iso_path = self.output_iso.as_deref().unwrap_or("prime_out.iso");
+
+        // The input is memory mapped, truncating it to write the output would pull the rug out
+        // from under the patcher
+        let same_file = match (fs::canonicalize(input_iso_path), fs::canonicalize(output_iso_path)) {
+            (Ok(input), Ok(output)) => input == output,
+            _ => false,
+        };
+        if same_file {
+            Err(format!("The output ISO can't overwrite the input ISO ({})", input_iso_path))?
+        }
+
+        let input_iso = open_input_iso(input_iso_path)?;
+
         // Parse version
         let version = {
-            let input_iso_path = self.input_iso.as_deref().unwrap_or("prime.iso");
-            let input_iso_file = File::open(input_iso_path.trim())
-                .map_err(|e| format!("Failed to open {}: {}", input_iso_path, e))?;
-            let input_iso = unsafe { memmap::Mmap::map(&input_iso_file) }
-                .map_err(|e| format!("Failed to open {}: {}", input_iso_path,  e))?;
-
             let mut reader = Reader::new(&input_iso[..]);
             let gc_disc: structs::GcDisc = reader.read(());
-        
+
+            if gc_disc.find_file("rel_config.bin").is_some() {
+                Err(format!(
+                    "{} has already been patched by randomprime, please use an unmodified copy of the game",
+                    input_iso_path
+                ))?
+            }
+
             match (&gc_disc.header.game_identifier(), gc_disc.header.disc_id, gc_disc.header.version) {
                 (b"GM8E01", 0, 0)  => Version::NtscU0_00,
                 (b"GM8E01", 0, 1)  => Version::NtscU0_01,
@@ -1743,47 +2698,86 @@ impl PatchConfigPrivate
             merge_json(&mut result, QOL)?;
         }
 
-        result.parse_inner(version)
+        if self.preferences.qol_game_breaking.unwrap_or(!force_vanilla_layout) {
+            merge_json(&mut result, GAME_BREAKING)?;
+        }
+
+        result.parse_inner(version, input_iso)
     }
 
-    fn parse_inner(&self, version: Version) -> Result<PatchConfig, String>
+    fn parse_inner(&self, version: Version, input_iso: memmap::Mmap) -> Result<PatchConfig, String>
     {
         let run_mode = {
             if self.run_mode.is_some() {
-                match self.run_mode.as_ref().unwrap().to_lowercase().trim() {
-                    "create_iso" => RunMode::CreateIso,
-                    "export_logbook" => RunMode::ExportLogbook,
-                    "export_assets" => RunMode::ExportAssets,
+                // Both "export_logbook" and "ExportLogbook" are accepted
+                match self.run_mode.as_ref().unwrap().to_lowercase().trim().replace('_', "").as_str() {
+                    "createiso" => RunMode::CreateIso,
+                    "exportlogbook" => RunMode::ExportLogbook,
+                    "exportassets" => RunMode::ExportAssets,
+                    "extractassets" => RunMode::ExtractAssets,
+                    "applypatch" => RunMode::ApplyPatch,
+                    "exportroommap" => RunMode::ExportRoomMap,
+                    "validateprofile" => RunMode::ValidateProfile,
                     _ => panic!("Unsupported run mode: {}", self.run_mode.as_ref().unwrap())
                 }
             } else {
                 RunMode::CreateIso
             }
         };
-
-        let input_iso_path = self.input_iso.as_deref().unwrap_or("prime.iso");
-        let input_iso_file = File::open(input_iso_path.trim())
-            .map_err(|e| format!("Failed to open {}: {}", input_iso_path, e))?;
-
-        let input_iso = unsafe { memmap::Mmap::map(&input_iso_file) }
-            .map_err(|e| format!("Failed to open {}: {}", input_iso_path,  e))?;
+        if run_mode == RunMode::ApplyPatch {
+            if !cfg!(feature = "bps") {
+                Err("This build of randomprime can't apply BPS patches")?
+            }
+            if self.patch_file.is_none() {
+                Err("The ApplyPatch run mode needs a patchFile")?
+            }
+        }
 
         let output_iso_path = self.output_iso.as_deref().unwrap_or("prime_out.iso");
+        let atomic_output = self.atomic_output.unwrap_or(true);
 
+        // Write to a temporary file which replaces the output once patching succeeds, so an
+        // interrupted run doesn't leave a half-written ISO behind
         let output_iso = OpenOptions::new()
+            .read(true) // applying a patch and verifying read back what was written
             .write(true)
             .create(true)
             .truncate(true)
-            .open(&output_iso_path)
+            .open(temp_output_path(output_iso_path, atomic_output))
             .map_err(|e| format!("Failed to open {}: {}", output_iso_path, e))?;
 
-        let iso_format = if output_iso_path.ends_with(".gcz") {
-            IsoFormat::Gcz
-        } else if output_iso_path.ends_with(".ciso") {
-            IsoFormat::Ciso
-        } else {
-            IsoFormat::Iso
+        let output_format = match self.output_format.as_deref() {
+            Some(output_format) => output_format.trim().to_lowercase(),
+            None => Path::new(output_iso_path).extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default(),
         };
+        let iso_format = match output_format.as_str() {
+            "gcz" => IsoFormat::Gcz,
+            "ciso" => IsoFormat::Ciso,
+            "bps" => IsoFormat::Bps,
+            "iso" => IsoFormat::Iso,
+            _ if self.output_format.is_some() => Err(format!("Unknown output format '{}'", output_format))?,
+            _ => IsoFormat::Iso,
+        };
+        match iso_format {
+            IsoFormat::Gcz if !cfg!(feature = "gcz") => Err("This build of randomprime can't write GCZ images")?,
+            IsoFormat::Ciso if !cfg!(feature = "ciso") => Err("This build of randomprime can't write CISO images")?,
+            IsoFormat::Bps if !cfg!(feature = "bps") => Err("This build of randomprime can't write BPS patches")?,
+            _ => (),
+        }
+        let verify = self.verify.unwrap_or(false);
+        if verify && !matches!(iso_format, IsoFormat::Iso) {
+            Err("Only ISO output can be verified")?
+        }
+        if self.smoke_test.is_some() {
+            if !cfg!(feature = "smoke-test") {
+                Err("This build of randomprime can't run smoke tests")?
+            }
+            if matches!(iso_format, IsoFormat::Bps) {
+                Err("A BPS patch can't be smoke tested, please output an ISO instead")?
+            }
+        }
 
         let force_vanilla_layout = self.force_vanilla_layout.unwrap_or(false);
 
@@ -1824,9 +2818,61 @@ impl PatchConfigPrivate
             }
         };
 
-        let flaahgra_music_files = self.preferences.trilogy_disc_path.as_ref()
-            .map(|path| extract_flaahgra_music_files(path))
-            .transpose()?;
+        let flaahgra_music_sources = [
+            self.preferences.trilogy_disc_path.is_some(),
+            self.preferences.flaahgra_music_dir.is_some(),
+            self.preferences.flaahgra_music_files.is_some(),
+        ];
+        if flaahgra_music_sources.iter().filter(|source| **source).count() > 1 {
+            Err("Only one of trilogyDiscPath, flaahgraMusicDir and flaahgraMusicFiles can be used")?
+        }
+        let flaahgra_music_paths = match (&self.preferences.flaahgra_music_dir, &self.preferences.flaahgra_music_files) {
+            (Some(dir), _) => Some([
+                Path::new(dir).join("rui_flaaghraR.dsp").to_string_lossy().into_owned(),
+                Path::new(dir).join("rui_flaaghraL.dsp").to_string_lossy().into_owned(),
+            ]),
+            (None, Some(files)) => Some(files.clone()),
+            (None, None) => None,
+        };
+        let flaahgra_music_files: Option<[Box<dyn WithRead>; 2]> = if let Some(paths) = flaahgra_music_paths {
+            let read = |path: &String| fs::read(path)
+                .map_err(|e| format!("Failed to read Flaahgra music file {}: {}", path, e));
+            Some([Box::new(read(&paths[0])?), Box::new(read(&paths[1])?)])
+        } else {
+            #[cfg(feature = "trilogy")]
+            let files = self.preferences.trilogy_disc_path.as_ref()
+                .map(|path| extract_flaahgra_music_files(path))
+                .transpose()?
+                .map(|[right, left]| -> [Box<dyn WithRead>; 2] { [Box::new(right), Box::new(left)] });
+            #[cfg(not(feature = "trilogy"))]
+            let files = if self.preferences.trilogy_disc_path.is_some() {
+                Err("This build of randomprime can't read Metroid Prime Trilogy discs (trilogyDiscPath)")?
+            } else {
+                None
+            };
+            files
+        };
+        if let Some([right, left]) = flaahgra_music_files.as_ref() {
+            let right = read_dsp_header(&**right).map_err(|e| format!("Right channel of the Flaahgra music: {}", e))?;
+            let left = read_dsp_header(&**left).map_err(|e| format!("Left channel of the Flaahgra music: {}", e))?;
+            if right != left {
+                Err(format!(
+                    "The channels of the Flaahgra music don't match, the right one has {} samples at {} Hz and the left one {} samples at {} Hz",
+                    right.sample_count, right.sample_rate, left.sample_count, left.sample_rate,
+                ))?
+            }
+        }
+
+        let music_gain = self.preferences.music_gain.unwrap_or(1.0);
+        let sfx_gain = self.preferences.sfx_gain.unwrap_or(1.0);
+        let music_track_gain = self.preferences.music_track_gain.clone().unwrap_or_default();
+        let gains = [("musicGain", music_gain), ("sfxGain", sfx_gain)].into_iter()
+            .chain(music_track_gain.values().map(|gain| ("musicTrackGain", *gain)));
+        for (name, gain) in gains {
+            if !gain.is_finite() || gain < 0.0 {
+                Err(format!("{} has to be a positive number, not {}", name, gain))?
+            }
+        }
 
         let mut item_max_capacity = match &self.game_config.item_max_capacity {
             Some(max_capacity) => {
@@ -1834,17 +2880,143 @@ impl PatchConfigPrivate
                     .map(|(name, capacity) | (PickupType::from_str(name), *capacity))
                     .collect()
             },
-            None => HashMap::new(),
+            None => BTreeMap::new(),
         };
         if !item_max_capacity.contains_key(&PickupType::EnergyTank) && !force_vanilla_layout {
             item_max_capacity.insert(PickupType::EnergyTank, 200);
         }
 
+        // A disabled combo can still be collected, but the player never has any of it
+        let beam_combos = self.game_config.beam_combos.clone().unwrap_or_default();
+        let combos = [
+            (PickupType::SuperMissile, beam_combos.super_missile),
+            (PickupType::Wavebuster, beam_combos.wavebuster),
+            (PickupType::IceSpreader, beam_combos.ice_spreader),
+            (PickupType::Flamethrower, beam_combos.flamethrower),
+        ];
+        for (pickup_type, enabled) in combos.iter() {
+            if *enabled == Some(false) {
+                item_max_capacity.insert(*pickup_type, 0);
+            }
+        }
+
+        // The game looks both of these up by the current beam: Power, Ice, Wave, Plasma, Phazon
+        let costs = [
+            beam_combos.super_missile_cost,
+            beam_combos.ice_spreader_cost,
+            beam_combos.wavebuster_cost,
+            beam_combos.flamethrower_cost,
+        ];
+        let combo_ammo_costs = if costs.iter().any(|cost| cost.is_some()) {
+            let mut combo_ammo_costs = [5, 10, 10, 10, 1];
+            for (i, cost) in costs.iter().enumerate() {
+                if let Some(cost) = cost {
+                    combo_ammo_costs[i] = *cost;
+                }
+            }
+            Some(combo_ammo_costs)
+        } else {
+            None
+        };
+
+        let periods = [
+            (2, "wavebusterDrainPeriod", beam_combos.wavebuster_drain_period),
+            (3, "flamethrowerDrainPeriod", beam_combos.flamethrower_drain_period),
+        ];
+        let combo_ammo_drain_periods = if periods.iter().any(|(_, _, period)| period.is_some()) {
+            let mut combo_ammo_drain_periods = [0.2; 5];
+            for (i, name, period) in periods.iter() {
+                if let Some(period) = period {
+                    if *period <= 0.0 {
+                        Err(format!("beamCombos.{} must be positive, got {}", name, period))?
+                    }
+                    combo_ammo_drain_periods[*i] = *period;
+                }
+            }
+            Some(combo_ammo_drain_periods)
+        } else {
+            None
+        };
+
+        if let Some(time) = self.game_config.frigate_escape_time {
+            if time <= 0.0 {
+                Err(format!("frigateEscapeTime must be positive, got {}", time))?
+            }
+        }
+
+        if let Some(time) = self.game_config.run_time_limit {
+            if time <= 0.0 {
+                Err(format!("runTimeLimit must be positive, got {}", time))?
+            }
+            // Both use the escape sequence timer
+            if self.game_config.escape_sequence_counts_up.unwrap_or(false) ||
+                self.game_config.frigate_escape_failure == Some(FrigateEscapeFailure::Continue)
+            {
+                Err("runTimeLimit can't be used when escape sequences count up")?
+            }
+        }
+
         if item_max_capacity.contains_key(&PickupType::Nothing) || item_max_capacity.contains_key(&PickupType::FloatyJump) || item_max_capacity.contains_key(&PickupType::IceTrap)
         {
             panic!("Illegal pickup name in 'itemMaxCapacity'");
         }
 
+        let pickup_pool: BTreeMap<PickupType, u32> = match &self.pickup_pool {
+            Some(pool) => {
+                pool.iter()
+                    .map(|(name, count)| (PickupType::from_str(name), *count))
+                    .collect()
+            },
+            None => BTreeMap::new(),
+        };
+        if !pickup_pool.is_empty() && force_vanilla_layout {
+            Err("pickupPool can't be used with forceVanillaLayout")?
+        }
+        if self.fill_mode.is_some() && self.pickup_pool.is_none() {
+            Err("fillMode only applies to pickupPool")?
+        }
+
+        if let Some(door_rando) = self.door_rando.as_ref() {
+            if force_vanilla_layout {
+                Err("doorRando can't be used with forceVanillaLayout")?
+            }
+            let door_types = door_rando.door_types.iter().chain(door_rando.open_types.iter()).flatten();
+            for door_type in door_types {
+                if DoorType::from_string(door_type.clone()).is_none() {
+                    Err(format!("Unknown door type in doorRando - {}", door_type))?
+                }
+            }
+            for blast_shield_type in door_rando.blast_shield_types.iter().flatten() {
+                if BlastShieldType::from_str(blast_shield_type).is_none() {
+                    Err(format!("Unknown blast shield type in doorRando - {}", blast_shield_type))?
+                }
+            }
+            if door_rando.door_types.as_ref().map(|types| types.is_empty()).unwrap_or(false) ||
+                door_rando.open_types.as_ref().map(|types| types.is_empty()).unwrap_or(false)
+            {
+                Err("doorRando needs at least one door type and one open type")?
+            }
+            let chance = door_rando.blast_shield_chance.unwrap_or(0.0);
+            if !(0.0..=1.0).contains(&chance) {
+                Err(format!("doorRando blastShieldChance has to be between 0 and 1, not {}", chance))?
+            }
+        }
+
+        let require_beatable = self.require_beatable.unwrap_or(false);
+        let logic = match self.logic_file.as_ref() {
+            Some(logic_file) => {
+                let json = fs::read_to_string(logic_file)
+                    .map_err(|e| format!("Failed to read logic file {}: {}", logic_file, e))?;
+                Some(LogicGraph::from_json(&json)?)
+            },
+            None if require_beatable => Some(LogicGraph::from_json(LOGIC)?),
+            None => None,
+        };
+        let tricks: BTreeSet<String> = self.tricks.clone().unwrap_or_default().into_iter().collect();
+        if let Some(trick) = tricks.iter().find(|trick| !logic.as_ref().map_or(false, |logic| logic.tricks.contains(trick))) {
+            Err(format!("Unknown trick '{}', tricks have to be listed by the logic file", trick))?
+        }
+
         let qol_game_breaking = self.preferences.qol_game_breaking.unwrap_or(!force_vanilla_layout);
         let qol_cosmetic = self.preferences.qol_cosmetic.unwrap_or(!force_vanilla_layout);
         let qol_pickup_scans = self.preferences.qol_pickup_scans.unwrap_or(!force_vanilla_layout);
@@ -1874,6 +3046,58 @@ impl PatchConfigPrivate
                 }
             }
         };
+        let starting_room = if starting_room.trim().eq_ignore_ascii_case("random") {
+            let starting_room = pick_random_starting_room(
+                self.game_config.starting_room_pool.as_deref(),
+                self.game_config.starting_room_blocklist.as_deref().unwrap_or(&[]),
+                self.seed.unwrap_or(123),
+            )?;
+            if !self.preferences.quiet.unwrap_or(false) {
+                println!("Random starting room: {}", starting_room);
+            }
+            starting_room
+        } else {
+            starting_room
+        };
+        let (starting_room, starting_spawn) = parse_starting_room(&starting_room)?;
+
+        // The logic graph starts at the Landing Site and follows the vanilla elevators and doors
+        if require_beatable {
+            let landing_site = SpawnRoomData::try_from_str("Tallon:Landing Site");
+            let mut unsupported = vec![];
+            if starting_spawn.is_some() || SpawnRoomData::try_from_str(&starting_room) != landing_site {
+                unsupported.push("startingRoom");
+            }
+            let shuffled_transport = self.level_data.values()
+                .flat_map(|level| level.transports.iter())
+                .any(|(elevator_name, destination)| {
+                    let elevator = match Elevator::from_str(elevator_name) {
+                        Some(elevator) => elevator,
+                        None => return false, // the escape and ending cutscenes
+                    };
+                    let default_dest = elevator.elevator_data().default_dest.elevator_data().mrea;
+                    SpawnRoomData::try_from_str(destination).map(|dest| dest.mrea) != Some(default_dest)
+                });
+            if shuffled_transport {
+                unsupported.push("transports");
+            }
+            let door_destination = self.level_data.values()
+                .flat_map(|level| level.rooms.values())
+                .flat_map(|room| room.doors.iter().flatten())
+                .any(|(_, door)| door.destination.is_some());
+            if door_destination {
+                unsupported.push("door destinations");
+            }
+            if self.door_rando.is_some() {
+                unsupported.push("doorRando");
+            }
+            if !unsupported.is_empty() {
+                Err(format!(
+                    "requireBeatable can't be combined with {}, the logic only knows the vanilla start, elevators and doors",
+                    unsupported.join(", "),
+                ))?
+            }
+        }
 
         let starting_items = {
             let items = self.game_config.starting_items.as_ref();
@@ -1909,9 +3133,23 @@ impl PatchConfigPrivate
             "scan" => Visor::Scan,
             "thermal" => Visor::Thermal,
             "xray" => Visor::XRay,
-            _ => panic!("Unknown starting visor {}", self.game_config.starting_visor.as_ref().unwrap()),
+            _ => Err(format!("Unknown starting visor {}", self.game_config.starting_visor.as_ref().unwrap()))?,
         };
 
+        // Spawning in a visor the player doesn't have leaves the HUD half switched. Without any
+        // visor the player spawns in the Scan Visor's HUD, that's handled when patching.
+        let has_starting_visor = match starting_visor {
+            Visor::Combat => starting_items.combat_visor,
+            Visor::Scan => starting_items.scan_visor,
+            Visor::Thermal => starting_items.thermal_visor,
+            Visor::XRay => starting_items.xray,
+        };
+        let no_visor = !starting_items.combat_visor && !starting_items.scan_visor &&
+            !starting_items.thermal_visor && !starting_items.xray;
+        if !has_starting_visor && !(no_visor && starting_visor == Visor::Scan) {
+            Err(format!("startingVisor is {:?}, but startingItems doesn't include that visor", starting_visor))?
+        }
+
         let default_starting_beam = if starting_items.power_beam {
             "power"
         } else if starting_items.plasma {
@@ -1929,8 +3167,87 @@ impl PatchConfigPrivate
             "ice" => Beam::Ice,
             "wave" => Beam::Wave,
             "plasma" => Beam::Plasma,
-            _ => panic!("Unknown starting beam {}", self.game_config.starting_beam.as_ref().unwrap()),
+            _ => Err(format!("Unknown starting beam {}", self.game_config.starting_beam.as_ref().unwrap()))?,
+        };
+
+        let has_starting_beam = match starting_beam {
+            Beam::Power => starting_items.power_beam,
+            Beam::Ice => starting_items.ice,
+            Beam::Wave => starting_items.wave,
+            Beam::Plasma => starting_items.plasma,
         };
+        let no_beam = !starting_items.power_beam && !starting_items.ice &&
+            !starting_items.wave && !starting_items.plasma;
+        if !has_starting_beam && !(no_beam && starting_beam == Beam::Power) {
+            Err(format!("startingBeam is {:?}, but startingItems doesn't include that beam", starting_beam))?
+        }
+
+        let suit_damage_reduction = self.game_config.suit_damage_reduction;
+        if let Some(reduction) = suit_damage_reduction {
+            if reduction.iter().any(|x| *x < 0.0 || *x > 100.0) {
+                Err(format!("suitDamageReduction values must be between 0 and 100, got {:?}", reduction))?
+            }
+        }
+
+        // 1: Pirate Data, 2: Chozo Lore, 3: Creatures, 4: Research, 5: Artifacts
+        let red_scans_logbook_category = self.game_config.red_scans_logbook_category;
+        if let Some(category) = red_scans_logbook_category {
+            if category < 1 || category > 5 {
+                Err(format!("redScansLogbookCategory must be between 1 and 5, got {}", category))?
+            }
+        }
+
+        let language = self.preferences.language;
+        if let Some(language) = language {
+            let supported = match language {
+                Language::English => true,
+                Language::Japanese => version == Version::NtscJ || version == Version::NtscJTrilogy,
+                _ => version == Version::Pal || version == Version::PalTrilogy,
+            };
+            if !supported {
+                Err(format!("The input ISO has no {:?} text", language))?
+            }
+        }
+
+        let color_grading = self.preferences.color_grading.clone().unwrap_or_default();
+        for world in color_grading.keys() {
+            if !World::iter().any(|w| w.to_json_key().eq_ignore_ascii_case(world.trim())) {
+                Err(format!("Unknown world '{}' in colorGrading", world))?
+            }
+        }
+
+        // Spawn positions move the destination room's spawn point, so two transports into the
+        // same room can't ask for different positions
+        let mut transport_spawn_positions: HashMap<u32, (&str, [f32;3])> = HashMap::new();
+        for level in self.level_data.values() {
+            for (elevator_name, position) in level.transport_spawn_positions.iter() {
+                let destination = level.transports.get(elevator_name)
+                    .and_then(|destination| SpawnRoomData::try_from_str(destination))
+                    .ok_or_else(|| format!(
+                        "transportSpawnPositions has '{}', which isn't a transport with a known destination",
+                        elevator_name,
+                    ))?;
+
+                match transport_spawn_positions.get(&destination.mrea) {
+                    Some((other_name, other_position)) if other_position != position => {
+                        Err(format!(
+                            "'{}' and '{}' lead to the same room but have different transportSpawnPositions",
+                            other_name, elevator_name,
+                        ))?
+                    },
+                    _ => {
+                        transport_spawn_positions.insert(destination.mrea, (elevator_name, *position));
+                    },
+                }
+            }
+        }
+
+        let objective_rooms = self.game_config.objective_rooms.clone().unwrap_or(Vec::new());
+        for room in objective_rooms.iter() {
+            if SpawnRoomData::try_from_str(room).is_none() {
+                Err(format!("Unknown room '{}' in objectiveRooms", room))?
+            }
+        }
 
         let spring_ball = self.game_config.spring_ball.unwrap_or(false);
         let warp_to_start = self.game_config.warp_to_start.unwrap_or(false);
@@ -2006,10 +3323,19 @@ impl PatchConfigPrivate
             run_mode,
             logbook_filename: self.logbook_filename.clone(),
             export_asset_dir: self.export_asset_dir.clone(),
+            room_map_filename: self.room_map_filename.clone(),
+            spoiler_filename: self.spoiler_filename.clone(),
+            patch_file: self.patch_file.clone(),
             version,
             input_iso,
             iso_format,
             output_iso,
+            output_iso_path: output_iso_path.to_string(),
+            atomic_output,
+            print_output_hash: self.print_output_hash.unwrap_or(false),
+            verify,
+            fill_junk: self.fill_junk.unwrap_or(false),
+            smoke_test: self.smoke_test.clone(),
             force_vanilla_layout,
 
             seed: self.seed.unwrap_or(123),
@@ -2017,11 +3343,20 @@ impl PatchConfigPrivate
             extern_assets_dir: self.extern_assets_dir.clone(),
 
             level_data: self.level_data.clone(),
+            pickup_pool,
+            fill_mode: self.fill_mode.unwrap_or(FillMode::Random),
+            door_rando: self.door_rando.clone(),
+            logic,
+            tricks,
+            require_beatable,
             strg: self.strg.clone(),
 
             qol_game_breaking,
             qol_cosmetic,
+            cosmetic_door_colors: self.preferences.cosmetic_door_colors.unwrap_or(false),
             qol_cutscenes,
+            cutscene_skip_prompt: self.preferences.cutscene_skip_prompt.unwrap_or(false),
+            room_name_popups: self.preferences.room_name_popups.unwrap_or(false),
             qol_pickup_scans,
             qol_general,
 
@@ -2032,6 +3367,8 @@ impl PatchConfigPrivate
             backwards_upper_mines: self.game_config.backwards_upper_mines.unwrap_or(true),
             backwards_lower_mines: self.game_config.backwards_lower_mines.unwrap_or(false),
             patch_power_conduits: self.game_config.patch_power_conduits.unwrap_or(false),
+            charge_beam_optional: self.game_config.charge_beam_optional.unwrap_or(false),
+            scan_visor_optional: self.game_config.scan_visor_optional.unwrap_or(false),
             remove_mine_security_station_locks: self.game_config.remove_mine_security_station_locks.unwrap_or(false),
             remove_hive_mecha: self.game_config.remove_hive_mecha.unwrap_or(false),
             power_bomb_arboretum_sandstone: self.game_config.power_bomb_arboretum_sandstone.unwrap_or(false),
@@ -2047,26 +3384,48 @@ impl PatchConfigPrivate
             visible_bounding_box: self.preferences.visible_bounding_box.unwrap_or(false),
             artifact_hint_behavior,
             flaahgra_music_files,
+            music_gain,
+            sfx_gain,
+            music_track_gain,
             suit_colors: self.preferences.suit_colors.clone(),
+            ball_colors: self.preferences.ball_colors.clone(),
+            beam_colors: self.preferences.beam_colors.clone(),
+            color_grading,
+            main_menu_music: self.preferences.main_menu_music.clone(),
+            language,
+            file_select_music: self.preferences.file_select_music.clone(),
+            suit_models: self.preferences.suit_models.clone(),
             force_fusion: self.preferences.force_fusion.clone().unwrap_or(false),
             cache_dir: self.preferences.cache_dir.clone().unwrap_or("cache".to_string()),
+            texture_overrides: self.preferences.texture_overrides.clone().unwrap_or_default(),
             skip_splash_screens: self.preferences.skip_splash_screens.unwrap_or(false),
             default_game_options: self.preferences.default_game_options.clone(),
             quiet: self.preferences.quiet.unwrap_or(false),
-            quickplay: self.preferences.quickplay.unwrap_or(false),
+            // The smoke test boots straight into the starting room
+            quickplay: self.preferences.quickplay.unwrap_or(false) || self.smoke_test.is_some(),
             quickpatch: self.preferences.quickpatch.unwrap_or(false),
 
             starting_room,
+            starting_spawn,
             starting_memo: self.game_config.starting_memo.clone(),
             spring_ball,
             warp_to_start,
             warp_to_start_delay_s: self.game_config.warp_to_start_delay_s.unwrap_or(0.0),
+            warp_to_start_fade: self.game_config.warp_to_start_fade.unwrap_or(false),
+            warp_to_start_sound: self.game_config.warp_to_start_sound.clone(),
+            warp_to_start_confirm: self.game_config.warp_to_start_confirm.unwrap_or(false),
 
             shuffle_pickup_position: self.game_config.shuffle_pickup_position.unwrap_or(false),
             shuffle_pickup_pos_all_rooms: self.game_config.shuffle_pickup_pos_all_rooms.unwrap_or(false),
+            shuffle_enemies: self.game_config.shuffle_enemies.unwrap_or(false),
+            shuffle_enemies_across_types: self.game_config.shuffle_enemies_across_types.unwrap_or(false),
+            shuffle_music: self.game_config.shuffle_music.unwrap_or(false),
+            shuffle_music_per_world: self.game_config.shuffle_music_per_world.unwrap_or(false),
+            shuffle_room_names: self.game_config.shuffle_room_names.unwrap_or(false),
             remove_vanilla_blast_shields: self.game_config.remove_vanilla_blast_shields.unwrap_or(false),
             nonvaria_heat_damage: self.game_config.nonvaria_heat_damage.unwrap_or(false),
             staggered_suit_damage: self.game_config.staggered_suit_damage.unwrap_or(false),
+            suit_damage_reduction,
             heat_damage_per_sec: self.game_config.heat_damage_per_sec.unwrap_or(10.0),
             poison_damage_per_sec: self.game_config.poison_damage_per_sec.unwrap_or(0.11),
             phazon_damage_per_sec: self.game_config.phazon_damage_per_sec.unwrap_or(0.964),
@@ -2077,17 +3436,27 @@ impl PatchConfigPrivate
             update_hint_state_replacement: self.game_config.update_hint_state_replacement.clone(),
             artifact_temple_layer_overrides: self.game_config.artifact_temple_layer_overrides.clone(),
             no_doors: self.game_config.no_doors.unwrap_or(false),
-            boss_sizes: self.game_config.boss_sizes.clone().unwrap_or(HashMap::new()),
+            boss_sizes: self.game_config.boss_sizes.clone().unwrap_or(BTreeMap::new()),
             shoot_in_grapple: self.game_config.shoot_in_grapple.unwrap_or(false),
             difficulty_behavior: self.game_config.difficulty_behavior.unwrap_or(DifficultyBehavior::Either),
             legacy_block_size: self.game_config.legacy_block_size.unwrap_or(false),
+            crate_loot: self.game_config.crate_loot.clone(),
+            pickup_aura: self.game_config.pickup_aura.clone(),
+            pickup_sounds: self.game_config.pickup_sounds.iter()
+                .flatten()
+                .map(|(name, sounds)| (PickupType::from_str(name), sounds.clone()))
+                .collect(),
+            enemy_config: self.game_config.enemy_config.clone(),
             map_default_state,
 
             starting_items,
             item_loss_items: self.game_config.item_loss_items.clone()
             .unwrap_or_else(|| StartingItems::from_u64(1)),
             disable_item_loss: self.game_config.disable_item_loss.unwrap_or(true),
-            escape_sequence_counts_up: self.game_config.escape_sequence_counts_up.unwrap_or(false),
+            escape_sequence_counts_up: self.game_config.escape_sequence_counts_up.unwrap_or(false) ||
+                self.game_config.frigate_escape_failure == Some(FrigateEscapeFailure::Continue),
+            frigate_escape_time: self.game_config.frigate_escape_time,
+            run_time_limit: self.game_config.run_time_limit,
             enable_ice_traps: self.game_config.enable_ice_traps.unwrap_or(false),
             missile_station_pb_refill: self.game_config.missile_station_pb_refill.unwrap_or(false),
             door_open_mode: self.game_config.door_open_mode.unwrap_or(DoorOpenMode::Original),
@@ -2095,18 +3464,30 @@ impl PatchConfigPrivate
             starting_beam,
 
             etank_capacity: self.game_config.etank_capacity.unwrap_or(100),
+            red_scans_logbook_category,
+            logbook_excluded_scans: self.game_config.logbook_excluded_scans.clone().unwrap_or_default(),
             item_max_capacity: item_max_capacity,
+            combo_ammo_costs,
+            combo_ammo_drain_periods,
 
             game_banner: self.game_config.game_banner.clone().unwrap_or_default(),
             comment: self.game_config.comment.clone().unwrap_or(String::new()),
             main_menu_message,
+            main_menu_seed_hash: self.game_config.main_menu_seed_hash.unwrap_or(false),
+            seed_info_scan: self.game_config.seed_info_scan.clone(),
 
             credits_string,
             results_string,
+            inventory_text: self.game_config.inventory_text.clone().unwrap_or_default(),
             artifact_hints: self.game_config.artifact_hints.clone(),
+            objective_rooms,
+            artifact_objectives: self.game_config.artifact_objectives.unwrap_or(false),
+            pause_screen_objectives: self.game_config.pause_screen_objectives.unwrap_or(false),
+            door_legend: self.game_config.door_legend.unwrap_or(false),
             required_artifact_count: self.game_config.required_artifact_count.clone(),
 
             ctwk_config: self.tweaks.clone(),
+            plugins: PatchPlugins::default(),
         };
 
 
@@ -2117,13 +3498,112 @@ impl PatchConfigPrivate
 
 /*** Helper Methods ***/
 
+fn temp_output_path(output_iso_path: &str, atomic_output: bool) -> String
+{
+    if atomic_output {
+        format!("{}.part", output_iso_path)
+    } else {
+        output_iso_path.to_string()
+    }
+}
+
+/// Maps the input disc image into memory, transparently decompressing GCZ, CISO and RVZ images
+fn open_input_iso(input_iso_path: &str) -> Result<memmap::Mmap, String>
+{
+    let input_iso_file = File::open(input_iso_path)
+        .map_err(|e| format!("Failed to open {}: {}", input_iso_path, e))?;
+    let input_iso = unsafe { memmap::Mmap::map(&input_iso_file) }
+        .map_err(|e| format!("Failed to open {}: {}", input_iso_path,  e))?;
+
+    let result = decompress_input_iso(&input_iso)
+        .map_err(|e| format!("Failed to read {}: {}", input_iso_path, e))?;
+    let input_iso = result.unwrap_or(input_iso);
+
+    // Wii discs keep their files in encrypted partitions, which the disc reader doesn't handle.
+    // Trilogy is patched from an image of its data partition instead.
+    if input_iso.get(0x18..0x1C) == Some(&WII_DISC_MAGIC[..]) {
+        Err(format!(
+            "{} is a Wii disc image, please extract its data partition into a plain ISO first (see the README)",
+            input_iso_path
+        ))?
+    }
+    Ok(input_iso)
+}
+
+const WII_DISC_MAGIC: [u8; 4] = [0x5D, 0x1C, 0x9E, 0xA3];
+
+/// Returns the decompressed image if `input_iso` uses one of the supported compressed formats
+fn decompress_input_iso(input_iso: &memmap::Mmap) -> Result<Option<memmap::Mmap>, String>
+{
+    #[cfg(feature = "gcz")]
+    if gcz_reader::is_gcz(input_iso) {
+        return gcz_reader::decompress_gcz(input_iso).map(Some);
+    }
+    #[cfg(feature = "ciso")]
+    if ciso_reader::is_ciso(input_iso) {
+        return ciso_reader::decompress_ciso(input_iso).map(Some);
+    }
+    #[cfg(feature = "rvz")]
+    if rvz_reader::is_rvz(input_iso) {
+        return rvz_reader::decompress_rvz(input_iso).map(Some);
+    }
+
+    const COMPRESSED_FORMATS: &[(&str, &[u8])] = &[
+        ("GCZ", &[0x01, 0xC0, 0x0B, 0xB1]),
+        ("CISO", b"CISO"),
+        ("RVZ", b"RVZ\x01"),
+        ("WIA", b"WIA\x01"),
+    ];
+    let unsupported_format = COMPRESSED_FORMATS.iter()
+        .find(|(_, magic)| input_iso.starts_with(magic));
+    match unsupported_format {
+        Some((name, _)) => Err(format!("{} images are not supported by this build, please convert to ISO", name)),
+        None => Ok(None),
+    }
+}
+
+/// What has to agree between the two channels of a stereo DSP file, and with the file it replaces
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DspHeader
+{
+    pub sample_count: u32,
+    pub sample_rate: u32,
+}
+
+/// Reads the header of a mono Nintendo ADPCM DSP file, checking the file is as long as it says
+pub fn read_dsp_header(file: &dyn WithRead) -> Result<DspHeader, String>
+{
+    let mut header = [0u8; 0x60];
+    file.with_read(&mut |reader| reader.read_exact(&mut header).map(|()| 0))
+        .map_err(|_| "Not a DSP file, it's too short".to_string())?;
+    let u32_at = |offset: usize| u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+    let u16_at = |offset: usize| u16::from_be_bytes(header[offset..offset + 2].try_into().unwrap());
+
+    let sample_count = u32_at(0x00);
+    let nibble_count = u32_at(0x04);
+    let sample_rate = u32_at(0x08);
+    if u16_at(0x0E) != 0 {
+        Err("Not a DSP file, only ADPCM is supported".to_string())?
+    }
+    if sample_rate == 0 || sample_count == 0 {
+        Err("Not a DSP file, it has no samples".to_string())?
+    }
+    let data_len = (nibble_count as usize + 1) / 2;
+    if file.len() < header.len() + data_len {
+        Err(format!("The file is cut short, it should be {} bytes long", header.len() + data_len))?
+    }
+
+    Ok(DspHeader { sample_count, sample_rate })
+}
+
+#[cfg(feature = "trilogy")]
 pub fn extract_flaahgra_music_files(iso_path: &str) -> Result<[nod_wrapper::FileWrapper; 2], String>
 {
     let res = (|| {
         let dw = nod_wrapper::DiscWrapper::new(iso_path)?;
         Ok([
-            dw.open_file(CStr::from_bytes_with_nul(b"rui_flaaghraR.dsp\0").unwrap())?,
-            dw.open_file(CStr::from_bytes_with_nul(b"rui_flaaghraL.dsp\0").unwrap())?,
+            dw.open_file(std::ffi::CStr::from_bytes_with_nul(b"rui_flaaghraR.dsp\0").unwrap())?,
+            dw.open_file(std::ffi::CStr::from_bytes_with_nul(b"rui_flaaghraL.dsp\0").unwrap())?,
         ])
     })();
     res.map_err(|s: String| format!("Failed to extract Flaahgra music files: {}", s))
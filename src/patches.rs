@@ -20,12 +20,19 @@ use crate::patch_config::{
     Visor,
     IsoFormat,
     PickupConfig,
+    PickupAuraConfig,
+    PickupSoundsConfig,
+    PickupSoundEffectConfig,
+    PickupShuffleZonesConfig,
     PatchConfig,
+    Language,
     GameBanner,
     LevelConfig,
     RoomConfig,
     CtwkConfig,
     CutsceneMode,
+    ColorAdjustment,
+    SuitColors,
     DoorConfig,
     HallOfTheEldersBombSlotCoversConfig,
     BombSlotCover,
@@ -40,38 +47,67 @@ use crate::patch_config::{
     ConnectionState,
     ConnectionMsg,
     DifficultyBehavior,
+    CrateLootConfig,
+    LocalizedText,
+    ColorGradingPreset,
+    read_dsp_header,
+    ScanConfig,
+    SeedInfoScanConfig,
+    FillMode,
+    RefillStationKind,
 };
 
 use std::{fs::{self, File}, io::Read, path::Path};
-use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, hash_map::DefaultHasher};
 use std::hash::{Hash, Hasher};
 
+#[cfg(feature = "bps")]
+use crate::bps_writer::BpsWriter;
+#[cfg(feature = "ciso")]
+use crate::ciso_writer::CisoWriter;
+#[cfg(feature = "gcz")]
+use crate::gcz_writer::GczWriter;
+
 use crate::{
     add_modify_obj_patches::*,
-    generic_edit::patch_edit_objects,
+    audio_conversions,
+    generic_edit::{patch_edit_objects, patch_edit_properties, patch_scale_enemies},
     custom_assets::{custom_asset_ids, PickupHashKey, collect_game_resources, custom_asset_filename},
     dol_patcher::DolPatcher,
-    ciso_writer::CisoWriter,
     elevators::{Elevator, SpawnRoom, SpawnRoomData, World, is_elevator, is_teleporter},
-    gcz_writer::GczWriter,
+    enemy_shuffle::{plan_enemy_shuffle, patch_shuffle_enemies},
+    music_shuffle::{plan_music_shuffle, patch_shuffle_music},
+    pickup_pool::place_pickup_pool,
+    door_rando::randomize_doors,
+    plugin::register_plugins,
+    spoiler::write_spoiler,
+    starting_room::{check_starting_room, patch_starting_room_spawn, starting_spawn_point},
+    memory_budget,
+    save_budget,
     mlvl_wrapper,
     pickup_meta::{self, PickupType, PickupModel, DoorLocation, ObjectsToRemove, ScriptObjectLocation, pickup_model_for_pickup, pickup_type_for_pickup},
     door_meta::{DoorType, BlastShieldType},
     patcher::{PatcherState, PrimePatcher},
     starting_items::StartingItems,
+    text_layout::{FontMetrics, TextBox, HUDMEMO_BOX, SCAN_BOX},
     txtr_conversions::{
         cmpr_compress,
         cmpr_decompress,
+        color_adjust_matrix,
         huerotate_color,
-        huerotate_matrix,
         huerotate_in_place,
+        multiply_color_matrices,
+        rgba8_to_txtr_with_format,
         POWER_SUIT_TEXTURES,
         VARIA_SUIT_TEXTURES,
         GRAVITY_SUIT_TEXTURES,
         PHAZON_SUIT_TEXTURES,
+        VISOR_TEXTURES,
+        SPIDER_BALL_TEXTURES,
+        ARM_CANNON_MODELS,
     },
     GcDiscLookupExtensions,
-    extern_assets::ExternPickupModel,
+    extern_assets::{ExternAsset, ExternPickupModel, read_standalone_asset},
 };
 
 use dol_symbol_table::mp1_symbol;
@@ -91,7 +127,8 @@ use structs::{MapState, res_id, ResId, scly_structs::DamageInfo, scly_structs::T
 
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
     convert::TryInto,
     ffi::CString,
     io::Write,
@@ -100,6 +137,8 @@ use std::{
     time::Instant,
 };
 
+use serde::Serialize;
+
 #[derive(Clone, Debug)]
 struct ModifiableDoorLocation {
     pub door_location: Option<ScriptObjectLocation>,
@@ -167,11 +206,40 @@ fn post_pickup_relay_template<'r>(instance_id: u32, connections: &'static [struc
     }
 }
 
+/// Names of the rooms which aren't shown under their own name, keyed by world key and room name
+fn displayed_room_names(
+    level_data: &BTreeMap<String, LevelConfig>,
+    shuffled_room_names: &HashMap<u32, ResId<res_id::STRG>>,
+)
+    -> HashMap<(String, String), String>
+{
+    let mut names = HashMap::new();
+    for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+        let world = World::from_pak(pak_name).unwrap();
+        for room_info in rooms.iter() {
+            let room_name = room_info.name().trim();
+            let map_name = level_data.get(world.to_json_key())
+                .and_then(|level| level.rooms.get(room_name))
+                .and_then(|room| room.map_name.clone());
+            let shuffled_name = shuffled_room_names.get(&room_info.room_id.to_u32())
+                .and_then(|name_id| rooms.iter().find(|other| other.name_id == *name_id))
+                .map(|other| other.name().trim().to_string());
+            if let Some(name) = map_name.or(shuffled_name) {
+                names.insert((world.to_json_key().to_string(), room_name.to_string()), name);
+            }
+        }
+    }
+    names
+}
+
+/// Without `artifact_hints`, hints are made up from where the artifacts are in `level_data`.
+/// They're jokes, unless `plain` asks for hints which just say where the artifact is.
 fn build_artifact_temple_totem_scan_strings<R>(
-    level_data: &HashMap<String, LevelConfig>,
+    level_data: &BTreeMap<String, LevelConfig>,
     rng: &mut R,
-    artifact_hints: Option<HashMap<String,String>>,
-
+    artifact_hints: Option<BTreeMap<String,String>>,
+    plain: bool,
+    room_names: &HashMap<(String, String), String>,
 )
     -> [String; 12]
     where R: Rng
@@ -192,12 +260,15 @@ fn build_artifact_temple_totem_scan_strings<R>(
     ];
     generic_text_templates.shuffle(rng);
     let mut generic_templates_iter = generic_text_templates.iter();
+    let plain_template = "The {pickup} can be found in &push;&main-color=#43CD80;{room}&pop;.\0";
 
-    // Where are the artifacts?
+    // Where are the artifacts? Rooms go by the name the player sees in game
     let mut artifact_locations = Vec::<(&str, PickupType)>::new();
-    for (_, level) in level_data.iter() {
+    for (world_key, level) in level_data.iter() {
         for (room_name, room) in level.rooms.iter() {
             if room.pickups.is_none() { continue };
+            let room_name = room_names.get(&(world_key.clone(), room_name.clone()))
+                .unwrap_or(room_name);
             for pickup in room.pickups.as_ref().unwrap().iter() {
                 let pickup_type = PickupType::from_str(&pickup.pickup_type);
                 if pickup_type.kind() >= PickupType::ArtifactOfTruth.kind() && pickup_type.kind() <= PickupType::ArtifactOfNewborn.kind() {
@@ -253,10 +324,14 @@ fn build_artifact_temple_totem_scan_strings<R>(
 
         // If there are specific messages for this room, choose one, otherwise choose a generic
         // message.
-        let template = specific_room_templates.iter_mut()
-            .find(|row| &row.0 == room_name)
-            .and_then(|row| row.1.pop())
-            .unwrap_or_else(|| generic_templates_iter.next().unwrap());
+        let template = if plain {
+            plain_template
+        } else {
+            specific_room_templates.iter_mut()
+                .find(|row| &row.0 == room_name)
+                .and_then(|row| row.1.pop())
+                .unwrap_or_else(|| generic_templates_iter.next().unwrap())
+        };
         let pickup_name = pt.name();
         scan_text[artifact_id] = template.replace("{room}", room_name).replace("{pickup}", pickup_name);
     }
@@ -294,11 +369,42 @@ fn build_artifact_temple_totem_scan_strings<R>(
     scan_text
 }
 
-fn patch_artifact_totem_scan_strg(res: &mut structs::Resource, text: &str)
+/// Whether generated text replaces the strings of a STRG string table. Unless a language is set,
+/// it replaces them in every language.
+fn replaces_strg_lang(language: Option<Language>, lang: FourCC) -> bool
+{
+    language.map(|language| language.strg_lang() == lang).unwrap_or(true)
+}
+
+/// Replaces the strings of a STRG. Tables of other languages than `language` keep theirs, but
+/// are cut or padded to the same number of strings.
+fn patch_strg_strings(res: &mut structs::Resource, strings: Vec<String>, language: Option<Language>)
+    -> Result<(), String>
+{
+    let strg = res.kind.as_strg_mut().unwrap();
+    for st in strg.string_tables.as_mut_vec().iter_mut() {
+        let replace = replaces_strg_lang(language, st.lang);
+        let table = st.strings.as_mut_vec();
+        for (i, string) in strings.iter().enumerate() {
+            if i >= table.len() {
+                table.push(string.clone().into());
+            } else if replace {
+                table[i] = string.clone().into();
+            }
+        }
+        table.truncate(strings.len());
+    }
+    Ok(())
+}
+
+fn patch_artifact_totem_scan_strg(res: &mut structs::Resource, text: &str, language: Option<Language>)
     -> Result<(), String>
 {
     let strg = res.kind.as_strg_mut().unwrap();
     for st in strg.string_tables.as_mut_vec().iter_mut() {
+        if !replaces_strg_lang(language, st.lang) {
+            continue;
+        }
         let strings = st.strings.as_mut_vec();
         *strings.last_mut().unwrap() = text.to_owned().into();
     }
@@ -384,6 +490,139 @@ fn patch_thermal_conduits_damage_vulnerabilities(_ps: &mut PatcherState, area: &
     Ok(())
 }
 
+/// Lets uncharged shots hit damageable triggers (switches, conduits, breakable walls, ...) which
+/// only respond to charged shots of a beam
+fn patch_chargeless_damageable_triggers(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+    -> Result<(), String>
+{
+    let blocks = |vulnerability: u32| {
+        vulnerability == TypeVulnerability::Reflect as u32 || vulnerability == TypeVulnerability::Immune as u32
+    };
+
+    let scly = area.mrea().scly_section_mut();
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            let dt = match obj.property_data.as_damageable_trigger_mut() {
+                Some(dt) => dt,
+                None => continue,
+            };
+
+            let vulnerability = &mut dt.damage_vulnerability;
+            let charged = vulnerability.charged_beams.clone();
+            let beams = [
+                (&mut vulnerability.power, charged.power),
+                (&mut vulnerability.ice, charged.ice),
+                (&mut vulnerability.wave, charged.wave),
+                (&mut vulnerability.plasma, charged.plasma),
+            ];
+            for (normal, charged) in beams {
+                if blocks(*normal) && !blocks(charged) {
+                    *normal = charged;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the need for the Scan Visor: hints no longer switch to it, and scan points which
+/// unlock something when scanned do so when the player walks up to them as well
+fn patch_scan_visor_optional(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+    -> Result<(), String>
+{
+    // Scanning these only shows text or plays a sound
+    let mut cosmetic_ids = HashSet::new();
+    let mut scan_points = vec![];
+    let scly = area.mrea().scly_section_mut();
+    for (layer_idx, layer) in scly.layers.as_mut_vec().iter_mut().enumerate() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            let property_data = &obj.property_data;
+            if property_data.is_hud_memo() || property_data.is_streamed_audio() ||
+                property_data.is_sound() || property_data.is_player_hint()
+            {
+                cosmetic_ids.insert(obj.instance_id & 0x00FFFFFF);
+            }
+
+            if let Some(player_hint) = obj.property_data.as_player_hint_mut() {
+                player_hint.inner_struct.unknowns[10] = 0; // switch to Scan Visor
+            }
+
+            if let Some(poi) = obj.property_data.as_point_of_interest_mut() {
+                let position: [f32; 3] = poi.position.into();
+                scan_points.push((layer_idx, obj.instance_id, position, poi.active));
+            }
+        }
+    }
+
+    for (layer_idx, poi_id, position, active) in scan_points {
+        let scly = area.mrea().scly_section_mut();
+        let objects = scly.layers.as_mut_vec().iter().flat_map(|layer| layer.objects.iter());
+
+        let mut connections = vec![];
+        let mut toggles = vec![];
+        for obj in objects {
+            for conn in obj.connections.iter() {
+                if obj.instance_id == poi_id && conn.state == structs::ConnectionState::SCAN_DONE &&
+                    !cosmetic_ids.contains(&(conn.target_object_id & 0x00FFFFFF))
+                {
+                    connections.push(structs::Connection {
+                        state: structs::ConnectionState::ENTERED,
+                        message: conn.message,
+                        target_object_id: conn.target_object_id,
+                    });
+                }
+                // The trigger has to be enabled and disabled along with the scan point
+                if conn.target_object_id & 0x00FFFFFF == poi_id & 0x00FFFFFF &&
+                    (conn.message == structs::ConnectionMsg::ACTIVATE ||
+                     conn.message == structs::ConnectionMsg::DEACTIVATE)
+                {
+                    toggles.push((obj.instance_id, conn.state, conn.message));
+                }
+            }
+        }
+        if connections.is_empty() {
+            continue;
+        }
+
+        let trigger_id = area.new_object_id_from_layer_id(layer_idx);
+        let scly = area.mrea().scly_section_mut();
+        for (obj_id, state, message) in toggles {
+            let obj = scly.layers.as_mut_vec().iter_mut()
+                .flat_map(|layer| layer.objects.as_mut_vec().iter_mut())
+                .find(|obj| obj.instance_id == obj_id)
+                .unwrap();
+            obj.connections.as_mut_vec().push(structs::Connection {
+                state,
+                message,
+                target_object_id: trigger_id,
+            });
+        }
+        scly.layers.as_mut_vec()[layer_idx].objects.as_mut_vec().push(structs::SclyObject {
+            instance_id: trigger_id,
+            property_data: structs::Trigger {
+                name: b"Trigger - Scan point without Scan Visor\0".as_cstr(),
+                position: position.into(),
+                scale: [8.0, 8.0, 8.0].into(),
+                damage_info: structs::scly_structs::DamageInfo {
+                    weapon_type: 0,
+                    damage: 0.0,
+                    radius: 0.0,
+                    knockback_power: 0.0
+                },
+                force: [0.0, 0.0, 0.0].into(),
+                flags: 1,
+                active,
+                deactivate_on_enter: 1,
+                deactivate_on_exit: 0
+            }.into(),
+            connections: connections.into(),
+        });
+    }
+
+    Ok(())
+}
+
 fn is_door_lock<'r>(obj: &structs::SclyObject<'r>) -> bool {
     let actor = obj.property_data.as_actor();
 
@@ -576,12 +815,92 @@ fn this_near_that(this: [f32;3], that: [f32;3]) -> bool
     f32::abs(this[2] - that[2]) < 3.0
 }
 
+// Gives every vanilla beam door in the room a random color without touching its vulnerability.
+// Doors which are recolored by door rando are patched afterwards, so they keep their real color.
+fn patch_cosmetic_door_colors<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    seed: u64,
+) -> Result<(), String>
+{
+    const COLORS: &[DoorType] = &[DoorType::Blue, DoorType::Purple, DoorType::White, DoorType::Red];
+
+    let mrea_id = area.mlvl_area.mrea.to_u32();
+    let mut rng = StdRng::seed_from_u64(seed ^ mrea_id as u64);
+
+    // (position, vanilla type, cosmetic type) of each door shield
+    let mut recolors: Vec<([f32; 3], DoorType, DoorType)> = Vec::new();
+    let scly = area.mrea().scly_section_mut();
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            let actor = match obj.property_data.as_actor_mut() {
+                Some(actor) => actor,
+                None => continue,
+            };
+            let vanilla_type = match DoorType::maybe_from_cmdl(&actor.cmdl.to_u32()) {
+                Some(door_type) => door_type,
+                None => continue,
+            };
+            if !COLORS.contains(&vanilla_type.to_horizontal()) {
+                continue;
+            }
+
+            // There is no vanilla vertical red door, and other patches derive the door type from
+            // the shield model
+            let door_type = if vanilla_type.is_vertical() {
+                COLORS[..3].choose(&mut rng).unwrap().to_vertical()
+            } else {
+                *COLORS.choose(&mut rng).unwrap()
+            };
+
+            actor.cmdl = door_type.shield_cmdl();
+            recolors.push((actor.position.into(), vanilla_type, door_type));
+        }
+    }
+
+    // The force field in front of the shield has to match, but keeps its vulnerability
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            let door_force = match obj.property_data.as_damageable_trigger_mut() {
+                Some(door_force) => door_force,
+                None => continue,
+            };
+            let recolor = recolors.iter().find(|(position, vanilla_type, _)|
+                door_force.color_txtr == vanilla_type.color_txtr() &&
+                this_near_that(door_force.position.into(), *position)
+            );
+            if let Some((_, _, door_type)) = recolor {
+                door_force.pattern_txtr0 = door_type.pattern0_txtr();
+                door_force.pattern_txtr1 = door_type.pattern1_txtr();
+                door_force.color_txtr = door_type.color_txtr();
+            }
+        }
+    }
+
+    let mut deps: Vec<(u32, FourCC)> = Vec::new();
+    for (_, _, door_type) in recolors.iter() {
+        deps.extend_from_slice(&door_type.dependencies());
+    }
+    let deps_iter = deps.iter()
+        .map(|&(file_id, fourcc)| structs::Dependency {
+                asset_id: file_id,
+                asset_type: fourcc,
+        }
+    );
+    area.add_dependencies(game_resources, 0, deps_iter);
+
+    Ok(())
+}
+
 fn patch_door<'r>(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
     door_loc: ModifiableDoorLocation,
     door_type: Option<DoorType>,
     blast_shield_type: Option<BlastShieldType>,
+    blast_shield_model: Option<ExternPickupModel>,
+    blast_shield_scan: Option<(ResId<res_id::SCAN>, ResId<res_id::STRG>)>,
     door_resources:&HashMap<(u32, FourCC), structs::Resource<'r>>,
     door_open_mode: DoorOpenMode,
 ) -> Result<(), String> {
@@ -590,6 +909,26 @@ fn patch_door<'r>(
     let mrea_id = area.mlvl_area.mrea.to_u32();
     let area_internal_id = area.mlvl_area.internal_id;
 
+    // Not every vertical dock is known ahead of time, so check the door's own model. Using the
+    // horizontal shield on a ceiling/floor door would leave it sideways.
+    let door_type = match (door_type, door_loc.door_location) {
+        (Some(door_type), Some(door_location)) if !door_type.is_vertical() => {
+            let scly = area.mrea().scly_section();
+            let layer = scly.layers.iter().nth(door_location.layer as usize).unwrap();
+            let is_vertical_door = layer.objects.iter()
+                .find(|obj| obj.instance_id == door_location.instance_id)
+                .and_then(|obj| obj.property_data.as_door().map(|door| door.ancs.file_id == 0xf57dd484))
+                .unwrap_or(false);
+
+            if is_vertical_door {
+                Some(door_type.to_vertical())
+            } else {
+                Some(door_type)
+            }
+        },
+        (door_type, _) => door_type,
+    };
+
     // Update dependencies based on the upcoming patch(es)
     let mut deps: Vec<(u32, FourCC)> = Vec::new();
 
@@ -600,6 +939,15 @@ fn patch_door<'r>(
     if blast_shield_type.is_some() {
         // Add dependencies
         deps.extend_from_slice(&blast_shield_type.as_ref().unwrap().dependencies(DO_GIBBS));
+
+        if let Some(model) = blast_shield_model.as_ref() {
+            deps.extend_from_slice(&model.dependencies);
+        }
+
+        if let Some((scan_id, strg_id)) = blast_shield_scan {
+            deps.push((scan_id.to_u32(), FourCC::from_bytes(b"SCAN")));
+            deps.push((strg_id.to_u32(), FourCC::from_bytes(b"STRG")));
+        }
     }
 
     let blast_shield_can_change_door = door_type.is_some() && blast_shield_type.is_some();
@@ -767,15 +1115,16 @@ fn patch_door<'r>(
         scan_offset = [0.0, 0.0, 0.0].into();
         hitbox = [0.0, 0.0, 0.0].into();
 
-        let door_rotation = door_loc.door_rotation.unwrap();
+        // Not every door got its rotation dumped, but the shield is always aligned with the door.
+        // Yaw is wrapped because the placement below only considers [-225, 225).
+        let mut door_rotation = door_loc.door_rotation.unwrap_or_else(||
+            [door_shield.rotation[0], door_shield.rotation[1], door_shield.rotation[2]]
+        );
+        door_rotation[2] = (door_rotation[2] + 180.0).rem_euclid(360.0) - 180.0;
         let mut is_ceiling = false;
         let mut is_floor = false;
 
         if is_vertical {
-            if door_loc.door_rotation.is_none() {
-                panic!("Vertical door in room {:X} didn't get position data dumped", mrea_id);
-            }
-
             if mrea_id == 0xFB54A0CB { // hall of the elders
                 scale = [1.6, 1.6, 1.6].into();
     
@@ -825,6 +1174,11 @@ fn patch_door<'r>(
             }
         }
 
+        let scale: GenericArray<f32, U3> = match blast_shield_model.as_ref() {
+            Some(model) => [scale[0]*model.scale, scale[1]*model.scale, scale[2]*model.scale].into(),
+            None => scale,
+        };
+
         // Create new blast shield actor //
         let blast_shield = structs::SclyObject {
             instance_id: blast_shield_instance_id,
@@ -850,7 +1204,9 @@ fn patch_door<'r>(
                         knockback_resistance: 1.0,
                     },
                     damage_vulnerability: blast_shield_type.vulnerability(),
-                    cmdl: blast_shield_type.cmdl(),
+                    cmdl: blast_shield_model.as_ref()
+                        .map(|model| ResId::new(model.cmdl))
+                        .unwrap_or(blast_shield_type.cmdl()),
                     ancs: structs::scly_structs::AncsProp {
                         file_id: ResId::invalid(),
                         node_index: 0,
@@ -1094,17 +1450,21 @@ fn patch_door<'r>(
                 }.into(),
             };
 
+        // Keep the scan point on the side of the shield the player is looking from
+        let poi_offset_z = if is_ceiling { -0.5 } else { 0.5 };
         let poi =  structs::SclyObject {
                 instance_id: poi_id,
                 connections: vec![].into(),
                 property_data: structs::SclyProperty::PointOfInterest(
                     structs::PointOfInterest {
                         name: b"mypoi\0".as_cstr(),
-                        position: [dt_pos[0], dt_pos[1], dt_pos[2] + 0.5].into(),
+                        position: [dt_pos[0], dt_pos[1], dt_pos[2] + poi_offset_z].into(),
                         rotation: [0.0, 0.0, 0.0].into(),
                         active: 0,
                         scan_param: structs::scly_structs::ScannableParameters {
-                            scan: blast_shield_type.scan(),
+                            scan: blast_shield_scan
+                                .map(|(scan_id, _)| scan_id)
+                                .unwrap_or(blast_shield_type.scan()),
                         },
                         point_size: 0.0,
                     }.into()
@@ -2075,6 +2435,78 @@ fn patch_door<'r>(
     Ok(())
 }
 
+/// Works out which model a pickup is displayed with. Models which are known not to work with
+/// the pickup's type, or whose animations, particles or textures are neither in the target area
+/// nor available to add to it, fall back to the type's own model instead of crashing in game.
+fn resolve_pickup_model<'a, F>(
+    pickup_config: &PickupConfig,
+    pickup_type: PickupType,
+    extern_models: &'a HashMap<String, ExternPickupModel>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource>,
+    area_has_dependency: F,
+) -> (Option<PickupModel>, Option<&'a ExternPickupModel>)
+    where F: Fn(&structs::Dependency) -> bool
+{
+    let missing_dependency = |deps: &[(u32, FourCC)]| deps.iter()
+        .find(|&&(asset_id, asset_type)| {
+            !game_resources.contains_key(&(asset_id, asset_type))
+                && !area_has_dependency(&structs::Dependency { asset_id, asset_type })
+        })
+        .cloned();
+
+    let model_name = match pickup_config.model.as_ref() {
+        Some(model_name) => model_name,
+        None => return (Some(PickupModel::from_type(pickup_type)), None), // No model specified, use pickup type as inspiration
+    };
+
+    let extern_model = extern_models.get(model_name);
+    let pickup_model = PickupModel::from_str(model_name);
+    if pickup_model.is_none() && extern_model.is_none() {
+        panic!("Unknown Model Type {}", model_name);
+    }
+
+    let fallback = PickupModel::from_type(pickup_type);
+
+    if let Some(pickup_model) = pickup_model {
+        if !pickup_model.is_compatible_with(pickup_type) {
+            println!(
+                "Warning, model '{}' is known to crash when used for '{}', using '{}' instead",
+                model_name, pickup_type.name(), fallback.name(),
+            );
+            return (Some(fallback), None);
+        }
+    }
+
+    if let Some(extern_model) = extern_model {
+        // Models without an ANCS don't have characters
+        if extern_model.ancs != u32::MAX && extern_model.character >= extern_model.character_count {
+            println!(
+                "Warning, model '{}' uses character {} but its ANCS only has {}, using '{}' instead",
+                model_name, extern_model.character, extern_model.character_count, fallback.name(),
+            );
+            return (Some(fallback), None);
+        }
+    }
+
+    let dependencies = match extern_model {
+        Some(extern_model) => &extern_model.dependencies[..],
+        None => pickup_model.unwrap().dependencies(),
+    };
+    if let Some((file_id, fourcc)) = missing_dependency(dependencies) {
+        println!(
+            "Warning, model '{}' needs 0x{:X}.{} which isn't available, using '{}' instead",
+            model_name, file_id, fourcc, fallback.name(),
+        );
+        return (Some(fallback), None);
+    }
+
+    (
+        pickup_model, // Some - Native Prime Model
+                      // None - External Model (e.g. Screw Attack)
+        extern_model,
+    )
+}
+
 // TODO: factor out shared code with modify_pickups_in_mrea
 fn patch_add_item<'r>(
     _ps: &mut PatcherState,
@@ -2088,9 +2520,11 @@ fn patch_add_item<'r>(
     skip_hudmemos: bool,
     extern_models: &HashMap<String, ExternPickupModel>,
     shuffle_position: bool,
+    shuffle_zones: Option<&PickupShuffleZonesConfig>,
     seed: u64,
     _no_starting_visor: bool,
     version: Version,
+    shuffled_positions: &RefCell<HashMap<PickupHashKey, [f32; 3]>>,
 ) -> Result<(), String>
 {
     let mut rng = StdRng::seed_from_u64(seed);
@@ -2110,27 +2544,14 @@ fn patch_add_item<'r>(
         area.add_dependencies(game_resources, 0, deps_iter);
     }
 
-    let extern_model = if pickup_config.model.is_some() {
-        extern_models.get(pickup_config.model.as_ref().unwrap())
-    } else {
-        None
-    };
-
     // Pickup to use for visuals/hitbox //
-    let pickup_model_type: Option<PickupModel> = {
-        if pickup_config.model.is_some() {
-            let model_name = pickup_config.model.as_ref().unwrap();
-            let pmt = PickupModel::from_str(&model_name);
-            if pmt.is_none() && !extern_model.is_some() {
-                panic!("Unknown Model Type {}", model_name);
-            }
-
-            pmt // Some - Native Prime Model
-                // None - External Model (e.g. Screw Attack)
-        } else {
-            Some(PickupModel::from_type(pickup_type)) // No model specified, use pickup type as inspiration
-        }
-    };
+    let (pickup_model_type, extern_model) = resolve_pickup_model(
+        pickup_config,
+        pickup_type,
+        extern_models,
+        game_resources,
+        |dep| area.has_dependency(dep),
+    );
 
     let pickup_model_type = pickup_model_type.clone().unwrap_or(PickupModel::Nothing);
     let mut pickup_model_data = pickup_model_type.pickup_data();
@@ -2149,6 +2570,9 @@ fn patch_add_item<'r>(
         pickup_model_data.actor_params.thermal_cmdl = ResId::invalid();
         pickup_model_data.actor_params.thermal_cskr = ResId::invalid();
     }
+    if let Some(aura) = pickup_config.aura.as_ref() {
+        apply_pickup_aura(&mut pickup_model_data, aura);
+    }
 
     let name = CString::new(format!("Randomizer - Pickup ({:?})", pickup_model_data.name)).unwrap();
     area.add_layer(Cow::Owned(name));
@@ -2264,7 +2688,9 @@ fn patch_add_item<'r>(
 
     let mut pickup_position = {
         if shuffle_position {
-            get_shuffled_position(area, &mut rng)
+            let position = get_shuffled_position(area, &mut rng, shuffle_zones);
+            shuffled_positions.borrow_mut().insert(pickup_hash_key, position);
+            position
         } else {
             if pickup_config.position.is_none() {
                 panic!("Position is required for additional pickup in room '0x{:X}'", pickup_hash_key.room_id);
@@ -2374,34 +2800,29 @@ fn patch_add_item<'r>(
     );
 
     // create attainment audio
-    let attainment_audio = structs::SclyObject {
-        instance_id: area.new_object_id_from_layer_id(new_layer_idx),
-        connections: vec![].into(),
-        property_data: structs::SclyProperty::Sound(
-            Box::new(structs::Sound { // copied from main plaza half-pipe
-                name: b"mysound\0".as_cstr(),
-                position: pickup_position.into(),
-                rotation: [0.0,0.0,0.0].into(),
-                sound_id: 117,
-                active: 1,
-                max_dist: 50.0,
-                dist_comp: 0.2,
-                start_delay: 0.0,
-                min_volume: 20,
-                volume: 127,
-                priority: 127,
-                pan: 64,
-                loops: 0,
-                non_emitter: 1,
-                auto_start: 0,
-                occlusion_test: 0,
-                acoustics: 0,
-                world_sfx: 0,
-                allow_duplicates: 0,
-                pitch: 0,
-            })
-        )
-    };
+    add_pickup_audio_group_dependency(area, game_resources, pickup_config);
+    let sound_id = pickup_config.sound_effect.as_ref()
+        .map(|sound_effect| sound_effect.id)
+        .unwrap_or(117);
+    let attainment_audio = pickup_sound(
+        area.new_object_id_from_layer_id(new_layer_idx),
+        pickup_position,
+        sound_id,
+    );
+
+    // Stream a jingle on top of the sound, like vanilla pickups do
+    let jingle = pickup_config.jingle.as_ref()
+        .and_then(|jingle| pickup_jingle_file_name(jingle))
+        .map(|file_name| pickup_jingle(area.new_object_id_from_layer_id(new_layer_idx), file_name));
+    if let Some(jingle) = jingle.as_ref() {
+        pickup_obj.connections.as_mut_vec().push(
+            structs::Connection {
+                state: structs::ConnectionState::ARRIVED,
+                message: structs::ConnectionMsg::PLAY,
+                target_object_id: jingle.instance_id,
+            }
+        );
+    }
 
     // Play the sound when item is picked up
     pickup_obj.connections.as_mut_vec().push(
@@ -2543,9 +2964,25 @@ fn patch_add_item<'r>(
         special_fn_artifact_layer_change_id = area.new_object_id_from_layer_name("Default");
     }
 
+    pickup_obj.connections.as_mut_vec().extend_from_slice(
+        &add_pickup_remote_connections(area, pickup_config)
+    );
+
+    let mut reveal_ids = None;
+    if pickup_config.scan_to_reveal.unwrap_or(false) {
+        pickup_obj.property_data.as_pickup_mut().unwrap().active = 0;
+        reveal_ids = Some(add_pickup_reveal_relay(area, pickup_obj_id));
+    }
+
     let scly = area.mrea().scly_section_mut();
     let layers = scly.layers.as_mut_vec();
 
+    if let Some((mem_relay_id, poi_id)) = reveal_ids {
+        layers[0].objects.as_mut_vec().push(
+            pickup_reveal_poi(poi_id, mem_relay_id, pickup_position, scan_id)
+        );
+    }
+
     if pickup_type == PickupType::FloatyJump {
         place_floaty_contraption(
             layers[0].objects.as_mut_vec(),
@@ -2661,6 +3098,9 @@ fn patch_add_item<'r>(
 
     layers[new_layer_idx as usize].objects.as_mut_vec().push(hudmemo);
     layers[new_layer_idx as usize].objects.as_mut_vec().push(attainment_audio);
+    if let Some(jingle) = jingle {
+        layers[new_layer_idx as usize].objects.as_mut_vec().push(jingle);
+    }
     layers[new_layer_idx as usize].objects.as_mut_vec().push(pickup_obj);
 
     // 2022-02-08 - I had to remove this because there's a bug in the vanilla engine where playerhint -> Scan Visor doesn't holster the weapon
@@ -2673,21 +3113,238 @@ fn patch_add_item<'r>(
     Ok(())
 }
 
-fn add_world_teleporter<'r>(
-    the_next_four_ids: [u32; 4],
-    objects: &mut Vec<structs::SclyObject<'r>>,
-    destination: &str,
-    version: Version,
+// Creates an inactive memory relay which forwards the configured messages when activated. The
+// connections are mirrored into the MLVL, so the targets receive the message when their room
+// loads even if it isn't this one. Returns the connection the pickup needs to trip the relay.
+fn add_pickup_remote_connections<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    pickup_config: &PickupConfig,
 ) -> Vec<structs::Connection>
 {
-    let destination = SpawnRoomData::from_str(&destination);
+    let remote_connections = match pickup_config.remote_connections.as_ref() {
+        Some(remote_connections) if !remote_connections.is_empty() => remote_connections,
+        _ => return vec![],
+    };
 
-    let world_transporter_id = the_next_four_ids[0];
-    let timer_id = the_next_four_ids[1];
-    let hudmemo_id = the_next_four_ids[2];
-    let player_hint_id = the_next_four_ids[3];
+    let mem_relay_id = area.new_object_id_from_layer_id(0);
+    let mem_relay = structs::SclyObject {
+        instance_id: mem_relay_id,
+        connections: remote_connections.iter().map(|conn|
+            structs::Connection {
+                state: structs::ConnectionState::ACTIVE,
+                message: structs::ConnectionMsg(conn.message as u32),
+                target_object_id: conn.target_id,
+            }
+        ).collect::<Vec<_>>().into(),
+        property_data: structs::MemoryRelay {
+            name: b"pickup remote relay\0".as_cstr(),
+            unknown: 0,
+            active: 0,
+        }.into(),
+    };
+    area.add_memory_relay(mem_relay);
 
-    // Teleporter
+    // Activating the relay both delivers the messages to objects which are already loaded and
+    // remembers that it happened, so rooms loaded later get them too
+    vec![
+        structs::Connection {
+            state: structs::ConnectionState::ARRIVED,
+            message: structs::ConnectionMsg::ACTIVATE,
+            target_object_id: mem_relay_id,
+        },
+    ]
+}
+
+// Maps a pickup's jingle option to the DSP to stream when it's collected, or None if it should
+// be silent
+fn pickup_jingle_file_name(jingle: &str) -> Option<String>
+{
+    match jingle.trim().to_lowercase().as_str() {
+        "major" => Some("/audio/jin_itemattain.dsp".to_string()),
+        "minor" => Some("/audio/itm_x_short_02.dsp".to_string()),
+        "silent" => None,
+        _ => Some(jingle.trim().to_string()),
+    }
+}
+
+fn add_pickup_audio_group_dependency<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    pickup_config: &PickupConfig,
+)
+{
+    let audio_group = pickup_config.sound_effect.as_ref().and_then(|sound_effect| sound_effect.audio_group);
+    if let Some(audio_group) = audio_group {
+        let dep = structs::Dependency {
+            asset_id: audio_group,
+            asset_type: FourCC::from_bytes(b"AGSC"),
+        };
+        area.add_dependencies(game_resources, 0, iter::once(dep));
+    }
+}
+
+fn pickup_sound<'r>(instance_id: u32, position: [f32; 3], sound_id: u32) -> structs::SclyObject<'r>
+{
+    structs::SclyObject {
+        instance_id,
+        connections: vec![].into(),
+        property_data: structs::SclyProperty::Sound(
+            Box::new(structs::Sound { // copied from main plaza half-pipe
+                name: b"mysound\0".as_cstr(),
+                position: position.into(),
+                rotation: [0.0,0.0,0.0].into(),
+                sound_id,
+                active: 1,
+                max_dist: 50.0,
+                dist_comp: 0.2,
+                start_delay: 0.0,
+                min_volume: 20,
+                volume: 127,
+                priority: 127,
+                pan: 64,
+                loops: 0,
+                non_emitter: 1,
+                auto_start: 0,
+                occlusion_test: 0,
+                acoustics: 0,
+                world_sfx: 0,
+                allow_duplicates: 0,
+                pitch: 0,
+            })
+        )
+    }
+}
+
+fn pickup_jingle<'r>(instance_id: u32, audio_file_name: String) -> structs::SclyObject<'r>
+{
+    structs::SclyObject {
+        instance_id,
+        connections: vec![].into(),
+        property_data: structs::SclyProperty::StreamedAudio(
+            Box::new(structs::StreamedAudio {
+                name: b"pickup jingle\0".as_cstr(),
+                active: 1,
+                audio_file_name: string_to_cstr(audio_file_name),
+                no_stop_on_deactivate: 0,
+                fade_in_time: 0.0,
+                fade_out_time: 0.0,
+                volume: 92,
+                oneshot: 1,
+                is_music: 1,
+            })
+        ),
+    }
+}
+
+// Vanilla pickups play their sound through the room's own objects, so a custom sound effect
+// needs a Sound object of its own. Returns the connections which play it on collection.
+fn add_pickup_sound_effect<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    pickup_config: &PickupConfig,
+) -> Vec<structs::Connection>
+{
+    let sound_effect = match pickup_config.sound_effect.as_ref() {
+        Some(sound_effect) => sound_effect,
+        None => return vec![],
+    };
+
+    add_pickup_audio_group_dependency(area, game_resources, pickup_config);
+
+    let sound_id = area.new_object_id_from_layer_id(0);
+    area.mrea().scly_section_mut().layers.as_mut_vec()[0].objects.as_mut_vec()
+        .push(pickup_sound(sound_id, [0.0, 0.0, 0.0], sound_effect.id));
+
+    vec![
+        structs::Connection {
+            state: structs::ConnectionState::ARRIVED,
+            message: structs::ConnectionMsg::PLAY,
+            target_object_id: sound_id,
+        },
+    ]
+}
+
+// Creates an inactive memory relay which makes a hidden pickup appear, and removes the point of
+// interest used to find it. The relay remembers the reveal so the pickup stays visible when the
+// room is reloaded. Returns the ids of the relay and of the point of interest to create.
+fn add_pickup_reveal_relay<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    pickup_id: u32,
+) -> (u32, u32)
+{
+    let mem_relay_id = area.new_object_id_from_layer_id(0);
+    let poi_id = area.new_object_id_from_layer_id(0);
+
+    area.add_memory_relay(structs::SclyObject {
+        instance_id: mem_relay_id,
+        connections: vec![
+            structs::Connection {
+                state: structs::ConnectionState::ACTIVE,
+                message: structs::ConnectionMsg::ACTIVATE,
+                target_object_id: pickup_id,
+            },
+            structs::Connection {
+                state: structs::ConnectionState::ACTIVE,
+                message: structs::ConnectionMsg::DEACTIVATE,
+                target_object_id: poi_id,
+            },
+        ].into(),
+        property_data: structs::MemoryRelay {
+            name: b"pickup reveal relay\0".as_cstr(),
+            unknown: 0,
+            active: 0,
+        }.into(),
+    });
+
+    (mem_relay_id, poi_id)
+}
+
+fn pickup_reveal_poi<'r>(
+    poi_id: u32,
+    mem_relay_id: u32,
+    position: [f32; 3],
+    scan_id: ResId<res_id::SCAN>,
+) -> structs::SclyObject<'r>
+{
+    structs::SclyObject {
+        instance_id: poi_id,
+        connections: vec![
+            structs::Connection {
+                state: structs::ConnectionState::SCAN_DONE,
+                message: structs::ConnectionMsg::ACTIVATE,
+                target_object_id: mem_relay_id,
+            },
+        ].into(),
+        property_data: structs::SclyProperty::PointOfInterest(
+            Box::new(structs::PointOfInterest {
+                name: b"pickup reveal poi\0".as_cstr(),
+                position: position.into(),
+                rotation: [0.0, 0.0, 0.0].into(),
+                active: 1,
+                scan_param: structs::scly_structs::ScannableParameters {
+                    scan: scan_id,
+                },
+                point_size: 12.0,
+            })
+        ),
+    }
+}
+
+fn add_world_teleporter<'r>(
+    the_next_four_ids: [u32; 4],
+    objects: &mut Vec<structs::SclyObject<'r>>,
+    destination: &str,
+    version: Version,
+) -> Vec<structs::Connection>
+{
+    let destination = SpawnRoomData::from_str(&destination);
+
+    let world_transporter_id = the_next_four_ids[0];
+    let timer_id = the_next_four_ids[1];
+    let hudmemo_id = the_next_four_ids[2];
+    let player_hint_id = the_next_four_ids[3];
+
+    // Teleporter
     objects.push(
         structs::SclyObject {
             instance_id: world_transporter_id,
@@ -3396,6 +4053,17 @@ fn patch_add_poi<'r>(
     let frme_dep: structs::Dependency = frme_id.into();
     area.add_dependencies(game_resources, 0, iter::once(frme_dep));
 
+    // Images shown in the scan's panes
+    let scan = game_resources.get(&(scan_id.to_u32(), FourCC::from_bytes(b"SCAN")))
+        .and_then(|res| res.kind.as_scan());
+    if let Some(scan) = scan {
+        let txtr_deps: Vec<structs::Dependency> = scan.images.iter()
+            .filter(|image| image.txtr != ResId::invalid())
+            .map(|image| image.txtr.into())
+            .collect();
+        area.add_dependencies(game_resources, 0, txtr_deps.into_iter());
+    }
+
     Ok(())
 }
 
@@ -3530,12 +4198,19 @@ where R: Rng
 fn get_shuffled_position<'r, R>(
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
     rng: &mut R,
+    shuffle_zones: Option<&PickupShuffleZonesConfig>,
 )
 -> [f32; 3]
 where R: Rng
 {
     let mrea_id = area.mlvl_area.mrea.to_u32();
 
+    if let Some(positions) = shuffle_zones.and_then(|zones| zones.positions.as_ref()) {
+        if let Some(position) = positions.choose(rng) {
+            return *position;
+        }
+    }
+
     // xmin, ymin, zmin,
     // xmax, ymax, zmax,
     let mut bounding_boxes: Vec<[f32; 6]> = Vec::new();
@@ -3580,6 +4255,17 @@ where R: Rng
         );
     }
 
+    if let Some(zones) = shuffle_zones.and_then(|zones| zones.bounding_boxes.as_ref()) {
+        if !zones.is_empty() {
+            bounding_boxes = zones.iter()
+                .map(|zone| [
+                    zone.min[0], zone.min[1], zone.min[2],
+                    zone.max[0], zone.max[1], zone.max[2],
+                ])
+                .collect();
+        }
+    }
+
     let mut offset_xy = 0.0;
     let mut offset_max_z = 0.0;
     if vec![
@@ -3650,6 +4336,53 @@ fn add_player_freeze_assets<'r>(file: &mut structs::FstEntryFile<'r>, resources:
     Ok(())
 }
 
+const SUIT_CSKRS: &[ResourceInfo] = &[
+    resource_info!("powersuit_high_rez_bound.CSKR"),
+    resource_info!("variasuit_high_rez_bound.CSKR"),
+    resource_info!("gravitysuit_high_rez_bound.CSKR"),
+    resource_info!("phazon_suit_high_rez_bound.CSKR"),
+    resource_info!("fusion_suit_high_rez_bound.CSKR"),
+    resource_info!("fusion_varia_high_rez_bound.CSKR"),
+    resource_info!("fusion_gravity_high_rez_bound.CSKR"),
+    resource_info!("fusion_phazon_high_rez_bound.CSKR"),
+];
+
+fn replace_resource_bytes(res: &mut structs::Resource, bytes: Vec<u8>) -> Result<(), String>
+{
+    let fourcc = res.fourcc();
+    res.kind = structs::ResourceKind::External(bytes, fourcc);
+    res.compressed = false;
+    Ok(())
+}
+
+/// Appends the assets a replacement suit model needs which aren't already in the PAK
+fn add_suit_model_assets(file: &mut structs::FstEntryFile<'_>, assets: &HashMap<u32, ExternAsset>)
+    -> Result<(), String>
+{
+    let pak = match file {
+        structs::FstEntryFile::Pak(pak) => pak,
+        _ => unreachable!(),
+    };
+
+    let existing: HashSet<u32> = pak.resources.iter().map(|res| res.file_id).collect();
+    let mut ids: Vec<&u32> = assets.keys().filter(|id| !existing.contains(id)).collect();
+    ids.sort();
+
+    // append at the end of the pak
+    let mut cursor = pak.resources.cursor();
+    while cursor.cursor_advancer().peek().is_some() {}
+    for id in ids {
+        let asset = &assets[id];
+        let mut res = crate::custom_assets::build_resource_raw(
+            *id,
+            structs::ResourceKind::External(asset.bytes.clone(), asset.fourcc)
+        );
+        res.compressed = false;
+        cursor.insert_after(iter::once(res));
+    }
+    Ok(())
+}
+
 fn add_map_pickup_icon_txtr(file: &mut structs::FstEntryFile<'_>)
     -> Result<(), String>
 {
@@ -3687,6 +4420,23 @@ fn add_pickups_to_mapa<'r>(
     Ok(())
 }
 
+fn add_station_to_mapa(
+    res: &mut structs::Resource,
+    object_type: structs::MapaObjectType,
+    position: [f32; 3],
+) -> Result<(), String>
+{
+    let mapa = res.kind.as_mapa_mut().unwrap();
+    mapa.add_object(
+        object_type,
+        structs::MapaObjectVisibilityMode::MapStationOrVisit,
+        0xFFFFFFFF, // not tied to any object
+        position,
+    );
+
+    Ok(())
+}
+
 fn modify_pickups_in_mrea<'r>(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
@@ -3702,10 +4452,12 @@ fn modify_pickups_in_mrea<'r>(
     qol_pickup_scans: bool,
     extern_models: &HashMap<String, ExternPickupModel>,
     shuffle_position: bool,
+    shuffle_zones: Option<&PickupShuffleZonesConfig>,
     seed: u64,
     _no_starting_visor: bool,
     version: Version,
     force_vanilla_layout: bool,
+    shuffled_positions: &RefCell<HashMap<PickupHashKey, [f32; 3]>>,
 )
 -> Result<(), String>
 {
@@ -3771,7 +4523,9 @@ fn modify_pickups_in_mrea<'r>(
 
     let mut position_override: Option<[f32;3]> = None;
     if shuffle_position {
-        position_override = Some(get_shuffled_position(area, &mut rng));
+        let position = get_shuffled_position(area, &mut rng, shuffle_zones);
+        shuffled_positions.borrow_mut().insert(pickup_hash_key, position);
+        position_override = Some(position);
     }
 
     // Pickup to use for game functionality //
@@ -3788,27 +4542,14 @@ fn modify_pickups_in_mrea<'r>(
         area.add_dependencies(game_resources, 0, deps_iter);
     }
 
-    let extern_model = if pickup_config.model.is_some() {
-        extern_models.get(pickup_config.model.as_ref().unwrap())
-    } else {
-        None
-    };
-
     // Pickup to use for visuals/hitbox //
-    let pickup_model_type: Option<PickupModel> = {
-        if pickup_config.model.is_some() {
-            let model_name = pickup_config.model.as_ref().unwrap();
-            let pmt = PickupModel::from_str(&model_name);
-            if pmt.is_none() && !extern_model.is_some() {
-                panic!("Unknown Model Type {}", model_name);
-            }
-
-            pmt // Some - Native Prime Model
-                // None - External Model (e.g. Screw Attack)
-        } else {
-            Some(PickupModel::from_type(pickup_type)) // No model specified, use pickup type as inspiration
-        }
-    };
+    let (pickup_model_type, extern_model) = resolve_pickup_model(
+        &pickup_config,
+        pickup_type,
+        extern_models,
+        game_resources,
+        |dep| area.has_dependency(dep),
+    );
 
     let pickup_model_type = pickup_model_type.clone().unwrap_or(PickupModel::Nothing);
     let mut pickup_model_data = pickup_model_type.pickup_data();
@@ -3827,6 +4568,9 @@ fn modify_pickups_in_mrea<'r>(
         pickup_model_data.actor_params.thermal_cmdl = ResId::invalid();
         pickup_model_data.actor_params.thermal_cskr = ResId::invalid();
     }
+    if let Some(aura) = pickup_config.aura.as_ref() {
+        apply_pickup_aura(&mut pickup_model_data, aura);
+    }
 
     // Add hudmemo string as dependency to room //
     let hudmemo_strg: ResId<res_id::STRG> = {
@@ -3940,6 +4684,28 @@ fn modify_pickups_in_mrea<'r>(
         area.new_object_id_from_layer_id(0),
     ];
 
+    let remote_connections = add_pickup_remote_connections(area, &pickup_config);
+    let sound_effect_connections = add_pickup_sound_effect(area, game_resources, &pickup_config);
+
+    let mut reveal_ids = None;
+    if pickup_config.scan_to_reveal.unwrap_or(false) && !force_vanilla_layout {
+        // Pickups which start inactive are spawned by the room itself (e.g. boss drops)
+        let starts_active = {
+            let scly = area.mrea().scly_section();
+            let layer = scly.layers.iter().nth(pickup_location.location.layer as usize).unwrap();
+            let pickup = layer.objects.iter()
+                .find(|obj| obj.instance_id == pickup_location.location.instance_id)
+                .unwrap();
+            pickup.property_data.as_pickup().unwrap().active != 0
+        };
+
+        if starts_active {
+            reveal_ids = Some(add_pickup_reveal_relay(area, pickup_location.location.instance_id));
+        } else {
+            println!("Warning, pickup in room 0x{:X} is spawned by the room and can't be hidden until scanned", mrea_id);
+        }
+    }
+
     let scly = area.mrea().scly_section_mut();
     let layers = scly.layers.as_mut_vec();
 
@@ -4318,6 +5084,8 @@ fn modify_pickups_in_mrea<'r>(
                 &world_teleporter_connections
             );
         }
+        additional_connections.extend_from_slice(&remote_connections);
+        additional_connections.extend_from_slice(&sound_effect_connections);
 
         let pickup_obj = layers[pickup_location.location.layer as usize].objects.iter_mut()
             .find(|obj| obj.instance_id == pickup_location.location.instance_id)
@@ -4326,6 +5094,10 @@ fn modify_pickups_in_mrea<'r>(
         if !force_vanilla_layout {
             (position, scan_id_out) = update_pickup(pickup_obj, pickup_type, pickup_model_data, &pickup_config, scan_id, position_override);
 
+            if reveal_ids.is_some() {
+                pickup_obj.property_data.as_pickup_mut().unwrap().active = 0;
+            }
+
             if additional_connections.len() > 0 {
                 pickup_obj.connections.as_mut_vec().extend_from_slice(&additional_connections);
             }
@@ -4346,6 +5118,12 @@ fn modify_pickups_in_mrea<'r>(
         );
     }
 
+    if let Some((mem_relay_id, poi_id)) = reveal_ids {
+        layers[0].objects.as_mut_vec().push(
+            pickup_reveal_poi(poi_id, mem_relay_id, position, scan_id_out)
+        );
+    }
+
     if jumbo_poi {
         layers[jumbo_poi_layer_idx].objects.as_mut_vec().push(
             structs::SclyObject {
@@ -4427,6 +5205,14 @@ fn modify_pickups_in_mrea<'r>(
         .unwrap();
     update_attainment_audio(attainment_audio, pickup_type);
 
+    if let Some(jingle) = pickup_config.jingle.as_ref() {
+        let streamed_audio = attainment_audio.property_data.as_streamed_audio_mut().unwrap();
+        match pickup_jingle_file_name(jingle) {
+            Some(file_name) => streamed_audio.audio_file_name = string_to_cstr(file_name),
+            None => streamed_audio.active = 0,
+        }
+    }
+
     Ok(())
 }
 
@@ -4538,6 +5324,32 @@ fn place_floaty_contraption<'r>(
     );
 }
 
+// Fills in the jingle and sound effect the pickup doesn't set from the ones for its type
+fn apply_pickup_sounds(pickup: &mut PickupConfig, pickup_sounds: &BTreeMap<PickupType, PickupSoundsConfig>)
+{
+    let sounds = match pickup_sounds.get(&PickupType::from_str(&pickup.pickup_type)) {
+        Some(sounds) => sounds,
+        None => return,
+    };
+    if pickup.jingle.is_none() {
+        pickup.jingle = sounds.jingle.clone();
+    }
+    if pickup.sound_effect.is_none() {
+        pickup.sound_effect = sounds.sound_effect.clone();
+    }
+}
+
+fn apply_pickup_aura(pickup_model_data: &mut structs::Pickup, aura: &PickupAuraConfig)
+{
+    if !aura.particle.unwrap_or(true) {
+        pickup_model_data.part = ResId::invalid();
+    }
+
+    if let Some(light_color) = aura.light_color {
+        pickup_model_data.actor_params.light_params.color = light_color.into();
+    }
+}
+
 fn update_pickup(
     pickup_obj: &mut structs::SclyObject,
     pickup_type: PickupType,
@@ -4629,6 +5441,19 @@ fn update_pickup(
         ].into();
     };
 
+    // Models taller than the original would poke through the floor once centered, so raise them
+    // until their bottoms line up. Pickups which spawn in (e.g. after a boss) also get a longer
+    // fade-in so the bigger model doesn't pop into existence.
+    let original_height = (original_aabb[5] - original_aabb[2]) * original_pickup.scale[2];
+    let new_height = (new_aabb[5] - new_aabb[2]) * scale[2];
+    let mut fade_in_timer = original_pickup.fade_in_timer;
+    if original_height > 0.0 && new_height > original_height {
+        let raise = (new_height - original_height) / 2.0;
+        position[2] += raise;
+        scan_offset[2] -= raise;
+        fade_in_timer *= f32::min(new_height / original_height, 2.0);
+    }
+
     *pickup = structs::Pickup {
         // Location Pickup Data
         // "How is this pickup integrated into the room?"
@@ -4637,7 +5462,7 @@ fn update_pickup(
         rotation: pickup_model_data.rotation.clone().into(),
         hitbox: original_pickup.hitbox,
         scan_offset: scan_offset.into(),
-        fade_in_timer:  original_pickup.fade_in_timer,
+        fade_in_timer,
         spawn_delay: original_pickup.spawn_delay,
         disappear_timer: original_pickup.disappear_timer,
         active: original_pickup.active,
@@ -4810,10 +5635,11 @@ fn patch_elevator_actor_size<'r>(
 
 fn make_elevators_patch<'a>(
     patcher: &mut PrimePatcher<'_, 'a>,
-    level_data: &HashMap<String, LevelConfig>,
+    level_data: &BTreeMap<String, LevelConfig>,
     auto_enabled_elevators: bool,
     player_size: f32,
     force_vanilla_layout: bool,
+    language: Option<Language>,
 )
 -> (bool, bool)
 {
@@ -4847,6 +5673,14 @@ fn make_elevators_patch<'a>(
             let elv = elv.unwrap();
             let dest = SpawnRoomData::from_str(destination_name);
 
+            if let Some(spawn_position) = level.transport_spawn_positions.get(elevator_name) {
+                let spawn_position = *spawn_position;
+                patcher.add_scly_patch(
+                    (dest.pak_name.as_bytes(), dest.mrea),
+                    move |ps, area| patch_spawn_point_position(ps, area, spawn_position, false, false, false)
+                );
+            }
+
             if dest.mlvl == World::FrigateOrpheon.mlvl() {
                 skip_frigate = false;
             }
@@ -4961,27 +5795,21 @@ fn make_elevators_patch<'a>(
 
             patcher.add_resource_patch((&[elv.pak_name.as_bytes()], elv.room_strg, b"STRG".into()), move |res| {
                 let string = format!("Transport to {}\u{0}", room_dest_name);
-                let strg = structs::Strg::from_strings(vec![string]);
-                res.kind = structs::ResourceKind::Strg(strg);
-                Ok(())
+                patch_strg_strings(res, vec![string], language)
             });
             patcher.add_resource_patch((&[elv.pak_name.as_bytes()], elv.hologram_strg, b"STRG".into()), move |res| {
                 let string = format!(
                     "Access to &main-color=#FF3333;{} &main-color=#89D6FF;granted. Please step into the hologram.\u{0}",
                     hologram_name,
                 );
-                let strg = structs::Strg::from_strings(vec![string]);
-                res.kind = structs::ResourceKind::Strg(strg);
-                Ok(())
+                patch_strg_strings(res, vec![string], language)
             });
             patcher.add_resource_patch((&[elv.pak_name.as_bytes()], elv.control_strg, b"STRG".into()), move |res| {
                 let string = format!(
                     "Transport to &main-color=#FF3333;{}&main-color=#89D6FF; active.\u{0}",
                     control_name,
                 );
-                let strg = structs::Strg::from_strings(vec![string]);
-                res.kind = structs::ResourceKind::Strg(strg);
-                Ok(())
+                patch_strg_strings(res, vec![string], language)
             });
         }
     }
@@ -5564,47 +6392,340 @@ fn patch_add_cutscene_skip_fn<'r>(
     Ok(())
 }
 
-pub fn string_to_cstr<'r>(string: String) -> CStr<'r>
-{
-    let x = CString::new(string).expect("CString conversion failed");
-    let x = Cow::Owned(x);
-    x
-}
-
-fn patch_edit_fog<'r>(
-    _ps: &mut PatcherState,
-    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
-    fog: FogConfig,
-) -> Result<(), String>
+/// Briefly shows the room's name, as on the map, whenever the player enters the room
+fn patch_room_name_popup(_ps: &mut PatcherState, area: &mut mlvl_wrapper::MlvlArea)
+    -> Result<(), String>
 {
-    let id = area.new_object_id_from_layer_id(0);
+    let name_strg = area.mlvl_area.area_name_strg;
 
-    let mut range_delta = [0.0, 0.0];
-    if fog.range_delta.is_some() {
-        range_delta = [fog.range_delta.as_ref().unwrap()[0], fog.range_delta.as_ref().unwrap()[1]];
+    // The STRG is already in the world's PAK for the map, it only needs to be loaded with the room
+    let name_dep: structs::Dependency = name_strg.into();
+    let deps = area.mlvl_area.dependencies.deps.as_mut_vec();
+    if deps.iter().all(|layer| layer.iter().all(|dep| *dep != name_dep)) {
+        deps[0].as_mut_vec().push(name_dep);
     }
 
-    let mut found = false;
-
-    let layers = area.mrea().scly_section_mut().layers.as_mut_vec();
-    for obj in layers[0].objects.as_mut_vec() {
-        if !obj.property_data.is_distance_fog() {
-            continue;
-        }
-
-        let distance_fog = obj.property_data.as_distance_fog_mut();
-        if distance_fog.is_none() {
-            continue;
-        }
-        let distance_fog = distance_fog.unwrap();
-
-        distance_fog.mode = fog.mode.unwrap_or(1);
+    let (_, _, bounding_box_extent, room_origin) = derrive_bounding_box_measurements(area);
+    let trigger_id = area.new_object_id_from_layer_id(0);
+    let hudmemo_id = area.new_object_id_from_layer_id(0);
 
-        let color = fog.color.unwrap_or([0.8, 0.8, 0.9, 0.0]);
-        distance_fog.color = color.into();
-        let range = fog.range.unwrap_or([30.0, 40.0]);
-        distance_fog.range = range.into();
-        distance_fog.color_delta = fog.color_delta.unwrap_or(0.0);
+    let scly = area.mrea().scly_section_mut();
+    let objects = scly.layers.as_mut_vec()[0].objects.as_mut_vec();
+    objects.push(structs::SclyObject {
+        instance_id: trigger_id,
+        property_data: structs::Trigger {
+            name: b"Trigger - Room name popup\0".as_cstr(),
+            position: room_origin.into(),
+            scale: [
+                bounding_box_extent[0]*2.0, // half-extent into full-extent
+                bounding_box_extent[1]*2.0,
+                bounding_box_extent[2]*2.0,
+            ].into(),
+            damage_info: structs::scly_structs::DamageInfo {
+                weapon_type: 0,
+                damage: 0.0,
+                radius: 0.0,
+                knockback_power: 0.0
+            },
+            force: [0.0, 0.0, 0.0].into(),
+            flags: 1,
+            active: 1,
+            deactivate_on_enter: 0,
+            deactivate_on_exit: 0
+        }.into(),
+        connections: vec![
+            structs::Connection {
+                state: structs::ConnectionState::ENTERED,
+                message: structs::ConnectionMsg::SET_TO_ZERO,
+                target_object_id: hudmemo_id,
+            },
+        ].into(),
+    });
+    objects.push(structs::SclyObject {
+        instance_id: hudmemo_id,
+        property_data: structs::HudMemo {
+            name: b"Room name hudmemo\0".as_cstr(),
+            first_message_timer: 2.5,
+            unknown: 1,
+            memo_type: 0,
+            strg: name_strg,
+            active: 1,
+        }.into(),
+        connections: vec![].into(),
+    });
+
+    Ok(())
+}
+
+/// Shows a hudmemo telling the player the cutscene can be skipped whenever one of the cutscene skip
+/// functions is enabled
+fn patch_cutscene_skip_prompt<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    skip_fn_ids: &[u32],
+)
+    -> Result<(), String>
+{
+    let hudmemo_id = area.new_object_id_from_layer_id(0);
+
+    let scly = area.mrea().scly_section_mut();
+    let mut prompted = false;
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            let mut prompts = vec![];
+            for conn in obj.connections.iter() {
+                if conn.message == structs::ConnectionMsg::INCREMENT &&
+                    skip_fn_ids.contains(&conn.target_object_id)
+                {
+                    prompts.push(structs::Connection {
+                        state: conn.state,
+                        message: structs::ConnectionMsg::SET_TO_ZERO,
+                        target_object_id: hudmemo_id,
+                    });
+                }
+            }
+            prompted |= !prompts.is_empty();
+            obj.connections.as_mut_vec().extend(prompts);
+        }
+    }
+    if !prompted {
+        return Ok(());
+    }
+
+    scly.layers.as_mut_vec()[0].objects.as_mut_vec().push(structs::SclyObject {
+        instance_id: hudmemo_id,
+        property_data: structs::HudMemo {
+            name: b"Cutscene skip hudmemo\0".as_cstr(),
+            first_message_timer: 3.0,
+            unknown: 1,
+            memo_type: 0,
+            strg: custom_asset_ids::CUTSCENE_SKIP_PROMPT_STRG,
+            active: 1,
+        }.into(),
+        connections: vec![].into(),
+    });
+    area.add_dependencies(
+        game_resources, 0,
+        iter::once(custom_asset_ids::CUTSCENE_SKIP_PROMPT_STRG.into())
+    );
+
+    Ok(())
+}
+
+fn objective_hint_name<'r>(idx: usize) -> CStr<'r>
+{
+    string_to_cstr(format!("Objective {}", idx))
+}
+
+fn objective_hint_system<'r>(instance_id: u32, idx: usize) -> structs::SclyObject<'r>
+{
+    structs::SclyObject {
+        instance_id,
+        property_data: structs::SpecialFunction {
+            name: b"objective hint\0".as_cstr(),
+            position: [0., 0., 0.].into(),
+            rotation: [0., 0., 0.].into(),
+            type_: 20, // HintSystem
+            unknown0: objective_hint_name(idx),
+            unknown1: 0.,
+            unknown2: 0.,
+            unknown3: 0.,
+            layer_change_room_id: 0xFFFFFFFF,
+            layer_change_layer_id: 0xFFFFFFFF,
+            item_id: 0,
+            unknown4: 1,
+            unknown5: 0.,
+            unknown6: 0xFFFFFFFF,
+            unknown7: 0xFFFFFFFF,
+            unknown8: 0xFFFFFFFF,
+        }.into(),
+        connections: vec![].into(),
+    }
+}
+
+/// Replaces the game's hints with one per entry of `hints`, each flashing its locations on the
+/// map. The vanilla hints point to where items were before randomization, so they're dropped.
+/// Only the first active hint is shown, so their order decides which objective is marked next.
+fn patch_objective_hint<'r>(
+    file: &mut structs::FstEntryFile<'r>,
+    hints: &[Vec<SpawnRoomData>],
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+) -> Result<(), String>
+{
+    let pak = match file {
+        structs::FstEntryFile::Pak(pak) => pak,
+        _ => unreachable!(),
+    };
+
+    let mut found = false;
+    let mut cursor = pak.resources.cursor();
+    while cursor.peek().is_some() {
+        let mut cursor = cursor.cursor_advancer();
+        let res = cursor.value().unwrap();
+        if res.fourcc() != b"HINT".into() {
+            continue;
+        }
+
+        let hint = res.kind.as_hint_mut().unwrap();
+        hint.hints = hints.iter()
+            .enumerate()
+            .map(|(idx, locations)| structs::HintDetails {
+                hint_name: objective_hint_name(idx),
+                intermediate_time: 0.0,
+                normal_time: 0.0,
+                popup_text_strg: custom_asset_ids::OBJECTIVE_HINT_STRG.to_u32(),
+                text_time: 5, // how long the popup stays up
+                locations: locations.iter()
+                    .map(|room| structs::HintLocation {
+                        mlvl: ResId::new(room.mlvl),
+                        mrea: ResId::new(room.mrea),
+                        target_room_index: room.mrea_idx,
+                        map_text_strg: custom_asset_ids::OBJECTIVE_MAP_STRG,
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            })
+            .collect::<Vec<_>>()
+            .into();
+        found = true;
+    }
+    if !found {
+        Err("Failed to find the hints in NoARAM.pak".to_string())?
+    }
+
+    // The hint's text has to be loaded wherever the player is
+    let mut cursor = pak.resources.cursor();
+    while cursor.cursor_advancer().peek().is_some() {}
+    for id in [custom_asset_ids::OBJECTIVE_HINT_STRG, custom_asset_ids::OBJECTIVE_MAP_STRG] {
+        cursor.insert_after(iter::once(game_resources[&id.into()].clone()));
+    }
+    Ok(())
+}
+
+/// Starts every objective hint as soon as the player spawns in the room
+fn patch_activate_objective_hint(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea,
+    hint_count: usize,
+) -> Result<(), String>
+{
+    let hint_system_ids: Vec<u32> = (0..hint_count)
+        .map(|_| area.new_object_id_from_layer_id(0))
+        .collect();
+    let timer_id = area.new_object_id_from_layer_id(0);
+
+    let layer = &mut area.mrea().scly_section_mut().layers.as_mut_vec()[0];
+    layer.objects.as_mut_vec().extend(
+        hint_system_ids.iter()
+            .enumerate()
+            .map(|(idx, id)| objective_hint_system(*id, idx))
+    );
+    layer.objects.as_mut_vec().push(
+        structs::SclyObject {
+            instance_id: timer_id,
+            property_data: structs::Timer {
+                name: b"objective hint timer\0".as_cstr(),
+                start_time: 1.0,
+                max_random_add: 0.0,
+                looping: 0,
+                start_immediately: 1,
+                active: 1,
+            }.into(),
+            connections: hint_system_ids.iter()
+                .map(|id| structs::Connection {
+                    state: structs::ConnectionState::ZERO,
+                    message: structs::ConnectionMsg::ACTION,
+                    target_object_id: *id,
+                })
+                .collect::<Vec<_>>()
+                .into(),
+        },
+    );
+
+    Ok(())
+}
+
+/// Retires objective hint `hint_idx` once the artifact it points to is collected, so the map moves
+/// on to the next artifact
+fn patch_complete_objective_hint(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea,
+    artifact_kind: u32,
+    hint_idx: usize,
+) -> Result<(), String>
+{
+    let hint_system_id = area.new_object_id_from_layer_id(0);
+
+    let layers = area.mrea().scly_section_mut().layers.as_mut_vec();
+    let mut found = false;
+    for layer in layers.iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            let is_artifact = obj.property_data.as_pickup()
+                .map(|pickup| pickup.kind == artifact_kind)
+                .unwrap_or(false);
+            if !is_artifact {
+                continue;
+            }
+
+            obj.connections.as_mut_vec().push(structs::Connection {
+                state: structs::ConnectionState::ARRIVED,
+                message: structs::ConnectionMsg::DECREMENT,
+                target_object_id: hint_system_id,
+            });
+            found = true;
+        }
+    }
+    if !found {
+        Err(format!("Failed to find the pickup for objective hint {}", hint_idx))?
+    }
+
+    layers[0].objects.as_mut_vec().push(objective_hint_system(hint_system_id, hint_idx));
+
+    Ok(())
+}
+
+pub fn string_to_cstr<'r>(string: String) -> CStr<'r>
+{
+    let x = CString::new(string).expect("CString conversion failed");
+    let x = Cow::Owned(x);
+    x
+}
+
+fn patch_edit_fog<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    fog: FogConfig,
+) -> Result<(), String>
+{
+    let id = area.new_object_id_from_layer_id(0);
+
+    let mut range_delta = [0.0, 0.0];
+    if fog.range_delta.is_some() {
+        range_delta = [fog.range_delta.as_ref().unwrap()[0], fog.range_delta.as_ref().unwrap()[1]];
+    }
+
+    let mut found = false;
+
+    let layers = area.mrea().scly_section_mut().layers.as_mut_vec();
+    for obj in layers[0].objects.as_mut_vec() {
+        if !obj.property_data.is_distance_fog() {
+            continue;
+        }
+
+        let distance_fog = obj.property_data.as_distance_fog_mut();
+        if distance_fog.is_none() {
+            continue;
+        }
+        let distance_fog = distance_fog.unwrap();
+
+        distance_fog.mode = fog.mode.unwrap_or(1);
+
+        let color = fog.color.unwrap_or([0.8, 0.8, 0.9, 0.0]);
+        distance_fog.color = color.into();
+        let range = fog.range.unwrap_or([30.0, 40.0]);
+        distance_fog.range = range.into();
+        distance_fog.color_delta = fog.color_delta.unwrap_or(0.0);
         distance_fog.range_delta = range_delta.into();
         distance_fog.explicit = fog.explicit.unwrap_or(true) as u8;
         distance_fog.active = 1;
@@ -5636,6 +6757,197 @@ fn patch_edit_fog<'r>(
     Ok(())
 }
 
+/// Highest volume sounds and streamed audio can be played at
+const MAX_AUDIO_VOLUME: u32 = 0x7F;
+
+/// Gains are clamped to `MAX_AUDIO_VOLUME`, so they can't make anything already at the limit
+/// louder
+fn scale_volume(volume: u32, gain: f32) -> u32
+{
+    ((volume as f32 * gain).round() as u32).min(MAX_AUDIO_VOLUME)
+}
+
+/// Scales the default velocity of every sound effect in an AGSC's SFX groups, which sets how loud
+/// the sound is before the room's Sound object volume is applied.
+///
+/// An AGSC is two strings followed by the MusyX pool, project, sample and sample directory, each
+/// prefixed with its length. The project is a list of groups ending with 0xFFFFFFFF; an SFX
+/// group's page table offset points at its entry count and 10 byte entries, the velocity being
+/// the 7th byte.
+fn scale_agsc_sfx_volumes(agsc: &mut [u8], gain: f32) -> Result<(), String>
+{
+    let u32_at = |bytes: &[u8], offset: usize| -> Result<usize, String> {
+        bytes.get(offset..offset + 4)
+            .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+            .ok_or_else(|| "AGSC is cut short".to_string())
+    };
+
+    // Skip the module and group names
+    let mut offset = 0;
+    for _ in 0..2 {
+        offset += agsc[offset..].iter().position(|&b| b == 0)
+            .ok_or_else(|| "AGSC is cut short".to_string())? + 1;
+    }
+    let pool_len = u32_at(agsc, offset)?;
+    offset += 4 + pool_len;
+    let proj_len = u32_at(agsc, offset)?;
+    let proj = agsc.get_mut(offset + 4..offset + 4 + proj_len)
+        .ok_or_else(|| "AGSC is cut short".to_string())?;
+
+    let mut group = 0;
+    loop {
+        let group_end = u32_at(proj, group)?;
+        if group_end == 0xFFFFFFFF {
+            break;
+        }
+        let group_type = u32_at(proj, group + 4)? & 0xFFFF;
+        if group_type == 1 {
+            let page_table = u32_at(proj, group + 0x20)?;
+            let count = u32_at(proj, page_table)? >> 16;
+            for i in 0..count {
+                let velocity = proj.get_mut(page_table + 4 + i * 10 + 6)
+                    .ok_or_else(|| "AGSC is cut short".to_string())?;
+                *velocity = scale_volume(*velocity as u32, gain) as u8;
+            }
+        }
+        if group_end <= group {
+            Err("AGSC has a malformed group".to_string())?
+        }
+        group = group_end;
+    }
+
+    Ok(())
+}
+
+fn patch_agsc_sfx_gain(file: &mut structs::FstEntryFile<'_>, gain: f32) -> Result<(), String>
+{
+    let pak = match file {
+        structs::FstEntryFile::Pak(pak) => pak,
+        _ => unreachable!(),
+    };
+
+    let mut cursor = pak.resources.cursor();
+    while cursor.peek().is_some() {
+        let mut cursor = cursor.cursor_advancer();
+        let res = cursor.value().unwrap();
+        if res.fourcc() != b"AGSC".into() {
+            continue;
+        }
+        let mut bytes = crate::ResourceData::new(res).decompress().into_owned();
+        scale_agsc_sfx_volumes(&mut bytes, gain)
+            .map_err(|e| format!("{} 0x{:08X}", e, res.file_id))?;
+        replace_resource_bytes(res, bytes)?;
+    }
+    Ok(())
+}
+
+/// Scales the volume every streamed audio in the room plays at. Music gets `music_gain`, and then
+/// the gain of any entry of `track_gains` which is part of its file name. Sound objects are left
+/// alone, their sound effects are scaled in the AGSCs by `patch_agsc_sfx_gain` instead.
+fn patch_audio_gain<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    music_gain: f32,
+    sfx_gain: f32,
+    track_gains: &BTreeMap<String, f32>,
+) -> Result<(), String>
+{
+    let scly = area.mrea().scly_section_mut();
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            if let Some(streamed_audio) = obj.property_data.as_streamed_audio_mut() {
+                let gain = if streamed_audio.is_music != 0 {
+                    let file_name = streamed_audio.audio_file_name.to_string_lossy().to_lowercase();
+                    track_gains.iter()
+                        .filter(|(track, _)| file_name.contains(&track.to_lowercase()))
+                        .fold(music_gain, |gain, (_, track_gain)| gain * track_gain)
+                } else {
+                    sfx_gain
+                };
+                streamed_audio.volume = scale_volume(streamed_audio.volume, gain);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Filter stage of the tint, which isn't used by the game's own camera filters
+const COLOR_GRADING_FILTER_STAGE: u32 = 4;
+
+/// Tints the room with `preset` as soon as it loads. Without a preset, the tint left over from a
+/// room in another world is removed instead, as camera filters outlive the room which set them.
+fn patch_color_grading<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    preset: Option<ColorGradingPreset>,
+) -> Result<(), String>
+{
+    let filter_id = area.new_object_id_from_layer_id(0);
+    let timer_id = area.new_object_id_from_layer_id(0);
+
+    let color = match preset {
+        Some(ColorGradingPreset::Warm) => [1.0, 0.9, 0.75, 1.0],
+        Some(ColorGradingPreset::Cool) => [0.8, 0.9, 1.0, 1.0],
+        Some(ColorGradingPreset::Noir) => [0.75, 0.75, 0.8, 1.0],
+        None => [1.0, 1.0, 1.0, 1.0],
+    };
+
+    let layers = area.mrea().scly_section_mut().layers.as_mut_vec();
+    if preset == Some(ColorGradingPreset::Noir) {
+        for layer in layers.iter_mut() {
+            for obj in layer.objects.as_mut_vec().iter_mut() {
+                if let Some(distance_fog) = obj.property_data.as_distance_fog_mut() {
+                    distance_fog.color = [0.05, 0.05, 0.06, distance_fog.color[3]].into();
+                }
+            }
+        }
+    }
+
+    layers[0].objects.as_mut_vec().extend([
+        structs::SclyObject {
+            instance_id: filter_id,
+            property_data: structs::CameraFilterKeyframe {
+                name: b"color grading\0".as_cstr(),
+                active: 1,
+                filter_type: 1, // multiply
+                filter_shape: 0,
+                unknown4: COLOR_GRADING_FILTER_STAGE,
+                unknown5: 0,
+                color: color.into(),
+                fade_in_time: 0.0,
+                fade_out_time: 0.0,
+                overlay_txtr: 0xFFFFFFFF,
+            }.into(),
+            connections: vec![].into(),
+        },
+        structs::SclyObject {
+            instance_id: timer_id,
+            property_data: structs::Timer {
+                name: b"color grading timer\0".as_cstr(),
+                start_time: 0.02,
+                max_random_add: 0.0,
+                looping: 0,
+                start_immediately: 1,
+                active: 1,
+            }.into(),
+            connections: vec![
+                structs::Connection {
+                    state: structs::ConnectionState::ZERO,
+                    message: if preset.is_some() {
+                        structs::ConnectionMsg::INCREMENT
+                    } else {
+                        structs::ConnectionMsg::DECREMENT
+                    },
+                    target_object_id: filter_id,
+                },
+            ].into(),
+        },
+    ]);
+
+    Ok(())
+}
+
 fn local_to_global_tranform(
     tranformation_matrix: [f32;12],
     coordinates: [f32;3],
@@ -5768,24 +7080,68 @@ fn patch_visible_aether_boundaries<'r>(
     Ok(())
 }
 
-fn patch_ambient_lighting<'r>(
+fn patch_ambient_lighting<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    scale: f32,
+) -> Result<(), String>
+{
+    let lights: &mut structs::Lights = area.mrea().lights_section_mut();
+    for light in lights.light_layers.as_mut_vec() {
+        if light.light_type != 0x0 { // local ambient
+            continue;
+        }
+
+        light.brightness = scale;
+    }
+
+    Ok(())
+}
+
+/// Empties the room's visibility tree (the VISI section). The game only uses the precomputed
+/// visibility when the section starts with its magic, so nothing in the room is culled by it.
+fn patch_disable_occlusion<'r>(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
-    scale: f32,
 ) -> Result<(), String>
 {
-    let lights: &mut structs::Lights = area.mrea().lights_section_mut();
-    for light in lights.light_layers.as_mut_vec() {
-        if light.light_type != 0x0 { // local ambient
-            continue;
-        }
+    let mrea = area.mrea();
+    let idx = mrea.visibility_tree_section_idx as usize;
+    if let Some(section) = mrea.sections.as_mut_vec().get_mut(idx) {
+        *section = structs::MreaSection::Unknown(Reader::new(&[]));
+    }
 
-        light.brightness = scale;
+    Ok(())
+}
+
+fn patch_room_name_strg(res: &mut structs::Resource, name: &str, language: Option<Language>)
+    -> Result<(), String>
+{
+    let strg = res.kind.as_strg_mut().unwrap();
+    for st in strg.string_tables.as_mut_vec().iter_mut() {
+        let replace = replaces_strg_lang(language, st.lang);
+        let strings = st.strings.as_mut_vec();
+        if strings.is_empty() {
+            strings.push(format!("{}\0", name).into());
+        } else if replace {
+            strings[0] = format!("{}\0", name).into();
+        }
     }
 
     Ok(())
 }
 
+/// Points the room at another room's name
+fn patch_room_name_id<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    name_id: ResId<res_id::STRG>,
+) -> Result<(), String>
+{
+    area.mlvl_area.area_name_strg = name_id;
+    Ok(())
+}
+
 // fn patch_add_orange_light<'r>(
 //     ps: &mut PatcherState,
 //     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
@@ -6149,7 +7505,7 @@ fn fix_artifact_of_truth_requirements(
     config: &PatchConfig,
 ) -> Result<(), String>
 {
-    let level_data: HashMap<String, LevelConfig> = config.level_data.clone();
+    let level_data: BTreeMap<String, LevelConfig> = config.level_data.clone();
     let artifact_temple_layer_overrides = config.artifact_temple_layer_overrides.clone().unwrap_or(HashMap::new());
 
     // Create a new layer that will be toggled on when the Artifact of Truth is collected
@@ -7297,6 +8653,158 @@ fn patch_audio_override<'r>
     Ok(())
 }
 
+/// Vanilla tracks are referenced by their path on the disc, anything else is a file from
+/// `extern_assets_dir` which gets added to the disc's Audio folder
+fn music_override_file_name(music_override: &str) -> String
+{
+    music_override.split('|')
+        .map(|name| {
+            let name = name.trim();
+            if name.to_lowercase().starts_with("/audio/") {
+                name.to_string()
+            } else {
+                format!("/audio/{}", name)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("|")
+}
+
+fn add_extern_music_files(gc_disc: &mut structs::GcDisc, config: &PatchConfig)
+    -> Result<(), String>
+{
+    let mut added = HashSet::new();
+    for level in config.level_data.values() {
+        for (room_name, room) in level.rooms.iter() {
+            let music_override = match room.music_override.as_ref() {
+                Some(music_override) => music_override,
+                None => continue,
+            };
+
+            for name in music_override.split('|').map(|name| name.trim()) {
+                if name.to_lowercase().starts_with("/audio/") || !added.insert(name.to_string()) {
+                    continue;
+                }
+
+                let extern_assets_dir = config.extern_assets_dir.as_ref()
+                    .ok_or_else(|| format!("musicOverride '{}' in {} requires externAssetsDir", name, room_name))?;
+                let bytes = fs::read(Path::new(extern_assets_dir).join(name))
+                    .map_err(|e| format!("Failed to read music file {}: {}", name, e))?;
+                gc_disc.add_file(
+                    &format!("Audio/{}", name),
+                    structs::FstEntryFile::ExternalFile(Box::new(bytes)),
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The title screen and file select music aren't DSPs streamed by the room, the front end plays
+// these directly
+const FRONT_END_MUSIC_FILES: &[&str] = &["Audio/frontend_1.rsf", "Audio/frontend_2.rsf"];
+
+/// Replaces the title screen and file select music, either with another RSF (e.g.
+/// /audio/ending3.rsf) or with DSPs converted to RSF. A stereo DSP is given as its two channels,
+/// "left.dsp|right.dsp", a single DSP is used for both. Files starting with /audio/ come from the
+/// disc, anything else from `extern_assets_dir`. The front end keeps looping at the original
+/// track's loop points, so the replacement should be at least as long.
+fn patch_front_end_music(gc_disc: &mut structs::GcDisc, config: &PatchConfig)
+    -> Result<(), String>
+{
+    let music = [config.main_menu_music.as_ref(), config.file_select_music.as_ref()];
+    for (file_name, music) in FRONT_END_MUSIC_FILES.iter().zip(music.iter()) {
+        let music = match music {
+            Some(music) => music.trim(),
+            None => continue,
+        };
+
+        let mut channels = vec![];
+        for name in music.split('|').map(|name| name.trim()) {
+            let bytes = if name.to_lowercase().starts_with("/audio/") {
+                let disc_name = format!("Audio/{}", &name["/audio/".len()..]);
+                match gc_disc.find_file(&disc_name).and_then(|f| f.file()) {
+                    Some(structs::FstEntryFile::Unknown(reader)) => reader[..].to_vec(),
+                    _ => Err(format!("Failed to find menu music {} on the disc", name))?,
+                }
+            } else {
+                let extern_assets_dir = config.extern_assets_dir.as_ref()
+                    .ok_or_else(|| format!("Menu music '{}' requires externAssetsDir", name))?;
+                fs::read(Path::new(extern_assets_dir).join(name))
+                    .map_err(|e| format!("Failed to read music file {}: {}", name, e))?
+            };
+            channels.push((name, bytes));
+        }
+
+        let rsf = match &channels[..] {
+            [(name, bytes)] if name.to_lowercase().ends_with(".rsf") => bytes.clone(),
+            [_] | [_, _] if channels.iter().all(|(name, _)| name.to_lowercase().ends_with(".dsp")) => {
+                let mut pcm = vec![];
+                for (name, bytes) in channels.iter() {
+                    let (samples, sample_rate) = audio_conversions::decode_dsp(bytes)
+                        .map_err(|e| format!("Menu music {}: {}", name, e))?;
+                    if sample_rate != audio_conversions::RSF_SAMPLE_RATE {
+                        Err(format!(
+                            "Menu music {} is {}Hz, the front end only plays {}Hz",
+                            name, sample_rate, audio_conversions::RSF_SAMPLE_RATE,
+                        ))?
+                    }
+                    pcm.push(samples);
+                }
+                audio_conversions::encode_rsf(&pcm[0], pcm.last().unwrap())
+            },
+            _ => Err(format!(
+                "Menu music '{}' must be an RSF file, a mono DSP or a \"left.dsp|right.dsp\" pair",
+                music,
+            ))?,
+        };
+
+        *gc_disc.find_file_mut(file_name)
+            .and_then(|f| f.file_mut())
+            .ok_or_else(|| format!("Failed to find {}", file_name))?
+            = structs::FstEntryFile::ExternalFile(Box::new(rsf));
+    }
+
+    Ok(())
+}
+
+fn patch_music_override<'r>
+(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    file_name: &str,
+)
+-> Result<(), String>
+{
+    let mrea_id = area.mlvl_area.mrea.to_u32();
+
+    let mut found = false;
+    let scly = area.mrea().scly_section_mut();
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        for obj in layer.objects.as_mut_vec() {
+            let streamed_audio = match obj.property_data.as_streamed_audio_mut() {
+                Some(streamed_audio) => streamed_audio,
+                None => continue,
+            };
+
+            // One-shot music is a jingle, not the room's background music
+            if streamed_audio.is_music == 0 || streamed_audio.oneshot != 0 {
+                continue;
+            }
+
+            streamed_audio.audio_file_name = string_to_cstr(file_name.to_string());
+            found = true;
+        }
+    }
+
+    if !found {
+        println!("Warning, room 0x{:X} has no background music to override", mrea_id);
+    }
+
+    Ok(())
+}
+
 fn patch_remove_ids<'r>
 (
     _ps: &mut PatcherState,
@@ -7313,11 +8821,151 @@ fn patch_remove_ids<'r>
     Ok(())
 }
 
+/// The pickups a crate can drop, and what they look like when dropped
+const CRATE_DROPS: &[(PickupType, PickupModel)] = &[
+    (PickupType::HealthRefill, PickupModel::HealthRefill),
+    (PickupType::Missile, PickupModel::MissileRefill),
+    (PickupType::PowerBomb, PickupModel::PowerBombRefill),
+];
+
+fn patch_crate_loot<'r>
+(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    loot: &CrateLootConfig,
+)
+-> Result<(), String>
+{
+    let weights: Vec<(PickupType, f32)> = loot.weights.iter()
+        .flat_map(|weights| weights.iter())
+        .map(|(name, weight)| {
+            let pickup_type = PickupType::from_str(name);
+            if !CRATE_DROPS.iter().any(|(drop_type, _)| *drop_type == pickup_type) {
+                Err(format!("Crates can't drop {}, only Health Refill, Missile and Power Bomb", name))?
+            }
+            Ok((pickup_type, *weight))
+        })
+        .collect::<Result<_, String>>()?;
+
+    let scly = area.mrea().scly_section_mut();
+    let layers = &mut scly.layers.as_mut_vec();
+
+    // Crates are actors which trigger a pickup generator when destroyed. Generators used by
+    // anything else (e.g. enemies) are left alone.
+    let mut generator_ids = HashSet::new();
+    for layer in layers.iter() {
+        for obj in layer.objects.iter() {
+            if obj.property_data.is_actor() {
+                generator_ids.extend(obj.connections.iter().map(|conn| conn.target_object_id & 0x00FFFFFF));
+            }
+        }
+    }
+
+    // Drops come from a pickup generator, which rolls its frequency and then picks one of the
+    // pickups it's connected to, weighted by their drop rate
+    let mut crate_generators = vec![];
+    let mut drop_ids = HashMap::new();
+    for layer in layers.iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            if !obj.property_data.is_pickup_generator() || !generator_ids.contains(&(obj.instance_id & 0x00FFFFFF)) {
+                continue;
+            }
+            crate_generators.push(obj.instance_id);
+
+            if let Some(drop_chance) = loot.drop_chance {
+                obj.property_data.as_pickup_generator_mut().unwrap().frequency = drop_chance;
+            }
+
+            for conn in obj.connections.iter() {
+                drop_ids.entry(conn.target_object_id & 0x00FFFFFF).or_insert_with(Vec::new).push(obj.instance_id);
+            }
+        }
+    }
+
+    // Types which aren't listed keep their vanilla drop rate
+    let mut connected: HashMap<u32, Vec<PickupType>> = HashMap::new();
+    for layer in layers.iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            let generator_ids = match drop_ids.get(&(obj.instance_id & 0x00FFFFFF)) {
+                Some(generator_ids) => generator_ids,
+                None => continue,
+            };
+
+            if let Some(pickup) = obj.property_data.as_pickup_mut() {
+                let weight = weights.iter().find(|(pickup_type, _)| pickup_type.kind() == pickup.kind);
+                if let Some((pickup_type, weight)) = weight {
+                    pickup.drop_rate = *weight;
+                    for generator_id in generator_ids {
+                        connected.entry(*generator_id).or_default().push(*pickup_type);
+                    }
+                }
+            }
+        }
+    }
+
+    // A crate which can't drop a listed type gets a new drop of that type, like the ones in the
+    // vanilla crates which do drop it
+    let mut new_drops = vec![];
+    for generator_id in crate_generators {
+        for (pickup_type, weight) in weights.iter() {
+            let already_connected = connected.get(&generator_id)
+                .map(|types| types.contains(pickup_type))
+                .unwrap_or(false);
+            if *weight > 0.0 && !already_connected {
+                new_drops.push((generator_id, *pickup_type, *weight));
+            }
+        }
+    }
+    for (pickup_type, model) in CRATE_DROPS.iter() {
+        if !new_drops.iter().any(|(_, new_type, _)| new_type == pickup_type) {
+            continue;
+        }
+        let deps_iter = model.dependencies().iter()
+            .map(|&(file_id, fourcc)| structs::Dependency {
+                asset_id: file_id,
+                asset_type: fourcc,
+            });
+        area.add_dependencies(game_resources, 0, deps_iter);
+    }
+
+    for (generator_id, pickup_type, weight) in new_drops {
+        let pickup_id = area.new_object_id_from_layer_id(0);
+        let model = CRATE_DROPS.iter().find(|(drop_type, _)| *drop_type == pickup_type).unwrap().1;
+
+        let layers = area.mrea().scly_section_mut().layers.as_mut_vec();
+        let generator = layers.iter_mut()
+            .flat_map(|layer| layer.objects.as_mut_vec().iter_mut())
+            .find(|obj| obj.instance_id == generator_id)
+            .unwrap();
+        let position = generator.property_data.as_pickup_generator().unwrap().offset;
+        // Generators pick from the pickups they send Follow to on Zero
+        generator.connections.as_mut_vec().push(structs::Connection {
+            state: structs::ConnectionState::ZERO,
+            message: structs::ConnectionMsg::FOLLOW,
+            target_object_id: pickup_id,
+        });
+
+        layers[0].objects.as_mut_vec().push(structs::SclyObject {
+            instance_id: pickup_id,
+            property_data: structs::Pickup {
+                name: b"my crate drop\0".as_cstr(),
+                position,
+                drop_rate: weight,
+                ..model.pickup_data()
+            }.into(),
+            connections: vec![].into(),
+        });
+    }
+
+    Ok(())
+}
+
 fn patch_set_layers<'r>
 (
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
-    layers: HashMap<u32, bool>,
+    layers: BTreeMap<u32, bool>,
 )
 -> Result<(), String>
 {
@@ -7358,7 +9006,7 @@ fn patch_move_objects<'r>
 (
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
-    layer_objs: HashMap<u32, u32>,
+    layer_objs: BTreeMap<u32, u32>,
 )
 -> Result<(), String>
 {
@@ -8705,6 +10353,9 @@ fn patch_poison_damage_per_sec<'a>(patcher: &mut PrimePatcher<'_, 'a>, poison_da
     }
 }
 
+/// How long the player has to refuse to save a second time when warping has to be confirmed
+const WARP_TO_START_CONFIRM_WINDOW_S: f32 = 10.0;
+
 fn patch_save_station_for_warp_to_start<'r>(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
@@ -8712,6 +10363,9 @@ fn patch_save_station_for_warp_to_start<'r>(
     spawn_room: SpawnRoomData,
     version: Version,
     warp_to_start_delay_s: f32,
+    fade: bool,
+    sound: Option<&PickupSoundEffectConfig>,
+    confirm: bool,
 ) -> Result<(), String>
 {
     let mrea_id = area.mlvl_area.mrea.to_u32().clone();
@@ -8731,11 +10385,31 @@ fn patch_save_station_for_warp_to_start<'r>(
         0,
         iter::once(custom_asset_ids::WARPING_TO_START_DELAY_STRG.into())
     );
+    if confirm {
+        area.add_dependencies(
+            &game_resources,
+            0,
+            iter::once(custom_asset_ids::WARP_TO_START_CONFIRM_STRG.into())
+        );
+    }
+    if let Some(audio_group) = sound.and_then(|sound| sound.audio_group) {
+        let dep = structs::Dependency {
+            asset_id: audio_group,
+            asset_type: FourCC::from_bytes(b"AGSC"),
+        };
+        area.add_dependencies(game_resources, 0, iter::once(dep));
+    }
 
     let world_transporter_id = area.new_object_id_from_layer_name("Default");
     let timer_id = area.new_object_id_from_layer_name("Default");
     let hudmemo_id = area.new_object_id_from_layer_name("Default");
     let player_hint_id = area.new_object_id_from_layer_name("Default");
+    let relay_id = area.new_object_id_from_layer_name("Default");
+    let fade_id = area.new_object_id_from_layer_name("Default");
+    let sound_id = area.new_object_id_from_layer_name("Default");
+    let counter_id = area.new_object_id_from_layer_name("Default");
+    let confirm_timer_id = area.new_object_id_from_layer_name("Default");
+    let confirm_hudmemo_id = area.new_object_id_from_layer_name("Default");
     let scly = area.mrea().scly_section_mut();
     let layer = &mut scly.layers.as_mut_vec()[0];
 
@@ -8778,94 +10452,237 @@ fn patch_save_station_for_warp_to_start<'r>(
             ].into(),
         });
 
-    // Inform the player that they are about to be warped
+    // Inform the player that they are about to be warped
+    layer.objects
+        .as_mut_vec()
+        .push(structs::SclyObject {
+           instance_id: hudmemo_id,
+           property_data: structs::HudMemo {
+                name: b"Warping hudmemo\0".as_cstr(),
+
+                first_message_timer: warp_to_start_delay_s,
+                unknown: 1,
+                memo_type: 0,
+                strg: custom_asset_ids::WARPING_TO_START_DELAY_STRG,
+                active: 1,
+            }.into(),
+           connections: vec![].into(),
+       });
+
+    // Stop the player from moving
+    layer.objects
+        .as_mut_vec()
+        .push(structs::SclyObject {
+           instance_id: player_hint_id,
+           property_data: structs::PlayerHint {
+
+            name: b"Warping playerhint\0".as_cstr(),
+
+            position: [0.0, 0.0, 0.0].into(),
+            rotation: [0.0, 0.0, 0.0].into(),
+
+            unknown0: 1, // active
+
+            inner_struct: structs::PlayerHintStruct {
+                unknowns: [
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    1, // disable
+                    1, // disable
+                    1, // disable
+                    1, // disable
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                ].into(),
+            }.into(),
+
+            unknown1: 10, // priority
+           }.into(),
+           connections: vec![].into(),
+    });
+
+    // Starts the warp once it's been asked for
+    let mut relay_connections = vec![
+        structs::Connection {
+            target_object_id: player_hint_id,
+            state: structs::ConnectionState::ZERO,
+            message: structs::ConnectionMsg::INCREMENT,
+        },
+        structs::Connection {
+            target_object_id: timer_id,
+            state: structs::ConnectionState::ZERO,
+            message: structs::ConnectionMsg::RESET_AND_START,
+        },
+        structs::Connection {
+            target_object_id: hudmemo_id,
+            state: structs::ConnectionState::ZERO,
+            message: structs::ConnectionMsg::SET_TO_ZERO,
+        },
+    ];
+
+    if mrea_id == 0x93668996 { // crater entry point
+        relay_connections.push(
+            structs::Connection {
+                target_object_id: 0x00000093, // memory relay that controls where the player spawns in from
+                state: structs::ConnectionState::ZERO,
+                message: structs::ConnectionMsg::DEACTIVATE,
+            },
+        );
+    }
+
+    // Fade to black for as long as the player waits
+    if fade {
+        layer.objects
+            .as_mut_vec()
+            .push(structs::SclyObject {
+                instance_id: fade_id,
+                property_data: structs::CameraFilterKeyframe {
+                    name: b"Warping fade\0".as_cstr(),
+                    active: 1,
+                    filter_type: 1,
+                    filter_shape: 0,
+                    unknown4: 2,
+                    unknown5: 0,
+                    color: [0.0, 0.0, 0.0, 1.0].into(),
+                    fade_in_time: warp_to_start_delay_s,
+                    fade_out_time: 0.0,
+                    overlay_txtr: 0xFFFFFFFF,
+                }.into(),
+                connections: vec![].into(),
+            });
+        relay_connections.push(
+            structs::Connection {
+                target_object_id: fade_id,
+                state: structs::ConnectionState::ZERO,
+                message: structs::ConnectionMsg::INCREMENT,
+            },
+        );
+    }
+
+    if let Some(sound) = sound {
+        layer.objects
+            .as_mut_vec()
+            .push(pickup_sound(sound_id, [0.0, 0.0, 0.0], sound.id));
+        relay_connections.push(
+            structs::Connection {
+                target_object_id: sound_id,
+                state: structs::ConnectionState::ZERO,
+                message: structs::ConnectionMsg::PLAY,
+            },
+        );
+    }
+
     layer.objects
         .as_mut_vec()
         .push(structs::SclyObject {
-           instance_id: hudmemo_id,
-           property_data: structs::HudMemo {
-                name: b"Warping hudmemo\0".as_cstr(),
-
-                first_message_timer: warp_to_start_delay_s,
-                unknown: 1,
-                memo_type: 0,
-                strg: custom_asset_ids::WARPING_TO_START_DELAY_STRG,
+            instance_id: relay_id,
+            property_data: structs::Relay {
+                name: b"Warping relay\0".as_cstr(),
                 active: 1,
             }.into(),
-           connections: vec![].into(),
-       });
+            connections: relay_connections.into(),
+        });
 
-    // Stop the player from moving
-    layer.objects
-        .as_mut_vec()
-        .push(structs::SclyObject {
-           instance_id: player_hint_id,
-           property_data: structs::PlayerHint {
+    let save_station_connections = if confirm {
+        // The first refusal only asks to refuse again. The count is forgotten after a while, so
+        // an old accidental refusal doesn't make the next one warp.
+        layer.objects
+            .as_mut_vec()
+            .push(structs::SclyObject {
+                instance_id: counter_id,
+                property_data: structs::Counter {
+                    name: b"Warp confirm counter\0".as_cstr(),
+                    start_value: 0,
+                    max_value: 2,
+                    reset_when_zero_max_reached: 1,
+                    active: 1,
+                }.into(),
+                connections: vec![
+                    structs::Connection {
+                        target_object_id: relay_id,
+                        state: structs::ConnectionState::MAX_REACHED,
+                        message: structs::ConnectionMsg::SET_TO_ZERO,
+                    },
+                ].into(),
+            });
 
-            name: b"Warping playerhint\0".as_cstr(),
+        layer.objects
+            .as_mut_vec()
+            .push(structs::SclyObject {
+                instance_id: confirm_timer_id,
+                property_data: structs::Timer {
+                    name: b"Warp confirm window\0".as_cstr(),
 
-            position: [0.0, 0.0, 0.0].into(),
-            rotation: [0.0, 0.0, 0.0].into(),
+                    start_time: WARP_TO_START_CONFIRM_WINDOW_S,
+                    max_random_add: 0.0,
+                    looping: 0,
+                    start_immediately: 0,
+                    active: 1,
+                }.into(),
+                connections: vec![
+                    structs::Connection {
+                        target_object_id: counter_id,
+                        state: structs::ConnectionState::ZERO,
+                        message: structs::ConnectionMsg::RESET,
+                    },
+                ].into(),
+            });
 
-            unknown0: 1, // active
+        layer.objects
+            .as_mut_vec()
+            .push(structs::SclyObject {
+                instance_id: confirm_hudmemo_id,
+                property_data: structs::HudMemo {
+                    name: b"Warp confirm hudmemo\0".as_cstr(),
 
-            inner_struct: structs::PlayerHintStruct {
-                unknowns: [
-                    0,
-                    0,
-                    0,
-                    0,
-                    0,
-                    1, // disable
-                    1, // disable
-                    1, // disable
-                    1, // disable
-                    0,
-                    0,
-                    0,
-                    0,
-                    0,
-                    0,
-                ].into(),
-            }.into(),
+                    first_message_timer: 3.0,
+                    unknown: 1,
+                    memo_type: 0,
+                    strg: custom_asset_ids::WARP_TO_START_CONFIRM_STRG,
+                    active: 1,
+                }.into(),
+                connections: vec![].into(),
+            });
 
-            unknown1: 10, // priority
-           }.into(),
-           connections: vec![].into(),
-    });
+        // The memo comes first so the warping one replaces it on the second refusal
+        vec![
+            structs::Connection {
+                target_object_id: confirm_hudmemo_id,
+                state: structs::ConnectionState::RETREAT,
+                message: structs::ConnectionMsg::SET_TO_ZERO,
+            },
+            structs::Connection {
+                target_object_id: confirm_timer_id,
+                state: structs::ConnectionState::RETREAT,
+                message: structs::ConnectionMsg::RESET_AND_START,
+            },
+            structs::Connection {
+                target_object_id: counter_id,
+                state: structs::ConnectionState::RETREAT,
+                message: structs::ConnectionMsg::INCREMENT,
+            },
+        ]
+    } else {
+        vec![
+            structs::Connection {
+                target_object_id: relay_id,
+                state: structs::ConnectionState::RETREAT,
+                message: structs::ConnectionMsg::SET_TO_ZERO,
+            },
+        ]
+    };
 
     for obj in layer.objects.iter_mut() {
         if let Some(sp_function) = obj.property_data.as_special_function_mut() {
             if sp_function.type_ == 7 { // Is Save Station function
-                obj.connections.as_mut_vec().extend_from_slice(
-                    &vec![
-                        structs::Connection {
-                            target_object_id: player_hint_id,
-                            state: structs::ConnectionState::RETREAT,
-                            message: structs::ConnectionMsg::INCREMENT,
-                        },
-                        structs::Connection {
-                            target_object_id: timer_id,
-                            state: structs::ConnectionState::RETREAT,
-                            message: structs::ConnectionMsg::RESET_AND_START,
-                        },
-                        structs::Connection {
-                            target_object_id: hudmemo_id,
-                            state: structs::ConnectionState::RETREAT,
-                            message: structs::ConnectionMsg::SET_TO_ZERO,
-                        },
-                    ]
-                );
-
-                if mrea_id == 0x93668996 { // crater entry point
-                    obj.connections.as_mut_vec().push(
-                        structs::Connection {
-                            target_object_id: 0x00000093, // memory relay that controls where the player spawns in from
-                            state: structs::ConnectionState::RETREAT,
-                            message: structs::ConnectionMsg::DEACTIVATE,
-                        },
-                    );
-                }
+                obj.connections.as_mut_vec().extend_from_slice(&save_station_connections);
             }
         }
     }
@@ -8924,7 +10741,6 @@ fn patch_main_strg(res: &mut structs::Resource, version: Version, msg: &str) ->
             .nth(37)
             .unwrap();
         *s = "&main-color=#FFFFFF;エクストラ\u{0}".to_string().into();
-        strings_jpn.push(format!("{}\0", msg).into());
     }
 
     let strings = res.kind.as_strg_mut().unwrap()
@@ -8940,7 +10756,11 @@ fn patch_main_strg(res: &mut structs::Resource, version: Version, msg: &str) ->
         .find(|s| *s == "Metroid Fusion Connection Bonuses\u{0}")
         .unwrap();
     *s = "Extras\u{0}".to_string().into();
-    strings.push(format!("{}\0", msg).into());
+
+    // Every language needs the message, they all have to have as many strings
+    for st in res.kind.as_strg_mut().unwrap().string_tables.as_mut_vec().iter_mut() {
+        st.strings.as_mut_vec().push(format!("{}\0", msg).into());
+    }
 
     Ok(())
 }
@@ -9018,7 +10838,7 @@ fn patch_credits(
     res: &mut structs::Resource,
     version: Version,
     config: &PatchConfig,
-    level_data: &HashMap<String, LevelConfig>,
+    level_data: &BTreeMap<String, LevelConfig>,
 )
     -> Result<(), String>
 {
@@ -9084,81 +10904,546 @@ fn patch_credits(
                 if _room_name.len() == 0 {
                     _room_name = "<Not Present>".to_string();
                 }
-
-                _room_name
-            };
-            let pickup_name = pickup_type.name();
-            write!(output, "\n\n{}: {}", pickup_name, room_name).unwrap();
+
+                _room_name
+            };
+            let pickup_name = pickup_type.name();
+            write!(output, "\n\n{}: {}", pickup_name, room_name).unwrap();
+        }
+    }
+    output = format!("{}{}", output, "\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\0");
+
+    // Every language needs the credits, they all have to have as many strings
+    let string_tables = res.kind
+        .as_strg_mut()
+        .unwrap()
+        .string_tables
+        .as_mut_vec();
+    for st in string_tables.iter_mut() {
+        st.strings.as_mut_vec().push(output.clone().into());
+    }
+
+    let strings = string_tables
+        .iter_mut()
+        .find(|table| table.lang == b"ENGL".into())
+        .unwrap()
+        .strings
+        .as_mut_vec();
+
+    /* We are who we choose to be */
+    /* https://mobile.twitter.com/ZoidCTF/status/1542699504041750528 */
+    strings[0] = strings[0]
+        .clone()
+        .into_string()
+        .replace("David 'Zoid' Kirsch", "Zoid Kirsch")
+        .to_owned()
+        .into();
+
+    Ok(())
+}
+
+fn patch_completion_screen(
+    res: &mut structs::Resource,
+    mut results_string: String,
+    language: Option<Language>,
+)
+    -> Result<(), String>
+{
+    results_string += "\nPercentage Complete\0";
+
+    let strg = res.kind.as_strg_mut().unwrap();
+    for st in strg.string_tables.as_mut_vec().iter_mut() {
+        if !replaces_strg_lang(language, st.lang) {
+            continue;
+        }
+        let strings = st.strings.as_mut_vec();
+        strings[1] = results_string.to_owned().into();
+    }
+    Ok(())
+}
+
+/// Indices of the item descriptions in STRG_PauseScreen, as listed by the inventory screen
+fn inventory_description_index(pickup_type: PickupType) -> Option<usize>
+{
+    let idx = match pickup_type {
+        PickupType::PowerBeam      => 0x46,
+        PickupType::WaveBeam       => 0x47,
+        PickupType::IceBeam        => 0x48,
+        PickupType::PlasmaBeam     => 0x49,
+        PickupType::PowerBomb      => 0x4A,
+        PickupType::BoostBall      => 0x4B,
+        PickupType::SpiderBall     => 0x4D,
+        PickupType::MorphBallBomb  => 0x4E,
+        PickupType::MorphBall      => 0x4F,
+        PickupType::PowerSuit      => 0x52,
+        PickupType::VariaSuit      => 0x53,
+        PickupType::GravitySuit    => 0x54,
+        PickupType::PhazonSuit     => 0x55,
+        PickupType::EnergyTank     => 0x56,
+        PickupType::CombatVisor    => 0x57,
+        PickupType::ScanVisor      => 0x58,
+        PickupType::XRayVisor      => 0x59,
+        PickupType::ThermalVisor   => 0x5A,
+        PickupType::SpaceJumpBoots => 0x5B,
+        PickupType::GrappleBeam    => 0x5C,
+        PickupType::Missile        => 0x5D,
+        PickupType::ChargeBeam     => 0x5E,
+        _ => return None,
+    };
+    Some(idx)
+}
+
+/// Which requirements each door color on the map stands for, as several door types share a
+/// color. Only colors used by the seed's door configuration are listed.
+fn door_legend(level_data: &BTreeMap<String, LevelConfig>) -> Vec<String>
+{
+    let mut requirements: BTreeMap<u32, BTreeSet<&'static str>> = BTreeMap::new();
+    let door_configs = level_data.values()
+        .flat_map(|level| level.rooms.values())
+        .flat_map(|room| room.doors.iter().flat_map(|doors| doors.values()));
+    for door_config in door_configs {
+        let door_type = door_config.shield_type.as_ref()
+            .and_then(|name| DoorType::from_string(name.to_string()));
+        let blast_shield_type = door_config.blast_shield_type.as_ref()
+            .and_then(|name| BlastShieldType::from_str(name))
+            .filter(|blast_shield| *blast_shield != BlastShieldType::None && *blast_shield != BlastShieldType::Unchanged);
+
+        // The same type the door's map icon is given
+        let door_type = match (door_type, blast_shield_type) {
+            (Some(door_type), _) => door_type,
+            (None, Some(blast_shield)) => blast_shield.door_type_counterpart(),
+            (None, None) => continue,
+        };
+        requirements.entry(door_type.map_object_type())
+            .or_insert_with(BTreeSet::new)
+            .insert(door_type.requirement());
+    }
+
+    requirements.into_iter()
+        .map(|(map_object_type, mut requirements)| {
+            // Doors which weren't changed keep their vanilla color
+            let (color, vanilla) = match map_object_type {
+                x if x == structs::MapaObjectType::DoorNormal as u32 => ("Blue", Some(DoorType::Blue)),
+                x if x == structs::MapaObjectType::DoorWave as u32 => ("Purple", Some(DoorType::Purple)),
+                x if x == structs::MapaObjectType::DoorIce as u32 => ("White", Some(DoorType::White)),
+                x if x == structs::MapaObjectType::DoorPlasma as u32 => ("Red", Some(DoorType::Red)),
+                _ => ("Other", None),
+            };
+            if let Some(vanilla) = vanilla {
+                requirements.insert(vanilla.requirement());
+            }
+            format!("{}: {}", color, requirements.into_iter().collect::<Vec<_>>().join(", "))
+        })
+        .collect()
+}
+
+/// Keys are item names, or the index of a string in STRG_PauseScreen for anything else
+fn patch_inventory_text(
+    res: &mut structs::Resource,
+    inventory_text: &BTreeMap<String, LocalizedText>,
+) -> Result<(), String>
+{
+    let mut replacements = Vec::new();
+    for (key, text) in inventory_text.iter() {
+        let idx = match key.trim().parse::<usize>() {
+            Ok(idx) => idx,
+            Err(_) => {
+                let pickup_type = PickupType::from_str(key);
+                inventory_description_index(pickup_type)
+                    .ok_or_else(|| format!("{} has no description in the inventory screen", pickup_type.name()))?
+            },
+        };
+        replacements.push((key, idx, text));
+    }
+
+    let strg = res.kind.as_strg_mut().unwrap();
+    for st in strg.string_tables.as_mut_vec().iter_mut() {
+        let lang = String::from_utf8_lossy(st.lang.as_bytes()).to_string();
+        let strings = st.strings.as_mut_vec();
+        for (key, idx, text) in replacements.iter() {
+            let text = match text.get(&lang) {
+                Some(text) => text,
+                None => continue,
+            };
+            let string = strings.get_mut(*idx)
+                .ok_or_else(|| format!("inventoryText '{}' is out of range", key))?;
+            *string = format!("{}\0", text).into();
+        }
+    }
+
+    Ok(())
+}
+
+/// Appends a list to the end of the Power Suit's inventory description
+fn patch_pause_screen_list(
+    res: &mut structs::Resource,
+    heading: &str,
+    items: &[String],
+    language: Option<Language>,
+) -> Result<(), String>
+{
+    let idx = inventory_description_index(PickupType::PowerSuit).unwrap();
+    let mut text = format!("\n\n{}:", heading);
+    for item in items {
+        text += &format!("\n- {}", item);
+    }
+
+    let strg = res.kind.as_strg_mut().unwrap();
+    for st in strg.string_tables.as_mut_vec().iter_mut() {
+        if !replaces_strg_lang(language, st.lang) {
+            continue;
+        }
+        let string = &mut st.strings.as_mut_vec()[idx];
+        *string = format!("{}{}\0", string.clone().into_string().trim_end_matches('\0'), text).into();
+    }
+
+    Ok(())
+}
+
+fn patch_start_button_strg(
+    res: &mut structs::Resource,
+    text: &str,
+    language: Option<Language>,
+) -> Result<(), String>
+{
+    let strg = res.kind.as_strg_mut().unwrap();
+
+    for st in strg.string_tables.as_mut_vec().iter_mut() {
+        if !replaces_strg_lang(language, st.lang) {
+            continue;
+        }
+        let strings = st.strings.as_mut_vec();
+        strings[67] = text.to_owned().into();
+    }
+
+    Ok(())
+}
+
+/// Texture overrides can be given by id, e.g. "0x1A2B3C4D" or "1A2B3C4D.TXTR"
+fn texture_override_id(texture: &str) -> Option<u32>
+{
+    let id = texture.strip_prefix("0x").unwrap_or(texture);
+    let id = if id.to_uppercase().ends_with(".TXTR") { &id[..id.len() - 5] } else { id };
+    if id.len() != 8 {
+        return None;
+    }
+    u32::from_str_radix(id, 16).ok()
+}
+
+/// Looks up a resource in the name tables of `paks`
+fn named_resource_id(gc_disc: &structs::GcDisc, paks: &[&str], name: &str, fourcc: FourCC)
+    -> Option<u32>
+{
+    for pak_name in paks {
+        let pak = match gc_disc.find_file(pak_name)?.file()? {
+            structs::FstEntryFile::Pak(pak) => pak.clone(),
+            structs::FstEntryFile::Unknown(reader) => reader.clone().read(()),
+            _ => continue,
+        };
+        let id = pak.named_resources.iter()
+            .find(|res| res.fourcc == fourcc && res.name.iter().take_while(|c| *c != 0).eq(name.bytes()))
+            .map(|res| res.file_id);
+        if id.is_some() {
+            return id;
+        }
+    }
+    None
+}
+
+/// Combines a suit's hue rotation with its color adjustment. Returns `None` if the colors are
+/// left unchanged.
+fn suit_color_matrix(deg: Option<i16>, adjustment: Option<&ColorAdjustment>) -> Option<[f32; 9]>
+{
+    let default = ColorAdjustment::default();
+    let adjustment = adjustment.unwrap_or(&default);
+
+    let hue_deg = (deg.unwrap_or(0) as i32 + adjustment.hue_deg.unwrap_or(0) as i32) % 360;
+    let saturation = adjustment.saturation.unwrap_or(1.0);
+    let value = adjustment.value.unwrap_or(1.0);
+    let tint = adjustment.tint.unwrap_or([255, 255, 255]);
+
+    if hue_deg == 0 && saturation == 1.0 && value == 1.0 && tint == [255, 255, 255] {
+        return None;
+    }
+
+    let tint = [tint[0] as f32 / 255.0, tint[1] as f32 / 255.0, tint[2] as f32 / 255.0];
+    Some(color_adjust_matrix(hue_deg as f32, saturation, value, tint))
+}
+
+// Root effects of each beam, everything they spawn is recolored along with them
+const BEAM_SHOT_EFFECTS: [&[ResourceInfo]; 4] = [
+    &[
+        resource_info!("PowerBeam.WPSC"),
+        resource_info!("PowerBall.WPSC"),
+        resource_info!("PowerMuzzle.PART"),
+        resource_info!("PowerAuxMuzzle.PART"),
+        resource_info!("Power2nd_1.PART"),
+    ],
+    &[
+        resource_info!("WaveBeam.WPSC"),
+        resource_info!("WaveBall.WPSC"),
+        resource_info!("WaveAuxMuzzle.PART"),
+        resource_info!("Wave2nd_1.ELSC"),
+        resource_info!("Wave2nd_2.ELSC"),
+        resource_info!("Wave2nd_3.PART"),
+    ],
+    &[
+        resource_info!("IceBeam.WPSC"),
+        resource_info!("IceBall.WPSC"),
+        resource_info!("IceMuzzle.PART"),
+        resource_info!("IceAuxMuzzle.PART"),
+        resource_info!("Ice2nd_1.PART"),
+        resource_info!("Ice2nd_2.PART"),
+    ],
+    &[
+        resource_info!("PlasmaBeam.WPSC"),
+        resource_info!("PlasmaBall.WPSC"),
+        resource_info!("PlasmaMuzzle.PART"),
+        resource_info!("Plasma2nd_1.PART"),
+    ],
+];
+
+const BEAM_CHARGE_EFFECTS: [&[ResourceInfo]; 4] = [
+    &[resource_info!("PowerCharge.PART")],
+    &[resource_info!("WaveCharge.PART")],
+    &[resource_info!("IceCharge.PART")],
+    &[resource_info!("PlasmaCharge.PART")],
+];
+
+const PARTICLE_FOURCCS: [&[u8; 4]; 4] = [b"PART", b"SWHC", b"ELSC", b"WPSC"];
+
+/// Collects `roots` and every particle effect they spawn. Effects reference each other with a
+/// `CNST` tag followed by the id, anything which looks like that and is the id of an effect in
+/// the same PAK is followed.
+fn particle_effect_tree(
+    particles: &HashMap<u32, (FourCC, Vec<u8>)>,
+    roots: &[ResourceInfo],
+) -> Vec<(u32, FourCC)>
+{
+    let mut tree: Vec<(u32, FourCC)> = roots.iter().map(|res| (res.res_id, res.fourcc)).collect();
+    let mut i = 0;
+    while i < tree.len() {
+        if let Some((_, bytes)) = particles.get(&tree[i].0) {
+            for window in bytes.windows(8) {
+                if &window[..4] != b"CNST" {
+                    continue;
+                }
+                let id = u32::from_be_bytes([window[4], window[5], window[6], window[7]]);
+                if let Some((fourcc, _)) = particles.get(&id) {
+                    if !tree.iter().any(|(other, _)| *other == id) {
+                        tree.push((id, *fourcc));
+                    }
+                }
+            }
         }
+        i += 1;
     }
-    output = format!("{}{}", output, "\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\0");
-    if version == Version::NtscJ {
-        let output_jpn = format!("{}", output);
-        res.kind.as_strg_mut().unwrap().string_tables
-            .as_mut_vec()
-            .iter_mut()
-            .find(|table| table.lang == b"JAPN".into())
-            .unwrap()
-            .strings
-            .as_mut_vec()
-            .push(output_jpn.into());
-    }
+    tree
+}
 
-    let string_tables = res.kind
-        .as_strg_mut()
-        .unwrap()
-        .string_tables
-        .as_mut_vec();
+/// Applies a color matrix to the constant and keyframed colors (`COLR` and `LCLR`) of a particle
+/// effect. Colors which are computed at runtime are left alone.
+fn recolor_particle_effect(bytes: &mut [u8], matrix: [f32; 9])
+{
+    fn read_f32(bytes: &[u8], offset: usize) -> f32
+    {
+        f32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
 
-    let strings = string_tables
-        .iter_mut()
-        .find(|table| table.lang == b"ENGL".into())
-        .unwrap()
-        .strings
-        .as_mut_vec();
+    fn recolor(bytes: &mut [u8], offsets: [usize; 3], matrix: [f32; 9])
+    {
+        let rgb = offsets.map(|offset| read_f32(bytes, offset));
+        for (row, offset) in offsets.iter().enumerate() {
+            let value = matrix[row * 3] * rgb[0] + matrix[row * 3 + 1] * rgb[1] + matrix[row * 3 + 2] * rgb[2];
+            bytes[*offset..*offset + 4].copy_from_slice(&value.max(0.0).to_be_bytes());
+        }
+    }
 
-    strings.push(output.into());
+    let mut i = 0;
+    while i + 8 <= bytes.len() {
+        let tag = &bytes[i..i + 4];
+        if tag != b"COLR" && tag != b"LCLR" {
+            i += 1;
+            continue;
+        }
 
-    /* We are who we choose to be */
-    /* https://mobile.twitter.com/ZoidCTF/status/1542699504041750528 */
-    strings[0] = strings[0]
-        .clone()
-        .into_string()
-        .replace("David 'Zoid' Kirsch", "Zoid Kirsch")
-        .to_owned()
-        .into();
+        let element = i + 4;
+        match &bytes[element..element + 4] {
+            // CNST followed by 4 constant reals
+            b"CNST" if element + 40 <= bytes.len() &&
+                (0..4).all(|c| &bytes[element + 4 + c * 8..element + 8 + c * 8] == b"CNST") => {
+                let offsets = [element + 8, element + 16, element + 24];
+                recolor(bytes, offsets, matrix);
+                i = element + 40;
+            },
+            // Percent, unknown, loop, unknown, loop end, loop start, then the RGBA keys
+            b"KEYE" | b"KEYP" if element + 26 <= bytes.len() => {
+                let count_offset = element + 4 + 18;
+                let count = u32::from_be_bytes(bytes[count_offset..count_offset + 4].try_into().unwrap()) as usize;
+                let keys = count_offset + 4;
+                if keys + count * 16 > bytes.len() {
+                    i += 1;
+                    continue;
+                }
+                for key in 0..count {
+                    let key = keys + key * 16;
+                    recolor(bytes, [key, key + 4, key + 8], matrix);
+                }
+                i = keys + count * 16;
+            },
+            _ => i += 1,
+        }
+    }
+}
 
-    Ok(())
+/// Lists the textures used by one of the vanilla models
+fn cmdl_texture_ids(gc_disc: &structs::GcDisc, cmdl: &ResourceInfo) -> Result<Vec<u32>, String>
+{
+    let pak_name = std::str::from_utf8(cmdl.paks[0]).unwrap();
+    let pak: structs::Pak = match gc_disc.find_file(pak_name).and_then(|f| f.file()) {
+        Some(structs::FstEntryFile::Pak(pak)) => pak.clone(),
+        Some(structs::FstEntryFile::Unknown(reader)) => reader.clone().read(()),
+        _ => Err(format!("Failed to read {}", pak_name))?,
+    };
+    let res = pak.resources.iter()
+        .find(|res| res.file_id == cmdl.res_id && res.fourcc() == cmdl.fourcc)
+        .ok_or_else(|| format!("Failed to find {} in {}", cmdl.long_name, pak_name))?;
+
+    let data = crate::ResourceData::new(&res).decompress().into_owned();
+    let model: structs::Cmdl = Reader::new(&data[..]).read(());
+
+    let mut ids = Vec::new();
+    for material_set in model.material_sets.iter() {
+        for id in material_set.texture_ids.iter() {
+            let id = id.to_u32();
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    Ok(ids)
 }
 
-fn patch_completion_screen(
+/// Applies a color matrix to every mipmap of a CMPR texture. Each recolored mipmap is cached in
+/// `cache_subdir`, so it only has to be recompressed the first time a given matrix is used.
+fn recolor_suit_texture(
     res: &mut structs::Resource,
-    mut results_string: String,
-)
-    -> Result<(), String>
+    cache_subdir: &str,
+    matrix: [f32; 9],
+    complained: &mut bool,
+) -> Result<(), String>
 {
-    results_string += "\nPercentage Complete\0";
+    let res_data;
+    let data;
+    let mut txtr: structs::Txtr = match &res.kind {
+        structs::ResourceKind::Unknown(_, _) => {
+            res_data = crate::ResourceData::new(res);
+            data = res_data.decompress().into_owned();
+            let mut reader = Reader::new(&data[..]);
+            reader.read(())
+        },
+        structs::ResourceKind::External(_, _) => {
+            res_data = crate::ResourceData::new_external(res);
+            data = res_data.decompress().into_owned();
+            let mut reader = Reader::new(&data[..]);
+            reader.read(())
+        },
+        _ => panic!("Unsupported resource kind for recoloring."),
+    };
+    if !matches!(txtr.format, structs::TxtrFormat::Cmpr) {
+        // Only the arm cannons can have these, and it's just the odd ramp or mask texture
+        return Ok(());
+    }
 
-    let strg = res.kind.as_strg_mut().unwrap();
-    for st in strg.string_tables.as_mut_vec().iter_mut() {
-        let strings = st.strings.as_mut_vec();
-        strings[1] = results_string.to_owned().into();
+    let mut w = txtr.width as usize;
+    let mut h = txtr.height as usize;
+    for mipmap in txtr.pixel_data.as_mut_vec() {
+        let hash: u64 = calculate_hash(&mipmap.as_mut_vec().to_vec());
+        // Read file contents to RAM
+        let filename = format!("{}/{}", cache_subdir, hash);
+        let file_ok = File::open(&filename).is_ok();
+        let file = File::open(&filename).ok();
+        if file_ok && file.is_some() {
+            let metadata = fs::metadata(&filename).expect("unable to read metadata");
+            let mut bytes = vec![0; metadata.len() as usize];
+            file.unwrap().read(&mut bytes)
+                .map_err(|e| format!("Failed to read cache file: {}", e))?;
+            *mipmap.as_mut_vec() = bytes;
+        }
+        else
+        {
+            let mut decompressed_bytes = vec![0u8; w * h * 4];
+            cmpr_decompress(&mipmap.as_mut_vec()[..], h, w, &mut decompressed_bytes[..]);
+            huerotate_in_place(&mut decompressed_bytes[..], w, h, matrix);
+            cmpr_compress(&(decompressed_bytes[..]), w, h, &mut mipmap.as_mut_vec()[..]);
+            match File::create(filename) {
+                Ok(mut file) => {
+                    match file.write_all(&mipmap.as_mut_vec().to_vec()) {
+                        Ok(()) => {},
+                        Err(error) => {
+                            if !*complained {
+                                println!("Failed to write cache file for optimal suit rotation: {}", error);
+                                *complained = true;
+                            }
+                        },
+                    }
+                },
+                Err(error) => {
+                    if !*complained {
+                        println!("Failed to create cache file for optimal suit rotation: {}", error);
+                        *complained = true;
+                    }
+                },
+            }
+        }
+        w = w / 2;
+        h = h / 2;
     }
+    let mut bytes = vec![];
+    txtr.write_to(&mut bytes).unwrap();
+    res.kind = structs::ResourceKind::External(bytes, b"TXTR".into());
+    res.compressed = false;
     Ok(())
 }
 
-fn patch_start_button_strg(
+/// Replaces a texture with an image, keeping the texture's format and mipmap count where
+/// possible so materials using it still look right
+fn patch_texture_override(
     res: &mut structs::Resource,
-    text: &str,
+    pixels: &[u8],
+    width: usize,
+    height: usize,
 ) -> Result<(), String>
 {
-    let strg = res.kind.as_strg_mut().unwrap();
+    let res_data;
+    let data;
+    let txtr: structs::Txtr = match &res.kind {
+        structs::ResourceKind::Unknown(_, _) => {
+            res_data = crate::ResourceData::new(res);
+            data = res_data.decompress().into_owned();
+            Reader::new(&data[..]).read(())
+        },
+        structs::ResourceKind::External(_, _) => {
+            res_data = crate::ResourceData::new_external(res);
+            data = res_data.decompress().into_owned();
+            Reader::new(&data[..]).read(())
+        },
+        _ => panic!("Unsupported resource kind for texture overrides."),
+    };
 
-    for st in strg.string_tables.as_mut_vec().iter_mut() {
-        let strings = st.strings.as_mut_vec();
-        strings[67] = text.to_owned().into();
-    }
+    // Paletted textures are replaced by the closest direct color format
+    let format = match txtr.format {
+        structs::TxtrFormat::C4(structs::TxtrPaletteFormat::Ia8, _) |
+        structs::TxtrFormat::C8(structs::TxtrPaletteFormat::Ia8, _) => structs::TxtrFormat::Ia8,
+        structs::TxtrFormat::C4(_, _) | structs::TxtrFormat::C8(_, _) => structs::TxtrFormat::Rgb5A3,
+        format => format,
+    };
+    let mipmap_count = txtr.pixel_data.iter().count();
 
+    let bytes = rgba8_to_txtr_with_format(pixels, width, height, format, mipmap_count)
+        .map_err(|e| format!("Failed to override texture 0x{:08X}: {}", res.file_id, e))?;
+    res.kind = structs::ResourceKind::External(bytes, b"TXTR".into());
+    res.compressed = false;
     Ok(())
 }
 
@@ -9297,17 +11582,36 @@ include!("../compile_to_ppc/patches_config.rs");
 fn create_rel_config_file(
     spawn_room: SpawnRoomData,
     quickplay: bool,
+    run_time_limit: Option<f32>,
 ) -> Vec<u8>
 {
     let config = RelConfig {
         quickplay_mlvl: if quickplay { spawn_room.mlvl } else { 0xFFFFFFFF },
         quickplay_mrea: if quickplay { spawn_room.mrea } else { 0xFFFFFFFF },
+        run_time_limit: run_time_limit.unwrap_or(0.0),
     };
     let mut buf = vec![0; mem::size_of::<RelConfig>()];
     ssmarshal::serialize(&mut buf, &config).unwrap();
     buf
 }
 
+const BUILD_INFO_PREFIX: &[u8] = b"!#$MetroidBuildInfo!#$";
+
+/// Where the build info string is in each revision's DOL,
+/// e.g. "!#$MetroidBuildInfo!#$ Build v1.088 10/29/2002 2:21:25"
+fn build_info_address(version: Version) -> Option<u32>
+{
+    match version {
+        Version::NtscU0_00    => Some(0x803cc588),
+        Version::NtscU0_01    => Some(0x803cc768),
+        Version::NtscU0_02    => Some(0x803cd648),
+        Version::NtscK        => Some(0x803cc688),
+        Version::NtscJ        => Some(0x803b86cc),
+        Version::Pal          => Some(0x803b6924),
+        _ => None,
+    }
+}
+
 fn patch_dol<'r>(
     file: &mut structs::FstEntryFile,
     spawn_room: SpawnRoomData,
@@ -9358,20 +11662,37 @@ fn patch_dol<'r>(
 
     let mut dol_patcher = DolPatcher::new(reader);
 
+    // The revision comes from the disc header, make sure the DOL agrees before patching it at
+    // that revision's addresses
+    let has_build_info = |version: Version| {
+        build_info_address(version)
+            .and_then(|addr| dol_patcher.original_bytes(addr, BUILD_INFO_PREFIX.len() as u32))
+            .map(|bytes| bytes == BUILD_INFO_PREFIX)
+            .unwrap_or(false)
+    };
+    if !has_build_info(version) {
+        let dol_version = [
+            Version::NtscU0_00, Version::NtscU0_01, Version::NtscU0_02,
+            Version::NtscK, Version::NtscJ, Version::Pal,
+        ].into_iter().find(|version| has_build_info(*version));
+        match dol_version {
+            Some(dol_version) => Err(format!(
+                "The disc header is from version {} of the game, but its main.dol is from version {}. Please use an unmodified dump.",
+                version, dol_version,
+            ))?,
+            None => Err(format!(
+                "The main.dol doesn't match version {} of the game (or any other). Please use an unmodified dump.",
+                version,
+            ))?,
+        }
+    }
+
     if uuid.is_some()
     {
         let uuid = uuid.unwrap();
 
-        // e.g. "!#$MetroidBuildInfo!#$ Build v1.088 10/29/2002 2:21:25"
-        let build_info_address: u32 = match version {
-            Version::NtscU0_00    => 0x803cc588,
-            Version::NtscU0_01    => 0x803cc768,
-            Version::NtscU0_02    => 0x803cd648,
-            Version::NtscK        => 0x803cc688,
-            Version::NtscJ        => 0x803b86cc,
-            Version::Pal          => 0x803b6924,
-            _ => panic!("This version of the game does not support etching a UUID into the dol"),
-        };
+        let build_info_address = build_info_address(version)
+            .expect("This version of the game does not support etching a UUID into the dol");
 
         // Leave the start alone for easier pattern matching
         let build_info_address = build_info_address + "!#$Met".len() as u32;
@@ -9463,8 +11784,9 @@ fn patch_dol<'r>(
         dol_patcher.patch(symbol_addr!("BallSwooshColorsJaggy"  , version), colors.clone().into())?;
         dol_patcher.patch(symbol_addr!("BallSwooshColorsCharged", version), colors.clone().into())?;
         dol_patcher.patch(symbol_addr!("BallGlowColors"         , version), colors.clone().into())?;
-    } else if config.suit_colors.is_some() {
-        let suit_colors = config.suit_colors.as_ref().unwrap();
+    } else if config.suit_colors.is_some() || config.ball_colors.is_some() {
+        let default_suit_colors = SuitColors::default();
+        let suit_colors = config.suit_colors.as_ref().unwrap_or(&default_suit_colors);
         let mut colors: Vec<Vec<u8>> = Vec::new();
         colors.push(vec![0xc2, 0x7e, 0x10, 0x66, 0xc4, 0xff, 0x60, 0xff, 0x90, 0x33, 0x33, 0xff, 0xff, 0x80, 0x80, 0x00, 0x9d, 0xb6, 0xd3, 0xf1, 0x00, 0x60, 0x33, 0xff, 0xfb, 0x98, 0x21]); // skBallInnerGlowColors
         colors.push(vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xd5, 0x19, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]); // BallAuxGlowColors
@@ -9474,25 +11796,24 @@ fn patch_dol<'r>(
         colors.push(vec![0xFF, 0xE6, 0x00, 0xFF, 0xE6, 0x00, 0xFF, 0xE6, 0x00, 0xFF, 0xE6, 0x00, 0xFF, 0x80, 0x20, 0xFF, 0xE6, 0x00, 0xFF, 0xE6, 0x00, 0xFF, 0xE6, 0x00, 0xFF, 0xE6, 0x00]); // BallSwooshColorsCharged
         colors.push(vec![0xc2, 0x7e, 0x10, 0x66, 0xc4, 0xff, 0x6c, 0xff, 0x61, 0x33, 0x33, 0xff, 0xff, 0x20, 0x20, 0x00, 0x9d, 0xb6, 0xd3, 0xf1, 0x00, 0xa6, 0x86, 0xd8, 0xfb, 0x98, 0x21]); // BallGlowColors
 
+        let power = suit_color_matrix(suit_colors.power_deg, suit_colors.power.as_ref());
+        let varia = suit_color_matrix(suit_colors.varia_deg, suit_colors.varia.as_ref());
+        let gravity = suit_color_matrix(suit_colors.gravity_deg, suit_colors.gravity.as_ref());
+        let phazon = suit_color_matrix(suit_colors.phazon_deg, suit_colors.phazon.as_ref());
+
         for i in 0..colors.len() {
             for j in 0..9 {
-                let angle = if vec![0].contains(&j) && suit_colors.power_deg.is_some() {
-                    suit_colors.power_deg.clone().unwrap()
-                } else if vec![1, 2].contains(&j)  && suit_colors.varia_deg.is_some() {
-                    suit_colors.varia_deg.clone().unwrap()
-                } else if vec![3].contains(&j)  && suit_colors.gravity_deg.is_some() {
-                    suit_colors.gravity_deg.clone().unwrap()
-                } else if vec![4].contains(&j)  && suit_colors.phazon_deg.is_some() {
-                    suit_colors.phazon_deg.clone().unwrap()
-                } else {
-                    0
+                let matrix = match j {
+                    0 => power,
+                    1 | 2 => varia,
+                    3 => gravity,
+                    4 => phazon,
+                    _ => None,
+                };
+                let matrix = match matrix {
+                    Some(matrix) => matrix,
+                    None => continue,
                 };
-
-                let angle = angle % 360;
-                if angle == 0 {
-                    continue;
-                }
-                let matrix = huerotate_matrix(angle as f32);
 
                 let r_idx = j*3;
                 let g_idx = r_idx+1;
@@ -9505,6 +11826,26 @@ fn patch_dol<'r>(
             }
         }
 
+        // Explicit ball colors win over the suits' rotation and apply to every suit
+        if let Some(ball_colors) = config.ball_colors.as_ref() {
+            let overrides = [
+                (ball_colors.glow, &[0, 2, 6][..]), // inner glow, morph flash, glow
+                (ball_colors.boost, &[3, 4][..]), // swoosh, jaggy swoosh
+                (ball_colors.boost_charged, &[5][..]),
+            ];
+            for (color, arrays) in overrides.iter() {
+                let color = match color {
+                    Some(color) => color,
+                    None => continue,
+                };
+                for i in arrays.iter() {
+                    for rgb in colors[*i].chunks_mut(3) {
+                        rgb.copy_from_slice(&color[..]);
+                    }
+                }
+            }
+        }
+
         let mut i = 0;
         dol_patcher.patch(symbol_addr!("skBallInnerGlowColors"  , version), colors[i].clone().into())?; i+=1;
         dol_patcher.patch(symbol_addr!("BallAuxGlowColors"      , version), colors[i].clone().into())?; i+=1;
@@ -10005,7 +12346,16 @@ fn patch_dol<'r>(
         dol_patcher.ppcasm_patch(&heat_damage_patch)?;
     }
 
-    if config.staggered_suit_damage {
+    if config.staggered_suit_damage || config.suit_damage_reduction.is_some() {
+        // Damage reduction by the number of suits collected, instead of by the best suit
+        let reduction = config.suit_damage_reduction.unwrap_or([0.0, 10.0, 20.0, 50.0]);
+        let (reduction0, reduction1, reduction2, reduction3) = (
+            reduction[0] / 100.0,
+            reduction[1] / 100.0,
+            reduction[2] / 100.0,
+            reduction[3] / 100.0,
+        );
+
         let (patch_offset, jump_offset) = if version == Version::Pal || version == Version::NtscJ {
             (0x11c, 0x1b8)
         } else {
@@ -10026,10 +12376,10 @@ fn patch_dol<'r>(
                 lfsx    f0, r4, r6;
                 b       { symbol_addr!("ApplyLocalDamage__13CStateManagerFRC9CVector3fRC9CVector3fR6CActorfRC11CWeaponMode", version) + jump_offset };
             data:
-                .float 0.0;
-                .float 0.1;
-                .float 0.2;
-                .float 0.5;
+                .float reduction0;
+                .float reduction1;
+                .float reduction2;
+                .float reduction3;
         });
         dol_patcher.ppcasm_patch(&staggered_suit_damage_patch)?;
     }
@@ -10081,7 +12431,8 @@ fn patch_dol<'r>(
         let mut reverse_y_axis    : bool = false;
         let mut rumble            : bool = true ;
         let mut swap_beam_controls: bool = false;
-        let hints                 : bool = false;
+        // The hint system is only used to mark objectives on the map
+        let hints                 : bool = !config.objective_rooms.is_empty() || config.artifact_objectives;
 
         /* Update with user-defined defaults */
         if config.default_game_options.is_some()
@@ -10422,6 +12773,62 @@ fn patch_dol<'r>(
         new_text_section.extend(warp_to_start_patch.encoded_bytes());
     }
 
+    if let Some(costs) = config.combo_ammo_costs {
+        if ![Version::NtscU0_00, Version::NtscU0_02, Version::Pal].contains(&version) {
+            Err(format!("beamCombos costs aren't supported on {}", version))?
+        }
+
+        let combo_ammo_cost_patch = ppcasm!(symbol_addr!("GetMissileCostForAltAttack__12CPlayerStateCFv", version), {
+                b         { new_text_section_end };
+        });
+        dol_patcher.ppcasm_patch(&combo_ammo_cost_patch)?;
+
+        let combo_ammo_cost_func = ppcasm!(new_text_section_end, {
+                lwz       r4, 0x8(r3); // current beam
+                rlwinm    r4, r4, 2, 0, 29;
+                lis       r5, data@h;
+                addi      r5, r5, data@l;
+                lwzx      r3, r5, r4;
+                blr;
+            data:
+                .long costs[0];
+                .long costs[1];
+                .long costs[2];
+                .long costs[3];
+                .long costs[4];
+        });
+        new_text_section_end = new_text_section_end + combo_ammo_cost_func.encoded_bytes().len() as u32;
+        new_text_section.extend(combo_ammo_cost_func.encoded_bytes());
+    }
+
+    if let Some(periods) = config.combo_ammo_drain_periods {
+        if ![Version::NtscU0_00, Version::NtscU0_02, Version::Pal].contains(&version) {
+            Err(format!("beamCombos drain periods aren't supported on {}", version))?
+        }
+
+        let combo_ammo_period_patch = ppcasm!(symbol_addr!("GetComboFireAmmoPeriod__12CPlayerStateCFv", version), {
+                b         { new_text_section_end };
+        });
+        dol_patcher.ppcasm_patch(&combo_ammo_period_patch)?;
+
+        let combo_ammo_period_func = ppcasm!(new_text_section_end, {
+                lwz       r4, 0x8(r3); // current beam
+                rlwinm    r4, r4, 2, 0, 29;
+                lis       r5, data@h;
+                addi      r5, r5, data@l;
+                lfsx      f1, r5, r4;
+                blr;
+            data:
+                .float periods[0];
+                .float periods[1];
+                .float periods[2];
+                .float periods[3];
+                .float periods[4];
+        });
+        new_text_section_end = new_text_section_end + combo_ammo_period_func.encoded_bytes().len() as u32;
+        new_text_section.extend(combo_ammo_period_func.encoded_bytes());
+    }
+
     // TO-DO :
     // Disable spring ball on Trilogy if config.spring_ball is set to false
     if config.spring_ball {
@@ -12028,6 +14435,26 @@ fn patch_combat_hud_color(res: &mut structs::Resource, ctwk_config: &CtwkConfig)
     Ok(())
 }
 
+// Indices into GuiColors.CTWK's color list for each part of the HUD
+const HUD_ENERGY_BAR_COLORS: &[usize] = &[12, 13, 14, 27]; // low energy bar and warning, the rest is per visor
+const HUD_RADAR_COLORS: &[usize] = &[1, 2, 3];
+const HUD_MISSILE_ICON_COLORS: &[usize] = &[9, 18, 19, 20, 21, 29, 33, 34, 35];
+const HUD_THREAT_METER_COLORS: &[usize] = &[28, 30, 31, 32, 36];
+const HUD_VISOR_FRAME_COLORS: &[usize] = &[6, 10, 11, 26];
+const HUD_TEXT_COLORS: &[usize] = &[4, 5];
+
+/// Replaces the hue of `old_color` with `new_color`, keeping its brightness and alpha
+fn scale_hud_color(old_color: &[f32], new_color: [f32;3]) -> [f32;4]
+{
+    let max_new = new_color[0].max(new_color[1]).max(new_color[2]);
+    if max_new < 0.0001 {
+        return [old_color[0], old_color[1], old_color[2], old_color[3]];
+    }
+    let max_original = old_color[0].max(old_color[1]).max(old_color[2]);
+    let scale = max_original / max_new;
+    [new_color[0]*scale, new_color[1]*scale, new_color[2]*scale, old_color[3]]
+}
+
 fn patch_ctwk_gui_colors(res: &mut structs::Resource, ctwk_config: &CtwkConfig)
 -> Result<(), String>
 {
@@ -12111,6 +14538,35 @@ fn patch_ctwk_gui_colors(res: &mut structs::Resource, ctwk_config: &CtwkConfig)
         }
     }
 
+    if let Some(hud_colors) = ctwk_config.hud_colors.as_ref() {
+        let elements = [
+            (hud_colors.energy_bar, HUD_ENERGY_BAR_COLORS),
+            (hud_colors.radar, HUD_RADAR_COLORS),
+            (hud_colors.missile_icon, HUD_MISSILE_ICON_COLORS),
+            (hud_colors.threat_meter, HUD_THREAT_METER_COLORS),
+            (hud_colors.visor_frames, HUD_VISOR_FRAME_COLORS),
+            (hud_colors.text, HUD_TEXT_COLORS),
+        ];
+        for (new_color, indices) in elements.iter() {
+            let new_color = match new_color {
+                Some(new_color) => *new_color,
+                None => continue,
+            };
+            for i in indices.iter() {
+                ctwk_gui_colors.colors[*i] = scale_hud_color(&ctwk_gui_colors.colors[*i], new_color).into();
+            }
+        }
+
+        // Energy bar, energy tanks and energy digits, once for each visor
+        if let Some(new_color) = hud_colors.energy_bar {
+            for visor_colors in ctwk_gui_colors.visor_colors.iter_mut() {
+                for color in visor_colors.iter_mut() {
+                    *color = scale_hud_color(color, new_color).into();
+                }
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -12224,6 +14680,99 @@ fn patch_remove_control_disabler<'r>(
     Ok(())
 }
 
+/// Where to spawn Samus in room `mrea_id` so she comes out of `door` at dock `dock_num`, facing
+/// into the room. Also returns whether she has to be morphed to fit through it.
+pub fn door_spawn_point(mrea_id: u32, dock_num: u32, dock_position: [f32; 3], door: Option<&structs::Door>)
+    -> ([f32; 3], [f32; 3], bool)
+{
+    let mut is_frigate_door = false;
+    let mut is_ceiling_door = false;
+    let mut is_floor_door = false;
+    let mut is_square_frigate_door = false;
+    let mut is_morphball_door = false;
+
+    let mut door_rotation: GenericArray<f32, U3> = [0.0, 0.0, 0.0].into();
+    if let Some(door) = door {
+        door_rotation = door.rotation.clone();
+        is_frigate_door = door.ancs.file_id == 0xfafb5784;
+        is_ceiling_door = door.ancs.file_id == 0xf57dd484 && door_rotation[0] > -90.0 && door_rotation[0] < 90.0;
+        is_floor_door = door.ancs.file_id == 0xf57dd484 && door_rotation[0] < -90.0 && door_rotation[0] > -270.0;
+        is_square_frigate_door = door.ancs.file_id == 0x26CCCB48;
+        is_morphball_door = door.is_morphball_door != 0;
+    }
+
+    if mrea_id == 0xC9D52BBC && dock_num == 0 { // energy core
+        is_morphball_door = true; // it's technically not actually a morph ball door
+    }
+
+    let mut spawn_point_position = dock_position;
+    let mut spawn_point_rotation = [0.0, 0.0, 0.0];
+    let mut door_offset = 3.0;
+    let mut vertical_offset = -2.0;
+
+    if is_frigate_door {
+        door_offset = -3.0;
+        vertical_offset = -2.0;
+        spawn_point_rotation[2] = 180.0;
+    } else if is_ceiling_door {
+        door_offset = 0.0;
+        vertical_offset = -5.0;
+    } else if is_floor_door {
+        door_offset = 2.5;
+        vertical_offset = 1.5;
+    } else if is_square_frigate_door {
+        spawn_point_rotation[2] += 90.0;
+    } else if is_morphball_door {
+        vertical_offset = 0.0;
+        door_offset = 4.0;
+    }
+
+    if mrea_id == 0xF5EF1862 && is_morphball_door { // fiery shores0
+        vertical_offset = -5.0;
+        door_offset = 0.0;
+    }
+
+    if mrea_id == 0x89A6CB8D && is_morphball_door { // warrior shrine
+        vertical_offset = 3.0;
+        door_offset = 2.0;
+        is_morphball_door = false;
+    }
+
+    if (mrea_id == 0xB4FBBEF5 || mrea_id == 0x86EB2E02) && is_morphball_door { // life grove + tunnel
+        vertical_offset = -1.5;
+    }
+
+    if mrea_id == 0x3F04F304 && is_morphball_door { // training chamber
+        door_offset = 2.0;
+    }
+
+    if mrea_id == 0x2B3F1CEE { // piston tunnel
+        door_offset = 2.0;
+        vertical_offset = -1.0;
+    }
+
+    if door_rotation[2] >= 45.0 && door_rotation[2] < 135.0 {
+        // Leads North (Y+)
+        spawn_point_position[1] = spawn_point_position[1] - door_offset;
+        spawn_point_rotation[2] += 180.0;
+    } else if (door_rotation[2] >= 135.0 && door_rotation[2] < 225.0) || (door_rotation[2] < -135.0 && door_rotation[2] > -225.0) {
+        // Leads East (X+)
+        spawn_point_position[0] = spawn_point_position[0] + door_offset;
+        spawn_point_rotation[2] += 270.0;
+    } else if door_rotation[2] >= -135.0 && door_rotation[2] < -45.0 {
+        // Leads South (Y-)
+        spawn_point_position[1] = spawn_point_position[1] + door_offset;
+        spawn_point_rotation[2] += 0.0;
+    } else if door_rotation[2] >= -45.0 && door_rotation[2] < 45.0 {
+        // Leads West (X-)
+        spawn_point_position[0] = spawn_point_position[0] - door_offset;
+        spawn_point_rotation[2] += 90.0;
+    }
+    spawn_point_position[2] = spawn_point_position[2] + vertical_offset;
+
+    (spawn_point_position, spawn_point_rotation, is_morphball_door)
+}
+
 fn patch_add_dock_teleport<'r>(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
@@ -12307,19 +14856,13 @@ fn patch_add_dock_teleport<'r>(
     if  f32::abs(source_position[0] - dock_position[0]) < 5.0 &&
         f32::abs(source_position[1] - dock_position[1]) < 5.0 &&
         f32::abs(source_position[2] - dock_position[2]) < 5.0
-    {
-        return Ok(()); // No teleport needed
-    }
-
-    // Find the nearest door
-    let mut is_frigate_door = false;
-    let mut is_ceiling_door = false;
-    let mut is_floor_door = false;
-    let mut is_square_frigate_door = false;
-    let mut is_morphball_door = false;
-    let mut door_id: u32 = 0;
+    {
+        return Ok(()); // No teleport needed
+    }
 
-    let mut door_rotation: GenericArray<f32, U3> = [0.0, 0.0, 0.0].into();
+    // Find the nearest door
+    let mut door_id: u32 = 0;
+    let mut nearest_door = None;
     let mut disable_ids: Vec<u32> = vec![];
     for obj in layer.objects.as_mut_vec() {
         if !obj.property_data.is_door() {
@@ -12340,82 +14883,15 @@ fn patch_add_dock_teleport<'r>(
             }
         }
 
-        door_rotation = door.rotation.clone();
-        is_frigate_door = door.ancs.file_id == 0xfafb5784;
-        is_ceiling_door = door.ancs.file_id == 0xf57dd484 && door_rotation[0] > -90.0 && door_rotation[0] < 90.0;
-        is_floor_door = door.ancs.file_id == 0xf57dd484 && door_rotation[0] < -90.0 && door_rotation[0] > -270.0;
-        is_square_frigate_door = door.ancs.file_id == 0x26CCCB48;
-        is_morphball_door = door.is_morphball_door != 0;
-    }
-
-    if mrea_id == 0xC9D52BBC && destination_dock_num == 0 { // energy core
-        is_morphball_door = true; // it's technically not actually a morph ball door
-    }
-
-    let mut spawn_point_position = dock_position.clone();
-    let mut spawn_point_rotation = [0.0, 0.0, 0.0];
-    let mut door_offset = 3.0;
-    let mut vertical_offset = -2.0;
-
-    if is_frigate_door {
-        door_offset = -3.0;
-        vertical_offset = -2.0;
-        spawn_point_rotation[2] = 180.0;
-    } else if is_ceiling_door {
-        door_offset = 0.0;
-        vertical_offset = -5.0;
-    } else if is_floor_door {
-        door_offset = 2.5;
-        vertical_offset = 1.5;
-    } else if is_square_frigate_door {
-        spawn_point_rotation[2] += 90.0;
-    } else if is_morphball_door {
-        vertical_offset = 0.0;
-        door_offset = 4.0;
-    }
-
-    if mrea_id == 0xF5EF1862 && is_morphball_door { // fiery shores0
-        vertical_offset = -5.0;
-        door_offset = 0.0;
-    }
-
-    if mrea_id == 0x89A6CB8D && is_morphball_door { // warrior shrine
-        vertical_offset = 3.0;
-        door_offset = 2.0;
-        is_morphball_door = false;
-    }
-
-    if (mrea_id == 0xB4FBBEF5 || mrea_id == 0x86EB2E02) && is_morphball_door { // life grove + tunnel
-        vertical_offset = -1.5;
-    }
-
-    if mrea_id == 0x3F04F304 && is_morphball_door { // training chamber
-        door_offset = 2.0;
-    }
-
-    if mrea_id == 0x2B3F1CEE { // piston tunnel
-        door_offset = 2.0;
-        vertical_offset = -1.0;
+        nearest_door = Some(door.into_owned());
     }
 
-    if door_rotation[2] >= 45.0 && door_rotation[2] < 135.0 {
-        // Leads North (Y+)
-        spawn_point_position[1] = spawn_point_position[1] - door_offset;
-        spawn_point_rotation[2] += 180.0;
-    } else if (door_rotation[2] >= 135.0 && door_rotation[2] < 225.0) || (door_rotation[2] < -135.0 && door_rotation[2] > -225.0) {
-        // Leads East (X+)
-        spawn_point_position[0] = spawn_point_position[0] + door_offset;
-        spawn_point_rotation[2] += 270.0;
-    } else if door_rotation[2] >= -135.0 && door_rotation[2] < -45.0 {
-        // Leads South (Y-)
-        spawn_point_position[1] = spawn_point_position[1] + door_offset;
-        spawn_point_rotation[2] += 0.0;
-    } else if door_rotation[2] >= -45.0 && door_rotation[2] < 45.0 {
-        // Leads West (X-)
-        spawn_point_position[0] = spawn_point_position[0] - door_offset;
-        spawn_point_rotation[2] += 90.0;
-    }
-    spawn_point_position[2] = spawn_point_position[2] + vertical_offset;
+    let (spawn_point_position, mut spawn_point_rotation, is_morphball_door) = door_spawn_point(
+        mrea_id,
+        destination_dock_num,
+        dock_position.into(),
+        nearest_door.as_ref(),
+    );
 
     if spawn_rotation.is_some() {
         spawn_point_rotation[2] = spawn_rotation.unwrap();
@@ -13367,28 +15843,11 @@ fn patch_qol_game_breaking(
         patch_fix_pca_crash
     );
 
-    // randomizer-induced bugfixes
-    patcher.add_scly_patch(
-        resource_info!("1a_morphballtunnel.MREA").into(),
-        move |ps, area| patch_spawn_point_position(ps, area, [124.53, -79.78, 22.84], false, false, false)
-    );
-    patcher.add_scly_patch(
-        resource_info!("05_bathhall.MREA").into(),
-        move |ps, area| patch_spawn_point_position(ps, area, [210.512, -82.424, 19.2174], false, false, false)
-    );
-    patcher.add_scly_patch(
-        resource_info!("00_mines_savestation_b.MREA").into(),
-        move |ps, area| patch_spawn_point_position(ps, area, [216.7245, 4.4046, -139.8873], false, true, false)
-    );
+    // randomizer-induced bugfixes (see also json_data/game_breaking.jsonc)
     patcher.add_scly_patch(
         resource_info!("00_mines_savestation_b.MREA").into(),
         move |ps, area| patch_spawn_point_position(ps, area, [216.7245, 4.4046, -139.8873], false, true, false)
     );
-    // Turrets in Vent Shaft Section B always spawn
-    patcher.add_scly_patch(
-        resource_info!("08b_intro_ventshaft.MREA").into(),
-        move |ps, area| patch_remove_ids(ps, area, vec![0x0013001A, 0x0013001C])
-    );
     if small_samus {
         patcher.add_scly_patch(
             resource_info!("01_over_mainplaza.MREA").into(), // landing site
@@ -14434,8 +16893,354 @@ fn patch_arboretum_sandstone<'a>(patcher: &mut PrimePatcher<'_, 'a>)
     });
 }
 
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ProgressStage
+{
+    WritingFile,
+    WritingHeader,
+    FlushingToDisk,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressUpdate
+{
+    pub stage: ProgressStage,
+    pub file_name: Option<String>, // only set when writing a file
+    pub percent: f64,
+    pub bytes_written: usize,
+    pub total_bytes: usize,
+}
+
+impl ProgressUpdate
+{
+    /// Human readable description of the update, e.g. for printing to a console
+    pub fn message(&self) -> String
+    {
+        match self.stage {
+            ProgressStage::WritingFile => format!("Writing file {:?}", self.file_name.as_deref().unwrap_or("")),
+            ProgressStage::WritingHeader => "Writing ISO header".to_string(),
+            ProgressStage::FlushingToDisk => "Flushing written data to the disk".to_string(),
+        }
+    }
+}
+
+struct CallbackProgressNotifier<F>
+    where F: FnMut(ProgressUpdate)
+{
+    total_size: usize,
+    bytes_so_far: usize,
+    callback: F,
+}
+
+impl<F> CallbackProgressNotifier<F>
+    where F: FnMut(ProgressUpdate)
+{
+    fn update(&mut self, stage: ProgressStage, file_name: Option<String>, percent: f64)
+    {
+        (self.callback)(ProgressUpdate {
+            stage,
+            file_name,
+            percent,
+            bytes_written: self.bytes_so_far,
+            total_bytes: self.total_size,
+        });
+    }
+
+    fn percent(&self) -> f64
+    {
+        self.bytes_so_far as f64 / self.total_size as f64 * 100.
+    }
+}
+
+impl<F> structs::ProgressNotifier for CallbackProgressNotifier<F>
+    where F: FnMut(ProgressUpdate)
+{
+    fn notify_total_bytes(&mut self, total_size: usize)
+    {
+        self.total_size = total_size
+    }
+
+    fn notify_writing_file(&mut self, file_name: &CStr, file_bytes: usize)
+    {
+        let file_name = file_name.to_string_lossy().into_owned();
+        self.update(ProgressStage::WritingFile, Some(file_name), self.percent());
+        self.bytes_so_far += file_bytes;
+    }
+
+    fn notify_writing_header(&mut self)
+    {
+        self.update(ProgressStage::WritingHeader, None, self.percent());
+    }
+
+    fn notify_flushing_to_disk(&mut self)
+    {
+        self.update(ProgressStage::FlushingToDisk, None, 100.);
+    }
+}
+
+/// Same as `patch_iso`, but reports structured progress to `callback` instead of requiring a
+/// `ProgressNotifier` implementation
+pub fn patch_iso_with_progress<F>(config: PatchConfig, callback: F) -> Result<(), String>
+    where F: FnMut(ProgressUpdate)
+{
+    patch_iso(config, CallbackProgressNotifier {
+        total_size: 0,
+        bytes_so_far: 0,
+        callback,
+    })
+}
+
 pub fn patch_iso<T>(config: PatchConfig, mut pn: T) -> Result<(), String>
     where T: structs::ProgressNotifier
+{
+    #[cfg(feature = "bps")]
+    if config.run_mode == RunMode::ApplyPatch {
+        let result = apply_patch_file(&config).and_then(|()| if config.verify {
+            crate::verify::verify_output(&config.output_iso, &config.input_iso[..])
+        } else {
+            Ok(())
+        });
+        let finished = config.finish_output(result.is_ok());
+        result.and(finished)?;
+        if config.print_output_hash {
+            print_output_hash(&config.output_iso_path)?;
+        }
+        return Ok(());
+    }
+
+    let mut written = false;
+    let result = patch_disc(&config, |gc_disc| {
+        let output_iso = config.output_iso.try_clone()
+            .map_err(|e| format!("Failed to prepare output file for writing: {}", e))?;
+
+        match config.iso_format {
+            IsoFormat::Iso => {
+                let mut file = output_iso;
+                file.set_len(structs::GC_DISC_LENGTH as u64)
+                    .map_err(|e| format!("Failed to resize output file: {}", e))?;
+                gc_disc.write(&mut file, &mut pn)
+                    .map_err(|e| format!("Error writing output file: {}", e))?;
+                pn.notify_flushing_to_disk();
+            },
+            #[cfg(feature = "gcz")]
+            IsoFormat::Gcz => {
+                let mut gcz_writer = GczWriter::new(output_iso, structs::GC_DISC_LENGTH as u64)
+                    .map_err(|e| format!("Failed to prepare output file for writing: {}", e))?;
+                gc_disc.write(&mut *gcz_writer, &mut pn)
+                    .map_err(|e| format!("Error writing output file: {}", e))?;
+                pn.notify_flushing_to_disk();
+            },
+            #[cfg(feature = "ciso")]
+            IsoFormat::Ciso => {
+                let mut ciso_writer = CisoWriter::new(output_iso)
+                    .map_err(|e| format!("Failed to prepare output file for writing: {}", e))?;
+                gc_disc.write(&mut ciso_writer, &mut pn)
+                    .map_err(|e| format!("Error writing output file: {}", e))?;
+                pn.notify_flushing_to_disk();
+            }
+            #[cfg(feature = "bps")]
+            IsoFormat::Bps => {
+                let mut bps_writer = BpsWriter::new(std::io::BufWriter::new(output_iso), &config.input_iso[..], structs::GC_DISC_LENGTH as u64)
+                    .map_err(|e| format!("Failed to prepare output file for writing: {}", e))?;
+                gc_disc.write(&mut bps_writer, &mut pn)
+                    .map_err(|e| format!("Error writing output file: {}", e))?;
+                pn.notify_flushing_to_disk();
+                bps_writer.finish()
+                    .map_err(|e| format!("Error writing output file: {}", e))?;
+            },
+            // Rejected when the config is parsed
+            #[allow(unreachable_patterns)]
+            _ => unreachable!(),
+        };
+        written = true;
+        Ok(())
+    })
+    .and_then(|()| if config.verify && written {
+        crate::verify::verify_output(&config.output_iso, &config.input_iso[..])
+    } else {
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => {
+            config.finish_output(written)?;
+            if config.print_output_hash {
+                print_output_hash(&config.output_iso_path)?;
+            }
+            #[cfg(feature = "smoke-test")]
+            if let Some(smoke_test) = config.smoke_test.as_ref() {
+                if !config.quiet {
+                    println!("Booting {} in Dolphin", config.output_iso_path);
+                }
+                crate::smoke_test::run_smoke_test(smoke_test, &config.output_iso_path)?;
+                if !config.quiet {
+                    println!("Smoke test passed");
+                }
+
+                let smoke_test = smoke_test.clone();
+                let mut config = config;
+                crate::smoke_test::visit_rooms(&mut config, &smoke_test, &mut pn)?;
+            }
+            Ok(())
+        },
+        Err(e) => {
+            // Keep the original error, the temporary file is just left behind if this fails
+            let _ = config.finish_output(false);
+            Err(e)
+        },
+    }
+}
+
+/// Writes the output ISO by applying `config.patch_file` to the input ISO
+#[cfg(feature = "bps")]
+fn apply_patch_file(config: &PatchConfig) -> Result<(), String>
+{
+    let patch_path = config.patch_file.as_ref()
+        .ok_or_else(|| "No patch file to apply".to_string())?;
+    let patch = fs::read(patch_path)
+        .map_err(|e| format!("Failed to read {}: {}", patch_path, e))?;
+    if !crate::bps_reader::is_bps(&patch) {
+        Err(format!("{} isn't a BPS patch", patch_path))?
+    }
+
+    let mut output_iso = config.output_iso.try_clone()
+        .map_err(|e| format!("Failed to prepare output file for writing: {}", e))?;
+    crate::bps_reader::apply_bps(&patch, &config.input_iso[..], &mut output_iso)
+}
+
+/// Prints the SHA-256 of the output, so e.g. race participants can check they all patched the
+/// same ISO
+fn print_output_hash(path: &str) -> Result<(), String>
+{
+    use sha2::{Digest, Sha256};
+
+    let mut file = File::open(path)
+        .map_err(|e| format!("Failed to open {} for hashing: {}", path, e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("Failed to read {} for hashing: {}", path, e))?;
+    let hash: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    println!("Output SHA-256: {}", hash);
+
+    Ok(())
+}
+
+/// Words the seed hash is made of. There are 64 so each word stands for 6 bits of the hash.
+const SEED_HASH_WORDS: [&str; 64] = [
+    "Samus", "Ridley", "Kraid", "Chozo", "Pirate", "Metroid", "Phazon", "Omega",
+    "Flaahgra", "Thardus", "Sheegoth", "Elite", "Drone", "Meta", "Parasite", "Beetle",
+    "Tallon", "Magmoor", "Phendrana", "Mines", "Frigate", "Crater", "Temple", "Ruins",
+    "Varia", "Gravity", "Wave", "Ice", "Plasma", "Power", "Charge", "Grapple",
+    "Morph", "Spider", "Boost", "Bomb", "Missile", "Thermal", "Visor", "Artifact",
+    "Elevator", "Save", "Ship", "Lore", "Scan", "Energy", "Tank", "Hive",
+    "Baby", "Jelzap", "Puffer", "Seedling", "Scarab", "Glider", "Oculus", "Bloodflower",
+    "Wallcrawler", "Hunter", "Trooper", "Warp", "Totem", "Aether", "Geemer", "Zoomer",
+];
+
+/// A few words identifying the seed, so racers can check they're playing the same one. They
+/// only depend on the seed and the layout (pickups, elevators, doors, starting room and items),
+/// so cosmetic options and file paths don't change them.
+fn seed_hash(config: &PatchConfig, level_data: &BTreeMap<String, LevelConfig>) -> Result<String, String>
+{
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    let mut add = |text: String| hasher.update(format!("{}\n", text).as_bytes());
+    add(format!("{} {:?} {} {:?}", config.seed, config.version, config.starting_room, config.starting_spawn));
+    add(serde_json::to_string(&config.starting_items).map_err(|e| format!("Failed to hash starting items: {}", e))?);
+    add(serde_json::to_string(&config.item_loss_items).map_err(|e| format!("Failed to hash item loss items: {}", e))?);
+    for (world_key, level) in level_data.iter() {
+        for (elevator, destination) in level.transports.iter() {
+            add(format!("{} {} -> {}", world_key, elevator, destination));
+        }
+        for (room_name, room) in level.rooms.iter() {
+            for pickup in room.pickups.iter().flatten() {
+                add(format!(
+                    "{} {} {} {:?} {:?} {:?}",
+                    world_key, room_name, pickup.pickup_type, pickup.curr_increase, pickup.max_increase, pickup.position,
+                ));
+            }
+            for (dock_num, door) in room.doors.iter().flatten() {
+                add(format!(
+                    "{} {} {} {:?} {:?}",
+                    world_key, room_name, dock_num, door.shield_type, door.blast_shield_type,
+                ));
+            }
+        }
+    }
+    let hash = hasher.finalize();
+
+    let words: Vec<&str> = hash.iter()
+        .take(4)
+        .map(|byte| SEED_HASH_WORDS[(byte & 0x3F) as usize])
+        .collect();
+    Ok(words.join(" "))
+}
+
+/// The scan `seedInfoScan` places in front of `spawn`, the position and rotation Samus spawns at
+fn seed_info_scan(
+    config: &PatchConfig,
+    level_data: &BTreeMap<String, LevelConfig>,
+    seed_info: &SeedInfoScanConfig,
+    spawn: ([f32; 3], [f32; 3]),
+)
+    -> Result<ScanConfig, String>
+{
+    let mut settings = vec![format!("Starting room: {}", config.starting_room)];
+    if !config.pickup_pool.is_empty() {
+        let count: u32 = config.pickup_pool.values().sum();
+        settings.push(match config.fill_mode {
+            FillMode::Random => format!("Pickup pool: {} items", count),
+            FillMode::MajorMinor => format!("Pickup pool: {} items, major/minor", count),
+        });
+    }
+    if config.logic.is_some() && config.require_beatable {
+        settings.push("Beatable".to_string());
+    }
+    if level_data.values().any(|level| !level.transports.is_empty()) {
+        settings.push("Elevators shuffled".to_string());
+    }
+    let shuffles = [
+        (config.shuffle_pickup_position || config.shuffle_pickup_pos_all_rooms, "Pickup positions shuffled"),
+        (config.shuffle_enemies, "Enemies shuffled"),
+        (config.shuffle_music || config.shuffle_music_per_world, "Music shuffled"),
+        (config.shuffle_room_names, "Room names shuffled"),
+        (config.enable_ice_traps, "Ice traps"),
+        (config.warp_to_start, "Warp to start"),
+        (config.spring_ball, "Spring ball"),
+    ];
+    settings.extend(shuffles.iter().filter(|(enabled, _)| *enabled).map(|(_, text)| text.to_string()));
+    if let Some(count) = config.required_artifact_count {
+        settings.push(format!("Artifacts required: {}", count));
+    }
+
+    let mut pages = vec![settings.join("\n")];
+    if let Some(permalink) = seed_info.permalink.as_ref() {
+        pages.push(format!("Permalink:\n{}", permalink));
+    }
+
+    // A few units in front of Samus, at about eye level
+    let (position, rotation) = spawn;
+    let yaw = rotation[2].to_radians();
+    Ok(ScanConfig {
+        position: [
+            position[0] - yaw.sin() * 3.0,
+            position[1] + yaw.cos() * 3.0,
+            position[2] + 1.0,
+        ],
+        combat_visible: Some(true),
+        text: format!("Seed {}\nHash: {}", config.seed, seed_hash(config, level_data)?),
+        pages: Some(pages),
+        ..Default::default()
+    })
+}
+
+/// Reads the input ISO and applies every patch, then hands the patched disc to `write_disc`. The
+/// export run modes return before `write_disc` is called.
+pub(crate) fn patch_disc<F>(config: &PatchConfig, write_disc: F) -> Result<(), String>
+    where F: FnOnce(&mut structs::GcDisc) -> Result<(), String>
 {
     let mut audio_override_patches: Vec<AudioOverridePatch> = Vec::new();
     for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
@@ -14493,17 +17298,29 @@ pub fn patch_iso<T>(config: PatchConfig, mut pn: T) -> Result<(), String>
     }
 
     if config.run_mode == RunMode::ExportLogbook {
-        export_logbook(&mut gc_disc, &config)?;
+        export_logbook(&mut gc_disc, config)?;
         return Ok(());
     } else if config.run_mode == RunMode::ExportAssets {
-        export_assets(&mut gc_disc, &config)?;
+        export_assets(&mut gc_disc, config)?;
+        return Ok(());
+    } else if config.run_mode == RunMode::ExtractAssets {
+        extract_assets(&mut gc_disc, config)?;
+        return Ok(());
+    } else if config.run_mode == RunMode::ExportRoomMap {
+        export_room_map(&mut gc_disc, config)?;
+        return Ok(());
+    } else if config.run_mode == RunMode::ValidateProfile {
+        validate_profile(&gc_disc, config)?;
         return Ok(());
     }
 
-    build_and_run_patches(&mut gc_disc, &config, audio_override_patches)?;
+    build_and_run_patches(&mut gc_disc, config, audio_override_patches)?;
 
     {
-        let json_string = serde_json::to_string(&config)
+        // Going through a Value sorts the keys of every map, so the same config always ends up
+        // as the same bytes
+        let json_string = serde_json::to_value(config)
+            .and_then(|value| serde_json::to_string(&value))
             .map_err(|e| format!("Failed to serialize patch config: {}", e))?;
         writeln!(ct, "{}", json_string).unwrap();
         gc_disc.add_file("randomprime.json", structs::FstEntryFile::Unknown(Reader::new(&ct)))?;
@@ -14520,6 +17337,9 @@ pub fn patch_iso<T>(config: PatchConfig, mut pn: T) -> Result<(), String>
         Version::NtscJTrilogy => None,
         Version::PalTrilogy => None,
     };
+    if patches_rel_bytes.is_none() && config.run_time_limit.is_some() {
+        Err("runTimeLimit isn't supported on the Trilogy versions of the game")?
+    }
     if let Some(patches_rel_bytes) = patches_rel_bytes {
         gc_disc.add_file(
             "patches.rel",
@@ -14527,31 +17347,8 @@ pub fn patch_iso<T>(config: PatchConfig, mut pn: T) -> Result<(), String>
         )?;
     }
 
-    match config.iso_format {
-        IsoFormat::Iso => {
-            let mut file = config.output_iso;
-            file.set_len(structs::GC_DISC_LENGTH as u64)
-                .map_err(|e| format!("Failed to resize output file: {}", e))?;
-            gc_disc.write(&mut file, &mut pn)
-                .map_err(|e| format!("Error writing output file: {}", e))?;
-            pn.notify_flushing_to_disk();
-        },
-        IsoFormat::Gcz => {
-            let mut gcz_writer = GczWriter::new(config.output_iso, structs::GC_DISC_LENGTH as u64)
-                .map_err(|e| format!("Failed to prepare output file for writing: {}", e))?;
-            gc_disc.write(&mut *gcz_writer, &mut pn)
-                .map_err(|e| format!("Error writing output file: {}", e))?;
-            pn.notify_flushing_to_disk();
-        },
-        IsoFormat::Ciso => {
-            let mut ciso_writer = CisoWriter::new(config.output_iso)
-                .map_err(|e| format!("Failed to prepare output file for writing: {}", e))?;
-            gc_disc.write(&mut ciso_writer, &mut pn)
-                .map_err(|e| format!("Error writing output file: {}", e))?;
-            pn.notify_flushing_to_disk();
-        }
-    };
-    Ok(())
+    gc_disc.fill_junk = config.fill_junk;
+    write_disc(&mut gc_disc)
 }
 
 fn export_logbook(gc_disc: &mut structs::GcDisc, config: &PatchConfig)
@@ -14604,32 +17401,148 @@ fn export_logbook(gc_disc: &mut structs::GcDisc, config: &PatchConfig)
                 continue; // lore, but not logbook entry
             }
 
-            if string_table[0].clone().into_string().contains(&"acquired!") {
-                continue; // modal text box that coincidentally has 3 strings
-            }
+            if string_table[0].clone().into_string().contains(&"acquired!") {
+                continue; // modal text box that coincidentally has 3 strings
+            }
+
+            let mut exists = false;
+            for s in strgs.iter() {
+                if s[1] == entry_name {
+                    exists = true;
+                    break;
+                }
+            }
+            if exists {continue;}
+
+            let mut strings = Vec::<String>::new();
+            for string in string_table.iter_mut() {
+                strings.push(string.clone().into_string().replace("\u{0}", ""));
+            }
+            strgs.push(strings);
+        }
+    }
+
+    let logbook = format!("{:?}", strgs);
+    let mut file = File::create(config.logbook_filename.as_ref().unwrap_or(&"logbook.json".to_string()))
+        .map_err(|e| format!("Failed to create logbook file: {}", e))?;
+    file.write_all(logbook.as_bytes())
+        .map_err(|e| format!("Failed to write logbook file: {}", e))?;
+
+    Ok(())
+}
+
+fn export_room_map(gc_disc: &mut structs::GcDisc, config: &PatchConfig)
+    -> Result<(), String>
+{
+    // (world, room name, mrea id, dock count) in MLVL order
+    let mut rows = Vec::<(&str, String, u32, usize)>::new();
+
+    for world in World::iter() {
+        let file_entry = gc_disc.find_file(world.to_pak_str())
+            .ok_or_else(|| format!("Could not find {} in the input ISO", world.to_pak_str()))?;
+        let pak = match *file_entry.file().unwrap() {
+            structs::FstEntryFile::Pak(ref pak) => pak.clone(),
+            structs::FstEntryFile::Unknown(ref reader) => reader.clone().read(()),
+            _ => panic!(),
+        };
+
+        let mut mlvl = pak.resources.iter()
+            .find(|res| res.file_id == world.mlvl())
+            .ok_or_else(|| format!("Could not find the MLVL of {}", world.to_str()))?
+            .into_owned();
+        let mlvl = mlvl.kind.as_mlvl_mut().unwrap();
+
+        for area in mlvl.areas.iter() {
+            let room_name = pak.resources.iter()
+                .find(|res| res.file_id == area.area_name_strg.to_u32())
+                .and_then(|res| {
+                    let mut res = res.into_owned();
+                    let strg = res.kind.as_strg_mut()?;
+                    let string = strg.string_tables.as_mut_vec()[0].strings.as_mut_vec().get(0)?.clone();
+                    Some(string.into_string().replace("\u{0}", ""))
+                })
+                .unwrap_or_else(|| format!("{:08X}", area.mrea.to_u32()));
+
+            rows.push((world.to_json_key(), room_name.trim().to_string(), area.mrea.to_u32(), area.docks.len()));
+        }
+    }
+
+    // Room names aren't unique, so rooms are keyed by their MREA id, written the same way in
+    // both formats
+    let filename = config.room_map_filename.clone().unwrap_or("room_map.json".to_string());
+    let room_map = if filename.to_lowercase().ends_with(".csv") {
+        let mut csv = "world,room,mreaId,dockCount\n".to_string();
+        for (world, room_name, mrea, dock_count) in rows.iter() {
+            csv += &format!("\"{}\",\"{}\",0x{:08X},{}\n", world, room_name.replace("\"", "\"\""), mrea, dock_count);
+        }
+        csv
+    } else {
+        let mut worlds = serde_json::Map::new();
+        for (world, room_name, mrea, dock_count) in rows.iter() {
+            let rooms = worlds.entry(world.to_string())
+                .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
+                .as_object_mut()
+                .unwrap();
+            rooms.insert(format!("0x{:08X}", mrea), serde_json::json!({
+                "room": room_name,
+                "dockCount": dock_count,
+            }));
+        }
+        serde_json::to_string_pretty(&worlds)
+            .map_err(|e| format!("Failed to serialize room map: {}", e))?
+    };
+
+    let mut file = File::create(&filename)
+        .map_err(|e| format!("Failed to create room map file: {}", e))?;
+    file.write_all(room_map.as_bytes())
+        .map_err(|e| format!("Failed to write room map file: {}", e))?;
+
+    Ok(())
+}
+
+/// Checks the profile's custom text against the text boxes it's shown in, without patching
+/// anything
+fn validate_profile(gc_disc: &structs::GcDisc, config: &PatchConfig) -> Result<(), String>
+{
+    let font = FontMetrics::from_disc(gc_disc)?;
 
-            let mut exists = false;
-            for s in strgs.iter() {
-                if s[1] == entry_name {
-                    exists = true;
-                    break;
+    let mut too_long = 0;
+    let mut check = |room_name: &str, text: &str, text_box: &TextBox| {
+        let (_, fits) = font.layout(text, text_box);
+        if !fits {
+            println!("Warning, {} text in {} doesn't fit on screen: '{}'", text_box.name, room_name, text);
+            too_long += 1;
+        }
+    };
+
+    for level in config.level_data.values() {
+        for (room_name, room) in level.rooms.iter() {
+            for pickup in room.pickups.iter().flatten() {
+                for text in pickup.hudmemo_text.iter().flat_map(|text| text.texts()) {
+                    check(room_name, text, &HUDMEMO_BOX);
+                }
+                for text in pickup.scan_text.iter().flat_map(|text| text.texts()) {
+                    check(room_name, text, &SCAN_BOX);
                 }
             }
-            if exists {continue;}
-
-            let mut strings = Vec::<String>::new();
-            for string in string_table.iter_mut() {
-                strings.push(string.clone().into_string().replace("\u{0}", ""));
+            for scan in room.extra_scans.iter().flatten() {
+                check(room_name, &scan.text, &SCAN_BOX);
+                for page in scan.pages.iter().flatten() {
+                    check(room_name, page, &SCAN_BOX);
+                }
+            }
+            for hudmemo in room.hudmemos.iter().flatten() {
+                if let Some(text) = hudmemo.text.as_ref() {
+                    check(room_name, text, &HUDMEMO_BOX);
+                }
             }
-            strgs.push(strings);
         }
     }
 
-    let logbook = format!("{:?}", strgs);
-    let mut file = File::create(config.logbook_filename.as_ref().unwrap_or(&"logbook.json".to_string()))
-        .map_err(|e| format!("Failed to create logbook file: {}", e))?;
-    file.write_all(logbook.as_bytes())
-        .map_err(|e| format!("Failed to write logbook file: {}", e))?;
+    if too_long > 0 {
+        Err(format!("{} strings don't fit on screen", too_long))?
+    }
+    println!("Profile is valid");
 
     Ok(())
 }
@@ -14660,8 +17573,8 @@ fn export_assets(gc_disc: &mut structs::GcDisc, config: &PatchConfig)
         }
     }
 
-    let (_, _, _, _, _, _, _, _, custom_assets) =
-        collect_game_resources(gc_disc, None, &config)?;
+    let (_, _, _, _, _, _, _, _, _, custom_assets) =
+        collect_game_resources(gc_disc, None, &config.level_data, &config)?;
 
     for resource in custom_assets {
         let mut bytes = vec![];
@@ -14675,6 +17588,155 @@ fn export_assets(gc_disc: &mut structs::GcDisc, config: &PatchConfig)
     Ok(())
 }
 
+/// Writes every resource of every PAK to `<dir>/<pak>/<id>.<FOURCC>`, the naming
+/// `extern_assets_dir` expects, so they can be edited and fed back to the patcher. Each PAK's
+/// named resources are listed in its `names.json` and the text of each STRG is also written as
+/// `<id>.STRG.json`.
+fn extract_assets(gc_disc: &mut structs::GcDisc, config: &PatchConfig)
+    -> Result<(), String>
+{
+    let default_dir = &"extracted_assets".to_string();
+    let asset_dir = config.export_asset_dir.as_ref().unwrap_or(default_dir);
+
+    for (path, fst_entry) in gc_disc.file_system_root.dir_files_iter_mut() {
+        let file_name = String::from_utf8_lossy(&path).to_string();
+        if !file_name.to_lowercase().ends_with(".pak") {
+            continue;
+        }
+        fst_entry.guess_kind();
+        let pak = match fst_entry.file() {
+            Some(structs::FstEntryFile::Pak(pak)) => pak,
+            _ => continue,
+        };
+        if !config.quiet {
+            println!("Extracting {}", file_name);
+        }
+
+        let pak_name = Path::new(&file_name).file_stem().unwrap().to_string_lossy().to_string();
+        let pak_dir = format!("{}/{}", asset_dir, pak_name);
+        fs::create_dir_all(&pak_dir)
+            .map_err(|e| format!("Failed to create {}: {}", pak_dir, e))?;
+
+        let mut names = serde_json::Map::new();
+        for named in pak.named_resources.iter() {
+            let name: Vec<u8> = named.name.iter().take_while(|c| *c != 0).collect();
+            names.insert(
+                String::from_utf8_lossy(&name).to_string(),
+                serde_json::Value::String(format!("{:08X}.{}", named.file_id, named.fourcc)),
+            );
+        }
+        let names = serde_json::to_string_pretty(&names)
+            .map_err(|e| format!("Failed to serialize resource names: {}", e))?;
+        export_asset(&pak_dir, "names.json".to_string(), names.into_bytes())?;
+
+        for res in pak.resources.iter() {
+            let bytes = crate::formats::resource_bytes(&res);
+
+            if res.fourcc() == b"STRG".into() {
+                let strg: structs::Strg = Reader::new(&bytes[..]).read(());
+                let mut tables = serde_json::Map::new();
+                for table in strg.string_tables.iter() {
+                    let strings = table.strings.iter()
+                        .map(|string| serde_json::Value::String(
+                            string.into_owned().into_string().replace("\u{0}", "")
+                        ))
+                        .collect();
+                    tables.insert(table.lang.to_string(), serde_json::Value::Array(strings));
+                }
+                let text = serde_json::to_string_pretty(&tables)
+                    .map_err(|e| format!("Failed to serialize STRG text: {}", e))?;
+                export_asset(&pak_dir, format!("{:08X}.STRG.json", res.file_id), text.into_bytes())?;
+            }
+
+            export_asset(&pak_dir, format!("{:08X}.{}", res.file_id, res.fourcc()), bytes)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets the countdown of the Frigate's escape sequence, which is started (and restarted when
+/// entering a new room) by SpecialFunctions
+fn patch_frigate_escape_time<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    time: f32,
+)
+    -> Result<(), String>
+{
+    let scly = area.mrea().scly_section_mut();
+    for layer in scly.layers.as_mut_vec().iter_mut() {
+        for obj in layer.objects.as_mut_vec().iter_mut() {
+            if let Some(special_function) = obj.property_data.as_special_function_mut() {
+                // Escape sequences with a time of 0 stop the countdown
+                if special_function.type_ == 11 && special_function.unknown1 > 0.0 {
+                    special_function.unknown1 = time;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Every object in the loaded rooms shares the engine's 1024 entry object list, and the
+/// rooms next to the current one are loaded too, so a single room should stay well under it
+const ROOM_OBJECT_BUDGET: usize = 500;
+
+/// Objects which are drawn with a model each frame, past this the GameCube starts dropping
+/// frames in the room
+const ROOM_MODEL_BUDGET: usize = 150;
+
+/// Liquids are drawn in several passes (surface, fog and reflections), a handful of them
+/// already costs as much as the rest of the room
+const ROOM_LIQUID_BUDGET: usize = 4;
+
+const ROOM_EFFECT_BUDGET: usize = 40;
+
+/// Warns about rooms which have grown past what the GameCube can run without lagging. Doesn't
+/// prevent anything, a profile may well know better.
+fn patch_check_entity_budget<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    room_name: &str,
+)
+    -> Result<(), String>
+{
+    let scly = area.mrea().scly_section_mut();
+
+    let mut objects = 0;
+    let mut models = 0;
+    let mut liquids = 0;
+    let mut effects = 0;
+    for layer in scly.layers.iter() {
+        for obj in layer.objects.iter() {
+            objects += 1;
+            match obj.property_data.object_type() {
+                structs::Actor::OBJECT_TYPE |
+                structs::Platform::OBJECT_TYPE |
+                structs::Pickup::OBJECT_TYPE |
+                structs::Door::OBJECT_TYPE => models += 1,
+                structs::Water::OBJECT_TYPE => liquids += 1,
+                structs::scly_props::Effect::OBJECT_TYPE => effects += 1,
+                _ => {},
+            }
+        }
+    }
+
+    let budgets = [
+        ("objects", objects, ROOM_OBJECT_BUDGET),
+        ("models", models, ROOM_MODEL_BUDGET),
+        ("liquids", liquids, ROOM_LIQUID_BUDGET),
+        ("particle effects", effects, ROOM_EFFECT_BUDGET),
+    ];
+    for (what, count, budget) in budgets.iter() {
+        if count > budget {
+            println!("Warning, {} has {} {} (more than {}), it may lag on console", room_name, count, what, budget);
+        }
+    }
+
+    Ok(())
+}
 
 fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchConfig, audio_override_patches: &'r Vec<AudioOverridePatch>)
     -> Result<(), String>
@@ -14687,17 +17749,34 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
     let move_item_loss_scan = player_size > 1.001;
     let mut rng = StdRng::seed_from_u64(config.seed);
 
-    let mut level_data: HashMap<String, LevelConfig> = config.level_data.clone();
+    let mut level_data: BTreeMap<String, LevelConfig> = config.level_data.clone();
     let starting_room = SpawnRoomData::from_str(&config.starting_room);
 
+    let is_beatable = |level_data: &BTreeMap<String, LevelConfig>| match config.logic.as_ref() {
+        Some(logic) if config.require_beatable => logic.is_beatable(level_data, &config.starting_items, &config.tricks),
+        _ => true,
+    };
+
+    if !config.pickup_pool.is_empty() {
+        place_pickup_pool(
+            gc_disc,
+            &mut level_data,
+            &config.pickup_pool,
+            config.fill_mode,
+            config.seed,
+            is_beatable,
+        )?;
+    }
+
     if config.shuffle_pickup_pos_all_rooms {
         for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
             let world = World::from_pak(pak_name).unwrap();
 
             if level_data.get(world.to_json_key()).is_none() {
                 level_data.insert(world.to_json_key().to_string(), LevelConfig {
-                        transports: HashMap::new(),
-                        rooms: HashMap::new(),
+                        transports: BTreeMap::new(),
+                        transport_spawn_positions: BTreeMap::new(),
+                        rooms: BTreeMap::new(),
                     }
                 );
             }
@@ -14786,6 +17865,13 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                             platforms: None,
                             camera_hints: None,
                             blocks: None,
+                            destructible_blocks: None,
+                            crate_loot: None,
+                            pickup_shuffle_zones: None,
+                            enemy_config: None,
+                            music_override: None,
+                            disable_occlusion: None,
+                            map_name: None,
                             ambient_lighting_scale: None,
                             lock_on_points: None,
                             escape_sequences: None,
@@ -14813,7 +17899,14 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                             streamed_audios: None,
                             layer_objs: None,
                             edit_objs: None,
+                            edit_properties: None,
                             waypoints: None,
+                            grapple_points: None,
+                            spider_tracks: None,
+                            save_stations: None,
+                            refill_stations: None,
+                            remove_grapple_points: None,
+                            remove_spider_tracks: None,
                         }
                     );
                 }
@@ -14842,6 +17935,11 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                                 invisible_and_silent: None,
                                 thermal_only: None,
                                 scale: None,
+                                remote_connections: None,
+                                aura: None,
+                                scan_to_reveal: None,
+                                jingle: None,
+                                sound_effect: None,
                             }
                         ]
                     );
@@ -14850,6 +17948,16 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         }
     }
 
+    // Doors set by the profile are kept, every other door gets a random shield
+    if let Some(door_rando) = config.door_rando.as_ref() {
+        randomize_doors(gc_disc, &mut level_data, door_rando, config.seed)?;
+    }
+
+    // A pool is only ever placed so it's beatable, but a layout from the profile may not be
+    if config.pickup_pool.is_empty() && !is_beatable(&level_data) {
+        Err("The seed can't be beaten according to the logic file")?
+    }
+
     let frigate_done_room = {
         let mut destination_name = "Tallon:Landing Site";
         let frigate_level = level_data.get(World::FrigateOrpheon.to_json_key());
@@ -14875,7 +17983,82 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         destination
     };
 
-    let artifact_totem_strings = build_artifact_temple_totem_scan_strings(&level_data, &mut rng, config.artifact_hints.clone());
+    // Rooms trade names with other rooms of their world, as that's where the name STRGs are.
+    // Rooms with a custom name keep it.
+    let mut shuffled_room_names = HashMap::new();
+    if config.shuffle_room_names {
+        let mut name_rng = StdRng::seed_from_u64(config.seed);
+        for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+            let world = World::from_pak(pak_name).unwrap();
+            let rooms: Vec<&pickup_meta::RoomInfo> = rooms.iter()
+                .filter(|room_info| {
+                    level_data.get(world.to_json_key())
+                        .and_then(|level| level.rooms.get(room_info.name().trim()))
+                        .and_then(|room| room.map_name.as_ref())
+                        .is_none()
+                })
+                .collect();
+            let mut name_ids: Vec<ResId<res_id::STRG>> = rooms.iter().map(|room_info| room_info.name_id).collect();
+            name_ids.shuffle(&mut name_rng);
+            for (room_info, name_id) in rooms.into_iter().zip(name_ids.into_iter()) {
+                shuffled_room_names.insert(room_info.room_id.to_u32(), name_id);
+            }
+        }
+    }
+
+    let artifact_totem_strings = build_artifact_temple_totem_scan_strings(
+        &level_data,
+        &mut rng,
+        config.artifact_hints.clone(),
+        config.artifact_hint_behavior == ArtifactHintBehavior::All,
+        &displayed_room_names(&level_data, &shuffled_room_names),
+    );
+
+    // Elevators and the special destinations always have a spawn point, so only rooms given by
+    // name are checked. Rooms which place their own spawn point are left as they are, unless
+    // startingRoom picks the spawn.
+    let starting_room_fixup = {
+        let room_config = World::from_pak(starting_room.pak_name)
+            .and_then(|world| level_data.get(world.to_json_key()))
+            .and_then(|level| level.rooms.get(starting_room.name.trim()));
+        let custom_spawn = room_config.map_or(false, |room| {
+            room.spawn_position_override.is_some() || room.spawn_points.is_some()
+        });
+        let checked = !custom_spawn || config.starting_spawn.is_some();
+        if starting_room.room_id == 0 && config.starting_room.contains(':') && checked {
+            check_starting_room(gc_disc, &starting_room, config.starting_spawn)?
+        } else {
+            None
+        }
+    };
+
+    // Added like the profile's own extra scans, so it has to be placed before they're collected
+    if let Some(seed_info) = config.seed_info_scan.as_ref() {
+        // Elevators are named after where they lead rather than the room they're in
+        let world_key = World::from_pak(starting_room.pak_name).unwrap().to_json_key();
+        let room_name = pickup_meta::ROOM_INFO.iter()
+            .find(|(pak_name, _)| *pak_name == starting_room.pak_name)
+            .and_then(|(_, rooms)| rooms.iter().find(|room_info| room_info.room_id.to_u32() == starting_room.mrea))
+            .map(|room_info| room_info.name().trim())
+            .unwrap_or(starting_room.name.trim());
+        let spawn_position_override = level_data.get(world_key)
+            .and_then(|level| level.rooms.get(room_name))
+            .and_then(|room| room.spawn_position_override);
+        let spawn = match spawn_position_override {
+            Some(position) => Some((position, [0.0, 0.0, 0.0])),
+            None => starting_spawn_point(gc_disc, &starting_room, starting_room_fixup)?,
+        };
+        match spawn {
+            Some(spawn) => {
+                let scan = seed_info_scan(config, &level_data, seed_info, spawn)?;
+                level_data.entry(world_key.to_string()).or_default()
+                    .rooms.entry(room_name.to_string()).or_default()
+                    .extra_scans.get_or_insert_with(Vec::new)
+                    .push(scan);
+            },
+            None => println!("Warning, could not find where Samus spawns in {}, skipping the seed info scan", room_name),
+        }
+    }
 
     let show_starting_memo = config.starting_memo.is_some();
 
@@ -14887,14 +18070,15 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         }
     };
 
-    let (game_resources, pickup_hudmemos, pickup_scans, extra_scans, savw_scans_to_add, local_savw_scans_to_add, savw_scan_logbook_category, extern_models, _) =
-        collect_game_resources(gc_disc, starting_memo, &config)?;
+    let (game_resources, pickup_hudmemos, pickup_scans, extra_scans, door_scans, savw_scans_to_add, local_savw_scans_to_add, savw_scan_logbook_category, extern_models, _) =
+        collect_game_resources(gc_disc, starting_memo, &level_data, &config)?;
 
     let extern_models = &extern_models;
     let game_resources = &game_resources;
     let pickup_hudmemos = &pickup_hudmemos;
     let pickup_scans = &pickup_scans;
     let extra_scans = &extra_scans;
+    let door_scans = &door_scans;
     let strgs = config.strg.clone();
     let strgs = &strgs;
 
@@ -14905,6 +18089,21 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
     let missile_station_refill_strings = vec!["&just=center;Ammunition fully replenished.".to_string()];
     let missile_station_refill_strings = &missile_station_refill_strings;
 
+    let enemy_shuffle = if config.shuffle_enemies {
+        Some(plan_enemy_shuffle(gc_disc, config.seed, config.shuffle_enemies_across_types)?)
+    } else {
+        None
+    };
+    let enemy_shuffle = &enemy_shuffle;
+    let enemy_shuffle_rooms = enemy_shuffle.as_ref().map(|x| x.rooms()).unwrap_or_default();
+
+    let music_shuffle = if config.shuffle_music {
+        Some(plan_music_shuffle(gc_disc, config.seed, config.shuffle_music_per_world)?)
+    } else {
+        None
+    };
+    let music_shuffle = &music_shuffle;
+
     // simplify iteration of additional patches
     let mut other_patches: Vec<((&[u8], u32), &RoomConfig)> = Vec::new();
     for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
@@ -15001,6 +18200,7 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
             ARTIFACT_TOTEM_SCAN_SCAN[i as usize].res_id
         );
     }
+    savw_to_remove_from_logbook.extend(config.logbook_excluded_scans.iter().copied());
     let savw_to_remove_from_logbook = &savw_to_remove_from_logbook;
 
     // XXX These values need to out live the patcher
@@ -15010,8 +18210,50 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
     let n = format!("Video/04_fileselect_playgame_{}.thp", select_game_fmv_suffix);
     let file_select_play_game_fmv = gc_disc.find_file(&n).unwrap().file().unwrap().clone();
 
+    let main_menu_message = if config.main_menu_seed_hash {
+        let seed_hash = seed_hash(config, &level_data)?;
+        if !config.quiet {
+            println!("Seed hash: {}", seed_hash);
+        }
+        if config.main_menu_message.is_empty() {
+            seed_hash
+        } else {
+            format!("{}\n{}", config.main_menu_message, seed_hash)
+        }
+    } else {
+        config.main_menu_message.clone()
+    };
+    let main_menu_message = &main_menu_message;
+
+    // Filled in while patching, for the spoiler
+    let shuffled_positions = RefCell::new(HashMap::new());
+    let shuffled_positions = &shuffled_positions;
+
     let mut patcher = PrimePatcher::new();
 
+    // Before the starting items are given to the room's spawn points
+    if let Some(fixup) = starting_room_fixup {
+        patcher.add_scly_patch(
+            (starting_room.pak_name.as_bytes(), starting_room.mrea),
+            move |ps, area| patch_starting_room_spawn(ps, area, fixup),
+        );
+    }
+
+    // Before the room patches, so escape sequences added by a room keep their own time
+    if let Some(time) = config.frigate_escape_time {
+        for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+            if World::from_pak(pak_name) != Some(World::FrigateOrpheon) {
+                continue;
+            }
+            for room_info in rooms.iter() {
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    move |ps, area| patch_frigate_escape_time(ps, area, time),
+                );
+            }
+        }
+    }
+
     // Add the freeze effect assets required by CPlayer::Freeze()
     if config.enable_ice_traps {
         patcher.add_file_patch(
@@ -15035,7 +18277,20 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         ];
         for (file_name, music_file) in MUSIC_FILE_NAME.iter().zip(flaahgra_music_files.iter()) {
             patcher.add_file_patch(file_name, move |file| {
-                *file = structs::FstEntryFile::ExternalFile(Box::new(music_file.clone()));
+                // The music is streamed at the rate of the file it replaces
+                if let structs::FstEntryFile::Unknown(reader) = file {
+                    let vanilla: &[u8] = reader;
+                    let vanilla = read_dsp_header(&vanilla)?;
+                    let replacement = read_dsp_header(&**music_file)?;
+                    if replacement.sample_rate != vanilla.sample_rate {
+                        Err(format!(
+                            "The Flaahgra music has to be at {} Hz like the original, not {} Hz",
+                            vanilla.sample_rate,
+                            replacement.sample_rate,
+                        ))?
+                    }
+                }
+                *file = structs::FstEntryFile::ExternalFile(music_file.clone());
                 Ok(())
             });
         }
@@ -15274,6 +18529,7 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
 
     // Patch pickups
     let mut seed: u64 = 1;
+
     for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
         let world = World::from_pak(pak_name).unwrap();
 
@@ -15295,6 +18551,20 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                 );
             }
 
+            if config.charge_beam_optional {
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    patch_chargeless_damageable_triggers,
+                );
+            }
+
+            if config.scan_visor_optional {
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    patch_scan_visor_optional,
+                );
+            }
+
             // Removed as this was letting the player unmorph in places they shouldn't
             // patcher.add_scly_patch(
             //     (pak_name.as_bytes(), room_info.room_id.to_u32()),
@@ -15331,11 +18601,84 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                 );
             }
 
+            if config.cosmetic_door_colors && !config.force_vanilla_layout {
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    move |ps, area| patch_cosmetic_door_colors(ps, area, game_resources, config.seed),
+                );
+            }
+
+            if let Some(enemy_shuffle) = enemy_shuffle {
+                if enemy_shuffle_rooms.contains(&room_info.room_id.to_u32()) {
+                    patcher.add_scly_patch(
+                        (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                        move |ps, area| patch_shuffle_enemies(ps, area, enemy_shuffle),
+                    );
+                }
+            }
+
+            let music_override = level_data.get(world.to_json_key())
+                .and_then(|level| level.rooms.get(room_info.name().trim()))
+                .and_then(|room| room.music_override.as_ref());
+            if let Some(music_override) = music_override {
+                let file_name = music_override_file_name(music_override);
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    move |ps, area| patch_music_override(ps, area, &file_name),
+                );
+            } else if let Some(music_shuffle) = music_shuffle {
+                if music_shuffle.contains_room(room_info.room_id.to_u32()) {
+                    patcher.add_scly_patch(
+                        (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                        move |ps, area| patch_shuffle_music(ps, area, music_shuffle),
+                    );
+                }
+            }
+
+            let map_name = level_data.get(world.to_json_key())
+                .and_then(|level| level.rooms.get(room_info.name().trim()))
+                .and_then(|room| room.map_name.as_ref());
+            if let Some(map_name) = map_name {
+                patcher.add_resource_patch(
+                    (&[pak_name.as_bytes()], room_info.name_id.to_u32(), FourCC::from_bytes(b"STRG")),
+                    move |res| patch_room_name_strg(res, map_name, config.language),
+                );
+            } else if let Some(name_id) = shuffled_room_names.get(&room_info.room_id.to_u32()) {
+                let name_id = *name_id;
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    move |ps, area| patch_room_name_id(ps, area, name_id),
+                );
+            }
+
+            let enemy_config = level_data.get(world.to_json_key())
+                .and_then(|level| level.rooms.get(room_info.name().trim()))
+                .and_then(|room| room.enemy_config.as_ref())
+                .or(config.enemy_config.as_ref());
+            if let Some(enemy_config) = enemy_config {
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    move |ps, area| patch_scale_enemies(ps, area, enemy_config),
+                );
+            }
+
+            // A room's loot table takes priority over the global one
+            let crate_loot = level_data.get(world.to_json_key())
+                .and_then(|level| level.rooms.get(room_info.name().trim()))
+                .and_then(|room| room.crate_loot.as_ref())
+                .or(config.crate_loot.as_ref());
+            if let Some(crate_loot) = crate_loot {
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    move |ps, area| patch_crate_loot(ps, area, game_resources, crate_loot),
+                );
+            }
+
             // Get list of patches specified for this room
             let (pickups, scans, doors, hudmemos) = {
                 let mut _pickups = Vec::new();
                 let mut _scans = Vec::new();
-                let mut _doors = HashMap::<u32, DoorConfig>::new();
+                let mut _doors = BTreeMap::<u32, DoorConfig>::new();
                 let mut _hudmemos = Vec::new();
 
                 let level = level_data.get(world.to_json_key());
@@ -15479,6 +18822,79 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                             }
                         }
 
+                        if let Some(grapple_points) = room.grapple_points.as_ref() {
+                            for config in grapple_points {
+                                patcher.add_scly_patch(
+                                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                                    move |ps, area| patch_add_grapple_point(
+                                        ps,
+                                        area,
+                                        config.clone(),
+                                    ),
+                                );
+                            }
+                        }
+
+                        if let Some(spider_tracks) = room.spider_tracks.as_ref() {
+                            for config in spider_tracks {
+                                patcher.add_scly_patch(
+                                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                                    move |ps, area| patch_add_spider_track(
+                                        ps,
+                                        area,
+                                        config.clone(),
+                                    ),
+                                );
+                            }
+                        }
+
+                        if let Some(save_stations) = room.save_stations.as_ref() {
+                            for config in save_stations {
+                                patcher.add_scly_patch(
+                                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                                    move |ps, area| patch_add_save_station(
+                                        ps,
+                                        area,
+                                        game_resources,
+                                        config.clone(),
+                                    ),
+                                );
+
+                                if config.map_icon.unwrap_or(true) && room_info.mapa_id != 0 {
+                                    let position = config.position;
+                                    patcher.add_resource_patch(
+                                        (&[pak_name.as_bytes()], room_info.mapa_id.to_u32(), FourCC::from_bytes(b"MAPA")),
+                                        move |res| add_station_to_mapa(res, structs::MapaObjectType::SaveStation, position),
+                                    );
+                                }
+                            }
+                        }
+
+                        if let Some(refill_stations) = room.refill_stations.as_ref() {
+                            for config in refill_stations {
+                                patcher.add_scly_patch(
+                                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                                    move |ps, area| patch_add_refill_station(
+                                        ps,
+                                        area,
+                                        game_resources,
+                                        config.clone(),
+                                    ),
+                                );
+
+                                // The map only has an icon for missile stations
+                                if config.kind == RefillStationKind::Missile
+                                    && config.map_icon.unwrap_or(true) && room_info.mapa_id != 0
+                                {
+                                    let position = config.position;
+                                    patcher.add_resource_patch(
+                                        (&[pak_name.as_bytes()], room_info.mapa_id.to_u32(), FourCC::from_bytes(b"MAPA")),
+                                        move |res| add_station_to_mapa(res, structs::MapaObjectType::MissileStation, position),
+                                    );
+                                }
+                            }
+                        }
+
                         if room.streamed_audios.is_some() {
                             for config in room.streamed_audios.as_ref().unwrap() {
                                 patcher.add_scly_patch(
@@ -15707,11 +19123,26 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                             );
                         }
 
-                        if room.blocks.is_some() {
-                            for block in room.blocks.as_ref().unwrap() {
+                        if room.blocks.is_some() {
+                            for block in room.blocks.as_ref().unwrap() {
+                                patcher.add_scly_patch(
+                                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                                    move |ps, area| patch_add_block(
+                                        ps,
+                                        area,
+                                        game_resources,
+                                        block.clone(),
+                                        config.legacy_block_size,
+                                    ),
+                                );
+                            }
+                        }
+
+                        if let Some(blocks) = room.destructible_blocks.as_ref() {
+                            for block in blocks {
                                 patcher.add_scly_patch(
                                     (pak_name.as_bytes(), room_info.room_id.to_u32()),
-                                    move |ps, area| patch_add_block(
+                                    move |ps, area| patch_add_destructible_block(
                                         ps,
                                         area,
                                         game_resources,
@@ -15778,6 +19209,13 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                             );
                         }
 
+                        if room.disable_occlusion.unwrap_or(false) {
+                            patcher.add_scly_patch(
+                                (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                                patch_disable_occlusion,
+                            );
+                        }
+
                         let map_default_state = room.map_default_state.clone().unwrap_or(config.map_default_state.into());
                         patcher.add_resource_patch(
                             (&[pak_name.as_bytes()], room_info.mapa_id.to_u32(), reader_writer::FourCC::from_bytes(b"MAPA")),
@@ -15813,11 +19251,15 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                 (_pickups, _scans, _doors, _hudmemos)
             };
 
+            let pickup_shuffle_zones = level_data.get(world.to_json_key())
+                .and_then(|level| level.rooms.get(room_info.name().trim()))
+                .and_then(|room| room.pickup_shuffle_zones.as_ref());
+
             // Patch existing item locations
             let mut idx = 0;
             let pickups_config_len = pickups.len();
             for pickup_location in room_info.pickup_locations.iter() {
-                let pickup = {
+                let mut pickup = {
                     if idx >= pickups_config_len {
                         PickupConfig {
                             id: None,
@@ -15836,11 +19278,20 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                             invisible_and_silent: None,
                             thermal_only: None,
                             scale: None,
+                            remote_connections: None,
+                            aura: None,
+                            scan_to_reveal: None,
+                            jingle: None,
+                            sound_effect: None,
                         }
                     } else {
                         pickups[idx].clone() // TODO: cloning is suboptimal
                     }
                 };
+                if pickup.aura.is_none() {
+                    pickup.aura = config.pickup_aura.clone();
+                }
+                apply_pickup_sounds(&mut pickup, &config.pickup_sounds);
                 let show_icon = pickup.show_icon.unwrap_or(false);
 
                 let key = PickupHashKey {
@@ -15894,10 +19345,12 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                             config.qol_pickup_scans,
                             extern_models,
                             config.shuffle_pickup_position,
+                            pickup_shuffle_zones,
                             config.seed + seed,
                             !config.starting_items.combat_visor && !config.starting_items.scan_visor && !config.starting_items.thermal_visor && !config.starting_items.xray,
                             config.version,
                             config.force_vanilla_layout,
+                            shuffled_positions,
                     )
                 );
 
@@ -15917,7 +19370,11 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
 
             // Patch extra item locations
             while idx < pickups_config_len {
-                let pickup = pickups[idx].clone(); // TODO: cloning is suboptimal
+                let mut pickup = pickups[idx].clone(); // TODO: cloning is suboptimal
+                if pickup.aura.is_none() {
+                    pickup.aura = config.pickup_aura.clone();
+                }
+                apply_pickup_sounds(&mut pickup, &config.pickup_sounds);
                 let show_icon = pickup.show_icon.unwrap_or(false);
                 let position = pickup.position.unwrap().clone();
 
@@ -15956,9 +19413,11 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                         skip_hudmemos,
                         extern_models,
                         config.shuffle_pickup_pos_all_rooms,
+                        pickup_shuffle_zones,
                         config.seed,
                         !config.starting_items.combat_visor && !config.starting_items.scan_visor && !config.starting_items.thermal_visor && !config.starting_items.xray,
                         config.version,
+                        shuffled_positions,
                     ),
                 );
 
@@ -16111,6 +19570,18 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                         break;
                     }
 
+                    let blast_shield_model = door_config.blast_shield_model.as_ref().map(|model_name| {
+                        extern_models.get(model_name)
+                            .unwrap_or_else(|| panic!("Unknown Blast Shield Model - {}", model_name))
+                            .clone()
+                    });
+
+                    let blast_shield_scan = door_scans.get(&PickupHashKey {
+                        level_id: world.mlvl(),
+                        room_id: room_info.room_id.to_u32(),
+                        pickup_idx: dock_num,
+                    }).copied();
+
                     patcher.add_scly_patch(
                         (pak_name.as_bytes(), room_info.room_id.to_u32()),
                         move |ps, area| patch_door(
@@ -16118,6 +19589,8 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                             local_dl.clone(),
                             door_type,
                             blast_shield_type,
+                            blast_shield_model.clone(),
+                            blast_shield_scan,
                             game_resources,
                             config.door_open_mode,
                         )
@@ -16307,6 +19780,7 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         config.auto_enabled_elevators,
         player_size,
         config.force_vanilla_layout,
+        config.language,
     );
     let skip_frigate = skip_frigate && starting_room.mlvl != World::FrigateOrpheon.mlvl();
 
@@ -16389,7 +19863,7 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         );
     }
 
-    let rel_config = create_rel_config_file(starting_room, config.quickplay);
+    let rel_config = create_rel_config_file(starting_room, config.quickplay, config.run_time_limit);
 
     if skip_frigate {
         // remove frigate data to save time/space
@@ -16430,6 +19904,9 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         structs::FstEntryFile::ExternalFile(Box::new(rel_config)),
     )?;
 
+    add_extern_music_files(gc_disc, config)?;
+    patch_front_end_music(gc_disc, config)?;
+
     if !config.force_vanilla_layout {
         const ARTIFACT_TOTEM_SCAN_STRGS: &[ResourceInfo] = &[
             resource_info!("07_Over_Stonehenge Totem 5.STRG"), // Lifegiver
@@ -16448,7 +19925,7 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         for (res_info, strg_text) in ARTIFACT_TOTEM_SCAN_STRGS.iter().zip(artifact_totem_strings.iter()) {
             patcher.add_resource_patch(
                 (*res_info).into(),
-                move |res| patch_artifact_totem_scan_strg(res, &strg_text),
+                move |res| patch_artifact_totem_scan_strg(res, &strg_text, config.language),
             );
         }
         patcher.add_scly_patch(
@@ -16458,7 +19935,7 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
     }
     patcher.add_resource_patch(
         resource_info!("STRG_Main.STRG").into(),// 0x0552a456
-        |res| patch_main_strg(res, config.version, &config.main_menu_message)
+        move |res| patch_main_strg(res, config.version, main_menu_message)
     );
     patcher.add_resource_patch(
         resource_info!("FRME_NewFileSelect.FRME").into(),
@@ -16469,10 +19946,62 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         |res| patch_credits(res, config.version, config, &level_data)
     );
 
+    if !config.inventory_text.is_empty() {
+        patcher.add_resource_patch(
+            resource_info!("STRG_PauseScreen.STRG").into(),
+            |res| patch_inventory_text(res, &config.inventory_text)
+        );
+    }
+
+    if config.pause_screen_objectives {
+        // Artifacts which aren't placed anywhere are already in the temple
+        let placed_artifacts: HashSet<PickupType> = level_data.values()
+            .flat_map(|level| level.rooms.values())
+            .flat_map(|room| room.pickups.iter().flatten())
+            .map(|pickup| PickupType::from_str(&pickup.pickup_type))
+            .filter(|pt| pt.kind() >= PickupType::ArtifactOfTruth.kind() && pt.kind() <= PickupType::ArtifactOfNewborn.kind())
+            .collect();
+        let artifact_count = config.required_artifact_count.unwrap_or(placed_artifacts.len() as u32);
+
+        let mut objectives = vec![];
+        if artifact_count > 0 {
+            objectives.push(format!(
+                "Return {} Chozo Artifact{} to the Artifact Temple (collected ones are in the Logbook)",
+                artifact_count,
+                if artifact_count == 1 { "" } else { "s" },
+            ));
+        }
+        for room in config.objective_rooms.iter() {
+            let room = SpawnRoomData::from_str(room);
+            let world = World::from_pak(room.pak_name).unwrap();
+            objectives.push(format!("Reach {} in {}", room.name.trim(), world.to_str()));
+        }
+        if config.artifact_objectives {
+            objectives.push("The next Artifact to collect is marked on the map".to_string());
+        }
+
+        if !objectives.is_empty() {
+            patcher.add_resource_patch(
+                resource_info!("STRG_PauseScreen.STRG").into(),
+                move |res| patch_pause_screen_list(res, "Objectives", &objectives, config.language)
+            );
+        }
+    }
+
+    if config.door_legend {
+        let legend = door_legend(&level_data);
+        if !legend.is_empty() {
+            patcher.add_resource_patch(
+                resource_info!("STRG_PauseScreen.STRG").into(),
+                move |res| patch_pause_screen_list(res, "Door colors on the map", &legend, config.language)
+            );
+        }
+    }
+
     if config.results_string.is_some() {
         patcher.add_resource_patch(
             resource_info!("STRG_CompletionScreen.STRG").into(),
-            |res| patch_completion_screen(res, config.results_string.clone().unwrap())
+            |res| patch_completion_screen(res, config.results_string.clone().unwrap(), config.language)
         );
     }
 
@@ -16490,7 +20019,8 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         patch_save_banner_txtr
     );
 
-    if config.patch_power_conduits {
+    // Conduits take charged Wave Beam shots in vanilla
+    if config.patch_power_conduits || config.charge_beam_optional {
         patch_power_conduits(&mut patcher);
     }
 
@@ -16498,7 +20028,8 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         patch_weaken_conduits(&mut patcher);
     }
 
-    if config.remove_mine_security_station_locks {
+    // The doors are unlocked by scanning the terminals
+    if config.remove_mine_security_station_locks || config.scan_visor_optional {
         patcher.add_scly_patch(
             resource_info!("02_mines_shotemup.MREA").into(), // Mines Security Station
             remove_door_locks,
@@ -16715,6 +20246,55 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         )
     );
 
+    // One hint per artifact, in order, so the map always marks the next artifact still missing.
+    // The objective rooms are marked by every hint, and by a last one once the artifacts are done.
+    let objective_rooms: Vec<SpawnRoomData> = config.objective_rooms.iter()
+        .map(|room| SpawnRoomData::from_str(room))
+        .collect();
+    let mut artifact_rooms = vec![];
+    if config.artifact_objectives {
+        for (world_key, level) in level_data.iter() {
+            for (room_name, room) in level.rooms.iter() {
+                for pickup in room.pickups.iter().flatten() {
+                    let pickup_type = PickupType::from_str(&pickup.pickup_type);
+                    if pickup_type.kind() >= PickupType::ArtifactOfTruth.kind() && pickup_type.kind() <= PickupType::ArtifactOfNewborn.kind() {
+                        artifact_rooms.push((pickup_type.kind(), SpawnRoomData::from_str(&format!("{}:{}", world_key, room_name))));
+                    }
+                }
+            }
+        }
+        artifact_rooms.sort_by_key(|(kind, _)| *kind);
+    }
+
+    let mut objective_hints: Vec<Vec<SpawnRoomData>> = artifact_rooms.iter()
+        .map(|(_, room)| objective_rooms.iter().chain(iter::once(room)).copied().collect())
+        .collect();
+    if !objective_rooms.is_empty() {
+        objective_hints.push(objective_rooms);
+    }
+    for locations in objective_hints.iter_mut() {
+        let mut marked_rooms = HashSet::new();
+        locations.retain(|room| marked_rooms.insert(room.mrea));
+    }
+
+    if !objective_hints.is_empty() {
+        let hint_count = objective_hints.len();
+        for (hint_idx, (artifact_kind, room)) in artifact_rooms.into_iter().enumerate() {
+            patcher.add_scly_patch(
+                (room.pak_name.as_bytes(), room.mrea),
+                move |ps, area| patch_complete_objective_hint(ps, area, artifact_kind, hint_idx),
+            );
+        }
+        patcher.add_file_patch(
+            b"NoARAM.pak",
+            move |file| patch_objective_hint(file, &objective_hints, game_resources),
+        );
+        patcher.add_scly_patch(
+            (starting_room.pak_name.as_bytes(), starting_room.mrea),
+            move |ps, area| patch_activate_objective_hint(ps, area, hint_count),
+        );
+    }
+
     if !skip_frigate {
         patcher.add_scly_patch(
             resource_info!("02_intro_elevator.MREA").into(),
@@ -17191,7 +20771,7 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         for pak in paks.iter() {
             patcher.add_resource_patch(
                 (&[pak.as_bytes()], 89302102, FourCC::from_bytes(b"STRG")),
-                move |res| patch_start_button_strg(res, text)
+                move |res| patch_start_button_strg(res, text, config.language)
             );
         }
     }
@@ -17284,6 +20864,60 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                 move |ps, area| patch_remove_ids(ps, area, ids.clone())
             );
         }
+
+        if let Some(positions) = room_config.remove_grapple_points.as_ref() {
+            patcher.add_scly_patch(
+                *room,
+                move |ps, area| patch_remove_grapple_points(ps, area, positions.clone())
+            );
+        }
+
+        if let Some(positions) = room_config.remove_spider_tracks.as_ref() {
+            patcher.add_scly_patch(
+                *room,
+                move |ps, area| patch_remove_spider_tracks(ps, area, positions.clone())
+            );
+        }
+    }
+
+    if config.room_name_popups {
+        for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+            for room_info in rooms.iter() {
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    patch_room_name_popup,
+                );
+            }
+        }
+    }
+
+    // After the connections are added, the prompt follows the ones which start the skips
+    if config.cutscene_skip_prompt {
+        for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+            let world = World::from_pak(pak_name).unwrap();
+            for room_info in rooms.iter() {
+                let room = match level_data.get(world.to_json_key())
+                    .and_then(|level| level.rooms.get(room_info.name().trim()))
+                {
+                    Some(room) => room,
+                    None => continue,
+                };
+                let skip_fn_ids: Vec<u32> = room.cutscene_skip_fns.iter().flatten().copied()
+                    .chain(
+                        room.special_functions.iter().flatten()
+                            .filter(|sf| sf.type_ == SpecialFunctionType::CinematicSkip)
+                            .filter_map(|sf| sf.id)
+                    )
+                    .collect();
+                if skip_fn_ids.is_empty() {
+                    continue;
+                }
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    move |ps, area| patch_cutscene_skip_prompt(ps, area, game_resources, &skip_fn_ids),
+                );
+            }
+        }
     }
 
     if config.disable_item_loss && !skip_frigate {
@@ -17293,28 +20927,176 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         );
     }
 
-    if config.suit_colors.is_some() {
-        let suit_colors = config.suit_colors.as_ref().unwrap();
-        let mut suit_textures = Vec::new();
-        let mut angles = Vec::new();
+    if let Some(suit_models) = config.suit_models.as_ref() {
+        let extern_assets_dir = config.extern_assets_dir.as_ref()
+            .ok_or_else(|| "suitModels requires externAssetsDir to be set".to_string())?;
 
-        if suit_colors.power_deg.is_some() {
-            suit_textures.push(POWER_SUIT_TEXTURES);
-            angles.push(suit_colors.power_deg.clone().unwrap());
+        let (power, varia, gravity, phazon) = if config.force_fusion {
+            (
+                resource_info!("fusion_suit_high_rez_bound.CMDL"),
+                resource_info!("fusion_varia_high_rez_bound.CMDL"),
+                resource_info!("fusion_gravity_high_rez_bound.CMDL"),
+                resource_info!("fusion_phazon_high_rez_bound.CMDL"),
+            )
+        } else {
+            (
+                resource_info!("powersuit_high_rez_bound.CMDL"),
+                resource_info!("variasuit_high_rez_bound.CMDL"),
+                resource_info!("gravitysuit_high_rez_bound.CMDL"),
+                resource_info!("phazon_suit_high_rez_bound.CMDL"),
+            )
+        };
+        let replacements = [
+            (&suit_models.power, power),
+            (&suit_models.varia, varia),
+            (&suit_models.gravity, gravity),
+            (&suit_models.phazon, phazon),
+        ];
+
+        for (file_name, cmdl) in replacements.iter() {
+            let file_name = match file_name {
+                Some(file_name) => file_name,
+                None => continue,
+            };
+
+            // The model is skinned by the vanilla CSKR unless one is provided alongside it
+            let (cmdl_bytes, mut assets) = read_standalone_asset(extern_assets_dir, file_name)?;
+            let cskr_file_name = Path::new(file_name).with_extension("CSKR");
+            let cskr_file_name = cskr_file_name.to_string_lossy();
+            let cskr_bytes = if Path::new(extern_assets_dir).join(&*cskr_file_name).exists() {
+                let (cskr_bytes, cskr_assets) = read_standalone_asset(extern_assets_dir, &cskr_file_name)?;
+                assets.extend(cskr_assets);
+                Some(cskr_bytes)
+            } else {
+                None
+            };
+
+            // Every PAK with Samus in it gets its own copy of the model's textures
+            let assets = std::rc::Rc::new(assets);
+            for pak in cmdl.paks.iter() {
+                let assets = assets.clone();
+                patcher.add_file_patch(pak, move |file| add_suit_model_assets(file, &assets));
+            }
+
+            patcher.add_resource_patch(
+                (*cmdl).into(),
+                move |res| replace_resource_bytes(res, cmdl_bytes.clone())
+            );
+            if let Some(cskr_bytes) = cskr_bytes {
+                let cmdl_name = cmdl.long_name.rsplit('/').next().unwrap();
+                let cskr = SUIT_CSKRS.iter()
+                    .find(|cskr| cskr.long_name.ends_with(&cmdl_name.replace(".CMDL", ".CSKR")))
+                    .unwrap();
+                patcher.add_resource_patch(
+                    (*cskr).into(),
+                    move |res| replace_resource_bytes(res, cskr_bytes.clone())
+                );
+            }
+        }
+    }
+
+    // Some textures are used by more than one suit (or by a suit and its visor), their matrices are
+    // combined so they're only recolored once
+    fn add_recolor(
+        recolors: &mut Vec<(&'static [&'static [u8]], u32, [f32; 9])>,
+        paks: &'static [&'static [u8]],
+        id: u32,
+        matrix: [f32; 9],
+    )
+    {
+        match recolors.iter_mut().find(|recolor| recolor.1 == id) {
+            Some(recolor) => recolor.2 = multiply_color_matrices(matrix, recolor.2),
+            None => recolors.push((paks, id, matrix)),
+        }
+    }
+
+    let mut recolors = Vec::new();
+    if let Some(suit_colors) = config.suit_colors.as_ref() {
+        let suits = [
+            (POWER_SUIT_TEXTURES, suit_colors.power_deg, suit_colors.power.as_ref()),
+            (VARIA_SUIT_TEXTURES, suit_colors.varia_deg, suit_colors.varia.as_ref()),
+            (GRAVITY_SUIT_TEXTURES, suit_colors.gravity_deg, suit_colors.gravity.as_ref()),
+            (PHAZON_SUIT_TEXTURES, suit_colors.phazon_deg, suit_colors.phazon.as_ref()),
+        ];
+        for (textures, deg, adjustment) in suits.iter() {
+            if let Some(matrix) = suit_color_matrix(*deg, *adjustment) {
+                for texture in textures.iter() {
+                    add_recolor(&mut recolors, texture.paks, texture.res_id, matrix);
+                }
+            }
+        }
+        if let Some(matrix) = suit_color_matrix(None, suit_colors.visor.as_ref()) {
+            for texture in VISOR_TEXTURES {
+                add_recolor(&mut recolors, texture.paks, texture.res_id, matrix);
+            }
         }
-        if suit_colors.varia_deg.is_some() {
-            suit_textures.push(VARIA_SUIT_TEXTURES);
-            angles.push(suit_colors.varia_deg.clone().unwrap());
+        if let Some(matrix) = suit_color_matrix(None, suit_colors.beam.as_ref()) {
+            for cmdl in ARM_CANNON_MODELS {
+                for id in cmdl_texture_ids(gc_disc, cmdl)? {
+                    add_recolor(&mut recolors, cmdl.paks, id, matrix);
+                }
+            }
+        }
+    }
+    if let Some(beam_colors) = config.beam_colors.as_ref() {
+        let pak_name = "SamGunFx.pak";
+        let pak: structs::Pak = match gc_disc.find_file(pak_name).and_then(|f| f.file()) {
+            Some(structs::FstEntryFile::Pak(pak)) => pak.clone(),
+            Some(structs::FstEntryFile::Unknown(reader)) => reader.clone().read(()),
+            _ => Err(format!("Failed to read {}", pak_name))?,
+        };
+        let particles: HashMap<u32, (FourCC, Vec<u8>)> = pak.resources.iter()
+            .filter(|res| PARTICLE_FOURCCS.iter().any(|fourcc| res.fourcc() == FourCC::from_bytes(fourcc)))
+            .map(|res| (res.file_id, (res.fourcc(), crate::formats::resource_bytes(&res))))
+            .collect();
+
+        // Effects shared by several beams get all of their adjustments
+        let mut effects: Vec<(u32, FourCC, [f32; 9])> = Vec::new();
+        let beams = [&beam_colors.power, &beam_colors.wave, &beam_colors.ice, &beam_colors.plasma];
+        for (i, beam) in beams.iter().enumerate() {
+            let beam = match beam {
+                Some(beam) => beam,
+                None => continue,
+            };
+            let adjustments = [
+                (BEAM_SHOT_EFFECTS[i], beam.shot.as_ref()),
+                (BEAM_CHARGE_EFFECTS[i], beam.charge.as_ref()),
+            ];
+            for (roots, adjustment) in adjustments.iter() {
+                let matrix = match suit_color_matrix(None, *adjustment) {
+                    Some(matrix) => matrix,
+                    None => continue,
+                };
+                for (id, fourcc) in particle_effect_tree(&particles, roots) {
+                    match effects.iter_mut().find(|effect| effect.0 == id) {
+                        Some(effect) => effect.2 = multiply_color_matrices(matrix, effect.2),
+                        None => effects.push((id, fourcc, matrix)),
+                    }
+                }
+            }
         }
-        if suit_colors.gravity_deg.is_some() {
-            suit_textures.push(GRAVITY_SUIT_TEXTURES);
-            angles.push(suit_colors.gravity_deg.clone().unwrap());
+
+        for (id, fourcc, matrix) in effects {
+            let mut bytes = match particles.get(&id) {
+                Some((_, bytes)) => bytes.clone(),
+                None => Err(format!("Failed to find {:08X}.{} in {}", id, fourcc, pak_name))?,
+            };
+            recolor_particle_effect(&mut bytes, matrix);
+            patcher.add_resource_patch(
+                (&[pak_name.as_bytes()], id, fourcc),
+                move |res| replace_resource_bytes(res, bytes.clone())
+            );
         }
-        if suit_colors.phazon_deg.is_some() {
-            suit_textures.push(PHAZON_SUIT_TEXTURES);
-            angles.push(suit_colors.phazon_deg.clone().unwrap());
+    }
+
+    let spider_ball = config.ball_colors.as_ref().and_then(|ball_colors| ball_colors.spider.as_ref());
+    if let Some(matrix) = suit_color_matrix(None, spider_ball) {
+        for texture in SPIDER_BALL_TEXTURES {
+            add_recolor(&mut recolors, texture.paks, texture.res_id, matrix);
         }
+    }
 
+    if !recolors.is_empty() {
         let mut complained: bool = false;
         if !Path::new(&config.cache_dir).is_dir() {
             match fs::create_dir(&config.cache_dir) {
@@ -17325,16 +21107,11 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                 },
             }
         }
-        for i in 0..suit_textures.len() {
-            let angle = angles[i] % 360;
-            if angle == 0 {
-                continue;
-            }
-            let angle = angle as f32;
-
-            let cache_subdir = format!("{}/{}", config.cache_dir, angle);
+        for (paks, id, matrix) in recolors {
+            let matrix_bits: Vec<u32> = matrix.iter().map(|x| x.to_bits()).collect();
+            let cache_subdir = format!("{}/{:016x}", config.cache_dir, calculate_hash(&matrix_bits));
             if !Path::new(&cache_subdir).is_dir() {
-                match fs::create_dir(cache_subdir) {
+                match fs::create_dir(&cache_subdir) {
                     Ok(()) => {},
                     Err(error) => {
                         if !complained {
@@ -17345,78 +21122,34 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                 }
             }
 
-            let matrix = huerotate_matrix(angle);
-            for texture in suit_textures[i] {
-                patcher.add_resource_patch((*texture).into(), move |res| {
-                    let res_data;
-                    let data;
-                    let mut txtr: structs::Txtr = match &res.kind {
-                        structs::ResourceKind::Unknown(_, _) => {
-                            res_data = crate::ResourceData::new(res);
-                            data = res_data.decompress().into_owned();
-                            let mut reader = Reader::new(&data[..]);
-                            reader.read(())
-                        },
-                        structs::ResourceKind::External(_, _) => {
-                            res_data = crate::ResourceData::new_external(res);
-                            data = res_data.decompress().into_owned();
-                            let mut reader = Reader::new(&data[..]);
-                            reader.read(())
-                        },
-                        _ => panic!("Unsupported resource kind for recoloring."),
-                    };
-                    let mut w = txtr.width as usize;
-                    let mut h = txtr.height as usize;
-                    for mipmap in txtr.pixel_data.as_mut_vec() {
-                        let hash: u64 = calculate_hash(&mipmap.as_mut_vec().to_vec());
-                        // Read file contents to RAM
-                        let filename = format!("{}/{}/{}", config.cache_dir, angle, hash);
-                        let file_ok = File::open(&filename).is_ok();
-                        let file = File::open(&filename).ok();
-                        if file_ok && file.is_some() {
-                            let metadata = fs::metadata(&filename).expect("unable to read metadata");
-                            let mut bytes = vec![0; metadata.len() as usize];
-                            file.unwrap().read(&mut bytes)
-                                .map_err(|e| format!("Failed to read cache file: {}", e))?;
-                            *mipmap.as_mut_vec() = bytes;
-                        }
-                        else
-                        {
-                            let mut decompressed_bytes = vec![0u8; w * h * 4];
-                            cmpr_decompress(&mipmap.as_mut_vec()[..], h, w, &mut decompressed_bytes[..]);
-                            huerotate_in_place(&mut decompressed_bytes[..], w, h, matrix);
-                            cmpr_compress(&(decompressed_bytes[..]), w, h, &mut mipmap.as_mut_vec()[..]);
-                            // cache.insert(hash, mipmap.as_mut_vec().to_vec());
-                            match File::create(filename) {
-                                Ok(mut file) => {
-                                    match file.write_all(&mipmap.as_mut_vec().to_vec()) {
-                                        Ok(()) => {},
-                                        Err(error) => {
-                                            if !complained {
-                                                println!("Failed to write cache file for optimal suit rotation: {}", error);
-                                                complained = true;
-                                            }
-                                        },
-                                    }
-                                },
-                                Err(error) => {
-                                    if !complained {
-                                        println!("Failed to create cache file for optimal suit rotation: {}", error);
-                                        complained = true;
-                                    }
-                                },
-                            }
-                        }
-                        w = w / 2;
-                        h = h / 2;
-                    }
-                    let mut bytes = vec![];
-                    txtr.write_to(&mut bytes).unwrap();
-                    res.kind = structs::ResourceKind::External(bytes, b"TXTR".into());
-                    res.compressed = false;
-                    Ok(())
-                })
-            }
+            patcher.add_resource_patch(
+                (paks, id, FourCC::from_bytes(b"TXTR")),
+                move |res| recolor_suit_texture(res, &cache_subdir, matrix, &mut complained)
+            );
+        }
+    }
+
+    for (texture, png_path) in config.texture_overrides.iter() {
+        let id = match texture_override_id(texture) {
+            Some(id) => id,
+            None => named_resource_id(gc_disc, &paks, texture, b"TXTR".into())
+                .ok_or_else(|| format!("Unknown texture '{}' in textureOverrides", texture))?,
+        };
+
+        let png = fs::read(png_path)
+            .map_err(|e| format!("Failed to read texture override '{}': {}", png_path, e))?;
+        let image = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+            .map_err(|e| format!("Failed to decode texture override '{}': {}", png_path, e))?
+            .into_rgba8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let pixels = std::rc::Rc::new(image.into_raw());
+
+        for pak in paks.iter() {
+            let pixels = pixels.clone();
+            patcher.add_resource_patch(
+                (&[pak.as_bytes()], id, FourCC::from_bytes(b"TXTR")),
+                move |res| patch_texture_override(res, &pixels, width, height)
+            );
         }
     }
 
@@ -17458,6 +21191,9 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
                     starting_room,
                     config.version,
                     config.warp_to_start_delay_s,
+                    config.warp_to_start_fade,
+                    config.warp_to_start_sound.as_ref(),
+                    config.warp_to_start_confirm,
                 )
             );
         }
@@ -17468,10 +21204,109 @@ fn build_and_run_patches<'r>(gc_disc: &mut structs::GcDisc<'r>, config: &PatchCo
         );
     }
 
+    // Every room gets a filter once any world is graded, so the tint doesn't leak into the others
+    if !config.color_grading.is_empty() {
+        for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+            let world = World::from_pak(pak_name).unwrap();
+            let preset = config.color_grading.iter()
+                .find(|(world_key, _)| World::from_json_key(world_key) == world)
+                .map(|(_, preset)| *preset);
+            for room_info in rooms.iter() {
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    move |ps, area| patch_color_grading(ps, area, preset),
+                );
+            }
+        }
+    }
+
+    if config.sfx_gain != 1.0 {
+        const PAKS: &[&[u8]] = &[
+            b"AudioGrp.pak",
+            b"Metroid1.pak",
+            b"Metroid2.pak",
+            b"Metroid3.pak",
+            b"Metroid4.pak",
+            b"metroid5.pak",
+            b"Metroid6.pak",
+            b"Metroid7.pak",
+            b"Metroid8.pak",
+        ];
+        for pak in PAKS {
+            patcher.add_file_patch(pak, move |file| patch_agsc_sfx_gain(file, config.sfx_gain));
+        }
+    }
+
+    // After the music shuffle, so track gains follow the tracks to where they're played
+    if config.music_gain != 1.0 || config.sfx_gain != 1.0 || !config.music_track_gain.is_empty() {
+        for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+            for room_info in rooms.iter() {
+                patcher.add_scly_patch(
+                    (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                    move |ps, area| patch_audio_gain(
+                        ps,
+                        area,
+                        config.music_gain,
+                        config.sfx_gain,
+                        &config.music_track_gain,
+                    ),
+                );
+            }
+        }
+    }
+
+    register_plugins(&mut patcher, config)?;
+
+    // Raw property edits leave their objects as untyped bytes, so they go after everything that
+    // might still want to read or modify those objects
+    for (room, room_config) in other_patches.iter() {
+        if let Some(edit_properties) = room_config.edit_properties.as_ref() {
+            patcher.add_scly_patch(
+                *room,
+                move |ps, area| patch_edit_properties(ps, area, edit_properties.clone())
+            );
+        }
+    }
+
+    // Last, so the counts include everything the other patches added
+    for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+        let world = World::from_pak(pak_name).unwrap();
+        let level = match level_data.get(world.to_json_key()) {
+            Some(level) => level,
+            None => continue,
+        };
+
+        for room_info in rooms.iter() {
+            let room_name = room_info.name().trim();
+            if !level.rooms.contains_key(room_name) {
+                continue;
+            }
+
+            patcher.add_scly_patch(
+                (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                move |ps, area| patch_check_entity_budget(ps, area, room_name),
+            );
+        }
+    }
+
+    let vanilla_memory = memory_budget::estimate_area_memory(gc_disc);
+    let vanilla_save_bits = save_budget::estimate_save_bits(gc_disc);
+
     let time = Instant::now();
     patcher.run(gc_disc)?;
     println!("Created patches in {:?}", time.elapsed());
 
+    // Lets tools tell seeds apart from the disc header alone, see GcDiscHeader::seed_metadata
+    let seed_metadata = format!("seed={} hash={}", config.seed, seed_hash(config, &level_data)?);
+    if !gc_disc.header.set_seed_metadata(&seed_metadata) {
+        println!("Warning, the game name in the disc header leaves no room for the seed metadata");
+    }
+
+    write_spoiler(config, &level_data, &shuffled_positions.borrow(), enemy_shuffle.as_ref())?;
+
+    memory_budget::check_memory_budget(&vanilla_memory, &memory_budget::estimate_area_memory(gc_disc));
+    save_budget::check_save_budget(vanilla_save_bits, save_budget::estimate_save_bits(gc_disc));
+
     Ok(())
 }
 
@@ -17919,3 +21754,135 @@ fn patch_elite_research_door_lock<'r>(_ps: &mut PatcherState, area: &mut mlvl_wr
 
     Ok (())
 }
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    /// An external model with a CMDL on the disc and a texture which isn't, like the resources
+    /// collect_game_resources finds for it
+    fn extern_model_with_missing_texture()
+        -> (HashMap<String, ExternPickupModel>, HashMap<(u32, FourCC), structs::Resource<'static>>)
+    {
+        let cmdl = (0x5678, FourCC::from_bytes(b"CMDL"));
+        let txtr = (0x9ABC, FourCC::from_bytes(b"TXTR"));
+        let extern_models = HashMap::from([(
+            "Screw Attack".to_string(),
+            ExternPickupModel {
+                ancs: u32::MAX,
+                cmdl: cmdl.0,
+                scale: 1.0,
+                character: 0,
+                character_count: 0,
+                dependencies: vec![cmdl, txtr],
+            },
+        )]);
+        let game_resources = HashMap::from([(
+            cmdl,
+            crate::custom_assets::build_resource_raw(cmdl.0, structs::ResourceKind::External(vec![], cmdl.1)),
+        )]);
+        (extern_models, game_resources)
+    }
+
+    #[test]
+    fn pickup_model_falls_back_when_dependencies_are_missing()
+    {
+        let (extern_models, game_resources) = extern_model_with_missing_texture();
+        let pickup_config = PickupConfig {
+            pickup_type: "Missile".to_string(),
+            model: Some("Screw Attack".to_string()),
+            ..Default::default()
+        };
+        let (model, extern_model) = resolve_pickup_model(
+            &pickup_config,
+            PickupType::Missile,
+            &extern_models,
+            &game_resources,
+            |_| false,
+        );
+        assert_eq!(model, Some(PickupModel::Missile));
+        assert!(extern_model.is_none());
+    }
+
+    #[test]
+    fn pickup_model_uses_dependencies_already_in_the_area()
+    {
+        let (extern_models, game_resources) = extern_model_with_missing_texture();
+        let pickup_config = PickupConfig {
+            pickup_type: "Missile".to_string(),
+            model: Some("Screw Attack".to_string()),
+            ..Default::default()
+        };
+        let (model, extern_model) = resolve_pickup_model(
+            &pickup_config,
+            PickupType::Missile,
+            &extern_models,
+            &game_resources,
+            |dep| dep.asset_id == 0x9ABC && dep.asset_type == FourCC::from_bytes(b"TXTR"),
+        );
+        assert_eq!(model, None);
+        assert_eq!(extern_model.map(|model| model.cmdl), Some(0x5678));
+    }
+
+    #[test]
+    fn pickup_model_falls_back_for_missing_character()
+    {
+        let extern_models = HashMap::from([(
+            "Screw Attack".to_string(),
+            ExternPickupModel {
+                ancs: 0x1234,
+                cmdl: 0x5678,
+                scale: 1.0,
+                character: 2,
+                character_count: 2,
+                dependencies: vec![],
+            },
+        )]);
+        let pickup_config = PickupConfig {
+            pickup_type: "Missile".to_string(),
+            model: Some("Screw Attack".to_string()),
+            ..Default::default()
+        };
+        let (model, extern_model) = resolve_pickup_model(
+            &pickup_config,
+            PickupType::Missile,
+            &extern_models,
+            &HashMap::new(),
+            |_| false,
+        );
+        assert_eq!(model, Some(PickupModel::Missile));
+        assert!(extern_model.is_none());
+    }
+
+    #[test]
+    fn agsc_sfx_velocities_are_scaled()
+    {
+        let mut proj = vec![0u8; 0x68];
+        // A song group, which is left alone
+        proj[0x00..0x04].copy_from_slice(&0x28u32.to_be_bytes());
+        // An SFX group with two entries
+        proj[0x28..0x2C].copy_from_slice(&0x68u32.to_be_bytes());
+        proj[0x2E..0x30].copy_from_slice(&1u16.to_be_bytes());
+        proj[0x48..0x4C].copy_from_slice(&0x50u32.to_be_bytes());
+        proj[0x50..0x52].copy_from_slice(&2u16.to_be_bytes());
+        proj[0x54 + 6] = 0x40;
+        proj[0x54 + 10 + 6] = 0x70;
+        proj.extend_from_slice(&0xFFFFFFFFu32.to_be_bytes());
+
+        let mut agsc = b"Audio/\0test\0".to_vec();
+        agsc.extend_from_slice(&0u32.to_be_bytes());
+        agsc.extend_from_slice(&(proj.len() as u32).to_be_bytes());
+        agsc.extend_from_slice(&proj);
+        agsc.extend_from_slice(&[0; 8]);
+        let proj_start = 12 + 8;
+
+        scale_agsc_sfx_volumes(&mut agsc, 1.5).unwrap();
+        assert_eq!(agsc[proj_start + 0x54 + 6], 0x60);
+        // Already near the limit, so it can only go up to it
+        assert_eq!(agsc[proj_start + 0x54 + 10 + 6], 0x7F);
+        assert_eq!(&agsc[proj_start..proj_start + 0x28], &proj[..0x28]);
+
+        assert!(scale_agsc_sfx_volumes(&mut agsc[..40], 1.5).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use std::mem;
 
+use schemars::JsonSchema;
 use serde::{Serialize, Deserialize};
 
 use reader_writer::{FourCC, Reader};
@@ -10,7 +11,7 @@ use crate::custom_assets::custom_asset_ids;
 /**
  * Pickup kind as defined by the game engine
  */
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, JsonSchema)]
 pub enum PickupType
 {
     PowerBeam = 0,
@@ -316,6 +317,11 @@ pub fn pickup_model_for_pickup(pickup: &structs::Pickup) -> Option<PickupModel>
     }
 }
 
+/* Model/type combinations which crash the game. Add to this as they're found, pickups using
+   one of these are given the default model for their type instead */
+const INCOMPATIBLE_PICKUP_MODELS: &[(PickupModel, PickupType)] = &[
+];
+
 /* CMDL which exist in the vanilla game, or are custom-made for randomprime */
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -421,6 +427,12 @@ impl PickupModel
         }
     }
 
+    /// Whether this model can safely be used for a pickup of the given type
+    pub fn is_compatible_with(&self, pickup_type: PickupType) -> bool
+    {
+        !INCOMPATIBLE_PICKUP_MODELS.contains(&(*self, pickup_type))
+    }
+
     pub fn pickup_data<'a>(&self) -> Pickup
     {
         let mut pickup: Pickup = Reader::new(self.raw_pickup_data()).read(());
@@ -0,0 +1,234 @@
+use rand::{
+    rngs::StdRng,
+    seq::SliceRandom,
+    SeedableRng,
+};
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+};
+
+use crate::{
+    elevators::World,
+    patch_config::{FillMode, LevelConfig, PickupConfig},
+    pickup_meta::{self, PickupType},
+    GcDiscLookupExtensions,
+};
+
+/// The vanilla game's main Power Bomb gives 4, its expansions give 1
+const MAIN_POWER_BOMB_CAPACITY: i32 = 4;
+
+/// How many times the pool is reshuffled before giving up on getting a layout that's accepted
+const MAX_PLACEMENT_ATTEMPTS: u32 = 1000;
+
+/// A pickup location which `level_data` doesn't put an item in
+struct FreeLocation
+{
+    world_key: &'static str,
+    room_name: &'static str,
+    idx: usize,
+    major: bool,
+}
+
+/// Whether the pickup in a vanilla location is a minor item, i.e. an expansion. The Missile
+/// Launcher looks just like a Missile Expansion, so its location counts as minor too.
+fn is_minor_location(pickup: &structs::Pickup) -> bool
+{
+    pickup.kind == PickupType::Missile.kind() ||
+        (pickup.kind == PickupType::PowerBomb.kind() && pickup.max_increase < MAIN_POWER_BOMB_CAPACITY)
+}
+
+fn is_minor_item(pickup_type: PickupType) -> bool
+{
+    match pickup_type {
+        PickupType::Missile |
+        PickupType::PowerBomb |
+        PickupType::HealthRefill |
+        PickupType::Nothing |
+        PickupType::FloatyJump |
+        PickupType::IceTrap => true,
+        _ => false,
+    }
+}
+
+/// Every pickup location which `level_data` leaves empty, in a fixed order. With `classify`
+/// the vanilla item in each location is looked up to tell major and minor locations apart.
+fn free_locations(
+    gc_disc: &structs::GcDisc,
+    level_data: &BTreeMap<String, LevelConfig>,
+    classify: bool,
+)
+    -> Result<Vec<FreeLocation>, String>
+{
+    let mut locations = vec![];
+    for (pak_name, room_infos) in pickup_meta::ROOM_INFO.iter() {
+        let world = World::from_pak(pak_name).unwrap();
+
+        let pak = if classify {
+            let file_entry = gc_disc.find_file(pak_name)
+                .ok_or_else(|| format!("Could not find {} in the input ISO", pak_name))?;
+            Some(match *file_entry.file().unwrap() {
+                structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
+                structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
+                _ => panic!(),
+            })
+        } else {
+            None
+        };
+
+        for room_info in room_infos.iter() {
+            let room_name = room_info.name().trim();
+            let placed = level_data.get(world.to_json_key())
+                .and_then(|level| level.rooms.get(room_name))
+                .and_then(|room| room.pickups.as_ref())
+                .map(|pickups| pickups.len())
+                .unwrap_or(0);
+            if placed >= room_info.pickup_locations.len() {
+                continue;
+            }
+
+            // Everything counts as major when the locations aren't classified
+            let mut majors = vec![true; room_info.pickup_locations.len()];
+            if let Some(pak) = pak.as_ref() {
+                let mrea_id = room_info.room_id.to_u32();
+                let mut res = pak.resources.iter()
+                    .find(|res| res.file_id == mrea_id)
+                    .ok_or_else(|| format!("Could not find room 0x{:X} in {}", mrea_id, pak_name))?
+                    .into_owned();
+                let scly = res.kind.as_mrea_mut().unwrap().scly_section_mut();
+                for (major, pickup_location) in majors.iter_mut().zip(room_info.pickup_locations.iter()) {
+                    let location = pickup_location.location;
+                    let obj = scly.layers.as_mut_vec()[location.layer as usize].objects.as_mut_vec().iter()
+                        .find(|obj| obj.instance_id == location.instance_id)
+                        .ok_or_else(|| format!("Could not find the pickup 0x{:X} in {}", location.instance_id, room_name))?;
+                    *major = !is_minor_location(&obj.property_data.as_pickup().unwrap());
+                }
+            }
+
+            for idx in placed..room_info.pickup_locations.len() {
+                locations.push(FreeLocation {
+                    world_key: world.to_json_key(),
+                    room_name,
+                    idx,
+                    major: majors[idx],
+                });
+            }
+        }
+    }
+
+    Ok(locations)
+}
+
+/// Shuffles `items` into `locations`, returning the placements sorted by location
+fn shuffle_placements<'a>(
+    locations: &'a [FreeLocation],
+    items: &[(PickupType, bool)],
+    fill_mode: FillMode,
+    rng: &mut StdRng,
+)
+    -> Vec<(&'a FreeLocation, (PickupType, bool))>
+{
+    let mut items = items.to_vec();
+    items.shuffle(rng);
+
+    let mut placements: Vec<(&FreeLocation, (PickupType, bool))> = vec![];
+    let mut remaining: Vec<&FreeLocation> = match fill_mode {
+        FillMode::Random => locations.iter().collect(),
+        FillMode::MajorMinor => {
+            let (majors, minors): (Vec<_>, Vec<_>) = items.into_iter()
+                .partition(|(pt, main)| *main || !is_minor_item(*pt));
+            items = minors;
+
+            // Upgrades which don't fit in the major locations spill over into the minor ones
+            let mut major_locations: Vec<&FreeLocation> = locations.iter().filter(|l| l.major).collect();
+            let mut minor_locations: Vec<&FreeLocation> = locations.iter().filter(|l| !l.major).collect();
+            major_locations.shuffle(rng);
+            minor_locations.shuffle(rng);
+            let mut ordered = major_locations.into_iter().chain(minor_locations.into_iter());
+            for item in majors {
+                placements.push((ordered.next().unwrap(), item));
+            }
+            ordered.collect()
+        },
+    };
+    remaining.shuffle(rng);
+    placements.extend(remaining.into_iter().zip(items.into_iter()));
+    placements.sort_by_key(|(location, _)| (location.world_key, location.room_name, location.idx));
+    placements
+}
+
+/// Places the items of `pool` in the pickup locations `level_data` leaves empty, deterministically
+/// from `seed`. With `FillMode::MajorMinor` upgrades go where the vanilla game has upgrades and
+/// expansions go where it has expansions, for as long as there's room. Locations left over once
+/// the pool runs out stay empty.
+///
+/// Placements are reshuffled until `is_accepted` returns true for the resulting level data.
+pub fn place_pickup_pool<F>(
+    gc_disc: &structs::GcDisc,
+    level_data: &mut BTreeMap<String, LevelConfig>,
+    pool: &BTreeMap<PickupType, u32>,
+    fill_mode: FillMode,
+    seed: u64,
+    mut is_accepted: F,
+)
+    -> Result<(), String>
+    where F: FnMut(&BTreeMap<String, LevelConfig>) -> bool
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut items = vec![];
+    for (pickup_type, count) in pool.iter() {
+        for _ in 0..*count {
+            items.push(*pickup_type);
+        }
+    }
+
+    let locations = free_locations(gc_disc, level_data, fill_mode == FillMode::MajorMinor)?;
+    if items.len() > locations.len() {
+        Err(format!(
+            "pickupPool has {} items, but there are only {} pickup locations left to put them in",
+            items.len(),
+            locations.len(),
+        ))?
+    }
+
+    // The first Power Bomb stands in for the main Power Bomb, the rest are expansions
+    let main_power_bomb = items.iter().position(|pt| *pt == PickupType::PowerBomb);
+    let items: Vec<(PickupType, bool)> = items.into_iter()
+        .enumerate()
+        .map(|(i, pt)| (pt, Some(i) == main_power_bomb))
+        .collect();
+
+    for _ in 0..MAX_PLACEMENT_ATTEMPTS {
+        let mut placed_level_data = level_data.clone();
+        for (location, (pickup_type, main)) in shuffle_placements(&locations, &items, fill_mode, &mut rng) {
+            let level = placed_level_data.entry(location.world_key.to_string()).or_default();
+            let room = level.rooms.entry(location.room_name.to_string()).or_default();
+            let pickups = room.pickups.get_or_insert_with(Vec::new);
+
+            // Locations before this one in the room which didn't get an item stay empty
+            while pickups.len() < location.idx {
+                pickups.push(PickupConfig {
+                    pickup_type: PickupType::Nothing.name().to_string(),
+                    curr_increase: Some(0),
+                    max_increase: Some(0),
+                    ..Default::default()
+                });
+            }
+
+            pickups.push(PickupConfig {
+                pickup_type: pickup_type.name().to_string(),
+                curr_increase: if main { Some(MAIN_POWER_BOMB_CAPACITY) } else { None },
+                ..Default::default()
+            });
+        }
+
+        if is_accepted(&placed_level_data) {
+            *level_data = placed_level_data;
+            return Ok(());
+        }
+    }
+
+    Err(format!("Failed to place pickupPool so the seed can be beaten after {} attempts", MAX_PLACEMENT_ATTEMPTS))
+}
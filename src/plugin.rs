@@ -0,0 +1,67 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    patch_config::PatchConfig,
+    patcher::PrimePatcher,
+};
+
+/// Patches which live outside of randomprime, added to `PatchConfig::plugins` by programs using
+/// it as a library.
+///
+/// `register` gets the same patcher the built-in patches are added to, so plugins can edit rooms
+/// with `add_scly_patch` and resources with `add_resource_patch` like they do. Plugins register
+/// after every built-in patch, so for any room or resource theirs run last, in the order the
+/// plugins were added. The only exception is the check of each room's entity budget, which runs
+/// after the plugins so it counts the objects they add.
+pub trait PatchPlugin: Send + Sync
+{
+    /// Used in error messages and logs
+    fn name(&self) -> &str;
+
+    fn register<'r, 's>(&'s self, patcher: &mut PrimePatcher<'r, 's>, config: &'s PatchConfig)
+        -> Result<(), String>;
+}
+
+#[derive(Clone, Default)]
+pub struct PatchPlugins(Vec<Arc<dyn PatchPlugin>>);
+
+impl PatchPlugins
+{
+    pub fn add<P: PatchPlugin + 'static>(&mut self, plugin: P)
+    {
+        self.0.push(Arc::new(plugin));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn PatchPlugin>
+    {
+        self.0.iter().map(|plugin| &**plugin)
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for PatchPlugins
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.debug_list().entries(self.iter().map(|plugin| plugin.name())).finish()
+    }
+}
+
+/// Registers every plugin's patches
+pub(crate) fn register_plugins<'r, 's>(patcher: &mut PrimePatcher<'r, 's>, config: &'s PatchConfig)
+    -> Result<(), String>
+{
+    for plugin in config.plugins.iter() {
+        plugin.register(patcher, config)
+            .map_err(|e| format!("Plugin {} failed to register its patches: {}", plugin.name(), e))?;
+        if !config.quiet {
+            println!("Registered the patches of plugin {}", plugin.name());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use reader_writer::byteorder::{BigEndian, ByteOrder};
+use structs::{LaggedFibonacciGenerator, LFG_SEED_SIZE};
+
+use std::{
+    borrow::Cow,
+    cmp::min,
+    io::Read,
+};
+
+// Format documentation
+// https://github.com/dolphin-emu/dolphin/blob/8f460a1cda1a4d4208c4da9e01bf775f5f704498/docs/WiaAndRvz.md
+// https://github.com/dolphin-emu/dolphin/blob/8f460a1cda1a4d4208c4da9e01bf775f5f704498/Source/Core/DiscIO/WIABlob.cpp
+
+const RVZ_MAGIC: &[u8; 4] = b"RVZ\x01";
+const HEADER_1_SIZE: usize = 0x48;
+const HEADER_2_SIZE: usize = 0xDC;
+const DISC_HEADER_SIZE: usize = 0x80;
+const SECTOR_SIZE: usize = 0x8000;
+
+const RAW_DATA_ENTRY_SIZE: usize = 24;
+const GROUP_ENTRY_SIZE: usize = 12;
+
+const DISC_TYPE_GAMECUBE: u32 = 1;
+
+const COMPRESSION_NONE: u32 = 0;
+const COMPRESSION_ZSTD: u32 = 5;
+
+pub fn is_rvz(data: &[u8]) -> bool
+{
+    data.len() >= HEADER_1_SIZE + HEADER_2_SIZE && &data[0..4] == RVZ_MAGIC
+}
+
+struct RawDataEntry
+{
+    data_offset: usize,
+    data_size: usize,
+    group_index: usize,
+    number_of_groups: usize,
+}
+
+struct GroupEntry
+{
+    data_offset: usize,
+    data_size: u32,
+    rvz_packed_size: u32,
+}
+
+/// Decompresses an entire GameCube RVZ image into an anonymous memory map
+pub fn decompress_rvz(data: &[u8]) -> Result<memmap::Mmap, String>
+{
+    if !is_rvz(data) {
+        Err("Not an RVZ file".to_string())?
+    }
+
+    let iso_file_size = BigEndian::read_u64(&data[0x24..0x2C]) as usize;
+
+    let header_2 = &data[HEADER_1_SIZE..HEADER_1_SIZE + HEADER_2_SIZE];
+    let disc_type = BigEndian::read_u32(&header_2[0x00..0x04]);
+    let compression_type = BigEndian::read_u32(&header_2[0x04..0x08]);
+    let chunk_size = BigEndian::read_u32(&header_2[0x0C..0x10]) as usize;
+    let disc_header = &header_2[0x10..0x10 + DISC_HEADER_SIZE];
+    let num_raw_data_entries = BigEndian::read_u32(&header_2[0xB4..0xB8]) as usize;
+    let raw_data_entries_offset = BigEndian::read_u64(&header_2[0xB8..0xC0]) as usize;
+    let raw_data_entries_size = BigEndian::read_u32(&header_2[0xC0..0xC4]) as usize;
+    let num_group_entries = BigEndian::read_u32(&header_2[0xC4..0xC8]) as usize;
+    let group_entries_offset = BigEndian::read_u64(&header_2[0xC8..0xD0]) as usize;
+    let group_entries_size = BigEndian::read_u32(&header_2[0xD0..0xD4]) as usize;
+
+    if disc_type != DISC_TYPE_GAMECUBE {
+        Err("Only GameCube RVZ images are supported".to_string())?
+    }
+    if compression_type != COMPRESSION_NONE && compression_type != COMPRESSION_ZSTD {
+        Err(format!(
+            "Unsupported RVZ compression method ({}), please recompress using Zstandard",
+            compression_type
+        ))?
+    }
+    if chunk_size == 0 {
+        Err("RVZ header is corrupt".to_string())?
+    }
+
+    let raw_data_entries = read_table(data, raw_data_entries_offset, raw_data_entries_size, compression_type)?;
+    let raw_data_entries: Vec<RawDataEntry> = raw_data_entries
+        .chunks_exact(RAW_DATA_ENTRY_SIZE)
+        .take(num_raw_data_entries)
+        .map(|e| RawDataEntry {
+            data_offset: BigEndian::read_u64(&e[0..8]) as usize,
+            data_size: BigEndian::read_u64(&e[8..16]) as usize,
+            group_index: BigEndian::read_u32(&e[16..20]) as usize,
+            number_of_groups: BigEndian::read_u32(&e[20..24]) as usize,
+        })
+        .collect();
+
+    let group_entries = read_table(data, group_entries_offset, group_entries_size, compression_type)?;
+    let group_entries: Vec<GroupEntry> = group_entries
+        .chunks_exact(GROUP_ENTRY_SIZE)
+        .take(num_group_entries)
+        .map(|e| GroupEntry {
+            data_offset: (BigEndian::read_u32(&e[0..4]) as usize) << 2,
+            data_size: BigEndian::read_u32(&e[4..8]),
+            rvz_packed_size: BigEndian::read_u32(&e[8..12]),
+        })
+        .collect();
+
+    let mut output = memmap::MmapMut::map_anon(iso_file_size)
+        .map_err(|e| format!("Failed to allocate memory for RVZ decompression: {}", e))?;
+
+    for entry in &raw_data_entries {
+        // Raw data always starts on a sector boundary, even if the entry itself doesn't
+        let skipped_data = entry.data_offset % SECTOR_SIZE;
+        let data_offset = entry.data_offset - skipped_data;
+        let data_size = entry.data_size + skipped_data;
+
+        for i in 0..entry.number_of_groups {
+            let group = group_entries.get(entry.group_index + i)
+                .ok_or_else(|| "RVZ group table is corrupt".to_string())?;
+
+            let group_offset_in_data = i * chunk_size;
+            let out_start = data_offset + group_offset_in_data;
+            let out_end = min(out_start + min(chunk_size, data_size - group_offset_in_data), iso_file_size);
+            if out_start >= out_end {
+                break;
+            }
+
+            let is_compressed = group.data_size & 0x80000000 != 0;
+            let group_data_size = (group.data_size & 0x7FFFFFFF) as usize;
+            if group_data_size == 0 {
+                // The whole group is zeroes
+                continue;
+            }
+
+            let group_data = data.get(group.data_offset..group.data_offset + group_data_size)
+                .ok_or_else(|| format!("RVZ group {} is out of bounds", entry.group_index + i))?;
+            let group_data = if is_compressed && compression_type == COMPRESSION_ZSTD {
+                Cow::Owned(zstd_decompress(group_data)?)
+            } else {
+                Cow::Borrowed(group_data)
+            };
+
+            let out = &mut output[out_start..out_end];
+            if group.rvz_packed_size != 0 {
+                rvz_unpack(&group_data, out, out_start)?;
+            } else {
+                let len = min(group_data.len(), out.len());
+                out[..len].copy_from_slice(&group_data[..len]);
+            }
+        }
+    }
+
+    // The first 0x80 bytes are always stored in the header rather than in the raw data
+    let len = min(DISC_HEADER_SIZE, iso_file_size);
+    output[..len].copy_from_slice(&disc_header[..len]);
+
+    output.make_read_only()
+        .map_err(|e| format!("Failed to decompress RVZ: {}", e))
+}
+
+fn read_table(data: &[u8], offset: usize, size: usize, compression_type: u32)
+    -> Result<Cow<'_, [u8]>, String>
+{
+    let table = data.get(offset..offset + size)
+        .ok_or_else(|| "RVZ header is corrupt".to_string())?;
+    if compression_type == COMPRESSION_ZSTD {
+        Ok(Cow::Owned(zstd_decompress(table)?))
+    } else {
+        Ok(Cow::Borrowed(table))
+    }
+}
+
+fn zstd_decompress(data: &[u8]) -> Result<Vec<u8>, String>
+{
+    let mut decoder = ruzstd::StreamingDecoder::new(data)
+        .map_err(|e| format!("Failed to decompress RVZ data: {}", e))?;
+    let mut result = Vec::new();
+    decoder.read_to_end(&mut result)
+        .map_err(|e| format!("Failed to decompress RVZ data: {}", e))?;
+    Ok(result)
+}
+
+/// Expands RVZ packed data, which is a sequence of literal runs and runs of junk data that are
+/// regenerated from a seed
+fn rvz_unpack(packed: &[u8], out: &mut [u8], disc_offset: usize) -> Result<(), String>
+{
+    let mut pos = 0;
+    let mut out_pos = 0;
+    while out_pos < out.len() {
+        let size = packed.get(pos..pos + 4)
+            .map(BigEndian::read_u32)
+            .ok_or_else(|| "RVZ packed data is truncated".to_string())?;
+        pos += 4;
+
+        let is_junk = size & 0x80000000 != 0;
+        let size = (size & 0x7FFFFFFF) as usize;
+        let len = min(size, out.len() - out_pos);
+
+        if is_junk {
+            let seed = packed.get(pos..pos + LFG_SEED_SIZE * 4)
+                .ok_or_else(|| "RVZ packed data is truncated".to_string())?;
+            pos += LFG_SEED_SIZE * 4;
+
+            let mut lfg = LaggedFibonacciGenerator::new(seed);
+            lfg.skip((disc_offset + out_pos) % SECTOR_SIZE);
+            lfg.get_bytes(&mut out[out_pos..out_pos + len]);
+        } else {
+            let bytes = packed.get(pos..pos + len)
+                .ok_or_else(|| "RVZ packed data is truncated".to_string())?;
+            out[out_pos..out_pos + len].copy_from_slice(bytes);
+            pos += size;
+        }
+        out_pos += len;
+    }
+    Ok(())
+}
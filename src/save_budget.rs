@@ -0,0 +1,139 @@
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+};
+
+use crate::{
+    elevators::World,
+    GcDiscLookupExtensions,
+};
+
+/// Each of the 3 save slots is serialized into a buffer of this size. The game's file on the
+/// memory card always takes the same 3 of a standard card's 59 blocks, so it's the slots which
+/// run out of space, not the card.
+const SAVE_SLOT_BYTES: usize = 940;
+
+/// Rough size of what's saved regardless of the worlds: the player's inventory and health,
+/// options, play time and which world they're in
+const FIXED_SAVE_BITS: usize = 1024;
+
+/// Bits the game uses to store a value between 0 and `max`
+fn bit_count(max: usize) -> usize
+{
+    (usize::BITS - max.leading_zeros()) as usize
+}
+
+/// Estimates the size of a save slot, in bits. It's an upper bound: every memory relay which can
+/// be saved is counted as active. Worlds whose PAK no longer has its MLVL, like the Frigate's once
+/// `empty_frigate_pak` is done with it, are skipped.
+pub fn estimate_save_bits(gc_disc: &structs::GcDisc) -> usize
+{
+    let mut bits = FIXED_SAVE_BITS;
+    let mut scans = HashSet::new();
+    for world in World::iter() {
+        let file_entry = match gc_disc.find_file(world.to_pak_str()) {
+            Some(file_entry) => file_entry,
+            None => continue,
+        };
+        let pak = match *file_entry.file().unwrap() {
+            structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
+            structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
+            _ => panic!(),
+        };
+        bits += estimate_world_save_bits(&pak, world, &mut scans);
+    }
+    bits += scans.len();
+
+    bits
+}
+
+/// Bits saved for `world`, except for its scans, which are added to `scans`
+fn estimate_world_save_bits(pak: &structs::Pak, world: World, scans: &mut HashSet<u32>) -> usize
+{
+    let mut mlvl = match pak.resources.iter().find(|res| res.file_id == world.mlvl()) {
+        Some(res) => res.into_owned(),
+        None => return 0,
+    };
+    let mlvl = mlvl.kind.as_mlvl_mut().unwrap();
+    let savw_id = mlvl.world_savw.to_u32();
+    let area_count = mlvl.areas.len();
+
+    let mut savw = match pak.resources.iter().find(|res| res.file_id == savw_id) {
+        Some(res) => res.into_owned(),
+        None => return 0,
+    };
+    let savw = savw.kind.as_savw_mut().unwrap();
+
+    // The area Samus is in and the world's asset id
+    let mut bits = 64;
+
+    // One bit per layer of every area
+    bits += mlvl.area_layer_flags.iter()
+        .map(|flags| flags.layer_count as usize)
+        .sum::<usize>();
+
+    // Whether each area was visited and mapped
+    bits += area_count * 2;
+
+    // Active memory relays are saved as indices into the list of the ones which can be
+    let relay_count = savw.memory_relay_array.len();
+    bits += bit_count(relay_count) * (relay_count + 1);
+
+    // Whether each door was opened
+    bits += savw.door_array.len();
+
+    // Scans are shared by every world, each gets a bit once
+    scans.extend(savw.scan_array.iter().map(|scan| scan.scan.to_u32()));
+
+    bits
+}
+
+/// Whether `patched_bits` no longer fit in a save slot. Saves which were already too large in
+/// the vanilla game don't count.
+fn exceeds_save_slot(vanilla_bits: usize, patched_bits: usize) -> bool
+{
+    patched_bits > SAVE_SLOT_BYTES * 8 && patched_bits > vanilla_bits
+}
+
+/// Warns when patching made the save data outgrow a save slot, which corrupts saves on console.
+/// Like `memory_budget`, vanilla is the reference, the estimate is only a rough upper bound.
+///
+/// Growing the slots isn't supported: their size and the game's 3 blocks are fixed by the DOL, so
+/// the memory card screens' "3 blocks" strings are never out of date and aren't patched. The only
+/// fix for a warning is to save less, e.g. fewer added layers or memory relays.
+pub fn check_save_budget(vanilla_bits: usize, patched_bits: usize)
+{
+    if exceeds_save_slot(vanilla_bits, patched_bits) {
+        println!(
+            "Warning, the save data needs about {} bytes (up from {}) of the {} a save slot holds, saving may fail on console",
+            (patched_bits + 7) / 8, (vanilla_bits + 7) / 8, SAVE_SLOT_BYTES,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn emptied_pak_is_skipped()
+    {
+        let pak = crate::memory_budget::test::emptied_pak();
+        let mut scans = HashSet::new();
+        assert_eq!(estimate_world_save_bits(&pak, World::FrigateOrpheon, &mut scans), 0);
+        assert!(scans.is_empty());
+    }
+
+    #[test]
+    fn only_growing_past_a_slot_warns()
+    {
+        let budget = SAVE_SLOT_BYTES * 8;
+        assert!(!exceeds_save_slot(budget - 100, budget));
+        assert!(exceeds_save_slot(budget - 100, budget + 1));
+        // Already too large in vanilla, and not any larger
+        assert!(!exceeds_save_slot(budget + 50, budget + 50));
+        assert!(!exceeds_save_slot(budget + 50, budget + 10));
+        assert!(exceeds_save_slot(budget + 50, budget + 51));
+    }
+}
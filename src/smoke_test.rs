@@ -0,0 +1,144 @@
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    patch_config::{PatchConfig, SmokeTestConfig},
+    patches::patch_disc,
+};
+
+const DEFAULT_SECONDS: u32 = 30;
+
+/// What Dolphin logs when the emulated game crashes. With panic handlers enabled these are
+/// printed instead of stopping the emulation, so they're the only sign of a crash.
+const CRASH_MESSAGES: &[&str] = &[
+    "Invalid read from",
+    "Invalid write to",
+    "Unknown instruction",
+    "Unknown opcode",
+    "Unhandled exception",
+];
+
+/// Boots `iso_path` in Dolphin and checks the game runs for the configured time without
+/// crashing. The ISO has to be patched with quickplay, so it goes straight to the starting room
+/// instead of waiting at the title screen.
+pub fn run_smoke_test(config: &SmokeTestConfig, iso_path: &str) -> Result<(), String>
+{
+    let duration = Duration::from_secs(config.seconds.unwrap_or(DEFAULT_SECONDS) as u64);
+
+    let mut dolphin = Command::new(&config.dolphin_path)
+        .arg("--batch")
+        .arg("--platform=headless")
+        .arg(format!("--exec={}", iso_path))
+        .arg("--config=Dolphin.Interface.UsePanicHandlers=True")
+        .arg("--config=Dolphin.Interface.ConfirmStop=False")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to launch Dolphin ({}): {}", config.dolphin_path, e))?;
+
+    // Dolphin prints its panic alerts on either stream depending on the version
+    let (sender, receiver) = mpsc::channel();
+    let stdout = dolphin.stdout.take().unwrap();
+    let stderr = dolphin.stderr.take().unwrap();
+    let stdout_sender = sender.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let _ = stdout_sender.send(line);
+        }
+    });
+    thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            let _ = sender.send(line);
+        }
+    });
+
+    let start = Instant::now();
+    let result = loop {
+        if let Some(line) = receiver.try_iter().find(|line| CRASH_MESSAGES.iter().any(|m| line.contains(m))) {
+            break Err(format!("The game crashed after {:.0?}: {}", start.elapsed(), line.trim()));
+        }
+        match dolphin.try_wait() {
+            Ok(Some(status)) => break Err(format!(
+                "Dolphin exited after {:.0?} ({}), the game probably crashed",
+                start.elapsed(),
+                status,
+            )),
+            Ok(None) => (),
+            Err(e) => break Err(format!("Failed to wait for Dolphin: {}", e)),
+        }
+        if start.elapsed() >= duration {
+            break Ok(());
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+
+    let _ = dolphin.kill();
+    let _ = dolphin.wait();
+    result
+}
+
+/// Patches a copy of the seed starting in each of `smoke_test.visit_rooms` and checks it loads
+/// in Dolphin. The copies are written next to the output ISO and deleted afterwards. Prints
+/// (and optionally writes) which rooms passed, and fails if any of them didn't.
+pub fn visit_rooms<T>(config: &mut PatchConfig, smoke_test: &SmokeTestConfig, pn: &mut T)
+    -> Result<(), String>
+    where T: structs::ProgressNotifier
+{
+    let rooms = match smoke_test.visit_rooms.as_ref() {
+        Some(rooms) if !rooms.is_empty() => rooms,
+        _ => return Ok(()),
+    };
+
+    let iso_path = format!("{}.visit.iso", config.output_iso_path);
+    let mut results = vec![];
+    for room in rooms.iter() {
+        if !config.quiet {
+            println!("Visiting {}", room);
+        }
+        config.starting_room = room.clone();
+        let result = patch_disc(config, |gc_disc| {
+            let mut file = File::create(&iso_path)
+                .map_err(|e| format!("Failed to create {}: {}", iso_path, e))?;
+            file.set_len(structs::GC_DISC_LENGTH as u64)
+                .map_err(|e| format!("Failed to resize {}: {}", iso_path, e))?;
+            gc_disc.write(&mut file, pn)
+                .map_err(|e| format!("Error writing {}: {}", iso_path, e))
+        })
+        .and_then(|()| run_smoke_test(smoke_test, &iso_path));
+        results.push((room, result));
+    }
+    let _ = fs::remove_file(&iso_path);
+
+    let mut report = vec![];
+    for (room, result) in results.iter() {
+        match result {
+            Ok(()) => println!("PASS {}", room),
+            Err(e) => println!("FAIL {}: {}", room, e),
+        }
+        report.push(serde_json::json!({
+            "room": room,
+            "passed": result.is_ok(),
+            "error": result.as_ref().err(),
+        }));
+    }
+    if let Some(report_path) = smoke_test.report_path.as_ref() {
+        let report = serde_json::to_string_pretty(&report)
+            .map_err(|e| format!("Failed to serialize smoke test report: {}", e))?;
+        fs::write(report_path, report)
+            .map_err(|e| format!("Failed to write smoke test report: {}", e))?;
+    }
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    if failed > 0 {
+        Err(format!("{} of {} rooms failed to load", failed, results.len()))?
+    }
+
+    Ok(())
+}
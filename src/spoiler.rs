@@ -0,0 +1,188 @@
+use serde::Serialize;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::Write,
+};
+
+use crate::{
+    custom_assets::PickupHashKey,
+    elevators::World,
+    enemy_shuffle::EnemyShuffle,
+    patch_config::{LevelConfig, PatchConfig},
+    pickup_meta,
+    starting_items::StartingItems,
+    starting_room::StartingSpawn,
+};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SpoilerPickup
+{
+    #[serde(rename = "type")]
+    pickup_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<[f32; 3]>, // only for pickups which don't stay in their vanilla location
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SpoilerDoor
+{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shield_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blast_shield_type: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SpoilerEnemy
+{
+    room: String,
+    instance_id: u32,
+    replaced_by_room: String,
+    replaced_by_instance_id: u32,
+}
+
+/// Where everything ended up once the seed's shuffles are done, for frontends to show players
+/// after the game. Worlds and rooms are named like in `levelData`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Spoiler
+{
+    seed: u64,
+    starting_room: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    starting_spawn: Option<StartingSpawn>,
+    starting_items: StartingItems,
+    pickups: BTreeMap<&'static str, BTreeMap<&'static str, Vec<SpoilerPickup>>>,
+    elevators: BTreeMap<&'static str, BTreeMap<String, String>>, // elevators not listed lead where they do in the vanilla game
+    doors: BTreeMap<&'static str, BTreeMap<&'static str, BTreeMap<u32, SpoilerDoor>>>, // by dock number
+    enemies: Vec<SpoilerEnemy>,
+}
+
+/// "<world>:<room>" for a room, or its MREA id if it isn't a known room
+fn room_string(mrea_id: u32) -> String
+{
+    pickup_meta::ROOM_INFO.iter()
+        .flat_map(|(pak_name, rooms)| rooms.iter().map(move |room_info| (pak_name, room_info)))
+        .find(|(_, room_info)| room_info.room_id.to_u32() == mrea_id)
+        .map(|(pak_name, room_info)| format!(
+            "{}:{}",
+            World::from_pak(pak_name).unwrap().to_json_key(),
+            room_info.name().trim(),
+        ))
+        .unwrap_or_else(|| format!("0x{:08X}", mrea_id))
+}
+
+/// Writes the spoiler for `level_data`, the layout after pickupPool and shuffles which happen
+/// before patching, to `config.spoiler_filename`. `shuffled_positions` are where pickups were
+/// moved to by the pickup position shuffle.
+pub fn write_spoiler(
+    config: &PatchConfig,
+    level_data: &BTreeMap<String, LevelConfig>,
+    shuffled_positions: &HashMap<PickupHashKey, [f32; 3]>,
+    enemy_shuffle: Option<&EnemyShuffle>,
+)
+    -> Result<(), String>
+{
+    let filename = match config.spoiler_filename.as_ref() {
+        Some(filename) => filename,
+        None => return Ok(()),
+    };
+
+    let mut pickups = BTreeMap::new();
+    let mut doors = BTreeMap::new();
+    for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
+        let world = World::from_pak(pak_name).unwrap();
+        let level = level_data.get(world.to_json_key());
+
+        for room_info in rooms.iter() {
+            let room_name = room_info.name().trim();
+            let room = level.and_then(|level| level.rooms.get(room_name));
+            let room_pickups = room.and_then(|room| room.pickups.as_ref());
+
+            // Locations the profile leaves empty get Nothing when patching
+            let pickup_count = room_pickups.map(|pickups| pickups.len())
+                .unwrap_or(0)
+                .max(room_info.pickup_locations.len());
+            let mut spoiler_pickups = vec![];
+            for idx in 0..pickup_count {
+                let pickup = room_pickups.and_then(|pickups| pickups.get(idx));
+                let key = PickupHashKey {
+                    level_id: world.mlvl(),
+                    room_id: room_info.room_id.to_u32(),
+                    pickup_idx: idx as u32,
+                };
+                spoiler_pickups.push(SpoilerPickup {
+                    pickup_type: pickup.map(|pickup| pickup.pickup_type.clone())
+                        .unwrap_or_else(|| "Nothing".to_string()),
+                    position: shuffled_positions.get(&key).copied()
+                        .or_else(|| pickup.and_then(|pickup| pickup.position)),
+                });
+            }
+            if !spoiler_pickups.is_empty() {
+                pickups.entry(world.to_json_key())
+                    .or_insert_with(BTreeMap::new)
+                    .insert(room_name, spoiler_pickups);
+            }
+
+            for (dock_num, door) in room.and_then(|room| room.doors.as_ref()).into_iter().flatten() {
+                if door.shield_type.is_none() && door.blast_shield_type.is_none() {
+                    continue;
+                }
+                doors.entry(world.to_json_key())
+                    .or_insert_with(BTreeMap::new)
+                    .entry(room_name)
+                    .or_insert_with(BTreeMap::new)
+                    .insert(*dock_num, SpoilerDoor {
+                        shield_type: door.shield_type.clone(),
+                        blast_shield_type: door.blast_shield_type.clone(),
+                    });
+            }
+        }
+    }
+
+    let elevators = World::iter()
+        .filter_map(|world| {
+            let transports = &level_data.get(world.to_json_key())?.transports;
+            if transports.is_empty() {
+                return None;
+            }
+            Some((world.to_json_key(), transports.clone()))
+        })
+        .collect();
+
+    let enemies = enemy_shuffle.map(|enemy_shuffle| enemy_shuffle.swaps())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|((mrea_id, instance_id), (source_mrea_id, source_instance_id))| SpoilerEnemy {
+            room: room_string(mrea_id),
+            instance_id,
+            replaced_by_room: room_string(source_mrea_id),
+            replaced_by_instance_id: source_instance_id,
+        })
+        .collect();
+
+    let spoiler = Spoiler {
+        seed: config.seed,
+        starting_room: config.starting_room.clone(),
+        starting_spawn: config.starting_spawn,
+        starting_items: config.starting_items.clone(),
+        pickups,
+        elevators,
+        doors,
+        enemies,
+    };
+
+    let json = serde_json::to_string_pretty(&spoiler)
+        .map_err(|e| format!("Failed to serialize spoiler: {}", e))?;
+    let mut file = File::create(filename)
+        .map_err(|e| format!("Failed to create spoiler file: {}", e))?;
+    file.write_all(json.as_bytes())
+        .map_err(|e| format!("Failed to write spoiler file: {}", e))?;
+
+    Ok(())
+}
@@ -0,0 +1,454 @@
+use rand::{
+    rngs::StdRng,
+    seq::SliceRandom,
+    SeedableRng,
+};
+
+use std::borrow::Cow;
+
+use reader_writer::CStrConversionExtension;
+use serde::Serialize;
+
+use crate::{
+    elevators::{Elevator, SpawnRoomData, World},
+    mlvl_wrapper,
+    patcher::PatcherState,
+    patches::door_spawn_point,
+    pickup_meta,
+    GcDiscLookupExtensions,
+};
+
+/// Rooms which can't be started in no matter where Samus spawns, and why
+const UNUSABLE_STARTING_ROOMS: &[(u32, &str)] = &[
+    (0x1A666C55, "the Metroid Prime Essence fight can't be left"), // Metroid Prime Lair
+    (0xB4B41C48, "it only plays the ending"), // End Cinema
+];
+
+/// Where Samus spawns in the starting room, when `startingRoom` picks it with "@<spawn>"
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum StartingSpawn
+{
+    /// "@2", counting the spawn points on the layers enabled on a new file
+    SpawnPoint(u32),
+    /// "@x,y,z", Samus faces the middle of the room
+    Position([f32; 3]),
+}
+
+/// What has to change for Samus to spawn safely in the starting room
+#[derive(Clone, Copy, Debug)]
+pub enum StartingRoomFixup
+{
+    /// The room has spawn points, but none is used when a new file starts there
+    ForceDefaultSpawn,
+    /// `startingRoom` picks one of the room's spawn points, which is made the only default one
+    UseSpawnPoint(u32),
+    /// The room has no spawn point, Samus comes out of a door instead
+    SpawnAtDoor
+    {
+        position: [f32; 3],
+        rotation: [f32; 3],
+        morphed: bool,
+    },
+    /// `startingRoom` gives where Samus spawns
+    SpawnAtPosition
+    {
+        position: [f32; 3],
+        rotation: [f32; 3],
+    },
+}
+
+/// "<world>:<room>" with the names used in `levelData` of a room given by `startingRoom`, or
+/// the name of the elevator
+fn canonical_starting_room(name: &str) -> Option<String>
+{
+    if let Some(elevator) = Elevator::from_str(name) {
+        return Some(elevator.name.to_string());
+    }
+
+    let (world_name, room_name) = name.split_once(':')?;
+    let world_name = world_name.trim().to_lowercase();
+    let world = World::iter()
+        .find(|world| world.to_json_key().to_lowercase().starts_with(&world_name))?;
+    pickup_meta::ROOM_INFO.iter()
+        .filter(|(pak_name, _)| World::from_pak(pak_name) == Some(world))
+        .flat_map(|(_, rooms)| rooms.iter())
+        .find(|room_info| room_info.name().trim().eq_ignore_ascii_case(room_name.trim()))
+        .map(|room_info| format!("{}:{}", world.to_json_key(), room_info.name().trim()))
+}
+
+/// Rooms `startingRoom: "random"` picks from by default. Save stations and elevators always have
+/// a spawn point and a way out, and the Landing Site is where the vanilla game starts.
+fn safe_starting_rooms() -> Vec<String>
+{
+    pickup_meta::ROOM_INFO.iter()
+        .flat_map(|(pak_name, rooms)| rooms.iter().map(move |room_info| (pak_name, room_info)))
+        .filter(|(pak_name, room_info)| {
+            let name = room_info.name().trim();
+            World::from_pak(pak_name) != Some(World::FrigateOrpheon) &&
+                (name.starts_with("Save Station") || name.starts_with("Transport to") || name == "Landing Site")
+        })
+        .map(|(pak_name, room_info)| format!(
+            "{}:{}",
+            World::from_pak(pak_name).unwrap().to_json_key(),
+            room_info.name().trim(),
+        ))
+        .collect()
+}
+
+/// Picks the starting room for `startingRoom: "random"` from `pool`, or the safe starting rooms
+/// if there's no pool, leaving out the rooms in `blocklist`
+pub fn pick_random_starting_room(pool: Option<&[String]>, blocklist: &[String], seed: u64)
+    -> Result<String, String>
+{
+    let canonical = |name: &String| canonical_starting_room(name)
+        .ok_or_else(|| format!("Unknown starting room '{}'", name));
+    let blocklist = blocklist.iter().map(canonical).collect::<Result<Vec<_>, _>>()?;
+    let candidates = match pool {
+        Some(pool) => pool.iter().map(canonical).collect::<Result<Vec<_>, _>>()?,
+        None => safe_starting_rooms(),
+    };
+    let candidates: Vec<String> = candidates.into_iter()
+        .filter(|room| !blocklist.contains(room))
+        .collect();
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    candidates.choose(&mut rng)
+        .cloned()
+        .ok_or_else(|| "Could not pick a random starting room, every room is blocked".to_string())
+}
+
+/// Splits "<world>:<room>@<spawn>" into the room and the spawn
+pub fn parse_starting_room(starting_room: &str) -> Result<(String, Option<StartingSpawn>), String>
+{
+    let (room, spawn) = match starting_room.split_once('@') {
+        Some((room, spawn)) => (room.trim(), spawn.trim()),
+        None => return Ok((starting_room.to_string(), None)),
+    };
+    if !room.contains(':') {
+        Err(format!(
+            "Could not use '{}' as the starting room, a spawn can only be picked in rooms given as <world>:<room>",
+            starting_room,
+        ))?
+    }
+
+    let invalid = || format!(
+        "Could not parse the spawn of starting room '{}', expected @<spawn point index> or @<x>,<y>,<z>",
+        starting_room,
+    );
+    let spawn = if spawn.contains(',') {
+        let coords = spawn.split(',')
+            .map(|coord| coord.trim().parse::<f32>().map_err(|_| invalid()))
+            .collect::<Result<Vec<f32>, String>>()?;
+        if coords.len() != 3 {
+            Err(invalid())?
+        }
+        StartingSpawn::Position([coords[0], coords[1], coords[2]])
+    } else {
+        StartingSpawn::SpawnPoint(spawn.parse().map_err(|_| invalid())?)
+    };
+
+    Ok((room.to_string(), Some(spawn)))
+}
+
+/// Rooms in the same world which are always safe to start in
+fn suggested_starting_rooms(starting_room: &SpawnRoomData) -> Vec<String>
+{
+    let world = World::from_pak(starting_room.pak_name).unwrap();
+    let rooms = pickup_meta::ROOM_INFO.iter()
+        .find(|(pak_name, _)| *pak_name == starting_room.pak_name)
+        .map(|(_, rooms)| *rooms)
+        .unwrap_or(&[]);
+
+    Elevator::iter()
+        .filter(|elv| elv.pak_name == starting_room.pak_name)
+        .filter_map(|elv| rooms.iter().find(|room_info| room_info.room_id.to_u32() == elv.mrea))
+        .map(|room_info| format!("{}:{}", world.to_json_key(), room_info.name().trim()))
+        .collect()
+}
+
+/// The starting room's MREA, which of its layers are enabled on a new file and its bounding box
+fn read_starting_room<'r>(gc_disc: &structs::GcDisc<'r>, starting_room: &SpawnRoomData)
+    -> Result<(u64, [f32; 6], structs::Resource<'r>), String>
+{
+    let file_entry = gc_disc.find_file(starting_room.pak_name)
+        .ok_or_else(|| format!("Could not find {} in the input ISO", starting_room.pak_name))?;
+    let pak = match *file_entry.file().unwrap() {
+        structs::FstEntryFile::Pak(ref pak) => Cow::Borrowed(pak),
+        structs::FstEntryFile::Unknown(ref reader) => Cow::Owned(reader.clone().read(())),
+        _ => panic!(),
+    };
+
+    let mut mlvl = pak.resources.iter()
+        .find(|res| res.file_id == starting_room.mlvl)
+        .ok_or_else(|| format!("Could not find the MLVL of {}", starting_room.pak_name))?
+        .into_owned();
+    let mlvl = mlvl.kind.as_mlvl_mut().unwrap();
+    let layer_flags = mlvl.area_layer_flags.iter()
+        .nth(starting_room.mrea_idx as usize)
+        .ok_or_else(|| format!("Could not find the layers of {}", starting_room.name.trim()))?
+        .flags;
+    let bounding_box: [f32; 6] = mlvl.areas.iter()
+        .nth(starting_room.mrea_idx as usize)
+        .ok_or_else(|| format!("Could not find the area of {}", starting_room.name.trim()))?
+        .area_bounding_box
+        .into();
+
+    let res = pak.resources.iter()
+        .find(|res| res.file_id == starting_room.mrea)
+        .ok_or_else(|| format!("Could not find room 0x{:X} in {}", starting_room.mrea, starting_room.pak_name))?
+        .into_owned();
+
+    Ok((layer_flags, bounding_box, res))
+}
+
+/// Positions and rotations of the room's spawn points on the layers enabled on a new file, and
+/// whether they're the default one
+fn spawn_points(layer_flags: u64, res: &mut structs::Resource) -> Vec<(bool, [f32; 3], [f32; 3])>
+{
+    let scly = res.kind.as_mrea_mut().unwrap().scly_section_mut();
+    let mut spawn_points = vec![];
+    for (layer_idx, layer) in scly.layers.as_mut_vec().iter_mut().enumerate() {
+        if layer_flags & (1 << layer_idx) == 0 {
+            continue;
+        }
+        for obj in layer.objects.as_mut_vec().iter() {
+            if let Some(spawn_point) = obj.property_data.as_spawn_point() {
+                let position: [f32; 3] = spawn_point.position.into();
+                let rotation: [f32; 3] = spawn_point.rotation.into();
+                spawn_points.push((spawn_point.default_spawn != 0, position, rotation));
+            }
+        }
+    }
+    spawn_points
+}
+
+/// Position and rotation of the spawn point Samus starts a new file at, going by the spawn
+/// points of the vanilla room. Layers which are disabled on a new file don't count.
+pub fn starting_spawn_point(
+    gc_disc: &structs::GcDisc,
+    starting_room: &SpawnRoomData,
+    fixup: Option<StartingRoomFixup>,
+)
+    -> Result<Option<([f32; 3], [f32; 3])>, String>
+{
+    match fixup {
+        Some(StartingRoomFixup::SpawnAtDoor { position, rotation, .. }) |
+        Some(StartingRoomFixup::SpawnAtPosition { position, rotation }) => {
+            return Ok(Some((position, rotation)));
+        },
+        _ => (),
+    }
+
+    let (layer_flags, _, mut res) = read_starting_room(gc_disc, starting_room)?;
+    let spawn_points = spawn_points(layer_flags, &mut res);
+
+    // The default spawn point if there's one, otherwise the one ForceDefaultSpawn picks
+    let spawn_point = match fixup {
+        Some(StartingRoomFixup::UseSpawnPoint(idx)) => spawn_points.get(idx as usize),
+        _ => spawn_points.iter()
+            .find(|(default_spawn, _, _)| *default_spawn)
+            .or_else(|| spawn_points.first()),
+    };
+    Ok(spawn_point.map(|(_, position, rotation)| (*position, *rotation)))
+}
+
+/// Checks Samus spawns somewhere she can play from in `starting_room`, which has to be a room
+/// rather than an elevator. Rooms without a spawn point used on a new file get one in front of a
+/// door, rooms which can't be started in at all are an error. `spawn` overrides where she spawns.
+pub fn check_starting_room(
+    gc_disc: &structs::GcDisc,
+    starting_room: &SpawnRoomData,
+    spawn: Option<StartingSpawn>,
+)
+    -> Result<Option<StartingRoomFixup>, String>
+{
+    if let Some((_, reason)) = UNUSABLE_STARTING_ROOMS.iter().find(|(mrea, _)| *mrea == starting_room.mrea) {
+        Err(format!(
+            "{} can't be used as the starting room, {}. Try one of: {}",
+            starting_room.name.trim(),
+            reason,
+            suggested_starting_rooms(starting_room).join(", "),
+        ))?
+    }
+
+    let (layer_flags, bounding_box, mut res) = read_starting_room(gc_disc, starting_room)?;
+    let spawn_points = spawn_points(layer_flags, &mut res);
+
+    match spawn {
+        Some(StartingSpawn::SpawnPoint(idx)) => {
+            if idx as usize >= spawn_points.len() {
+                Err(format!(
+                    "Could not use spawn point {} of {}, it only has {} spawn points",
+                    idx,
+                    starting_room.name.trim(),
+                    spawn_points.len(),
+                ))?
+            }
+            return Ok(Some(StartingRoomFixup::UseSpawnPoint(idx)));
+        },
+        Some(StartingSpawn::Position(position)) => {
+            let inside = (0..3).all(|i| position[i] >= bounding_box[i] && position[i] <= bounding_box[i + 3]);
+            if !inside {
+                Err(format!(
+                    "Could not spawn at {:?} in {}, it's outside of the room's bounds {:?} to {:?}",
+                    position,
+                    starting_room.name.trim(),
+                    &bounding_box[..3],
+                    &bounding_box[3..],
+                ))?
+            }
+
+            // There's no collision to stand on outside of the room's geometry, so the best guess
+            // of a good direction to face is towards the middle of the room
+            let dx = (bounding_box[0] + bounding_box[3]) / 2.0 - position[0];
+            let dy = (bounding_box[1] + bounding_box[4]) / 2.0 - position[1];
+            let rotation = [0.0, 0.0, (-dx).atan2(dy).to_degrees()];
+            return Ok(Some(StartingRoomFixup::SpawnAtPosition { position, rotation }));
+        },
+        None => (),
+    }
+
+    if spawn_points.iter().any(|(default_spawn, _, _)| *default_spawn) {
+        return Ok(None);
+    }
+    if !spawn_points.is_empty() {
+        return Ok(Some(StartingRoomFixup::ForceDefaultSpawn));
+    }
+    let scly = res.kind.as_mrea_mut().unwrap().scly_section_mut();
+
+    let room_info = pickup_meta::ROOM_INFO.iter()
+        .find(|(pak_name, _)| *pak_name == starting_room.pak_name)
+        .and_then(|(_, rooms)| rooms.iter().find(|room_info| room_info.room_id.to_u32() == starting_room.mrea))
+        .ok_or_else(|| format!("Could not find room 0x{:X} in {}", starting_room.mrea, starting_room.pak_name))?;
+
+    // Morph Ball tunnels aren't a good place to start, so prefer the room's normal doors
+    let mut spawns = vec![];
+    for door_location in room_info.door_locations.iter() {
+        let location = match door_location.door_location {
+            Some(location) => location,
+            None => continue,
+        };
+        let door = scly.layers.as_mut_vec()[location.layer as usize].objects.as_mut_vec().iter()
+            .find(|obj| obj.instance_id == location.instance_id)
+            .and_then(|obj| obj.property_data.as_door())
+            .map(|door| door.into_owned());
+        let (position, rotation, morphed) = door_spawn_point(
+            starting_room.mrea,
+            door_location.dock_number,
+            door_location.dock_position,
+            door.as_ref(),
+        );
+        spawns.push(StartingRoomFixup::SpawnAtDoor { position, rotation, morphed });
+    }
+    spawns.sort_by_key(|spawn| match spawn {
+        StartingRoomFixup::SpawnAtDoor { morphed, .. } => *morphed,
+        _ => false,
+    });
+
+    match spawns.first() {
+        Some(spawn) => Ok(Some(*spawn)),
+        None => Err(format!(
+            "{} can't be used as the starting room, it has no spawn point or door for Samus to come out of. Try one of: {}",
+            starting_room.name.trim(),
+            suggested_starting_rooms(starting_room).join(", "),
+        ))?,
+    }
+}
+
+/// A spawn point used when starting a new file
+fn starting_spawn_point_obj(position: [f32; 3], rotation: [f32; 3], morphed: bool) -> structs::SpawnPoint<'static>
+{
+    structs::SpawnPoint {
+        name: b"starting room spawnpoint\0".as_cstr(),
+        position: position.into(),
+        rotation: rotation.into(),
+        power: 0,
+        ice: 0,
+        wave: 0,
+        plasma: 0,
+        missiles: 0,
+        scan_visor: 0,
+        bombs: 0,
+        power_bombs: 0,
+        flamethrower: 0,
+        thermal_visor: 0,
+        charge: 0,
+        super_missile: 0,
+        grapple: 0,
+        xray: 0,
+        ice_spreader: 0,
+        space_jump: 0,
+        morph_ball: 0,
+        combat_visor: 0,
+        boost_ball: 0,
+        spider_ball: 0,
+        power_suit: 0,
+        gravity_suit: 0,
+        varia_suit: 0,
+        phazon_suit: 0,
+        energy_tanks: 0,
+        unknown0: 0,
+        health_refill: 0,
+        unknown1: 0,
+        wavebuster: 0,
+        default_spawn: 1,
+        active: 1,
+        morphed: morphed as u8,
+    }
+}
+
+pub fn patch_starting_room_spawn<'r>(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    fixup: StartingRoomFixup,
+)
+    -> Result<(), String>
+{
+    let spawn_point_id = area.new_object_id_from_layer_name("Default");
+    let layer_flags = area.layer_flags.flags;
+    let scly = area.mrea().scly_section_mut();
+
+    let mut active_spawn_points = scly.layers.as_mut_vec().iter_mut()
+        .enumerate()
+        .filter(|(layer_idx, _)| layer_flags & (1 << layer_idx) != 0)
+        .flat_map(|(_, layer)| layer.objects.as_mut_vec().iter_mut())
+        .filter_map(|obj| obj.property_data.as_spawn_point_mut())
+        .collect::<Vec<_>>();
+
+    let spawn_point = match fixup {
+        StartingRoomFixup::ForceDefaultSpawn => {
+            let spawn_point = active_spawn_points.first_mut()
+                .ok_or_else(|| "Could not find a spawn point in the starting room".to_string())?;
+            spawn_point.default_spawn = 1;
+            None
+        },
+        StartingRoomFixup::UseSpawnPoint(idx) => {
+            for (i, spawn_point) in active_spawn_points.iter_mut().enumerate() {
+                spawn_point.default_spawn = (i == idx as usize) as u8;
+            }
+            None
+        },
+        StartingRoomFixup::SpawnAtDoor { position, rotation, morphed } => {
+            Some(starting_spawn_point_obj(position, rotation, morphed))
+        },
+        StartingRoomFixup::SpawnAtPosition { position, rotation } => {
+            // The room's own spawn points would win otherwise
+            for spawn_point in active_spawn_points.iter_mut() {
+                spawn_point.default_spawn = 0;
+            }
+            Some(starting_spawn_point_obj(position, rotation, false))
+        },
+    };
+    drop(active_spawn_points);
+
+    if let Some(spawn_point) = spawn_point {
+        scly.layers.as_mut_vec()[0].objects.as_mut_vec().push(structs::SclyObject {
+            instance_id: spawn_point_id,
+            property_data: spawn_point.into(),
+            connections: vec![].into(),
+        });
+    }
+
+    Ok(())
+}
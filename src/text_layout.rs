@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+
+use crate::GcDiscLookupExtensions;
+use resource_info_table::{resource_info, ResourceInfo};
+
+/// The font hudmemos and scans are drawn with
+const TEXT_FONT: ResourceInfo = resource_info!("Deface14B_O.FONT");
+
+/// The area text is laid out in, in the font's pixels. The widths are approximate, they're on the
+/// conservative side of what the game's text panes fit.
+pub struct TextBox
+{
+    pub name: &'static str,
+    pub width: i32,
+    pub max_lines: usize,
+}
+
+pub const HUDMEMO_BOX: TextBox = TextBox {
+    name: "hudmemo",
+    width: 520,
+    max_lines: 2,
+};
+
+// Scans page through their text, but anything past a few pages is a chore to read
+pub const SCAN_BOX: TextBox = TextBox {
+    name: "scan",
+    width: 300,
+    max_lines: 15,
+};
+
+/// A single page of a scan
+pub const SCAN_PAGE_BOX: TextBox = TextBox {
+    name: "scan page",
+    width: 300,
+    max_lines: 3,
+};
+
+/// Glyph advances and kerning read from one of the game's FONT resources
+pub struct FontMetrics
+{
+    advances: HashMap<char, i32>,
+    kerning: HashMap<(char, char), i32>,
+    default_advance: i32,
+}
+
+struct FontReader<'a>
+{
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> FontReader<'a>
+{
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String>
+    {
+        let bytes = self.data.get(self.pos..self.pos + len)
+            .ok_or_else(|| "Unexpected end of FONT".to_string())?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String>
+    {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String>
+    {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String>
+    {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn cstr(&mut self) -> Result<(), String>
+    {
+        while self.u8()? != 0 {}
+        Ok(())
+    }
+}
+
+impl FontMetrics
+{
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String>
+    {
+        let mut reader = FontReader { data, pos: 0 };
+        if reader.take(4)? != b"FONT" {
+            Err("Not a FONT resource".to_string())?
+        }
+        let version = reader.u32()?;
+        reader.take(8)?; // mono width/height
+        if version >= 1 {
+            reader.u32()?; // baseline
+        }
+        if version >= 2 {
+            reader.u32()?; // line margin
+        }
+        reader.take(2 + 4 + 4)?; // flags and font size
+        reader.cstr()?; // name
+        reader.take(4 + 4)?; // texture id and format
+
+        let glyph_count = reader.u32()?;
+        let mut advances = HashMap::with_capacity(glyph_count as usize);
+        for _ in 0..glyph_count {
+            let chr = reader.u16()?;
+            reader.take(16)?; // uv rect
+            let (left, width, right) = if version < 4 {
+                let left = reader.u32()? as i32;
+                let width = reader.u32()? as i32;
+                let right = reader.u32()? as i32;
+                reader.take(4 * 4)?;
+                (left, width, right)
+            } else {
+                reader.u8()?; // layer
+                let left = reader.u8()? as i8 as i32;
+                let width = reader.u8()? as i8 as i32;
+                let right = reader.u8()? as i8 as i32;
+                reader.take(3 + 2)?;
+                (left, width, right)
+            };
+            if let Some(chr) = char::from_u32(chr as u32) {
+                advances.insert(chr, left + width + right);
+            }
+        }
+
+        let kerning_count = reader.u32()?;
+        let mut kerning = HashMap::with_capacity(kerning_count as usize);
+        for _ in 0..kerning_count {
+            let first = reader.u16()?;
+            let second = reader.u16()?;
+            let amount = reader.u32()? as i32;
+            if let (Some(first), Some(second)) = (char::from_u32(first as u32), char::from_u32(second as u32)) {
+                kerning.insert((first, second), amount);
+            }
+        }
+
+        // Characters missing from the font aren't drawn by the game, but assume they take space
+        let default_advance = advances.get(&'M').copied().unwrap_or(16);
+        Ok(FontMetrics { advances, kerning, default_advance })
+    }
+
+    /// Reads the font hudmemos and scans are drawn with
+    pub fn from_disc(gc_disc: &structs::GcDisc) -> Result<Self, String>
+    {
+        let pak_name = std::str::from_utf8(TEXT_FONT.paks[0]).unwrap();
+        let pak: structs::Pak = match gc_disc.find_file(pak_name).and_then(|f| f.file()) {
+            Some(structs::FstEntryFile::Pak(pak)) => pak.clone(),
+            Some(structs::FstEntryFile::Unknown(reader)) => reader.clone().read(()),
+            _ => Err(format!("Failed to read {}", pak_name))?,
+        };
+        let res = pak.resources.iter()
+            .find(|res| res.file_id == TEXT_FONT.res_id && res.fourcc() == TEXT_FONT.fourcc)
+            .ok_or_else(|| format!("Failed to find {} in {}", TEXT_FONT.long_name, pak_name))?;
+        FontMetrics::from_bytes(&crate::formats::resource_bytes(&res))
+    }
+
+    /// Width of a single line, ignoring formatting tags like `&push;`
+    pub fn text_width(&self, line: &str) -> i32
+    {
+        let mut width = 0;
+        let mut prev = None;
+        for chr in visible_chars(line) {
+            width += self.advances.get(&chr).copied().unwrap_or(self.default_advance);
+            if let Some(prev) = prev {
+                width += self.kerning.get(&(prev, chr)).copied().unwrap_or(0);
+            }
+            prev = Some(chr);
+        }
+        width
+    }
+
+    /// Breaks `text` into lines no wider than `width` at spaces, keeping existing line breaks.
+    /// A word which is wider than `width` on its own gets a line to itself.
+    pub fn wrap(&self, text: &str, width: i32) -> Vec<String>
+    {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut line = String::new();
+            for word in paragraph.split(' ') {
+                if line.is_empty() {
+                    line.push_str(word);
+                    continue;
+                }
+                let candidate = format!("{} {}", line, word);
+                if self.text_width(&candidate) > width {
+                    lines.push(std::mem::replace(&mut line, word.to_string()));
+                } else {
+                    line = candidate;
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Wraps a STRG string to fit `text_box`. Returns the wrapped string and whether it fits.
+    pub fn layout(&self, text: &str, text_box: &TextBox) -> (String, bool)
+    {
+        let terminated = text.ends_with('\0');
+        let text = text.trim_end_matches('\0');
+
+        let lines = self.wrap(text, text_box.width);
+        let fits = lines.len() <= text_box.max_lines &&
+            lines.iter().all(|line| self.text_width(line) <= text_box.width);
+
+        let mut wrapped = lines.join("\n");
+        if terminated {
+            wrapped.push('\0');
+        }
+        (wrapped, fits)
+    }
+
+    /// Wraps a STRG string to fit `text_box` and splits it into pages of at most
+    /// `text_box.max_lines` lines. Formatting which is still in effect at the end of a page is
+    /// closed there and repeated at the start of the next one, as the game doesn't carry it over
+    /// (and crashes on a page with unbalanced `&push;`/`&pop;`). Returns the pages and whether
+    /// every line fits.
+    pub fn paginate(&self, text: &str, text_box: &TextBox) -> (Vec<String>, bool)
+    {
+        let terminated = text.ends_with('\0');
+        let text = text.trim_end_matches('\0');
+
+        let lines = self.wrap(text, text_box.width);
+        let fits = lines.iter().all(|line| self.text_width(line) <= text_box.width);
+
+        let mut formatting = Formatting::new();
+        let pages = lines.chunks(text_box.max_lines.max(1))
+            .map(|page_lines| {
+                let mut page = formatting.reopen();
+                for line in page_lines {
+                    formatting.apply(line);
+                }
+                page.push_str(&page_lines.join("\n"));
+                page.push_str(&formatting.close());
+                if terminated {
+                    page.push('\0');
+                }
+                page
+            })
+            .collect();
+        (pages, fits)
+    }
+}
+
+/// The formatting in effect at some point of a string, one list of tags per `&push;` level
+struct Formatting
+{
+    levels: Vec<Vec<String>>,
+}
+
+impl Formatting
+{
+    fn new() -> Self
+    {
+        Formatting { levels: vec![vec![]] }
+    }
+
+    fn apply(&mut self, text: &str)
+    {
+        for tag in tags(text) {
+            match tag {
+                "push" => self.levels.push(vec![]),
+                "pop" => {
+                    if self.levels.len() > 1 {
+                        self.levels.pop();
+                    }
+                },
+                _ => self.levels.last_mut().unwrap().push(format!("&{};", tag)),
+            }
+        }
+    }
+
+    /// Tags which restore this formatting at the start of a string
+    fn reopen(&self) -> String
+    {
+        let mut text = self.levels[0].concat();
+        for level in &self.levels[1..] {
+            text.push_str("&push;");
+            text.push_str(&level.concat());
+        }
+        text
+    }
+
+    /// Tags which balance the `&push;`es still open
+    fn close(&self) -> String
+    {
+        "&pop;".repeat(self.levels.len() - 1)
+    }
+}
+
+/// The contents of the `&tag;` and `&tag=value;` formatting in `text`
+fn tags(text: &str) -> Vec<&str>
+{
+    let mut tags = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        rest = &rest[start + 1..];
+        let tag_len = rest.find(';')
+            .filter(|len| rest[..*len].chars().all(|c| !c.is_whitespace()));
+        if let Some(tag_len) = tag_len {
+            tags.push(&rest[..tag_len]);
+            rest = &rest[tag_len + 1..];
+        }
+    }
+    tags
+}
+
+/// Characters which are drawn, i.e. everything but `&tag;` and `&tag=value;` formatting
+fn visible_chars(text: &str) -> impl Iterator<Item = char> + '_
+{
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        while let Some((i, chr)) = chars.next() {
+            if chr == '&' {
+                let rest = &text[i + 1..];
+                let tag_len = rest.find(';')
+                    .filter(|len| rest[..*len].chars().all(|c| !c.is_whitespace()));
+                if let Some(tag_len) = tag_len {
+                    while chars.peek().map(|(j, _)| *j <= i + 1 + tag_len).unwrap_or(false) {
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+            return Some(chr);
+        }
+        None
+    })
+}
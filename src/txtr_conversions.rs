@@ -1,5 +1,8 @@
 use std::convert::TryInto;
 
+use reader_writer::Writable;
+use structs::{Txtr, TxtrFormat};
+
 use libsquish_wrapper::{compress_dxt1gcn_block, decompress_dxt1gcn_block};
 use resource_info_table::{resource_info, ResourceInfo};
 
@@ -147,6 +150,44 @@ pub const PHAZON_SUIT_TEXTURES: &[ResourceInfo] = &[
 
 ];
 
+// Glowing parts of each suit's helmet
+pub const VISOR_TEXTURES: &[ResourceInfo] = &[
+    resource_info!("power_head_chest_incan.TXTR"), // Power and Varia
+    resource_info!("f_varia_head_incan.TXTR"),
+    resource_info!("phason_head_incandescence.TXTR"),
+];
+
+// Spider balls of every suit which has one, including their glass and low poly models
+pub const SPIDER_BALL_TEXTURES: &[ResourceInfo] = &[
+    // Varia
+    resource_info!("2EE6F56F.TXTR"),
+    resource_info!("AD3748D3.TXTR"),
+    resource_info!("9024CB39.TXTR"),
+    resource_info!("7A755049.TXTR"),
+    resource_info!("07675658.TXTR"),
+
+    // Gravity
+    resource_info!("50A70472.TXTR"),
+    resource_info!("1AEC5A79.TXTR"),
+    resource_info!("27FFD993.TXTR"),
+    resource_info!("BA7DF5D6.TXTR"),
+
+    // Phazon
+    resource_info!("8B105F2E.TXTR"),
+    resource_info!("2F1AC0DD.TXTR"),
+    resource_info!("8BF681E5.TXTR"),
+    resource_info!("51F20A44.TXTR"),
+    resource_info!("06CE2C16.TXTR"),
+];
+
+// First-person arm cannons, their textures are looked up when patching
+pub const ARM_CANNON_MODELS: &[ResourceInfo] = &[
+    resource_info!("PowerArm.CMDL"),
+    resource_info!("VariaArm.CMDL"),
+    resource_info!("GravityArm.CMDL"),
+    resource_info!("PhazonArm.CMDL"),
+];
+
 // Fusion morph ball ANCS TestAnim/Fusion_Ball.ANCS
 pub const FUSION_POWER_SUIT_TEXTURES: &[ResourceInfo] = &[
     // High res Characters/Samus/cooked/fusion_suit_high_rez_bound.CMDL
@@ -267,6 +308,40 @@ pub fn huerotate_matrix(angle: f32) -> [f32; 9]
     ]
 }
 
+/// Builds a matrix which rotates the hue, then scales the saturation and value and finally
+/// multiplies each channel by `tint` (0.0 to 1.0)
+pub fn color_adjust_matrix(hue_deg: f32, saturation: f32, value: f32, tint: [f32; 3]) -> [f32; 9]
+{
+    // Same luminance weights as the hue rotation, so desaturating keeps the brightness
+    let (lr, lg, lb) = (0.213, 0.715, 0.072);
+    let s = saturation;
+    let saturation_matrix = [
+        lr * (1.0 - s) + s, lg * (1.0 - s),     lb * (1.0 - s),
+        lr * (1.0 - s),     lg * (1.0 - s) + s, lb * (1.0 - s),
+        lr * (1.0 - s),     lg * (1.0 - s),     lb * (1.0 - s) + s,
+    ];
+    let tint_matrix = [
+        tint[0] * value, 0.0, 0.0,
+        0.0, tint[1] * value, 0.0,
+        0.0, 0.0, tint[2] * value,
+    ];
+
+    let matrix = multiply_color_matrices(saturation_matrix, huerotate_matrix(hue_deg));
+    multiply_color_matrices(tint_matrix, matrix)
+}
+
+/// Combines two color matrices into one which applies `b` first, then `a`
+pub fn multiply_color_matrices(a: [f32; 9], b: [f32; 9]) -> [f32; 9]
+{
+    let mut result = [0.0; 9];
+    for row in 0..3 {
+        for col in 0..3 {
+            result[row * 3 + col] = (0..3).map(|i| a[row * 3 + i] * b[i * 3 + col]).sum();
+        }
+    }
+    result
+}
+
 pub fn huerotate_color(matrix: [f32; 9], r: u8, g: u8, b: u8) -> [u8; 3]
 {
     let r = r as f32;
@@ -302,3 +377,196 @@ where
         }
     }
 }
+
+pub fn box_filter_pixels(pixels: &[u8], w: usize, h: usize, chan_count: usize, discretize_alpha: bool)
+    -> Vec<u8>
+{
+    let mut output = Vec::with_capacity(w * h * chan_count / 4);
+    for iy in 0..h / 2 {
+        for ix in 0..w / 2 {
+            let y = iy * 2;
+            let x = ix * 2;
+            for c in 0..chan_count {
+                output.push(((
+                        (pixels[(y * w + x) * chan_count + c] as u16)
+                        + (pixels[(y * w + x + 1) * chan_count + c] as u16)
+                        + (pixels[((y + 1) * w + x ) * chan_count + c] as u16)
+                        + (pixels[((y + 1) * w + x + 1) * chan_count + c] as u16)
+                    ) / 4) as u8
+                );
+                if discretize_alpha && c == chan_count - 1 {
+                    let last = output.last_mut().unwrap();
+                    if *last > 0 {
+                        *last = 0xff;
+                    }
+                }
+            }
+        }
+    }
+    output
+}
+
+pub fn encode_rgb5a3(pixel: [u8; 4]) -> [u8; 2] {
+    let v = if pixel[3] == 0xff {
+        0x8000 | ((pixel[0] as u16 >> 3) << 10)
+            | ((pixel[1] as u16 >> 3) << 5)
+            | (pixel[2] as u16 >> 3)
+    } else {
+        ((pixel[0] as u16 >> 4) << 8)
+            | ((pixel[1] as u16 >> 4) << 4)
+            | (pixel[2] as u16 >> 4)
+            | ((pixel[3] as u16 >> 5) << 12)
+    };
+    v.to_be_bytes()
+}
+
+/// Encodes tightly packed RGBA8 pixels (top row first) as an RGB5A3 TXTR with a full mipmap
+/// chain. Both dimensions must be multiples of 4.
+pub fn rgba8_to_txtr(pixels: &[u8], width: usize, height: usize) -> Vec<u8>
+{
+    rgba8_to_txtr_with_format(pixels, width, height, TxtrFormat::Rgb5A3, usize::MAX).unwrap()
+}
+
+fn block_dimensions(format: &TxtrFormat) -> (usize, usize)
+{
+    match format {
+        TxtrFormat::I4 | TxtrFormat::Cmpr | TxtrFormat::C4(_, _) => (8, 8),
+        TxtrFormat::I8 | TxtrFormat::Ia4 | TxtrFormat::C8(_, _) => (8, 4),
+        TxtrFormat::Ia8 | TxtrFormat::Rgb565 | TxtrFormat::Rgb5A3 | TxtrFormat::Rgba8 => (4, 4),
+    }
+}
+
+fn encode_rgb565(pixel: &[u8]) -> [u8; 2]
+{
+    let v = ((pixel[0] as u16 >> 3) << 11) | ((pixel[1] as u16 >> 2) << 5) | (pixel[2] as u16 >> 3);
+    v.to_be_bytes()
+}
+
+fn intensity(pixel: &[u8]) -> u8
+{
+    ((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000) as u8
+}
+
+/// Encodes one block of RGBA8 pixels, laid out row by row
+fn encode_block(format: &TxtrFormat, pixels: &[u8], block: &mut [u8])
+{
+    match format {
+        TxtrFormat::I4 => {
+            for (byte, pixels) in block.iter_mut().zip(pixels.chunks(8)) {
+                *byte = (intensity(&pixels[0..4]) & 0xF0) | (intensity(&pixels[4..8]) >> 4);
+            }
+        },
+        TxtrFormat::I8 => {
+            for (byte, pixel) in block.iter_mut().zip(pixels.chunks(4)) {
+                *byte = intensity(pixel);
+            }
+        },
+        TxtrFormat::Ia4 => {
+            for (byte, pixel) in block.iter_mut().zip(pixels.chunks(4)) {
+                *byte = (pixel[3] & 0xF0) | (intensity(pixel) >> 4);
+            }
+        },
+        TxtrFormat::Ia8 => {
+            for (texel, pixel) in block.chunks_mut(2).zip(pixels.chunks(4)) {
+                texel[0] = pixel[3];
+                texel[1] = intensity(pixel);
+            }
+        },
+        TxtrFormat::Rgb565 => {
+            for (texel, pixel) in block.chunks_mut(2).zip(pixels.chunks(4)) {
+                texel.copy_from_slice(&encode_rgb565(pixel));
+            }
+        },
+        TxtrFormat::Rgb5A3 => {
+            for (texel, pixel) in block.chunks_mut(2).zip(pixels.chunks(4)) {
+                texel.copy_from_slice(&encode_rgb5a3(pixel.try_into().unwrap()));
+            }
+        },
+        TxtrFormat::Rgba8 => {
+            // Alpha and red for every texel, then green and blue
+            let (ar, gb) = block.split_at_mut(32);
+            for (i, pixel) in pixels.chunks(4).enumerate() {
+                ar[i * 2] = pixel[3];
+                ar[i * 2 + 1] = pixel[0];
+                gb[i * 2] = pixel[1];
+                gb[i * 2 + 1] = pixel[2];
+            }
+        },
+        TxtrFormat::Cmpr => {
+            // Four DXT1 blocks in a 2x2 grid
+            let mut sub_block_pixels = [[0u8; 4]; 16];
+            for (i, sub_block) in block.chunks_mut(8).enumerate() {
+                for (k, pixel) in sub_block_pixels.iter_mut().enumerate() {
+                    let x = i % 2 * 4 + k % 4;
+                    let y = i / 2 * 4 + k / 4;
+                    pixel.copy_from_slice(&pixels[(y * 8 + x) * 4..(y * 8 + x) * 4 + 4]);
+                }
+                compress_dxt1gcn_block(&sub_block_pixels, sub_block.try_into().unwrap());
+            }
+        },
+        TxtrFormat::C4(_, _) | TxtrFormat::C8(_, _) => unreachable!(),
+    }
+}
+
+/// Encodes tightly packed RGBA8 pixels (top row first) as a TXTR of the given format, with up to
+/// `max_mipmaps` mipmaps. Paletted formats aren't supported.
+pub fn rgba8_to_txtr_with_format(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    format: TxtrFormat,
+    max_mipmaps: usize,
+) -> Result<Vec<u8>, String>
+{
+    assert_eq!(pixels.len(), width * height * 4);
+    if matches!(format, TxtrFormat::C4(_, _) | TxtrFormat::C8(_, _)) {
+        Err("Paletted texture formats can't be encoded".to_string())?
+    }
+    let (block_w, block_h) = block_dimensions(&format);
+    if width == 0 || height == 0 || width % block_w != 0 || height % block_h != 0 {
+        Err(format!(
+            "The image's width and height ({}, {}) must be multiples of {:?}'s block dimensions ({}, {})",
+            width, height, format, block_w, block_h,
+        ))?
+    }
+    let bytes_per_block = if matches!(format, TxtrFormat::Rgba8) { 64 } else { 32 };
+
+    let mut mipmaps = vec![];
+    let mut pixels = pixels.to_vec();
+    let (mut w, mut h) = (width, height);
+    let mut block_pixels = vec![0u8; block_w * block_h * 4];
+    loop {
+        // Blocks are stored bottom row first
+        let mut blocks = vec![0u8; (w / block_w) * (h / block_h) * bytes_per_block];
+        for (i, block) in blocks.chunks_mut(bytes_per_block).enumerate() {
+            let outer_x = (i % (w / block_w)) * block_w;
+            let outer_y = (i / (w / block_w)) * block_h;
+            for inner_y in 0..block_h {
+                let y = h - 1 - (outer_y + inner_y);
+                let start = (y * w + outer_x) * 4;
+                block_pixels[inner_y * block_w * 4..(inner_y + 1) * block_w * 4]
+                    .copy_from_slice(&pixels[start..start + block_w * 4]);
+            }
+            encode_block(&format, &block_pixels, block);
+        }
+        mipmaps.push(blocks.into());
+
+        if mipmaps.len() >= max_mipmaps || (w / 2) % block_w != 0 || (h / 2) % block_h != 0 {
+            break;
+        }
+        pixels = box_filter_pixels(&pixels, w, h, 4, matches!(format, TxtrFormat::Cmpr));
+        w /= 2;
+        h /= 2;
+    }
+
+    let txtr = Txtr {
+        format,
+        width: width as u16,
+        height: height as u16,
+        pixel_data: mipmaps.into(),
+    };
+    let mut bytes = vec![];
+    txtr.write_to(&mut bytes).unwrap();
+    bytes.resize((bytes.len() + 31) & !31, 0);
+    Ok(bytes)
+}
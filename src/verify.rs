@@ -0,0 +1,332 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    panic::{self, AssertUnwindSafe},
+};
+
+use reader_writer::{FourCC, Reader};
+
+use crate::elevators::World;
+
+// Based on http://hitmen.c02.at/files/yagcd/yagcd/chap13.html
+
+const DISC_MAGIC: u32 = 0xc2339f3d;
+const FST_ENTRY_SIZE: usize = 12;
+const PAK_VERSION: u32 = 0x00030005;
+
+/// Files are placed on 32 byte boundaries when the disc is written, as are the resources
+/// inside a PAK
+const FILE_ALIGNMENT: u32 = 32;
+
+/// Where the GameCube's 24MiB of main RAM is mapped
+const RAM_START: u32 = 0x80000000;
+const RAM_END: u32 = 0x81800000;
+
+struct BeReader<'a>
+{
+    data: &'a [u8],
+    what: &'a str,
+}
+
+impl<'a> BeReader<'a>
+{
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], String>
+    {
+        offset.checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| format!("Unexpected end of {}", self.what))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, String>
+    {
+        let bytes = self.bytes(offset, 4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn cstr(&self, offset: usize) -> Result<String, String>
+    {
+        let rest = self.data.get(offset..)
+            .ok_or_else(|| format!("Unexpected end of {}", self.what))?;
+        let len = rest.iter().position(|b| *b == 0)
+            .ok_or_else(|| format!("Unterminated string in {}", self.what))?;
+        Ok(String::from_utf8_lossy(&rest[..len]).into_owned())
+    }
+}
+
+struct FstFile
+{
+    path: String,
+    offset: u32,
+    length: u32,
+}
+
+impl FstFile
+{
+    fn contents<'a>(&self, disc: &'a [u8]) -> &'a [u8]
+    {
+        // Already checked to be in bounds
+        &disc[self.offset as usize..(self.offset + self.length) as usize]
+    }
+}
+
+/// Reads the FST and checks its files are aligned, inside the disc and don't overlap with
+/// each other or with the header
+fn check_fst(disc: &[u8]) -> Result<Vec<FstFile>, String>
+{
+    let header = BeReader { data: disc, what: "the disc header" };
+    if header.u32(0x1c)? != DISC_MAGIC {
+        Err("The disc header is corrupt")?
+    }
+    let fst_offset = header.u32(0x424)? as usize;
+    let fst_length = header.u32(0x428)? as usize;
+    let fst = BeReader { data: header.bytes(fst_offset, fst_length)?, what: "the FST" };
+
+    let entry_count = fst.u32(8)? as usize;
+    let strings = BeReader {
+        data: fst.data.get(entry_count * FST_ENTRY_SIZE..)
+            .ok_or_else(|| "The FST's entries don't fit in it".to_string())?,
+        what: "the FST's string table",
+    };
+
+    // (index past the directory's last entry, path of the directory)
+    let mut dirs = vec![(entry_count, String::new())];
+    let mut files = vec![];
+    for i in 1..entry_count {
+        while dirs.last().map(|(end, _)| *end <= i).unwrap_or(false) {
+            dirs.pop();
+        }
+        let (dir_end, dir_path) = dirs.last()
+            .ok_or_else(|| format!("FST entry {} isn't in any directory", i))?
+            .clone();
+
+        let flags_and_name = fst.u32(i * FST_ENTRY_SIZE)?;
+        let offset = fst.u32(i * FST_ENTRY_SIZE + 4)?;
+        let length = fst.u32(i * FST_ENTRY_SIZE + 8)?;
+        let path = format!("{}{}", dir_path, strings.cstr((flags_and_name & 0xffffff) as usize)?);
+
+        if flags_and_name >> 24 == 1 {
+            // For directories, the offset is the parent's index and the length is the index
+            // past its last entry
+            if offset as usize >= i || length as usize <= i || length as usize > dir_end {
+                Err(format!("The FST entry of directory {} is corrupt", path))?
+            }
+            dirs.push((length as usize, format!("{}/", path)));
+        } else {
+            if offset % FILE_ALIGNMENT != 0 {
+                Err(format!("{} isn't aligned to {} bytes (offset {:#x})", path, FILE_ALIGNMENT, offset))?
+            }
+            if (offset as usize) < fst_offset + fst_length {
+                Err(format!("{} overlaps the disc header or FST (offset {:#x})", path, offset))?
+            }
+            if offset as u64 + length as u64 > disc.len() as u64 {
+                Err(format!("{} goes past the end of the disc", path))?
+            }
+            files.push(FstFile { path, offset, length });
+        }
+    }
+
+    let mut sorted: Vec<&FstFile> = files.iter().filter(|f| f.length > 0).collect();
+    sorted.sort_by_key(|f| f.offset);
+    for pair in sorted.windows(2) {
+        if pair[0].offset + pair[0].length > pair[1].offset {
+            Err(format!("{} and {} overlap", pair[0].path, pair[1].path))?
+        }
+    }
+
+    Ok(files)
+}
+
+/// Checks the DOL's segments are inside the file and load into main RAM, and that the entry
+/// point is in one of them
+fn check_dol(dol: &[u8], dol_offset: u32) -> Result<(), String>
+{
+    let reader = BeReader { data: dol, what: "default.dol" };
+    if dol_offset % FILE_ALIGNMENT != 0 {
+        Err(format!("default.dol isn't aligned to {} bytes", FILE_ALIGNMENT))?
+    }
+
+    let mut text_segments = vec![];
+    for i in 0..18 {
+        let offset = reader.u32(i * 4)?;
+        let load_addr = reader.u32(0x48 + i * 4)?;
+        let size = reader.u32(0x90 + i * 4)?;
+        if size == 0 {
+            continue;
+        }
+        if offset as u64 + size as u64 > dol.len() as u64 {
+            Err(format!("DOL segment {} goes past the end of default.dol", i))?
+        }
+        if load_addr < RAM_START || load_addr as u64 + size as u64 > RAM_END as u64 {
+            Err(format!("DOL segment {} loads outside of main RAM ({:#x})", i, load_addr))?
+        }
+        if i < 7 {
+            text_segments.push((load_addr, size));
+        }
+    }
+
+    let entry_point = reader.u32(0xe0)?;
+    if !text_segments.iter().any(|(addr, size)| entry_point >= *addr && entry_point < addr + size) {
+        Err(format!("The DOL's entry point ({:#x}) isn't in a text segment", entry_point))?
+    }
+
+    Ok(())
+}
+
+/// Checks a PAK's resources are aligned, inside the PAK and don't overlap. Returns every
+/// resource in it.
+fn check_pak(pak: &[u8], name: &str) -> Result<HashSet<(u32, FourCC)>, String>
+{
+    let reader = BeReader { data: pak, what: name };
+    if reader.u32(0)? != PAK_VERSION {
+        Err(format!("{} has an unknown version", name))?
+    }
+
+    let named_count = reader.u32(8)? as usize;
+    let mut pos = 12;
+    let mut named = vec![];
+    for _ in 0..named_count {
+        let fourcc = FourCC::new(reader.u32(pos)?);
+        let file_id = reader.u32(pos + 4)?;
+        let name_length = reader.u32(pos + 8)? as usize;
+        named.push((file_id, fourcc));
+        pos += 12 + name_length;
+    }
+
+    let resource_count = reader.u32(pos)? as usize;
+    pos += 4;
+    let mut resources = HashSet::new();
+    let mut extents = vec![];
+    for _ in 0..resource_count {
+        let fourcc = FourCC::new(reader.u32(pos + 4)?);
+        let file_id = reader.u32(pos + 8)?;
+        let size = reader.u32(pos + 12)?;
+        let offset = reader.u32(pos + 16)?;
+        pos += 20;
+
+        if offset % FILE_ALIGNMENT != 0 {
+            Err(format!("{:08X}.{} in {} isn't aligned to {} bytes", file_id, fourcc, name, FILE_ALIGNMENT))?
+        }
+        if offset as u64 + size as u64 > pak.len() as u64 {
+            Err(format!("{:08X}.{} goes past the end of {}", file_id, fourcc, name))?
+        }
+        resources.insert((file_id, fourcc));
+        extents.push((offset, size, file_id, fourcc));
+    }
+    let header_end = pos as u32;
+
+    // The same resource is sometimes listed more than once, but always with the same data
+    extents.sort_by_key(|(offset, size, _, _)| (*offset, *size));
+    extents.dedup_by_key(|(offset, size, _, _)| (*offset, *size));
+    if let Some((offset, _, file_id, fourcc)) = extents.first() {
+        if *offset < header_end {
+            Err(format!("{:08X}.{} overlaps the header of {}", file_id, fourcc, name))?
+        }
+    }
+    for pair in extents.windows(2) {
+        let (offset, size, file_id, fourcc) = pair[0];
+        let (next_offset, _, next_file_id, next_fourcc) = pair[1];
+        if offset + size > next_offset {
+            Err(format!(
+                "{:08X}.{} and {:08X}.{} overlap in {}",
+                file_id, fourcc, next_file_id, next_fourcc, name,
+            ))?
+        }
+    }
+
+    for (file_id, fourcc) in named {
+        if !resources.contains(&(file_id, fourcc)) {
+            Err(format!("{} names {:08X}.{}, which it doesn't contain", name, file_id, fourcc))?
+        }
+    }
+
+    Ok(resources)
+}
+
+/// The areas and dependencies of every world which can't be found in the world's PAK or one
+/// of the PAKs the game always has loaded
+fn missing_dependencies(disc: &[u8], files: &[FstFile]) -> Result<HashSet<(String, u32, FourCC)>, String>
+{
+    let is_world_pak = |path: &str| World::iter().any(|world| world.to_pak_str() == path);
+
+    let mut shared = HashSet::new();
+    for file in files.iter().filter(|f| f.path.to_lowercase().ends_with(".pak") && !is_world_pak(&f.path)) {
+        shared.extend(check_pak(file.contents(disc), &file.path)?);
+    }
+
+    let mut missing = HashSet::new();
+    for world in World::iter() {
+        let file = files.iter()
+            .find(|f| f.path == world.to_pak_str())
+            .ok_or_else(|| format!("{} is missing", world.to_pak_str()))?;
+        let bytes = file.contents(disc);
+        let resources = check_pak(bytes, &file.path)?;
+
+        // The structs panic on malformed data rather than returning an error
+        let areas = panic::catch_unwind(AssertUnwindSafe(|| {
+            let pak: structs::Pak = Reader::new(bytes).read(());
+            let mut mlvl = pak.resources.iter()
+                .find(|res| res.file_id == world.mlvl())?
+                .into_owned();
+            let mlvl = mlvl.kind.as_mlvl_mut()?;
+            let areas: Vec<_> = mlvl.areas.iter()
+                .map(|area| {
+                    let deps: Vec<_> = area.dependencies.deps.iter()
+                        .flat_map(|layer| layer.iter().map(|dep| (dep.asset_id, dep.asset_type)).collect::<Vec<_>>())
+                        .collect();
+                    (area.mrea.to_u32(), deps)
+                })
+                .collect();
+            Some(areas)
+        }))
+        .map_err(|_| format!("Failed to read the MLVL of {}", world.to_pak_str()))?
+        .ok_or_else(|| format!("{} has no MLVL", world.to_pak_str()))?;
+
+        for (mrea, deps) in areas {
+            let deps = std::iter::once((mrea, b"MREA".into())).chain(deps);
+            for dep in deps {
+                if !resources.contains(&dep) && !shared.contains(&dep) {
+                    missing.insert((world.to_pak_str().to_string(), dep.0, dep.1));
+                }
+            }
+        }
+    }
+
+    Ok(missing)
+}
+
+/// Re-reads the written ISO and checks its structure: the FST, the DOL and every PAK. Area
+/// dependencies which can't be found are only reported if they could be in `input_iso`, the
+/// vanilla game has a few of its own.
+pub fn verify_output(output: &File, input_iso: &[u8]) -> Result<(), String>
+{
+    let disc = unsafe { memmap::Mmap::map(output) }
+        .map_err(|e| format!("Failed to read back the output ISO: {}", e))?;
+    let disc = &disc[..];
+    let fail = |e: String| format!("Verifying the output ISO failed: {}", e);
+
+    let files = check_fst(disc).map_err(fail)?;
+
+    let dol_offset = BeReader { data: disc, what: "the disc header" }.u32(0x420).map_err(fail)?;
+    let dol = files.iter()
+        .find(|f| f.path == "default.dol")
+        .ok_or_else(|| fail("default.dol is missing".to_string()))?;
+    if dol.offset != dol_offset {
+        Err(fail("The disc header doesn't point to default.dol".to_string()))?
+    }
+    check_dol(dol.contents(disc), dol_offset).map_err(fail)?;
+
+    let missing = missing_dependencies(disc, &files).map_err(fail)?;
+    let vanilla_missing = check_fst(input_iso)
+        .and_then(|input_files| missing_dependencies(input_iso, &input_files))
+        .unwrap_or_default();
+    let mut missing: Vec<_> = missing.difference(&vanilla_missing).collect();
+    missing.sort();
+    if let Some((pak, file_id, fourcc)) = missing.first() {
+        Err(fail(format!(
+            "{} depends on {:08X}.{}, which isn't in any PAK it can be loaded from ({} missing in total)",
+            pak, file_id, fourcc, missing.len(),
+        )))?
+    }
+
+    Ok(())
+}
@@ -6,8 +6,12 @@ use reader_writer::generic_array::GenericArray;
 
 use std::io::{self, Write};
 use std::iter;
+use std::mem;
+use std::sync::mpsc;
+use std::thread;
 
 use crate::{
+    junk::write_junk,
     pak::Pak,
     thp::Thp,
     bnr::Bnr,
@@ -17,12 +21,21 @@ use crate::{
 
 pub const GC_DISC_LENGTH: usize = 1_459_978_240;
 
+// Rebuilt PAKs are handed to the disc writer in chunks of this size, each PAK can only be
+// CHUNKS_IN_FLIGHT chunks ahead of it
+const CHUNK_SIZE: usize = 1024 * 1024;
+const CHUNKS_IN_FLIGHT: usize = 4;
+
 pub struct GcDisc<'r>
 {
     pub header: GcDiscHeader,
     header_info: GenericArray<u8, U8192>,
     apploader: GcDiscApploader<'r>,
     pub file_system_root: FstEntry<'r>,
+
+    /// Fill the space between files with the junk Nintendo's mastering tools use instead of
+    /// zeroes, as it would be on a real disc
+    pub fill_junk: bool,
 }
 
 impl<'r> Readable<'r> for GcDisc<'r>
@@ -48,6 +61,7 @@ impl<'r> Readable<'r> for GcDisc<'r>
             header_info: header_info,
             apploader: apploader,
             file_system_root: fst,
+            fill_junk: false,
         };
         gc_disc
     }
@@ -129,8 +143,17 @@ impl<'r> GcDisc<'r>
         }
 
         let fst_end = (self.header.fst_offset + self.header.fst_length) as u64;
-        writer.skip_bytes(files_offset as u64 - fst_end)?;
-        FstEntry::write_files(writer, notifier, &raw_fst)
+        let junk = if self.fill_junk {
+            let game_id = self.header.game_identifier();
+            Some(([game_id[0], game_id[1], game_id[2], game_id[3]], self.header.disc_id))
+        } else {
+            None
+        };
+        match junk {
+            Some((game_id, disc_number)) => write_junk(writer, game_id, disc_number, fst_end, files_offset as u64 - fst_end)?,
+            None => writer.skip_bytes(files_offset as u64 - fst_end)?,
+        }
+        FstEntry::write_files(writer, notifier, &raw_fst, junk)
     }
 }
 
@@ -173,6 +196,11 @@ pub struct GcDiscHeader
     pub unused3: u32,
 }
 
+/// Seed metadata is kept in the last bytes of `game_name`, which the game's name never reaches.
+/// Anything showing the name stops at its terminator, so the metadata doesn't show up there.
+const SEED_METADATA_LEN: usize = 64;
+const SEED_METADATA_MAGIC: &[u8] = b"randomprime:";
+
 impl GcDiscHeader
 {
     pub fn game_identifier(&self) -> [u8; 6]
@@ -180,6 +208,39 @@ impl GcDiscHeader
         [self.console_id, self.game_code[0], self.game_code[1], self.country_code,
          self.maker_code[0], self.maker_code[1]]
     }
+
+    /// The metadata written by `set_seed_metadata`, if there is any
+    pub fn seed_metadata(&self) -> Option<String>
+    {
+        let tail = &self.game_name[self.game_name.len() - SEED_METADATA_LEN..];
+        let text = tail.strip_prefix(SEED_METADATA_MAGIC)?;
+        let len = text.iter().position(|b| *b == 0).unwrap_or(text.len());
+        Some(String::from_utf8_lossy(&text[..len]).into_owned())
+    }
+
+    /// Writes `metadata` to the unused end of `game_name`, cut short if it doesn't fit. Returns
+    /// false without writing anything if the name itself runs into that space.
+    pub fn set_seed_metadata(&mut self, metadata: &str) -> bool
+    {
+        let tail_start = self.game_name.len() - SEED_METADATA_LEN;
+        let name_len = self.game_name.iter().position(|b| *b == 0).unwrap_or(self.game_name.len());
+        if name_len >= tail_start {
+            return false;
+        }
+
+        // Leave room for the terminator, and don't cut a character in half
+        let mut len = metadata.len().min(SEED_METADATA_LEN - SEED_METADATA_MAGIC.len() - 1);
+        while !metadata.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let tail = &mut self.game_name[tail_start..];
+        tail.iter_mut().for_each(|b| *b = 0);
+        tail[..SEED_METADATA_MAGIC.len()].copy_from_slice(SEED_METADATA_MAGIC);
+        tail[SEED_METADATA_MAGIC.len()..SEED_METADATA_MAGIC.len() + len]
+            .copy_from_slice(&metadata.as_bytes()[..len]);
+        true
+    }
 }
 
 
@@ -334,7 +395,12 @@ impl<'r> FstEntry<'r>
         state.entries
     }
 
-    fn write_files<W, N>(writer: &mut W, notifier: &mut N, fst_entries: &[WrappedFstEntry])
+    fn write_files<W, N>(
+        writer: &mut W,
+        notifier: &mut N,
+        fst_entries: &[WrappedFstEntry],
+        junk: Option<([u8; 4], u8)>,
+    )
         -> io::Result<()>
         where W: Write,
               N: ProgressNotifier,
@@ -349,15 +415,96 @@ impl<'r> FstEntry<'r>
             .collect();
         entries_and_zeroes.push((entries[entries.len() - 1], 0));
 
-        let zero_bytes = [0u8; 32];
-        for (e, zeroes) in entries_and_zeroes {
-            if let Some(f) = e.file {
-                notifier.notify_writing_file(&e.name, e.raw_entry.length as usize);
-                f.write_to(writer)?;
-                writer.write_all(&zero_bytes[0..zeroes as usize])?;
+        thread::scope(|scope| {
+            // Rebuilding a PAK which was edited is slow, so they're all serialized at once on
+            // their own threads. None of them gets more than a few chunks ahead of what's been
+            // written, so whole PAKs are never held in memory.
+            let mut streams: Vec<_> = entries_and_zeroes.iter()
+                .map(|(e, _)| match e.file {
+                    Some(FstEntryFile::Pak(pak)) if pak.original_bytes().is_none() => {
+                        let (sender, receiver) = mpsc::sync_channel(CHUNKS_IN_FLIGHT);
+                        let handle = scope.spawn(move || {
+                            let mut chunk_writer = ChunkWriter::new(sender);
+                            pak.write_to(&mut chunk_writer)?;
+                            chunk_writer.flush()
+                        });
+                        Some((receiver, handle))
+                    },
+                    _ => None,
+                })
+                .collect();
+
+            let zero_bytes = [0u8; 32];
+            for ((e, zeroes), stream) in entries_and_zeroes.iter().zip(streams.iter_mut()) {
+                if let Some(f) = e.file {
+                    notifier.notify_writing_file(&e.name, e.raw_entry.length as usize);
+                    match stream.take() {
+                        Some((receiver, handle)) => {
+                            for chunk in receiver.iter() {
+                                writer.write_all(&chunk)?;
+                            }
+                            match handle.join() {
+                                Ok(result) => result?,
+                                Err(panic) => std::panic::resume_unwind(panic),
+                            }
+                        },
+                        None => {
+                            f.write_to(writer)?;
+                        },
+                    }
+                    match junk {
+                        Some((game_id, disc_number)) => {
+                            let offset = e.raw_entry.offset as u64 + e.raw_entry.length as u64;
+                            write_junk(writer, game_id, disc_number, offset, *zeroes as u64)?;
+                        },
+                        None => writer.write_all(&zero_bytes[0..*zeroes as usize])?,
+                    }
+                }
             }
+            Ok(())
+        })
+    }
+}
+
+/// Sends everything written to it to the disc writer in `CHUNK_SIZE` chunks
+struct ChunkWriter
+{
+    sender: mpsc::SyncSender<Vec<u8>>,
+    buf: Vec<u8>,
+}
+
+impl ChunkWriter
+{
+    fn new(sender: mpsc::SyncSender<Vec<u8>>) -> Self
+    {
+        ChunkWriter {
+            sender,
+            buf: Vec::with_capacity(CHUNK_SIZE),
+        }
+    }
+}
+
+impl Write for ChunkWriter
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+        let l = std::cmp::min(buf.len(), CHUNK_SIZE - self.buf.len());
+        self.buf.extend_from_slice(&buf[..l]);
+        if self.buf.len() == CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(l)
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        if self.buf.is_empty() {
+            return Ok(());
         }
-        Ok(())
+        let chunk = mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+        // Only fails if the disc writer gave up
+        self.sender.send(chunk)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Stopped writing the disc"))
     }
 }
 
@@ -542,7 +689,14 @@ impl<'r> FstEntryFile<'r>
     fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<u64>
     {
         match *self {
-            FstEntryFile::Pak(ref pak) => pak.write_to(writer),
+            FstEntryFile::Pak(ref pak) => match pak.original_bytes() {
+                // Parsed but unchanged, copy it straight from the input
+                Some(bytes) => {
+                    writer.write_all(bytes)?;
+                    Ok(bytes.len() as u64)
+                },
+                None => pak.write_to(writer),
+            },
             FstEntryFile::Thp(ref thp) => thp.write_to(writer),
             FstEntryFile::Bnr(ref bnr) => bnr.write_to(writer),
             FstEntryFile::ExternalFile(ref i) => i.with_read(&mut |r| io::copy(r, writer)),
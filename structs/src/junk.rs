@@ -0,0 +1,198 @@
+use std::io::{self, Write};
+
+// Based on
+// https://github.com/dolphin-emu/dolphin/blob/8f460a1cda1a4d4208c4da9e01bf775f5f704498/Source/Core/DiscIO/LaggedFibonacciGenerator.cpp
+// and the seeding used by Wiimm's ISO Tools to recreate the junk of GameCube and Wii discs
+
+pub const LFG_SEED_SIZE: usize = 17;
+const LFG_K: usize = 521;
+const LFG_J: usize = 32;
+
+/// The generator is reseeded at the start of every sector of the disc
+pub const JUNK_SECTOR_SIZE: u64 = 0x8000;
+
+/// The generator Nintendo's mastering tools used to fill unused disc space with junk
+pub struct LaggedFibonacciGenerator
+{
+    buffer: [u32; LFG_K],
+    position_bytes: usize,
+}
+
+impl LaggedFibonacciGenerator
+{
+    /// `seed` is `LFG_SEED_SIZE` big endian words
+    pub fn new(seed: &[u8]) -> Self
+    {
+        let mut words = [0u32; LFG_SEED_SIZE];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = u32::from_be_bytes([seed[i * 4], seed[i * 4 + 1], seed[i * 4 + 2], seed[i * 4 + 3]]);
+        }
+        Self::from_seed_words(&words)
+    }
+
+    /// Generator for the junk at `offset` on a disc
+    pub fn for_disc(game_id: [u8; 4], disc_number: u8, offset: u64) -> Self
+    {
+        let seed = u32::from_be_bytes([
+            game_id[2],
+            game_id[1],
+            game_id[3].wrapping_add(game_id[2]),
+            game_id[0].wrapping_add(game_id[1]),
+        ]) ^ disc_number as u32;
+        let sector = (offset / JUNK_SECTOR_SIZE) as u32;
+
+        let mut n = seed.wrapping_mul(0x260bcd5) ^ sector.wrapping_mul(0x1ef29123);
+        let mut words = [0u32; LFG_SEED_SIZE];
+        for word in words.iter_mut() {
+            for _ in 0..32 {
+                n = n.wrapping_mul(0x5d588b65).wrapping_add(1);
+                *word = (*word >> 1) | (n & 0x80000000);
+            }
+        }
+        words[16] ^= (words[0] >> 9) ^ (words[16] << 23);
+
+        let mut lfg = Self::from_seed_words(&words);
+        lfg.skip((offset % JUNK_SECTOR_SIZE) as usize);
+        lfg
+    }
+
+    fn from_seed_words(seed: &[u32; LFG_SEED_SIZE]) -> Self
+    {
+        let mut buffer = [0u32; LFG_K];
+        buffer[..LFG_SEED_SIZE].copy_from_slice(seed);
+        for i in LFG_SEED_SIZE..LFG_K {
+            buffer[i] = (buffer[i - 17] << 23) ^ (buffer[i - 16] >> 9) ^ buffer[i - 1];
+        }
+
+        // The real generator shifts by 18 instead of 16 when outputting, do that up front instead
+        for x in buffer.iter_mut() {
+            *x = (*x & 0xFF00FFFF) | ((*x >> 2) & 0x00FF0000);
+        }
+
+        let mut lfg = LaggedFibonacciGenerator { buffer, position_bytes: 0 };
+        for _ in 0..4 {
+            lfg.forward();
+        }
+        lfg
+    }
+
+    fn forward(&mut self)
+    {
+        for i in 0..LFG_J {
+            self.buffer[i] ^= self.buffer[i + LFG_K - LFG_J];
+        }
+        for i in LFG_J..LFG_K {
+            self.buffer[i] ^= self.buffer[i - LFG_J];
+        }
+    }
+
+    pub fn skip(&mut self, count: usize)
+    {
+        self.position_bytes += count;
+        while self.position_bytes >= LFG_K * 4 {
+            self.forward();
+            self.position_bytes -= LFG_K * 4;
+        }
+    }
+
+    pub fn get_bytes(&mut self, out: &mut [u8])
+    {
+        for b in out.iter_mut() {
+            *b = self.buffer[self.position_bytes / 4].to_be_bytes()[self.position_bytes % 4];
+            self.position_bytes += 1;
+            if self.position_bytes == LFG_K * 4 {
+                self.forward();
+                self.position_bytes = 0;
+            }
+        }
+    }
+}
+
+/// Writes `len` bytes of the junk which belongs at `offset` on the disc
+pub fn write_junk<W: Write>(writer: &mut W, game_id: [u8; 4], disc_number: u8, mut offset: u64, len: u64)
+    -> io::Result<()>
+{
+    let end = offset + len;
+    let mut buf = vec![0u8; JUNK_SECTOR_SIZE as usize];
+    while offset < end {
+        let sector_end = std::cmp::min((offset / JUNK_SECTOR_SIZE + 1) * JUNK_SECTOR_SIZE, end);
+        let buf = &mut buf[..(sector_end - offset) as usize];
+        LaggedFibonacciGenerator::for_disc(game_id, disc_number, offset).get_bytes(buf);
+        writer.write_all(buf)?;
+        offset = sector_end;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::{fs::File, io::{Read, Seek, SeekFrom}};
+
+    /// Compares the generator against the junk of a retail disc, read from the gaps between its
+    /// files. Retail discs can't be shipped with the tests, so this needs a dump of GM8E01.
+    #[test]
+    #[ignore = "needs a retail GM8E01 dump, set RANDOMPRIME_RETAIL_ISO to its path"]
+    fn matches_retail_junk()
+    {
+        let path = std::env::var("RANDOMPRIME_RETAIL_ISO")
+            .expect("RANDOMPRIME_RETAIL_ISO isn't set");
+        let mut iso = File::open(path).unwrap();
+        let mut read_at = |offset: u64, len: usize| {
+            let mut buf = vec![0u8; len];
+            iso.seek(SeekFrom::Start(offset)).unwrap();
+            iso.read_exact(&mut buf).unwrap();
+            buf
+        };
+        let u32_at = |buf: &[u8], offset: usize| {
+            u32::from_be_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+        };
+
+        let header = read_at(0, 0x440);
+        assert_eq!(&header[..6], b"GM8E01");
+        let game_id = [header[0], header[1], header[2], header[3]];
+        let disc_number = header[6];
+
+        let fst_offset = u32_at(&header, 0x424) as u64;
+        let fst_length = u32_at(&header, 0x428) as usize;
+        let fst = read_at(fst_offset, fst_length);
+        let entry_count = u32_at(&fst, 8) as usize;
+        let mut files: Vec<(u64, u64)> = (1..entry_count)
+            .filter(|i| fst[i * 12] == 0)
+            .map(|i| (u32_at(&fst, i * 12 + 4) as u64, u32_at(&fst, i * 12 + 8) as u64))
+            .collect();
+        files.sort();
+
+        // Check a block at the start of a junk sector in every gap which has one, so the
+        // comparison doesn't depend on how the junk lines up with the end of the file before it
+        let mut checked = 0;
+        for pair in files.windows(2) {
+            let gap_start = pair[0].0 + pair[0].1;
+            let block_start = (gap_start + JUNK_SECTOR_SIZE - 1) / JUNK_SECTOR_SIZE * JUNK_SECTOR_SIZE;
+            if block_start + 0x1000 > pair[1].0 {
+                continue;
+            }
+
+            let mut expected = vec![0u8; 0x1000];
+            LaggedFibonacciGenerator::for_disc(game_id, disc_number, block_start).get_bytes(&mut expected);
+            assert!(read_at(block_start, expected.len()) == expected, "junk differs at 0x{:X}", block_start);
+            checked += 1;
+        }
+        assert!(checked > 0, "no junk found between the files");
+    }
+
+    #[test]
+    fn starting_partway_through_a_sector_continues_the_sector()
+    {
+        let offset = 0x123 * JUNK_SECTOR_SIZE;
+        let mut sector = vec![0u8; JUNK_SECTOR_SIZE as usize];
+        LaggedFibonacciGenerator::for_disc(*b"GM8E", 0, offset).get_bytes(&mut sector);
+
+        for skip in [1, 0x800, LFG_K * 4, LFG_K * 4 + 3] {
+            let mut tail = vec![0u8; sector.len() - skip];
+            LaggedFibonacciGenerator::for_disc(*b"GM8E", 0, offset + skip as u64).get_bytes(&mut tail);
+            assert!(tail == sector[skip..], "differs after skipping {} bytes", skip);
+        }
+    }
+}
@@ -11,6 +11,7 @@ mod font;
 mod frme;
 mod gc_disc;
 mod hint;
+mod junk;
 mod mapa;
 mod mapw;
 pub mod mlvl;
@@ -58,6 +59,8 @@ pub mod scly_props
     pub mod sound;
     pub mod spawn_point;
     pub mod special_function;
+    pub mod spider_ball_attraction_surface;
+    pub mod spider_ball_waypoint;
     pub mod streamed_audio;
     pub mod timer;
     pub mod trigger;
@@ -154,6 +157,8 @@ pub mod scly_props
     pub use self::sound::*;
     pub use self::spawn_point::*;
     pub use self::special_function::*;
+    pub use self::spider_ball_attraction_surface::*;
+    pub use self::spider_ball_waypoint::*;
     pub use self::streamed_audio::*;
     pub use self::timer::*;
     pub use self::trigger::*;
@@ -229,6 +234,7 @@ pub use scly_props::camera_blur_keyframe::*;
 pub use scly_props::camera_filter_keyframe::*;
 pub use scly_props::camera_hint_trigger::*;
 pub use scly_props::camera_hint::*;
+pub use scly_props::counter::*;
 pub use scly_props::damageable_trigger::*;
 pub use scly_props::distance_fog::*;
 pub use scly_props::dock::*;
@@ -249,6 +255,8 @@ pub use scly_props::snake_weed_swarm::*;
 pub use scly_props::sound::*;
 pub use scly_props::spawn_point::*;
 pub use scly_props::special_function::*;
+pub use scly_props::spider_ball_attraction_surface::*;
+pub use scly_props::spider_ball_waypoint::*;
 pub use scly_props::streamed_audio::*;
 pub use scly_props::timer::*;
 pub use scly_props::trigger::*;
@@ -326,6 +334,7 @@ pub use font::*;
 pub use frme::*;
 pub use gc_disc::*;
 pub use hint::*;
+pub use junk::*;
 pub use mapa::*;
 pub use mapw::*;
 pub use mlvl::*;
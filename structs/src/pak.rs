@@ -67,6 +67,36 @@ pub struct Pak<'r>
     _pad: (),
 }
 
+impl<'r> Pak<'r>
+{
+    /// The PAK as it was read, if nothing in it changed since and writing it out again would
+    /// produce the same bytes, so they can be copied as is
+    pub fn original_bytes(&self) -> Option<&'r [u8]>
+    {
+        let original_unused: u32 = self.start.offset(4).read(());
+        if self.unused != original_unused {
+            return None;
+        }
+        let resource_count: u32 = self.start.offset(12 + self.named_resources.size()).read(());
+        let infos = self.resources.untouched_infos(resource_count as usize)?;
+
+        // Writing packs the resources one after the other, which the original PAK might not
+        let mut offset = align_byte_count(32,
+            self.named_resources.size() +
+            <u32 as Readable>::fixed_size().unwrap() * 4 +
+            <ResourceInfo as Readable>::fixed_size().unwrap() * infos.len()
+        ) as u32;
+        for info in infos.iter() {
+            if info.offset != offset || info.size % 32 != 0 {
+                return None;
+            }
+            offset += info.size;
+        }
+
+        self.start.get(..self.size())
+    }
+}
+
 
 #[auto_struct(Readable, Writable)]
 #[derive(Debug, Clone)]
@@ -143,6 +173,16 @@ impl<'r> ResourceList<'r>
 
     }
 
+    /// The infos of the `resource_count` resources this was read with, if it's still exactly
+    /// that list. Any resource taken out of the list for editing counts as a change.
+    fn untouched_infos(&self, resource_count: usize) -> Option<&RoArray<'r, ResourceInfo>>
+    {
+        match &self.list[..] {
+            [ResourceListElem::Array(a)] if a.len() == resource_count => Some(a),
+            _ => None,
+        }
+    }
+
     pub fn iter<'s>(&'s self) -> ResourceListIter<'r, 's>
     {
         ResourceListIter {
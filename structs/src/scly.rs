@@ -209,6 +209,13 @@ macro_rules! build_scly_property {
                 data: Reader<'r>
             },
 
+            // Raw bytes that no longer live in the source pak (e.g. after a byte-level edit). These
+            // are written back verbatim and never parsed into a typed property.
+            External {
+                object_type: u8,
+                data: Vec<u8>
+            },
+
             $($name(Box<scly_props::$name<'r >> ),)*
         }
 
@@ -218,6 +225,7 @@ macro_rules! build_scly_property {
             {
                 match *self {
                     SclyProperty::Unknown { object_type, .. } => object_type,
+                    SclyProperty::External { object_type, .. } => object_type,
                     $(SclyProperty::$name(_) =>
                       <scly_props::$name as SclyPropertyData>::OBJECT_TYPE,)*
                 }
@@ -238,7 +246,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind(); // TODO: shouldn't need mutability for read
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support position (get)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support position (get)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             let prop = self.$accessor();
@@ -252,7 +261,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support position (set)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support position (set)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             self.$accessor_mut().unwrap().impl_set_position(pos.into());
@@ -276,7 +286,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support rotation (get)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support rotation (get)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             let prop = self.$accessor();
@@ -290,7 +301,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support rotation (set)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support rotation (set)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             self.$accessor_mut().unwrap().impl_set_rotation(pos.into());
@@ -314,7 +326,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support scale (get)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support scale (get)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             let prop = self.$accessor();
@@ -328,7 +341,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support scale (set)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support scale (set)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             self.$accessor_mut().unwrap().impl_set_scale(pos.into());
@@ -352,7 +366,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support patterned_infos (get)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support patterned_infos (get)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             let prop = self.$accessor();
@@ -366,7 +381,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support patterned_infos (set)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support patterned_infos (set)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             self.$accessor_mut().unwrap().impl_set_patterned_infos(x);
@@ -390,7 +406,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support damage_infos (get)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support damage_infos (get)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             let prop = self.$accessor();
@@ -404,7 +421,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support damage_infos (set)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support damage_infos (set)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             self.$accessor_mut().unwrap().impl_set_damage_infos(x);
@@ -428,7 +446,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support vulnerabilities (get)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support vulnerabilities (get)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             let prop = self.$accessor();
@@ -442,7 +461,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support vulnerabilities (set)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support vulnerabilities (set)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             self.$accessor_mut().unwrap().impl_set_vulnerabilities(x);
@@ -466,7 +486,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support health infos (get)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support health infos (get)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             let prop = self.$accessor();
@@ -480,7 +501,8 @@ macro_rules! build_scly_property {
             {
                 self.guess_kind();
                 match *self {
-                    SclyProperty::Unknown { object_type, .. } => panic!("0x{:X} doesn't support health infos (set)", object_type),
+                    SclyProperty::Unknown { object_type, .. } |
+                    SclyProperty::External { object_type, .. } => panic!("0x{:X} doesn't support health infos (set)", object_type),
                     $(
                         SclyProperty::$name(_) => {
                             self.$accessor_mut().unwrap().impl_set_health_infos(x);
@@ -584,6 +606,7 @@ macro_rules! build_scly_property {
             {
                 match *self {
                     SclyProperty::Unknown { ref data, .. } => data.len(),
+                    SclyProperty::External { ref data, .. } => data.len(),
                     $(SclyProperty::$name(ref i) => i.size(),)*
                 }
             }
@@ -598,6 +621,10 @@ macro_rules! build_scly_property {
                         writer.write_all(&data)?;
                         Ok(data.len() as u64)
                     },
+                    SclyProperty::External { ref data, .. } => {
+                        writer.write_all(data)?;
+                        Ok(data.len() as u64)
+                    },
                     $(SclyProperty::$name(ref i) => i.write_to(writer),)*
                 }
             }
@@ -647,6 +674,8 @@ build_scly_property!(
     Sound,                is_sound,                  as_sound,                  as_sound_mut,
     SpawnPoint,           is_spawn_point,            as_spawn_point,            as_spawn_point_mut,
     SpecialFunction,      is_special_function,       as_special_function,       as_special_function_mut,
+    SpiderBallAttractionSurface, is_spider_ball_attraction_surface, as_spider_ball_attraction_surface, as_spider_ball_attraction_surface_mut,
+    SpiderBallWaypoint,   is_spider_ball_waypoint,   as_spider_ball_waypoint,   as_spider_ball_waypoint_mut,
     StreamedAudio,        is_streamed_audio,         as_streamed_audio,         as_streamed_audio_mut,
     Timer,                is_timer,                  as_timer,                  as_timer_mut,
     Trigger,              is_trigger,                as_trigger,                as_trigger_mut,